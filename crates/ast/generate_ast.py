@@ -11,6 +11,7 @@ RUST_BUILTIN_TYPES = {
     'bool',
     'f64',
     'String',
+    'Wtf8',
 }
 
 RUST_PARAMETERIZED_TYPES = {
@@ -45,6 +46,8 @@ class Type(TypeBase):
         params_str = ", ".join(p.to_rust_type(ast) for p in self.params)
         if self.name == 'String':
             return "&'alloc str"
+        if self.name == 'Wtf8':
+            return "&'alloc Wtf8"
         if self.name == 'Option':
             return "Option<{}>".format(params_str)
         if self.name == 'Box':
@@ -179,28 +182,41 @@ def stack_value(ast):
             write_impl(f, *args)
         write(0, "// WARNING: This file is auto-generated by crates/ast/generate_ast.py.")
         write(0, "")
-        write(0, "use crate::token::Token;")
-        write(0, "use ast::arena;")
+        write(0, "use crate::token::{InlineToken, Token};")
+        write(0, "use ast::arena::{self, CloneIn};")
         write(0, "use ast::types::*;")
+        write(0, "use bumpalo::Bump;")
         write(0, "use std::convert::Infallible;")
         write(0, "")
         write(0, "pub type AstError = String;")
-        write(0, "type AstResult<'alloc, T> = Result<arena::Box<'alloc, T>, AstError>;")
         write(0, "")
         write(0, "#[derive(Debug)]")
         write(0, "pub enum StackValue<'alloc> {")
         for ty in types:
             write(1, "{}({}),", ty.rust_variant_name(), Type('Box', [ty]).to_rust_type(ast))
+        write(1, "InlineToken(InlineToken),")
         write(0, "}")
         write(0, "")
         write(0, "impl<'alloc> StackValue<'alloc> {")
-        write(1, "pub fn to_ast<T: StackValueItem<'alloc>>(self) -> AstResult<'alloc, T> {")
+        write(1, "pub fn to_ast<T: StackValueItem<'alloc>>(self) -> Result<T, AstError> {")
         write(2, "T::to_ast(self)")
         write(1, "}")
         write(0, "}")
         write(0, "")
+        write(0, "impl<'alloc> CloneIn<'alloc> for StackValue<'alloc> {")
+        write(1, "fn clone_in(&self, allocator: &'alloc Bump) -> Self {")
+        write(2, "match self {")
+        for ty in types:
+            write(3, "StackValue::{0}(v) => StackValue::{0}(v.clone_in(allocator)),",
+                  ty.rust_variant_name())
+        write(3, "StackValue::InlineToken(v) => StackValue::InlineToken(v.clone_in(allocator)),")
+        write(2, "}")
+        write(1, "}")
+        write(0, "}")
+        write(0, "")
+        write(0, "/// Values that can be taken out of StackValues, fallibly.")
         write(0, "pub trait StackValueItem<'alloc>: Sized {")
-        write(1, "fn to_ast(sv: StackValue<'alloc>) -> AstResult<'alloc, Self>;")
+        write(1, "fn to_ast(sv: StackValue<'alloc>) -> Result<Self, AstError>;")
         write(0, "}")
         write(0, "")
         write(0, "/// Values that can be converted to StackValues, fallibly.")
@@ -210,10 +226,20 @@ def stack_value(ast):
         write(0, "}")
         write(0, "")
         for ty in types:
-            write(0, "impl<'alloc> StackValueItem<'alloc> for {} {{", ty.to_rust_type(ast))
-            write(1, "fn to_ast(sv: StackValue<'alloc>) -> AstResult<'alloc, Self> {")
-            write(2, "match sv {")
-            write(3, "StackValue::{}(v) => Ok(v),", ty.rust_variant_name())
+            rust_ty = ty.to_rust_type(ast)
+            if ty.name == 'Token':
+                # Tokens are popped by value, whichever way they are stored.
+                write(0, "impl<'alloc> StackValueItem<'alloc> for {} {{", rust_ty)
+                write(1, "fn to_ast(sv: StackValue<'alloc>) -> Result<Self, AstError> {")
+                write(2, "match sv {")
+                write(3, "StackValue::Token(v) => Ok(v.unbox()),")
+                write(3, "StackValue::InlineToken(v) => Ok(v.to_token()),")
+            else:
+                write(0, "impl<'alloc> StackValueItem<'alloc> for arena::Box<'alloc, {}> {{",
+                      rust_ty)
+                write(1, "fn to_ast(sv: StackValue<'alloc>) -> Result<Self, AstError> {")
+                write(2, "match sv {")
+                write(3, "StackValue::{}(v) => Ok(v),", ty.rust_variant_name())
             write(3, "_ => Err(format!(\"StackValue expected {}, got {{:?}}\", sv)),", ty)
             write(2, "}")
             write(1, "}")
@@ -223,7 +249,7 @@ def stack_value(ast):
             rust_ty = ty.to_rust_type(ast)
             write(0, "impl<'alloc> TryIntoStack<'alloc> for arena::Box<'alloc, {}> {{", rust_ty)
             write(1, "type Error = Infallible;")
-            write(1, "fn try_into_stack(self) -> Result<StackValue<'alloc>, Infallible> {{", rust_ty)
+            write(1, "fn try_into_stack(self) -> Result<StackValue<'alloc>, Infallible> {")
             write(2, "Ok(StackValue::{}(self))".format(ty.rust_variant_name()))
             write(1, "}")
             write(0, "}")
@@ -274,8 +300,7 @@ def loc_trait(ast):
             write(0, "impl<'alloc> SourceLocationAccessor for {} {{", rust_ty)
             if isinstance(decl, Struct):
                 write(1, "fn set_loc(&mut self, start: SourceLocation, end: SourceLocation) {")
-                write(2, "self.loc.start = start.start;")
-                write(2, "self.loc.end = end.end;")
+                write(2, "self.loc.set_range(start, end);")
                 write(1, "}")
                 write(0, "")
                 write(1, "fn get_loc(&self) -> SourceLocation {")
@@ -286,14 +311,12 @@ def loc_trait(ast):
                 write(2, "match self {")
                 for variant_name, variant_ty in decl.variants.items():
                     if variant_ty is None:
-                        write(3, "{}::{} {{ mut loc }} => {{", ty.name, variant_name)
-                        write(4, "loc.start = start.start;")
-                        write(4, "loc.end = end.end;")
+                        write(3, "{}::{} {{ loc }} => {{", ty.name, variant_name)
+                        write(4, "loc.set_range(start, end);")
                         write(3, "}}", ty.name, variant_name)
                     elif isinstance(variant_ty, dict):
-                        write(3, "{}::{} {{ mut loc, .. }} => {{", ty.name, variant_name)
-                        write(4, "loc.start = start.start;")
-                        write(4, "loc.end = end.end;")
+                        write(3, "{}::{} {{ loc, .. }} => {{", ty.name, variant_name)
+                        write(4, "loc.set_range(start, end);")
                         write(3, "}")
                     else:
                         write(3, "{}::{}(content) => {{ content.set_loc(start, end) }}", ty.name, variant_name)
@@ -412,9 +435,16 @@ def pass_(ast):
         write(0, "#![allow(dead_code)]")
         write(0, "")
         write(0, "use crate::arena;")
+        write(0, "use crate::source_location::SourceLocation;")
         write(0, "use crate::types::*;")
+        write(0, "use crate::wtf8::Wtf8;")
         write(0, "")
         write(0, "pub trait Pass<'alloc> {")
+        write(1, "// Called for the location of each node, before visiting its")
+        write(1, "// children.")
+        write(1, "fn visit_source_location(&mut self, loc: &mut SourceLocation) {")
+        write(1, "}")
+        write(0, "")
         for name, type_decl in ast.type_decls.items():
             if name == "Void":
                 # Hack in a quick fix
@@ -565,11 +595,32 @@ def ast_(ast):
         write(0, "")
         write(0, "use crate::source_location::SourceLocation;")
         write(0, "use crate::arena;")
+        write(0, "use crate::wtf8::Wtf8;")
         write(0, "")
         for type_decl in ast.type_decls.values():
             type_decl.write_rust_type_decl(ast, write)
 
 
+def size_report(ast):
+    with open("src/size_report_generated.rs", "w+") as f:
+        def write(*args):
+            write_impl(f, *args)
+        write(0, "// WARNING: This file is auto-generated by crates/ast/generate_ast.py.")
+        write(0, "")
+        write(0, "use crate::types::*;")
+        write(0, "use std::mem::size_of;")
+        write(0, "")
+        write(0, "/// The size in bytes of each AST node type, in declaration order, as laid")
+        write(0, "/// out in this build.")
+        write(0, "pub static NODE_SIZES: &[(&str, usize)] = &[")
+        for type_decl in ast.type_decls.values():
+            if type_decl.name == 'Void':
+                continue
+            lifetime = "<'static>" if type_decl.has_lifetime else ""
+            write(1, "(\"{0}\", size_of::<{0}{1}>()),", type_decl.name, lifetime)
+        write(0, "];")
+
+
 def type_id(ast):
     with open("src/type_id_generated.rs", "w+") as f:
         def write(*args):
@@ -610,6 +661,61 @@ def type_id(ast):
             write(0, "")
 
 
+def clone_in(ast):
+    with open("src/clone_in_generated.rs", "w+") as f:
+        def write(*args):
+            write_impl(f, *args)
+        write(0, "// WARNING: This file is auto-generated by crates/ast/generate_ast.py.")
+        write(0, "")
+        write(0, "use crate::arena::CloneIn;")
+        write(0, "use crate::types::*;")
+        write(0, "use bumpalo::Bump;")
+        write(0, "")
+
+        for type_decl in ast.type_decls.values():
+            if any(ty is not None for ty in type_decl.field_types()):
+                allocator = "allocator"
+            else:
+                allocator = "_allocator"
+            write(0, "impl<'alloc> CloneIn<'alloc> for {}{} {{",
+                  type_decl.name,
+                  type_decl.lifetime_params())
+            write(1, "fn clone_in(&self, {}: &'alloc Bump) -> Self {{", allocator)
+            type_decl.write_rust_clone_in_method_body(write)
+            write(1, "}")
+            write(0, "}")
+            write(0, "")
+
+
+class Layout(collections.namedtuple("Layout", "size align has_niche")):
+    """Estimated memory layout of a Rust type. `has_niche` is true if the type
+    has invalid bit patterns that Rust can use to store an enum tag."""
+
+    @staticmethod
+    def of_fields(fields):
+        align = max((f.align for f in fields), default=1)
+        size = sum(f.size for f in fields)
+        return Layout(-(-size // align) * align, align,
+                      any(f.has_niche for f in fields))
+
+
+LEAF_LAYOUTS = {
+    'bool': Layout(1, 1, True),
+    'f64': Layout(8, 8, False),
+    'String': Layout(16, 8, True),
+    'Wtf8': Layout(16, 8, True),
+    'Box': Layout(8, 8, True),
+    'Vec': Layout(32, 8, True),
+    'Void': Layout(0, 1, False),
+    'SourceLocation': Layout(16, 8, False),
+}
+
+# Tuple variant payloads estimated to be larger than this many bytes are
+# boxed, so that the rarely used big variants (mostly functions and classes)
+# don't set the size of every Expression and Statement.
+BOX_VARIANT_THRESHOLD = 128
+
+
 class AggregateTypeDecl:
     def __init__(self):
         self.has_lifetime = None
@@ -633,6 +739,11 @@ class Struct(AggregateTypeDecl):
     def field_types(self):
         return iter(self.fields.values())
 
+    def layout(self, ast):
+        return Layout.of_fields(
+            [ast.type_layout(ty) for ty in self.fields.values()]
+            + [LEAF_LAYOUTS['SourceLocation']])
+
     def write_rust_type_decl(self, ast, write):
         if len(self.fields) == 0:
             write(0, "#[derive(Default, Debug, PartialEq)]")
@@ -646,10 +757,18 @@ class Struct(AggregateTypeDecl):
         write(0, "}")
         write(0, "")
 
+    def write_rust_clone_in_method_body(self, write):
+        write(2, "{} {{", self.name)
+        for name in self.fields:
+            write(3, "{}: self.{}.clone_in(allocator),", name, name)
+        write(3, "loc: self.loc,")
+        write(2, "}")
+
     def write_rust_pass_method_body(self, write, emit_call,
                                     emit_variant_dict_call,
                                     emit_variant_tuple_call,
                                     emit_variant_none_call):
+        write(2, "self.visit_source_location(&mut ast.loc);")
         for name, ty in self.fields.items():
             emit_call(2, ty, "&mut ast.{}".format(name))
 
@@ -680,6 +799,26 @@ class Enum(AggregateTypeDecl):
             else:
                 yield var
 
+    def layout(self, ast):
+        location = LEAF_LAYOUTS['SourceLocation']
+        payloads = []
+        for ty in self.variants.values():
+            if ty is None:
+                payloads.append(location)
+            elif isinstance(ty, dict):
+                payloads.append(Layout.of_fields(
+                    [ast.type_layout(field_ty) for field_ty in ty.values()]
+                    + [location]))
+            else:
+                payloads.append(ast.type_layout(ty))
+        if not payloads:
+            return LEAF_LAYOUTS['Void']
+        # Assume the tag needs its own word unless there's a single variant.
+        largest = max(payloads, key=lambda p: p.size)
+        if len(payloads) == 1:
+            return largest
+        return Layout.of_fields([Layout(1, 1, True), largest])
+
     def write_rust_type_decl(self, ast, write):
         write(0, "#[derive(Debug, PartialEq)]")
         lifetime_params = self.lifetime_params()
@@ -700,6 +839,28 @@ class Enum(AggregateTypeDecl):
         write(0, "}")
         write(0, "")
 
+    def write_rust_clone_in_method_body(self, write):
+        if len(self.variants) == 0:
+            write(2, "match *self {}")
+            return
+        write(2, "match self {")
+        for variant_name, variant_type in self.variants.items():
+            if variant_type is None:
+                write(3, "{}::{} {{ loc }} => {}::{} {{ loc: *loc }},",
+                      self.name, variant_name, self.name, variant_name)
+            elif isinstance(variant_type, dict):
+                write(3, "{}::{} {{ {}, loc }} => {}::{} {{",
+                      self.name, variant_name, ', '.join(variant_type.keys()),
+                      self.name, variant_name)
+                for field_name in variant_type:
+                    write(4, "{}: {}.clone_in(allocator),", field_name, field_name)
+                write(4, "loc: *loc,")
+                write(3, "},")
+            else:
+                write(3, "{}::{}(content) => {}::{}(content.clone_in(allocator)),",
+                      self.name, variant_name, self.name, variant_name)
+        write(2, "}")
+
     def write_rust_pass_method_body(self, write, emit_call,
                                     emit_variant_dict_call,
                                     emit_variant_tuple_call,
@@ -707,11 +868,13 @@ class Enum(AggregateTypeDecl):
         write(2, "match ast {")
         for variant_name, variant_type in self.variants.items():
             if variant_type is None:
-                write(3, "{}::{} {{ .. }} => {{", self.name, variant_name)
+                write(3, "{}::{} {{ loc }} => {{", self.name, variant_name)
+                write(4, "self.visit_source_location(loc);")
                 emit_variant_none_call(4, self.name, variant_name)
                 write(3, "}")
             elif isinstance(variant_type, dict):
-                write(3, "{}::{} {{ {}, .. }} => {{", self.name, variant_name, ', '.join(variant_type.keys()))
+                write(3, "{}::{} {{ {}, loc }} => {{", self.name, variant_name, ', '.join(variant_type.keys()))
+                write(4, "self.visit_source_location(loc);")
                 emit_variant_dict_call(4, self.name, variant_name, variant_type);
                 write(3, "}")
             else:
@@ -748,6 +911,10 @@ class Ast:
         for name in self.type_decls:
             self._has_lifetime(name)
 
+        self.layouts = {}
+        for name in self.type_decls:
+            self._box_large_variants(name)
+
     def _has_lifetime(self, name):
         ty = self.type_decls[name]
         if ty.has_lifetime == "computing":
@@ -766,6 +933,7 @@ class Ast:
             ty is not None
             and (ty.name == 'Token'
                  or ty.name == 'String'
+                 or ty.name == 'Wtf8'
                  or ty.name == 'Box'
                  or ty.name == 'Vec'
                  or any(self.type_has_lifetime(u)
@@ -773,6 +941,69 @@ class Ast:
                  or (ty.name in self.type_decls
                      and self._has_lifetime(ty.name))))
 
+    def _box_large_variants(self, name):
+        """Box the payloads of tuple variants of `name` that would make every
+        value of the enum large, and compute the layout of `name`.
+
+        Variants are visited bottom-up, so the layout of each payload already
+        reflects boxing done inside it.
+        """
+        if name in self.layouts:
+            return self.layouts[name]
+        decl = self.type_decls[name]
+        if isinstance(decl, Enum):
+            for variant_name, ty in decl.variants.items():
+                if isinstance(ty, dict):
+                    self._box_large_fields(ty)
+                elif (ty is not None
+                      and self.type_layout(ty).size > BOX_VARIANT_THRESHOLD):
+                    decl.variants[variant_name] = Type('Box', [ty])
+        self.layouts[name] = layout = decl.layout(self)
+        return layout
+
+    def _box_large_fields(self, fields):
+        """Box the largest node-typed fields of a struct-like variant until the
+        variant is no bigger than the threshold."""
+        def boxed(ty):
+            if ty.name == 'Option':
+                inner = boxed(ty.params[0])
+                return inner and Type('Option', [inner])
+            if ty.name in self.type_decls:
+                return Type('Box', [ty])
+            return None
+
+        while True:
+            layout = Layout.of_fields(
+                [self.type_layout(ty) for ty in fields.values()]
+                + [LEAF_LAYOUTS['SourceLocation']])
+            if layout.size <= BOX_VARIANT_THRESHOLD:
+                return
+            candidates = [(self.type_layout(ty).size, name)
+                          for name, ty in fields.items()
+                          if boxed(ty) is not None]
+            if not candidates:
+                return
+            size, name = max(candidates)
+            if size <= LEAF_LAYOUTS['Box'].size:
+                return
+            fields[name] = boxed(fields[name])
+
+    def type_layout(self, ty):
+        """Estimate the size and alignment of `ty` on a 64-bit target, with
+        the default (non-compact) source locations.
+
+        This only has to be good enough to decide what to box; the generated
+        size report has the real numbers.
+        """
+        if ty.name in LEAF_LAYOUTS:
+            return LEAF_LAYOUTS[ty.name]
+        if ty.name == 'Option':
+            inner = self.type_layout(ty.params[0])
+            if inner.has_niche:
+                return Layout(inner.size, inner.align, False)
+            return Layout.of_fields([Layout(1, 1, True), inner])
+        return self._box_large_variants(ty.name)
+
 
 def main():
     with open("ast.json", "r") as json_file:
@@ -784,6 +1015,8 @@ def main():
     stack_value(ast)
     loc_trait(ast)
     pass_(ast)
+    clone_in(ast)
+    size_report(ast)
 
 
 if __name__ == "__main__":
@@ -65,6 +65,54 @@ pub fn alloc_str<'alloc>(allocator: &'alloc Bump, value: &str) -> &'alloc str {
     String::from_str_in(value, allocator).into_bump_str()
 }
 
+/// Types that can be deep-copied into an arena.
+///
+/// `Box` owns its contents, so it can't be `Clone`. Instead, the copy of each
+/// node is allocated in `allocator`.
+pub trait CloneIn<'alloc>: Sized {
+    fn clone_in(&self, allocator: &'alloc Bump) -> Self;
+}
+
+impl<'alloc, T: CloneIn<'alloc>> CloneIn<'alloc> for Box<'alloc, T> {
+    fn clone_in(&self, allocator: &'alloc Bump) -> Self {
+        alloc(allocator, (**self).clone_in(allocator))
+    }
+}
+
+impl<'alloc, T: CloneIn<'alloc>> CloneIn<'alloc> for Vec<'alloc, T> {
+    fn clone_in(&self, allocator: &'alloc Bump) -> Self {
+        let mut out = Vec::with_capacity_in(self.len(), allocator);
+        for item in self.iter() {
+            out.push(item.clone_in(allocator));
+        }
+        out
+    }
+}
+
+impl<'alloc, T: CloneIn<'alloc>> CloneIn<'alloc> for Option<T> {
+    fn clone_in(&self, allocator: &'alloc Bump) -> Self {
+        self.as_ref().map(|value| value.clone_in(allocator))
+    }
+}
+
+impl<'alloc> CloneIn<'alloc> for &'alloc str {
+    fn clone_in(&self, _allocator: &'alloc Bump) -> Self {
+        self
+    }
+}
+
+impl<'alloc> CloneIn<'alloc> for bool {
+    fn clone_in(&self, _allocator: &'alloc Bump) -> Self {
+        *self
+    }
+}
+
+impl<'alloc> CloneIn<'alloc> for f64 {
+    fn clone_in(&self, _allocator: &'alloc Bump) -> Self {
+        *self
+    }
+}
+
 pub fn map_vec<'alloc, A, B>(
     source: &mut Vec<A>,
     mut map_fn: impl FnMut(&mut A) -> B,
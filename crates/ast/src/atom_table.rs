@@ -0,0 +1,155 @@
+//! Interning of the names in an AST, so that repeated occurrences share one
+//! string.
+
+use crate::arena;
+use bumpalo::Bump;
+use std::cell::RefCell;
+
+/// The index of a string in an AtomTable.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Atom {
+    index: u32,
+}
+
+impl Atom {
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+}
+
+/// A set of strings allocated in an arena, each identified by an Atom.
+///
+/// All the memory of the table, including the table itself, can come from
+/// the arena, so it goes away with the AST and doesn't need to be dropped.
+pub struct AtomTable<'alloc> {
+    allocator: &'alloc Bump,
+    inner: RefCell<AtomTableInner<'alloc>>,
+}
+
+struct AtomTableInner<'alloc> {
+    /// The interned strings, indexed by Atom.
+    strings: arena::Vec<'alloc, &'alloc str>,
+
+    /// Open-addressing hash table of indexes into `strings`. Its length is
+    /// zero or a power of two, and it is never more than half full.
+    slots: arena::Vec<'alloc, u32>,
+
+    /// Buffer for strings passed to `intern_with`.
+    scratch: arena::String<'alloc>,
+}
+
+const EMPTY_SLOT: u32 = u32::MAX;
+const MIN_SLOTS: usize = 64;
+
+// FNV-1a. Names are short, so this is faster than the default SipHash.
+fn hash(s: &str) -> usize {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in s.as_bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash as usize
+}
+
+impl<'alloc> AtomTableInner<'alloc> {
+    /// Find `s` in the table. If it's missing, return the slot to store it
+    /// in instead.
+    fn find(&self, s: &str) -> Result<Atom, usize> {
+        let mask = self.slots.len() - 1;
+        let mut slot = hash(s) & mask;
+        loop {
+            let index = self.slots[slot];
+            if index == EMPTY_SLOT {
+                return Err(slot);
+            }
+            if self.strings[index as usize] == s {
+                return Ok(Atom { index });
+            }
+            slot = (slot + 1) & mask;
+        }
+    }
+
+    /// Make room for one more string.
+    fn reserve_one(&mut self, allocator: &'alloc Bump) {
+        if (self.strings.len() + 1) * 2 <= self.slots.len() {
+            return;
+        }
+        let len = usize::max(self.slots.len() * 2, MIN_SLOTS);
+        self.slots = bumpalo::vec![in allocator; EMPTY_SLOT; len];
+        for (index, s) in self.strings.iter().enumerate() {
+            let mut slot = hash(s) & (len - 1);
+            while self.slots[slot] != EMPTY_SLOT {
+                slot = (slot + 1) & (len - 1);
+            }
+            self.slots[slot] = index as u32;
+        }
+    }
+
+    fn insert(&mut self, slot: usize, s: &'alloc str) -> Atom {
+        let index = self.strings.len() as u32;
+        self.strings.push(s);
+        self.slots[slot] = index;
+        Atom { index }
+    }
+}
+
+impl<'alloc> AtomTable<'alloc> {
+    pub fn new_in(allocator: &'alloc Bump) -> Self {
+        Self {
+            allocator,
+            inner: RefCell::new(AtomTableInner {
+                strings: arena::Vec::new_in(allocator),
+                slots: arena::Vec::new_in(allocator),
+                scratch: arena::String::new_in(allocator),
+            }),
+        }
+    }
+
+    /// Return the atom for `s`, copying `s` into the arena if it's new.
+    pub fn intern(&self, s: &str) -> Atom {
+        let mut inner = self.inner.borrow_mut();
+        inner.reserve_one(self.allocator);
+        match inner.find(s) {
+            Ok(atom) => atom,
+            Err(slot) => inner.insert(slot, arena::alloc_str(self.allocator, s)),
+        }
+    }
+
+    /// Like `intern`, for a string written by `write` into an empty buffer.
+    /// The buffer is reused, so only new strings are allocated. `write` must
+    /// not use this table.
+    pub fn intern_with(&self, write: impl FnOnce(&mut arena::String<'alloc>)) -> Atom {
+        let mut inner = self.inner.borrow_mut();
+        inner.reserve_one(self.allocator);
+        inner.scratch.clear();
+        write(&mut inner.scratch);
+        match inner.find(&inner.scratch) {
+            Ok(atom) => atom,
+            Err(slot) => {
+                let s = arena::alloc_str(self.allocator, &inner.scratch);
+                inner.insert(slot, s)
+            }
+        }
+    }
+
+    /// Return the atom for `s`, if it's in the table.
+    pub fn find(&self, s: &str) -> Option<Atom> {
+        let inner = self.inner.borrow();
+        if inner.slots.is_empty() {
+            return None;
+        }
+        inner.find(s).ok()
+    }
+
+    pub fn get(&self, atom: Atom) -> &'alloc str {
+        self.inner.borrow().strings[atom.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.borrow().strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
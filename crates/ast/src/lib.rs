@@ -1,8 +1,12 @@
 //! The Visage AST (abstract syntax tree).
 
 pub mod arena;
+pub mod atom_table;
 pub mod associated_data;
 pub mod source_location;
+pub mod wtf8;
+
+mod clone_in_generated;
 
 mod source_location_accessor_generated;
 pub mod source_location_accessor {
@@ -16,6 +20,10 @@ mod visit_generated;
 pub mod visit {
     pub use crate::visit_generated::*;
 }
+mod size_report_generated;
+pub mod size_report {
+    pub use crate::size_report_generated::*;
+}
 mod type_id_generated;
 pub mod type_id {
     pub use crate::type_id_generated::*;
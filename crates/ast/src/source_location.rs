@@ -1,14 +1,58 @@
+// With the `compact_locations` feature, offsets are stored in 32 bits, which
+// halves the size of the location in every AST node, but limits the source to
+// 4GB. Either way, offsets are passed in and out as `usize`.
+#[cfg(not(feature = "compact_locations"))]
+mod offset {
+    pub type Offset = usize;
+
+    pub fn from_usize(offset: usize) -> Offset {
+        offset
+    }
+
+    pub const fn to_usize(offset: Offset) -> usize {
+        offset
+    }
+
+    pub fn fits(_offset: usize) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "compact_locations")]
+mod offset {
+    use std::convert::TryFrom;
+
+    pub type Offset = u32;
+
+    pub fn from_usize(offset: usize) -> Offset {
+        Offset::try_from(offset).expect("source offset doesn't fit in 32 bits")
+    }
+
+    pub const fn to_usize(offset: Offset) -> usize {
+        offset as usize
+    }
+
+    pub fn fits(offset: usize) -> bool {
+        Offset::try_from(offset).is_ok()
+    }
+}
+
+use offset::{fits, from_usize, to_usize, Offset};
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct SourceLocation {
-    pub start: usize,
-    pub end: usize,
+    start: Offset,
+    end: Offset,
 }
 
 impl SourceLocation {
     pub fn new(start: usize, end: usize) -> Self {
         debug_assert!(start <= end);
 
-        Self { start, end }
+        Self {
+            start: from_usize(start),
+            end: from_usize(end),
+        }
     }
 
     pub fn from_parts(start: SourceLocation, end: SourceLocation) -> Self {
@@ -20,6 +64,20 @@ impl SourceLocation {
         }
     }
 
+    /// True if `offset` can be stored in a SourceLocation. Sources longer
+    /// than this are rejected by the parser.
+    pub fn offset_fits(offset: usize) -> bool {
+        fits(offset)
+    }
+
+    pub fn start(&self) -> usize {
+        to_usize(self.start)
+    }
+
+    pub fn end(&self) -> usize {
+        to_usize(self.end)
+    }
+
     pub fn set_range(&mut self, start: SourceLocation, end: SourceLocation) {
         debug_assert!(start.start <= end.end);
         self.start = start.start;
@@ -0,0 +1,233 @@
+//! Strings that can contain unpaired surrogates, like the values of JS
+//! strings.
+
+use crate::arena;
+use bumpalo::Bump;
+use std::borrow::Cow;
+use std::char;
+use std::fmt::{self, Debug, Formatter, Write};
+
+/// Text in WTF-8, the generalization of UTF-8 that can also encode unpaired
+/// surrogates, as three bytes each. Paired surrogates are always encoded as
+/// the character they stand for, so each sequence of UTF-16 code units has a
+/// single encoding.
+///
+/// JS string values are sequences of code units, so a string literal in
+/// UTF-16 source, or with an escape sequence like `\uD800`, can have a value
+/// that isn't valid Unicode, and so isn't a `str`.
+///
+/// See <https://simonsapin.github.io/wtf-8/>.
+#[derive(PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Wtf8 {
+    bytes: [u8],
+}
+
+impl Wtf8 {
+    pub fn new(s: &str) -> &Wtf8 {
+        Self::from_bytes_unchecked(s.as_bytes())
+    }
+
+    fn from_bytes_unchecked(bytes: &[u8]) -> &Wtf8 {
+        // Wtf8 is a transparent wrapper around [u8].
+        unsafe { &*(bytes as *const [u8] as *const Wtf8) }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Return the text as a `str`, unless it contains unpaired surrogates.
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.bytes).ok()
+    }
+
+    /// Return the text with each unpaired surrogate replaced by U+FFFD
+    /// REPLACEMENT CHARACTER.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        match self.as_str() {
+            Some(s) => Cow::Borrowed(s),
+            None => Cow::Owned(
+                self.code_points()
+                    .map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The code points of the text, including unpaired surrogates.
+    pub fn code_points(&self) -> CodePoints<'_> {
+        CodePoints {
+            bytes: self.bytes.iter(),
+        }
+    }
+
+    /// The UTF-16 code units of the text, as in the JS string value.
+    pub fn encode_utf16(&self) -> impl Iterator<Item = u16> + '_ {
+        self.code_points().flat_map(|c| {
+            let mut units = [0; 2];
+            let len = match char::from_u32(c) {
+                Some(c) => c.encode_utf16(&mut units).len(),
+                None => {
+                    units[0] = c as u16;
+                    1
+                }
+            };
+            (0..len).map(move |i| units[i])
+        })
+    }
+}
+
+impl PartialEq<str> for Wtf8 {
+    fn eq(&self, other: &str) -> bool {
+        self.bytes == *other.as_bytes()
+    }
+}
+
+impl Debug for Wtf8 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(s) = self.as_str() {
+            return Debug::fmt(s, f);
+        }
+        f.write_char('"')?;
+        for c in self.code_points() {
+            match char::from_u32(c) {
+                Some(c) => {
+                    for e in c.escape_debug() {
+                        f.write_char(e)?;
+                    }
+                }
+                None => write!(f, "\\u{{{:x}}}", c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+impl<'alloc> arena::CloneIn<'alloc> for &'alloc Wtf8 {
+    fn clone_in(&self, _allocator: &'alloc Bump) -> Self {
+        self
+    }
+}
+
+/// Iterator over the code points of a Wtf8, returned by `code_points`.
+pub struct CodePoints<'a> {
+    bytes: std::slice::Iter<'a, u8>,
+}
+
+impl<'a> Iterator for CodePoints<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let first = *self.bytes.next()? as u32;
+        let (len, mut c) = match first {
+            0x00..=0x7f => return Some(first),
+            0xc0..=0xdf => (1, first & 0x1f),
+            0xe0..=0xef => (2, first & 0x0f),
+            _ => (3, first & 0x07),
+        };
+        for _ in 0..len {
+            c = (c << 6) | (*self.bytes.next().unwrap() as u32 & 0x3f);
+        }
+        Some(c)
+    }
+}
+
+/// A Wtf8 being built in an arena.
+pub struct Wtf8Buf<'alloc> {
+    bytes: arena::Vec<'alloc, u8>,
+}
+
+impl<'alloc> Wtf8Buf<'alloc> {
+    pub fn with_capacity_in(capacity: usize, allocator: &'alloc Bump) -> Self {
+        Self {
+            bytes: arena::Vec::with_capacity_in(capacity, allocator),
+        }
+    }
+
+    pub fn from_str_in(s: &str, allocator: &'alloc Bump) -> Self {
+        let mut buf = Self::with_capacity_in(s.len(), allocator);
+        buf.push_str(s);
+        buf
+    }
+
+    pub fn push(&mut self, c: char) {
+        let mut bytes = [0; 4];
+        self.bytes
+            .extend_from_slice(c.encode_utf8(&mut bytes).as_bytes());
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    /// Append `text`, joining a trailing surrogate at its start with a leading
+    /// surrogate at the end of this buffer.
+    pub fn push_wtf8(&mut self, text: &Wtf8) {
+        let mut bytes = text.as_bytes();
+        if let [0xed, b1 @ 0xb0..=0xbf, b2, ..] = *bytes {
+            self.push_code_unit(0xd000 | ((b1 as u16 & 0x3f) << 6) | (b2 as u16 & 0x3f));
+            bytes = &bytes[3..];
+        }
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Append a UTF-16 code unit. A trailing surrogate that follows a leading
+    /// surrogate is combined with it into a single character.
+    pub fn push_code_unit(&mut self, unit: u16) {
+        if let Some(c) = char::from_u32(unit as u32) {
+            self.push(c);
+            return;
+        }
+
+        if (0xdc00..=0xdfff).contains(&unit) {
+            if let Some(lead) = self.last_leading_surrogate() {
+                let c = 0x10000 + ((lead as u32 - 0xd800) << 10) + (unit as u32 - 0xdc00);
+                let len = self.bytes.len();
+                self.bytes.truncate(len - 3);
+                self.push(char::from_u32(c).unwrap());
+                return;
+            }
+        }
+
+        // The generalized UTF-8 encoding of the surrogate.
+        self.bytes.extend_from_slice(&[
+            0xe0 | (unit >> 12) as u8,
+            0x80 | ((unit >> 6) & 0x3f) as u8,
+            0x80 | (unit & 0x3f) as u8,
+        ]);
+    }
+
+    /// Append a code point, which may be a surrogate.
+    pub fn push_code_point(&mut self, c: u32) {
+        match char::from_u32(c) {
+            Some(c) => self.push(c),
+            None => self.push_code_unit(c as u16),
+        }
+    }
+
+    fn last_leading_surrogate(&self) -> Option<u16> {
+        match self.bytes[..] {
+            [.., 0xed, b1 @ 0xa0..=0xaf, b2] => {
+                Some(0xd000 | ((b1 as u16 & 0x3f) << 6) | (b2 as u16 & 0x3f))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_wtf8(&self) -> &Wtf8 {
+        Wtf8::from_bytes_unchecked(&self.bytes)
+    }
+
+    pub fn into_bump_wtf8(self) -> &'alloc Wtf8 {
+        Wtf8::from_bytes_unchecked(self.bytes.into_bump_slice())
+    }
+}
@@ -5,6 +5,7 @@ use std::fs;
 use std::io;
 use std::io::prelude::*; // flush() at least
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 extern crate jsparagus_ast as ast;
 extern crate jsparagus_emitter as emitter;
@@ -13,7 +14,7 @@ extern crate jsparagus_parser as parser;
 
 use ast::types::{Program, Script};
 use bumpalo::Bump;
-use parser::{is_partial_script, parse_script, ParseOptions};
+use parser::{is_partial_script, parse_script, ParseOptions, ParseSession};
 
 use rustyline::error::ReadlineError;
 use rustyline::validate::{ValidationContext, ValidationResult, Validator};
@@ -27,6 +28,12 @@ pub struct DemoStats {
 
     /// Total size of all the files attempted, in bytes.
     total_bytes: u64,
+
+    /// Total time spent in the parser, not counting reading the files.
+    parse_time: Duration,
+
+    /// Total arena memory used by the parser, in bytes.
+    arena_bytes: u64,
 }
 
 impl DemoStats {
@@ -34,11 +41,18 @@ impl DemoStats {
         DemoStats::default()
     }
 
-    pub fn new_single(size_bytes: u64, success: bool) -> DemoStats {
+    pub fn new_single(
+        size_bytes: u64,
+        success: bool,
+        parse_time: Duration,
+        arena_bytes: u64,
+    ) -> DemoStats {
         DemoStats {
             files_attempted: 1,
             files_parsed: if success { 1 } else { 0 },
             total_bytes: size_bytes,
+            parse_time,
+            arena_bytes,
         }
     }
 
@@ -46,6 +60,16 @@ impl DemoStats {
         self.files_attempted += other.files_attempted;
         self.files_parsed += other.files_parsed;
         self.total_bytes += other.total_bytes;
+        self.parse_time += other.parse_time;
+        self.arena_bytes += other.arena_bytes;
+    }
+
+    /// Arena memory used per megabyte of source, in bytes.
+    pub fn arena_bytes_per_mb(&self) -> u64 {
+        if self.total_bytes == 0 {
+            return 0;
+        }
+        self.arena_bytes * (1 << 20) / self.total_bytes
     }
 }
 
@@ -53,41 +77,53 @@ impl DemoStats {
 ///
 /// Returns an Err only if opening or reading the file fails;
 /// parse errors are simply printed to stdout.
-fn parse_file(path: &Path, size_bytes: u64) -> io::Result<DemoStats> {
+fn parse_file(session: &mut ParseSession, path: &Path, size_bytes: u64) -> io::Result<DemoStats> {
     print!("{}:", path.display());
     io::stdout().flush()?;
     let contents = match fs::read_to_string(path) {
         Err(err) => {
             println!(" error reading file: {}", err);
-            return Ok(DemoStats::new_single(size_bytes, false));
+            return Ok(DemoStats::new_single(
+                size_bytes,
+                false,
+                Duration::default(),
+                0,
+            ));
         }
         Ok(s) => s,
     };
-    let allocator = &Bump::new();
+    session.reset();
     let options = ParseOptions::new();
-    let result = parse_script(allocator, &contents, &options);
-    let stats = DemoStats::new_single(size_bytes, result.is_ok());
+    let start = Instant::now();
+    let result = session.parse_script(contents.as_str(), &options);
+    let parse_time = start.elapsed();
+    let success = result.is_ok();
     match result {
         Ok(_ast) => println!(" ok"),
         Err(err) => println!(" error: {}", err.message()),
     }
-    Ok(stats)
+    Ok(DemoStats::new_single(
+        size_bytes,
+        success,
+        parse_time,
+        session.allocated_bytes() as u64,
+    ))
 }
 
 /// Try parsing all the files in a directory, recursively.
 ///
 /// Returns an Err only if reading a file or directory fails;
 /// parse errors are simply printed to stdout.
-fn parse_dir(path: &Path) -> io::Result<DemoStats> {
+fn parse_dir(session: &mut ParseSession, path: &Path) -> io::Result<DemoStats> {
     let mut summary = DemoStats::new();
     for entry_result in fs::read_dir(&path)? {
         let entry = entry_result?;
         let file = entry.path();
         let metadata = entry.metadata()?;
         let stats = if metadata.is_file() {
-            parse_file(&file, metadata.len())?
+            parse_file(session, &file, metadata.len())?
         } else if metadata.is_dir() {
-            parse_dir(&file)?
+            parse_dir(session, &file)?
         } else {
             DemoStats::new()
         };
@@ -103,13 +139,24 @@ fn parse_dir(path: &Path) -> io::Result<DemoStats> {
 pub fn parse_file_or_dir(filename: &impl AsRef<OsStr>) -> io::Result<DemoStats> {
     let path = Path::new(filename);
     let metadata = path.metadata()?;
+    // One arena for all the files, emptied before each one.
+    let mut session = ParseSession::new();
     if metadata.is_dir() {
-        parse_dir(path)
+        parse_dir(&mut session, path)
     } else {
         // No `if metadata.is_file()` here, we instead try opening it and let
         // that fail if this is some exotic filesystem thingy. That way the
         // user gets an error message.
-        parse_file(Path::new(filename), metadata.len())
+        parse_file(&mut session, Path::new(filename), metadata.len())
+    }
+}
+
+/// Print the size of each AST node type, largest first.
+pub fn print_node_sizes() {
+    let mut sizes = ast::size_report::NODE_SIZES.to_vec();
+    sizes.sort_by(|(name_a, size_a), (name_b, size_b)| size_b.cmp(size_a).then(name_a.cmp(name_b)));
+    for (name, size) in sizes {
+        println!("{:>5} {}", size, name);
     }
 }
 
@@ -162,7 +209,7 @@ pub fn read_print_loop() {
         rl.add_history_entry(input.as_str());
 
         let allocator = &Bump::new();
-        let script = parse_script(allocator, &input, &ParseOptions::new());
+        let script = parse_script(allocator, input.as_str(), &ParseOptions::new());
         match script {
             Err(err) => {
                 eprintln!("error: {}", err);
@@ -14,14 +14,19 @@ fn main() {
     let args: Vec<String> = env::args().collect();
     match args.len() {
         1 => demo::read_print_loop(),
+        2 if args[1] == "--sizes" => demo::print_node_sizes(),
         2 => match demo::parse_file_or_dir(&args[1]) {
             Ok(stats) => {
                 println!("{:#?}", stats);
+                println!(
+                    "arena bytes per MB of source: {}",
+                    stats.arena_bytes_per_mb()
+                );
             }
             Err(err) => {
                 eprintln!("{}", err);
             }
         },
-        _ => eprintln!("usage: parser [FILE/DIR]"),
+        _ => eprintln!("usage: parser [FILE/DIR | --sizes]"),
     }
 }
@@ -11,21 +11,42 @@ pub fn emit_program(ast: &Program, options: &EmitOptions) -> Result<EmitResult,
     let mut emitter = AstEmitter {
         emit: InstructionWriter::new(),
         options,
+        depth: 0,
     };
 
     match ast {
-        Program::Script(script) => emitter.emit_script(script)?,
-        _ => {
-            return Err(EmitError::NotImplemented("TODO: modules"));
+        Program::Script(script) => {
+            emitter.emit_script(script)?;
+            Ok(emitter.emit.into_emit_result())
+        }
+        Program::Module(module) => {
+            emitter.emit_module(module)?;
+
+            let mut result = emitter.emit.into_emit_result();
+            // Module code is always strict mode code.
+            result.strict = true;
+            result.is_module = true;
+            result.has_module_goal = true;
+            result.is_async = module.is_async;
+            if module.is_async {
+                result.max_fixed_slots = 1;
+            }
+            Ok(result)
         }
     }
-
-    Ok(emitter.emit.into_emit_result())
 }
 
+// The fixed slot that holds the generator object of an async module, which
+// is suspended by each `await`.
+const GENERATOR_SLOT: u32 = 0;
+
 struct AstEmitter<'alloc> {
     emit: InstructionWriter,
     options: &'alloc EmitOptions,
+
+    // The number of statements and expressions being emitted, which is the
+    // depth of the recursion of emit_statement and emit_expression.
+    depth: usize,
 }
 
 impl<'alloc> AstEmitter<'alloc> {
@@ -38,7 +59,48 @@ impl<'alloc> AstEmitter<'alloc> {
         Ok(())
     }
 
+    fn emit_module(&mut self, ast: &Module) -> Result<(), EmitError> {
+        if ast.is_async {
+            self.emit.generator();
+            self.emit.set_local(GENERATOR_SLOT);
+            self.emit.pop();
+        }
+        for item in &ast.items {
+            match item {
+                ModuleItems::ImportDeclaration(_) => {
+                    return Err(EmitError::NotImplemented("TODO: ImportDeclaration"));
+                }
+                ModuleItems::ExportDeclaration(_) => {
+                    return Err(EmitError::NotImplemented("TODO: ExportDeclaration"));
+                }
+                ModuleItems::Statement(statement) => {
+                    self.emit_statement(statement)?;
+                }
+            }
+        }
+        self.emit.ret_rval();
+
+        Ok(())
+    }
+
+    fn enter_nested(&mut self) -> Result<(), EmitError> {
+        if let Some(max_nesting_depth) = self.options.max_nesting_depth {
+            if self.depth >= max_nesting_depth {
+                return Err(EmitError::TooDeeplyNested);
+            }
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
     fn emit_statement(&mut self, ast: &Statement) -> Result<(), EmitError> {
+        self.enter_nested()?;
+        let result = self.emit_statement_kind(ast);
+        self.depth -= 1;
+        result
+    }
+
+    fn emit_statement_kind(&mut self, ast: &Statement) -> Result<(), EmitError> {
         match ast {
             Statement::ClassDeclaration(_) => {
                 return Err(EmitError::NotImplemented("TODO: ClassDeclaration"));
@@ -73,6 +135,9 @@ impl<'alloc> AstEmitter<'alloc> {
             Statement::ForOfStatement { .. } => {
                 return Err(EmitError::NotImplemented("TODO: ForOfStatement"));
             }
+            Statement::ForAwaitOfStatement { .. } => {
+                return Err(EmitError::NotImplemented("TODO: ForAwaitOfStatement"));
+            }
             Statement::ForStatement { .. } => {
                 return Err(EmitError::NotImplemented("TODO: ForStatement"));
             }
@@ -156,6 +221,13 @@ impl<'alloc> AstEmitter<'alloc> {
     }
 
     fn emit_expression(&mut self, ast: &Expression) -> Result<(), EmitError> {
+        self.enter_nested()?;
+        let result = self.emit_expression_kind(ast);
+        self.depth -= 1;
+        result
+    }
+
+    fn emit_expression_kind(&mut self, ast: &Expression) -> Result<(), EmitError> {
         match ast {
             Expression::MemberExpression(MemberExpression::ComputedMemberExpression(
                 ComputedMemberExpression {
@@ -240,6 +312,9 @@ impl<'alloc> AstEmitter<'alloc> {
             }
 
             Expression::LiteralStringExpression { value, .. } => {
+                let value = value.as_str().ok_or(EmitError::NotImplemented(
+                    "TODO: string literals with unpaired surrogates",
+                ))?;
                 let str_index = self.emit.get_atom_index(value);
                 self.emit.string(str_index);
             }
@@ -281,6 +356,15 @@ impl<'alloc> AstEmitter<'alloc> {
                 ));
             }
 
+            Expression::LogicalAssignmentExpression {
+                operator,
+                binding,
+                expression,
+                ..
+            } => {
+                self.emit_logical_assignment_expression(operator, binding, expression)?;
+            }
+
             Expression::ConditionalExpression {
                 test,
                 consequent,
@@ -308,16 +392,22 @@ impl<'alloc> AstEmitter<'alloc> {
                 return Err(EmitError::NotImplemented("TODO: NewTargetExpression"));
             }
 
+            Expression::ImportMetaExpression { .. } => {
+                self.emit.import_meta();
+            }
+
             Expression::ObjectExpression(ast) => {
                 self.emit_object_expression(ast)?;
             }
 
             Expression::OptionalChain { .. } => {
+                // OptionalChain appears only as the tail of
+                // OptionalExpression, and is handled there.
                 return Err(EmitError::NotImplemented("TODO: OptionalChain"));
             }
 
-            Expression::OptionalExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: OptionalExpression"));
+            Expression::OptionalExpression { object, tail, .. } => {
+                self.emit_optional_expression(object, tail)?;
             }
 
             Expression::UnaryExpression {
@@ -360,12 +450,20 @@ impl<'alloc> AstEmitter<'alloc> {
                 return Err(EmitError::NotImplemented("TODO: YieldGeneratorExpression"));
             }
 
-            Expression::AwaitExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: AwaitExpression"));
+            Expression::AwaitExpression { expression, .. } => {
+                // Functions aren't emitted yet, so this is a top-level
+                // `await` in a module.
+                self.emit_expression(expression)?;
+                self.emit.get_local(GENERATOR_SLOT);
+                let resume_index = self.emit.new_resume_index();
+                self.emit.await_(resume_index);
+                self.emit.after_yield();
+                self.emit.check_resume_kind();
             }
 
-            Expression::ImportCallExpression { .. } => {
-                return Err(EmitError::NotImplemented("TODO: ImportCallExpression"));
+            Expression::ImportCallExpression { argument, .. } => {
+                self.emit_expression(argument)?;
+                self.emit.dynamic_import();
             }
         }
 
@@ -468,6 +566,176 @@ impl<'alloc> AstEmitter<'alloc> {
         return Ok(());
     }
 
+    fn emit_logical_assignment_expression(
+        &mut self,
+        operator: &LogicalAssignmentOperator,
+        binding: &SimpleAssignmentTarget,
+        expression: &Expression,
+    ) -> Result<(), EmitError> {
+        // The assignment is only performed (and the right hand side only
+        // evaluated) when the short-circuit jump is not taken. In both cases
+        // the value of the whole expression is left on the stack.
+        let mut jumplist: Vec<BytecodeOffset> = Vec::with_capacity(1);
+
+        match binding {
+            SimpleAssignmentTarget::AssignmentTargetIdentifier(AssignmentTargetIdentifier {
+                name,
+                ..
+            }) => {
+                let name_index = self.emit.get_atom_index(name.value);
+                self.emit.get_g_name(name_index);
+                // [stack] LHS
+
+                self.emit_logical_assignment_jump(operator, &mut jumplist);
+                // [stack] LHS
+
+                self.emit.jump_target();
+                self.emit.pop();
+                // [stack]
+
+                self.emit.bind_g_name(name_index);
+                // [stack] ENV
+
+                self.emit_expression(expression)?;
+                // [stack] ENV RHS
+
+                self.emit.set_g_name(name_index);
+                // [stack] RHS
+
+                self.emit_jump_target(jumplist);
+                // [stack] RESULT
+            }
+
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::StaticMemberAssignmentTarget(
+                    StaticMemberAssignmentTarget {
+                        object: ExpressionOrSuper::Expression(object),
+                        property,
+                        ..
+                    },
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                // [stack] OBJ
+
+                self.emit.dup();
+                // [stack] OBJ OBJ
+
+                let name_index = self.emit.get_atom_index(property.value);
+                self.emit.get_prop(name_index);
+                // [stack] OBJ LHS
+
+                self.emit_logical_assignment_jump(operator, &mut jumplist);
+                // [stack] OBJ LHS
+
+                self.emit.jump_target();
+                self.emit.pop();
+                // [stack] OBJ
+
+                self.emit_expression(expression)?;
+                // [stack] OBJ RHS
+
+                self.emit.set_prop(name_index);
+                // [stack] RHS
+
+                let offset_final = self.emit.bytecode_offset();
+                self.emit.goto(0);
+
+                // Short-circuit branch
+                self.emit_jump_target(jumplist);
+                // [stack] OBJ LHS
+
+                self.emit.swap();
+                self.emit.pop();
+                // [stack] LHS
+
+                self.emit_jump_target(vec![offset_final]);
+                // [stack] RESULT
+            }
+
+            SimpleAssignmentTarget::MemberAssignmentTarget(
+                MemberAssignmentTarget::ComputedMemberAssignmentTarget(
+                    ComputedMemberAssignmentTarget {
+                        object: ExpressionOrSuper::Expression(object),
+                        expression: key,
+                        ..
+                    },
+                ),
+            ) => {
+                self.emit_expression(object)?;
+                self.emit_expression(key)?;
+                // [stack] OBJ KEY
+
+                self.emit.dup2();
+                // [stack] OBJ KEY OBJ KEY
+
+                self.emit.get_elem();
+                // [stack] OBJ KEY LHS
+
+                self.emit_logical_assignment_jump(operator, &mut jumplist);
+                // [stack] OBJ KEY LHS
+
+                self.emit.jump_target();
+                self.emit.pop();
+                // [stack] OBJ KEY
+
+                self.emit_expression(expression)?;
+                // [stack] OBJ KEY RHS
+
+                self.emit.set_elem();
+                // [stack] RHS
+
+                let offset_final = self.emit.bytecode_offset();
+                self.emit.goto(0);
+
+                // Short-circuit branch
+                self.emit_jump_target(jumplist);
+                // [stack] OBJ KEY LHS
+
+                self.emit.unpick(2);
+                // [stack] LHS OBJ KEY
+
+                self.emit.pop_n(2);
+                // [stack] LHS
+
+                self.emit_jump_target(vec![offset_final]);
+                // [stack] RESULT
+            }
+
+            _ => {
+                return Err(EmitError::NotImplemented(
+                    "TODO: LogicalAssignmentExpression (super)",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn emit_logical_assignment_jump(
+        &mut self,
+        operator: &LogicalAssignmentOperator,
+        jumplist: &mut Vec<BytecodeOffset>,
+    ) {
+        let offset: BytecodeOffset = self.emit.bytecode_offset();
+        jumplist.push(offset);
+
+        // Placeholder offset, patched later in patch_jump_target (see
+        // emit_jump).
+        let placeholder_offset: i32 = 0;
+        match operator {
+            LogicalAssignmentOperator::Coalesce { .. } => {
+                self.emit.coalesce(placeholder_offset);
+            }
+            LogicalAssignmentOperator::Or { .. } => {
+                self.emit.or(placeholder_offset);
+            }
+            LogicalAssignmentOperator::And { .. } => {
+                self.emit.and(placeholder_offset);
+            }
+        }
+    }
+
     fn emit_jump_target(&mut self, jumplist: Vec<BytecodeOffset>) {
         self.emit.patch_jump_target(jumplist);
         self.emit.jump_target();
@@ -518,6 +786,10 @@ impl<'alloc> AstEmitter<'alloc> {
                     }
                 }
             }
+            ObjectProperty::ProtoProperty(ProtoProperty { expression, .. }) => {
+                self.emit_expression(expression)?;
+                self.emit.mutate_proto();
+            }
             _ => return Err(EmitError::NotImplemented("TODO: non data property")),
         }
 
@@ -712,4 +984,354 @@ impl<'alloc> AstEmitter<'alloc> {
 
         Ok(())
     }
+
+    fn emit_optional_expression(
+        &mut self,
+        object: &ExpressionOrSuper,
+        tail: &Expression,
+    ) -> Result<(), EmitError> {
+        // Each `?.` in the chain short-circuits the rest of the chain,
+        // including the enclosing OptionalExpressions: the whole chain
+        // evaluates to undefined.
+        let mut chain = OptionalChainState {
+            initial_stack_depth: self.emit.stack_depth(),
+            short_circuit_jumps: Vec::new(),
+        };
+
+        self.emit_optional_chain(object, tail, &mut chain)?;
+        // [stack] RESULT
+
+        self.emit_jump_target(chain.short_circuit_jumps);
+        // [stack] RESULT
+
+        Ok(())
+    }
+
+    // Emit the OptionalChain `tail`, whose leftmost `?.` applies to `object`.
+    fn emit_optional_chain(
+        &mut self,
+        object: &ExpressionOrSuper,
+        tail: &Expression,
+        chain: &mut OptionalChainState,
+    ) -> Result<(), EmitError> {
+        match tail {
+            Expression::OptionalChain(OptionalChain::StaticMemberExpressionTail {
+                property,
+                ..
+            }) => {
+                self.emit_optional_object(object, chain)?;
+                self.emit_optional_short_circuit(1, chain);
+                // [stack] OBJ
+
+                let name_index = self.emit.get_atom_index(property.value);
+                self.emit.get_prop(name_index);
+                // [stack] VALUE
+            }
+
+            Expression::OptionalChain(OptionalChain::ComputedMemberExpressionTail {
+                expression,
+                ..
+            }) => {
+                self.emit_optional_object(object, chain)?;
+                self.emit_optional_short_circuit(1, chain);
+                // [stack] OBJ
+
+                self.emit_expression(expression)?;
+                // [stack] OBJ KEY
+
+                self.emit.get_elem();
+                // [stack] VALUE
+            }
+
+            Expression::OptionalChain(OptionalChain::CallExpressionTail { arguments, .. }) => {
+                self.emit_optional_callee_object(object, chain)?;
+                // [stack] THIS CALLEE
+
+                self.emit_optional_short_circuit(2, chain);
+                // [stack] THIS CALLEE
+
+                self.emit.swap();
+                // [stack] CALLEE THIS
+
+                self.emit_arguments(arguments)?;
+                self.emit.call(arguments.args.len() as u16);
+                // [stack] RVAL
+            }
+
+            Expression::OptionalChain(OptionalChain::StaticMemberExpression(
+                StaticMemberExpression {
+                    object: ExpressionOrSuper::Expression(inner),
+                    property,
+                    ..
+                },
+            )) => {
+                self.emit_optional_chain(object, inner, chain)?;
+                // [stack] OBJ
+
+                let name_index = self.emit.get_atom_index(property.value);
+                self.emit.get_prop(name_index);
+                // [stack] VALUE
+            }
+
+            Expression::OptionalChain(OptionalChain::ComputedMemberExpression(
+                ComputedMemberExpression {
+                    object: ExpressionOrSuper::Expression(inner),
+                    expression,
+                    ..
+                },
+            )) => {
+                self.emit_optional_chain(object, inner, chain)?;
+                // [stack] OBJ
+
+                self.emit_expression(expression)?;
+                // [stack] OBJ KEY
+
+                self.emit.get_elem();
+                // [stack] VALUE
+            }
+
+            Expression::OptionalChain(OptionalChain::CallExpression(CallExpression {
+                callee: ExpressionOrSuper::Expression(inner),
+                arguments,
+                ..
+            })) => {
+                self.emit_optional_callee(object, inner, chain)?;
+                // [stack] THIS CALLEE
+
+                self.emit.swap();
+                // [stack] CALLEE THIS
+
+                self.emit_arguments(arguments)?;
+                self.emit.call(arguments.args.len() as u16);
+                // [stack] RVAL
+            }
+
+            _ => {
+                return Err(EmitError::NotImplemented("TODO: OptionalChain (super)"));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Emit the OptionalChain `tail` as the callee of a call, leaving the
+    // `this` value for the call below it on the stack.
+    fn emit_optional_callee(
+        &mut self,
+        object: &ExpressionOrSuper,
+        tail: &Expression,
+        chain: &mut OptionalChainState,
+    ) -> Result<(), EmitError> {
+        match tail {
+            Expression::OptionalChain(OptionalChain::StaticMemberExpressionTail {
+                property,
+                ..
+            }) => {
+                self.emit_optional_object(object, chain)?;
+                self.emit_optional_short_circuit(1, chain);
+                // [stack] OBJ
+
+                self.emit_this_and_call_prop(property);
+                // [stack] THIS CALLEE
+            }
+
+            Expression::OptionalChain(OptionalChain::ComputedMemberExpressionTail {
+                expression,
+                ..
+            }) => {
+                self.emit_optional_object(object, chain)?;
+                self.emit_optional_short_circuit(1, chain);
+                // [stack] OBJ
+
+                self.emit_this_and_call_elem(expression)?;
+                // [stack] THIS CALLEE
+            }
+
+            Expression::OptionalChain(OptionalChain::StaticMemberExpression(
+                StaticMemberExpression {
+                    object: ExpressionOrSuper::Expression(inner),
+                    property,
+                    ..
+                },
+            )) => {
+                self.emit_optional_chain(object, inner, chain)?;
+                // [stack] OBJ
+
+                self.emit_this_and_call_prop(property);
+                // [stack] THIS CALLEE
+            }
+
+            Expression::OptionalChain(OptionalChain::ComputedMemberExpression(
+                ComputedMemberExpression {
+                    object: ExpressionOrSuper::Expression(inner),
+                    expression,
+                    ..
+                },
+            )) => {
+                self.emit_optional_chain(object, inner, chain)?;
+                // [stack] OBJ
+
+                self.emit_this_and_call_elem(expression)?;
+                // [stack] THIS CALLEE
+            }
+
+            _ => {
+                self.emit_optional_chain(object, tail, chain)?;
+                // [stack] CALLEE
+
+                self.emit.undefined();
+                self.emit.swap();
+                // [stack] THIS CALLEE
+            }
+        }
+
+        Ok(())
+    }
+
+    // Emit the object of an OptionalExpression. If it's another
+    // OptionalExpression, its short-circuiting covers this chain too.
+    fn emit_optional_object(
+        &mut self,
+        object: &ExpressionOrSuper,
+        chain: &mut OptionalChainState,
+    ) -> Result<(), EmitError> {
+        match object {
+            ExpressionOrSuper::Expression(expr) => match &**expr {
+                Expression::OptionalExpression { object, tail, .. } => {
+                    self.emit_optional_chain(object, tail, chain)?;
+                }
+                _ => {
+                    self.emit_expression(expr)?;
+                }
+            },
+            ExpressionOrSuper::Super { .. } => {
+                return Err(EmitError::NotImplemented("TODO: Super"));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Emit the object of an OptionalExpression whose tail is `?.` Arguments,
+    // leaving the `this` value for the call and the callee on the stack.
+    fn emit_optional_callee_object(
+        &mut self,
+        object: &ExpressionOrSuper,
+        chain: &mut OptionalChainState,
+    ) -> Result<(), EmitError> {
+        let expr = match object {
+            ExpressionOrSuper::Expression(expr) => expr,
+            ExpressionOrSuper::Super { .. } => {
+                return Err(EmitError::NotImplemented("TODO: Super"));
+            }
+        };
+
+        match &**expr {
+            Expression::OptionalExpression { object, tail, .. } => {
+                self.emit_optional_callee(object, tail, chain)?;
+            }
+
+            Expression::IdentifierExpression(IdentifierExpression { name, .. }) => {
+                let name_index = self.emit.get_atom_index(name.value);
+                self.emit.g_implicit_this(name_index);
+                self.emit_expression(expr)?;
+            }
+
+            Expression::MemberExpression(MemberExpression::StaticMemberExpression(
+                StaticMemberExpression {
+                    object: ExpressionOrSuper::Expression(object),
+                    property,
+                    ..
+                },
+            )) => {
+                self.emit_expression(object)?;
+                self.emit_this_and_call_prop(property);
+            }
+
+            Expression::MemberExpression(MemberExpression::ComputedMemberExpression(
+                ComputedMemberExpression {
+                    object: ExpressionOrSuper::Expression(object),
+                    expression,
+                    ..
+                },
+            )) => {
+                self.emit_expression(object)?;
+                self.emit_this_and_call_elem(expression)?;
+            }
+
+            _ => {
+                self.emit.undefined();
+                self.emit_expression(expr)?;
+            }
+        }
+        // [stack] THIS CALLEE
+
+        Ok(())
+    }
+
+    fn emit_this_and_call_prop(&mut self, property: &IdentifierName) {
+        // [stack] OBJ
+
+        self.emit.dup();
+        // [stack] OBJ OBJ
+
+        let name_index = self.emit.get_atom_index(property.value);
+        self.emit.call_prop(name_index);
+        // [stack] OBJ/THIS CALLEE
+    }
+
+    fn emit_this_and_call_elem(&mut self, expression: &Expression) -> Result<(), EmitError> {
+        // [stack] OBJ
+
+        self.emit.dup();
+        // [stack] OBJ OBJ
+
+        self.emit_expression(expression)?;
+        // [stack] OBJ OBJ KEY
+
+        self.emit.call_elem();
+        // [stack] OBJ/THIS CALLEE
+
+        Ok(())
+    }
+
+    // Short-circuit the chain if the value on the top of the stack is null or
+    // undefined. `num_values` is the number of values the chain has pushed to
+    // the stack so far, including that one.
+    fn emit_optional_short_circuit(&mut self, num_values: usize, chain: &mut OptionalChainState) {
+        // [stack] ... VALUE
+
+        let offset_continue = self.emit.bytecode_offset();
+        self.emit.coalesce(0);
+        // [stack] ... VALUE
+
+        // Short-circuit branch
+        self.emit.jump_target();
+        if num_values == 1 {
+            self.emit.pop();
+        } else {
+            self.emit.pop_n(num_values as u16);
+        }
+        // [stack]
+
+        self.emit.undefined();
+        // [stack] UNDEFINED
+
+        chain.short_circuit_jumps.push(self.emit.bytecode_offset());
+        self.emit.goto(0);
+
+        self.emit_jump_target(vec![offset_continue]);
+        self.emit
+            .set_stack_depth(chain.initial_stack_depth + num_values);
+        // [stack] ... VALUE
+    }
+}
+
+// The state of the OptionalExpression being emitted.
+struct OptionalChainState {
+    // The stack depth before the chain.
+    initial_stack_depth: usize,
+
+    // The jumps to the end of the chain, taken when it's short-circuited.
+    short_circuit_jumps: Vec<BytecodeOffset>,
 }
@@ -59,20 +59,33 @@ pub struct InstructionWriter {
 
     /// Number of instructions in this script that have JOF_TYPESET.
     num_type_sets: usize,
+
+    /// Offsets where execution continues after each Yield or Await, indexed
+    /// by their resume index.
+    resume_offsets: Vec<usize>,
 }
 
 #[derive(Debug)]
 pub struct EmitOptions {
     pub no_script_rval: bool,
+
+    // The maximum number of nested statements and expressions. The emitter
+    // is recursive, so deeper ASTs are rejected with
+    // EmitError::TooDeeplyNested instead of overflowing the stack.
+    pub max_nesting_depth: Option<usize>,
 }
 impl EmitOptions {
     pub fn new() -> Self {
         Self {
             no_script_rval: false,
+            max_nesting_depth: Some(DEFAULT_MAX_NESTING_DEPTH),
         }
     }
 }
 
+/// The default `EmitOptions::max_nesting_depth`.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 1000;
+
 /// The output of bytecode-compiling a script or module.
 #[derive(Debug)]
 pub struct EmitResult {
@@ -89,6 +102,7 @@ pub struct EmitResult {
     pub body_scope_index: u32,
     pub num_ic_entries: u32,
     pub num_type_sets: u32,
+    pub resume_offsets: Vec<usize>,
 
     pub strict: bool,
     pub bindings_accessed_dynamically: bool,
@@ -96,6 +110,7 @@ pub struct EmitResult {
     pub is_for_eval: bool,
     pub is_module: bool,
     pub is_function: bool,
+    pub is_async: bool,
     pub has_non_syntactic_scope: bool,
     pub needs_function_environment_objects: bool,
     pub has_module_goal: bool,
@@ -105,12 +120,14 @@ pub struct EmitResult {
 #[derive(Clone, Debug)]
 pub enum EmitError {
     NotImplemented(&'static str),
+    TooDeeplyNested,
 }
 
 impl fmt::Display for EmitError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             EmitError::NotImplemented(message) => write!(f, "not implemented: {}", message),
+            EmitError::TooDeeplyNested => write!(f, "too many nested expressions or statements"),
         }
     }
 }
@@ -124,6 +141,7 @@ impl InstructionWriter {
             maximum_stack_depth: 0,
             num_ic_entries: 0,
             num_type_sets: 0,
+            resume_offsets: Vec::new(),
         }
     }
 
@@ -144,6 +162,7 @@ impl InstructionWriter {
             body_scope_index: 0,
             num_ic_entries: self.num_ic_entries.try_into().unwrap(),
             num_type_sets: self.num_type_sets.try_into().unwrap(),
+            resume_offsets: self.resume_offsets,
 
             strict: false,
             bindings_accessed_dynamically: false,
@@ -151,6 +170,7 @@ impl InstructionWriter {
             is_for_eval: false,
             is_module: false,
             is_function: false,
+            is_async: false,
             has_non_syntactic_scope: false,
             needs_function_environment_objects: false,
             has_module_goal: false,
@@ -770,6 +790,15 @@ impl InstructionWriter {
         self.emit_u24(Opcode::Await, resume_index);
     }
 
+    /// Allocate a resume index for a Yield or Await emitted next. Execution
+    /// resumes right after that instruction.
+    pub fn new_resume_index(&mut self) -> u24 {
+        let resume_index = self.resume_offsets.len().try_into().unwrap();
+        // Yield and Await take an opcode byte and a u24 operand.
+        self.resume_offsets.push(self.bytecode.len() + 4);
+        resume_index
+    }
+
     pub fn try_skip_await(&mut self) {
         self.emit1(Opcode::TrySkipAwait);
     }
@@ -778,10 +807,25 @@ impl InstructionWriter {
         self.emit_u8(Opcode::Resume, kind as u8);
     }
 
+    pub fn check_resume_kind(&mut self) {
+        self.emit1(Opcode::CheckResumeKind);
+    }
+
     pub fn jump_target(&mut self) {
         self.emit_op(Opcode::JumpTarget);
     }
 
+    /// Stack depth after the instructions emitted so far.
+    pub fn stack_depth(&self) -> usize {
+        self.stack_depth
+    }
+
+    /// Set the stack depth at a jump target that isn't reached by falling
+    /// through from the previous instruction.
+    pub fn set_stack_depth(&mut self, depth: usize) {
+        self.stack_depth = depth;
+    }
+
     pub fn bytecode_offset(&mut self) -> BytecodeOffset {
         BytecodeOffset {
             offset: self.bytecode.len(),
@@ -6,7 +6,7 @@ pub mod opcode;
 
 extern crate jsparagus_ast as ast;
 
-pub use crate::emitter::{EmitError, EmitOptions, EmitResult};
+pub use crate::emitter::{EmitError, EmitOptions, EmitResult, DEFAULT_MAX_NESTING_DEPTH};
 pub use dis::dis;
 
 pub fn emit(ast: &mut ast::types::Program, options: &EmitOptions) -> Result<EmitResult, EmitError> {
@@ -18,11 +18,11 @@ pub fn emit(ast: &mut ast::types::Program, options: &EmitOptions) -> Result<Emit
 mod tests {
     extern crate jsparagus_parser as parser;
 
-    use super::{emit, EmitOptions};
+    use super::{emit, EmitError, EmitOptions, EmitResult};
     use crate::dis::*;
     use crate::opcode::*;
     use bumpalo::Bump;
-    use parser::{parse_script, ParseOptions};
+    use parser::{parse_module, parse_script, ParseOptions};
 
     fn bytecode(source: &str) -> Vec<u8> {
         let alloc = &Bump::new();
@@ -41,6 +41,16 @@ mod tests {
         bc
     }
 
+    fn emit_module(source: &str) -> Result<EmitResult, EmitError> {
+        let alloc = &Bump::new();
+        let parse_options = ParseOptions::new();
+        let parse_result = parse_module(alloc, source, &parse_options).expect("Failed to parse");
+        emit(
+            &mut ast::types::Program::Module(parse_result.unbox()),
+            &EmitOptions::new(),
+        )
+    }
+
     #[test]
     fn it_works() {
         assert_eq!(
@@ -108,4 +118,334 @@ mod tests {
         //    ]
         //);
     }
+
+    #[test]
+    fn logical_assignment() {
+        assert_eq!(
+            bytecode("a ||= 1"),
+            vec![
+                Opcode::GetGName as u8,
+                0,
+                0,
+                0,
+                0,
+                Opcode::Or as u8,
+                23,
+                0,
+                0,
+                0,
+                Opcode::JumpTarget as u8,
+                2,
+                0,
+                0,
+                0,
+                Opcode::Pop as u8,
+                Opcode::BindGName as u8,
+                0,
+                0,
+                0,
+                0,
+                Opcode::Int8 as u8,
+                1,
+                Opcode::SetGName as u8,
+                0,
+                0,
+                0,
+                0,
+                Opcode::JumpTarget as u8,
+                4,
+                0,
+                0,
+                0,
+                Opcode::SetRval as u8,
+                Opcode::RetRval as u8,
+            ]
+        );
+    }
+
+    #[test]
+    fn proto_property() {
+        assert_eq!(
+            bytecode("({__proto__: null})"),
+            vec![
+                Opcode::NewInit as u8,
+                0,
+                0,
+                0,
+                0,
+                Opcode::Null as u8,
+                Opcode::MutateProto as u8,
+                Opcode::SetRval as u8,
+                Opcode::RetRval as u8,
+            ]
+        );
+    }
+
+    #[test]
+    fn dynamic_import() {
+        assert_eq!(
+            bytecode("import(x)"),
+            vec![
+                Opcode::GetGName as u8,
+                0,
+                0,
+                0,
+                0,
+                Opcode::DynamicImport as u8,
+                Opcode::SetRval as u8,
+                Opcode::RetRval as u8,
+            ]
+        );
+    }
+
+    #[test]
+    fn module() {
+        let result = emit_module("1").expect("Should work!");
+        assert_eq!(
+            result.bytecode,
+            vec![
+                Opcode::Int8 as u8,
+                1,
+                Opcode::SetRval as u8,
+                Opcode::RetRval as u8,
+            ]
+        );
+        assert!(result.is_module);
+        assert!(result.strict);
+        assert!(!result.is_async);
+        assert_eq!(result.max_fixed_slots, 0);
+    }
+
+    #[test]
+    fn top_level_await() {
+        let result = emit_module("await 1").expect("Should work!");
+        assert!(result.is_module);
+        assert!(result.is_async);
+        assert_eq!(result.max_fixed_slots, 1);
+        assert_eq!(
+            dis(&result.bytecode),
+            concat!(
+                "Generator\n",
+                "SetLocal 0 0 0\n",
+                "Pop\n",
+                "Int8 1\n",
+                "GetLocal 0 0 0\n",
+                "Await 0 0 0\n",
+                "AfterYield 0 0 0 0\n",
+                "CheckResumeKind\n",
+                "SetRval\n",
+                "RetRval\n",
+            )
+        );
+        assert_eq!(result.resume_offsets, vec![16]);
+        assert_eq!(result.bytecode[16], Opcode::AfterYield as u8);
+    }
+
+    #[test]
+    fn optional_member() {
+        assert_eq!(
+            dis(&bytecode("a?.b")),
+            concat!(
+                "GetGName 0 0 0 0\n",
+                "Coalesce 17 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "Pop\n",
+                "Undefined\n",
+                "Goto 15 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "GetProp 1 0 0 0\n",
+                "JumpTarget 2 0 0 0\n",
+                "SetRval\n",
+                "RetRval\n",
+            )
+        );
+
+        assert_eq!(
+            dis(&bytecode("a?.[k]")),
+            concat!(
+                "GetGName 0 0 0 0\n",
+                "Coalesce 17 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "Pop\n",
+                "Undefined\n",
+                "Goto 16 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "GetGName 1 0 0 0\n",
+                "GetElem\n",
+                "JumpTarget 3 0 0 0\n",
+                "SetRval\n",
+                "RetRval\n",
+            )
+        );
+    }
+
+    #[test]
+    fn optional_call() {
+        // The callee is checked, and `a` is passed as `this`.
+        assert_eq!(
+            dis(&bytecode("a.b?.()")),
+            concat!(
+                "GetGName 0 0 0 0\n",
+                "Dup\n",
+                "CallProp 1 0 0 0\n",
+                "Coalesce 19 0 0 0\n",
+                "JumpTarget 2 0 0 0\n",
+                "PopN 2 0\n",
+                "Undefined\n",
+                "Goto 14 0 0 0\n",
+                "JumpTarget 2 0 0 0\n",
+                "Swap\n",
+                "Call 0 0\n",
+                "JumpTarget 3 0 0 0\n",
+                "SetRval\n",
+                "RetRval\n",
+            )
+        );
+
+        assert_eq!(
+            dis(&bytecode("f?.()")),
+            concat!(
+                "GImplicitThis 0 0 0 0\n",
+                "GetGName 0 0 0 0\n",
+                "Coalesce 19 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "PopN 2 0\n",
+                "Undefined\n",
+                "Goto 14 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "Swap\n",
+                "Call 0 0\n",
+                "JumpTarget 2 0 0 0\n",
+                "SetRval\n",
+                "RetRval\n",
+            )
+        );
+
+        assert_eq!(
+            dis(&bytecode("a?.b()")),
+            concat!(
+                "GetGName 0 0 0 0\n",
+                "Coalesce 17 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "Pop\n",
+                "Undefined\n",
+                "Goto 20 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "Dup\n",
+                "CallProp 1 0 0 0\n",
+                "Swap\n",
+                "Call 0 0\n",
+                "JumpTarget 3 0 0 0\n",
+                "SetRval\n",
+                "RetRval\n",
+            )
+        );
+    }
+
+    #[test]
+    fn optional_chain() {
+        // Each `?.` short-circuits the rest of the chain.
+        assert_eq!(
+            dis(&bytecode("a?.b.c(1)?.[k]")),
+            concat!(
+                "GetGName 0 0 0 0\n",
+                "Coalesce 17 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "Pop\n",
+                "Undefined\n",
+                "Goto 55 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "GetProp 1 0 0 0\n",
+                "Dup\n",
+                "CallProp 2 0 0 0\n",
+                "Swap\n",
+                "Int8 1\n",
+                "Call 1 0\n",
+                "Coalesce 17 0 0 0\n",
+                "JumpTarget 4 0 0 0\n",
+                "Pop\n",
+                "Undefined\n",
+                "Goto 16 0 0 0\n",
+                "JumpTarget 4 0 0 0\n",
+                "GetGName 3 0 0 0\n",
+                "GetElem\n",
+                "JumpTarget 6 0 0 0\n",
+                "SetRval\n",
+                "RetRval\n",
+            )
+        );
+
+        assert_eq!(
+            dis(&bytecode("a?.b?.c")),
+            concat!(
+                "GetGName 0 0 0 0\n",
+                "Coalesce 17 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "Pop\n",
+                "Undefined\n",
+                "Goto 42 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "GetProp 1 0 0 0\n",
+                "Coalesce 17 0 0 0\n",
+                "JumpTarget 2 0 0 0\n",
+                "Pop\n",
+                "Undefined\n",
+                "Goto 15 0 0 0\n",
+                "JumpTarget 2 0 0 0\n",
+                "GetProp 2 0 0 0\n",
+                "JumpTarget 3 0 0 0\n",
+                "SetRval\n",
+                "RetRval\n",
+            )
+        );
+
+        // The parentheses end the chain.
+        assert_eq!(
+            dis(&bytecode("(a?.b).c")),
+            concat!(
+                "GetGName 0 0 0 0\n",
+                "Coalesce 17 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "Pop\n",
+                "Undefined\n",
+                "Goto 15 0 0 0\n",
+                "JumpTarget 1 0 0 0\n",
+                "GetProp 1 0 0 0\n",
+                "JumpTarget 2 0 0 0\n",
+                "GetProp 2 0 0 0\n",
+                "SetRval\n",
+                "RetRval\n",
+            )
+        );
+    }
+
+    #[test]
+    fn nesting_depth() {
+        let alloc = &Bump::new();
+        let mut options = EmitOptions::new();
+        options.max_nesting_depth = Some(100);
+
+        // Left-associative operators don't increase the depth of the parser
+        // stack, but they do increase the depth of the AST.
+        let source = vec!["1"; 101].join(" + ");
+        let script = parse_script(alloc, source.as_str(), &ParseOptions::new()).unwrap();
+        let mut program = ast::types::Program::Script(script.unbox());
+        assert!(matches!(
+            emit(&mut program, &options),
+            Err(EmitError::TooDeeplyNested)
+        ));
+
+        let source = vec!["1"; 99].join(" + ");
+        let script = parse_script(alloc, source.as_str(), &ParseOptions::new()).unwrap();
+        let mut program = ast::types::Program::Script(script.unbox());
+        assert!(emit(&mut program, &options).is_ok());
+
+        options.max_nesting_depth = Some(3);
+        let script = parse_script(alloc, "-(-1); -(-(-1));", &ParseOptions::new()).unwrap();
+        let mut program = ast::types::Program::Script(script.unbox());
+        assert!(matches!(
+            emit(&mut program, &options),
+            Err(EmitError::TooDeeplyNested)
+        ));
+    }
 }
@@ -1,94 +1,309 @@
+use crate::context_stack::{
+    BindingInfo, BindingKind, BlockFunctionState, ContextMetadata, FunctionReference,
+    FunctionReferenceKind, JumpInfo, JumpKind, LabelInfo,
+};
 use crate::declaration_kind::DeclarationKind;
 use crate::early_errors::*;
 use crate::error::{ParseError, Result};
 use crate::Token;
-use ast::{arena, source_location_accessor::SourceLocationAccessor, types::*, SourceLocation};
+use ast::{
+    arena::{self, CloneIn},
+    source_location_accessor::SourceLocationAccessor,
+    types::*,
+    visit::Pass,
+    SourceLocation,
+};
 use bumpalo::{vec, Bump};
+use std::collections::HashMap;
 
-// The kind of BindingIdentifier found while parsing.
-//
-// Given we don't yet have the context stack, at the point of finding
-// a BindingIdentifier, we don't know what kind of binding it is.
-// So it's marked as `Unknown`.
-//
-// Once the parser reaches the end of a declaration, bindings found in the
-// range are marked as corresponding kind.
-//
-// This is a separate enum than `DeclarationKind` for the following reason:
-//   * `DeclarationKind` is determined only when the parser reaches the end of
-//     the entire context (also because we don't have context stack), not each
-//     declaration
-//   * As long as `BindingKind` is known for each binding, we can map it to
-//     `DeclarationKind`
-//   * `DeclarationKind::CatchParameter` and some others don't to be marked
-//     this way, because `AstBuilder` knows where they are in the `bindings`
-//     vector.
 #[derive(Debug, PartialEq, Clone, Copy)]
-enum BindingKind {
-    // The initial state.
-    // BindingIdentifier is found, and haven't yet marked as any kind.
-    Unknown,
+struct PrivateNameReference<'alloc> {
+    name: &'alloc str,
+    // The location of the PrivateIdentifier in the source.
+    loc: SourceLocation,
+}
 
-    // BindingIdentifier is inside VariableDeclaration.
-    Var,
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct NameReference<'alloc> {
+    name: &'alloc str,
+    // The location of the IdentifierReference in the source.
+    loc: SourceLocation,
+}
 
-    // BindingIdentifier is the name of FunctionDeclaration.
-    Function,
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum SuperKind {
+    // SuperCall : `super` Arguments
+    Call,
+    // SuperProperty : `super` `[` Expression `]`
+    // SuperProperty : `super` `.` IdentifierName
+    Property,
+}
 
-    // BindingIdentifier is the name of GeneratorDeclaration,
-    // AsyncFunctionDeclaration, or AsyncGeneratorDeclaration.
-    AsyncOrGenerator,
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct SuperReference {
+    kind: SuperKind,
+    // The offset of the `super` token in the source.
+    offset: usize,
+}
 
-    // BindingIdentifier is inside LexicalDeclaration with let.
-    Let,
+// CoverInitializedName : IdentifierReference Initializer
+//
+// The Initializer can't be represented in ObjectProperty, so it's kept here
+// until the enclosing ObjectLiteral is reinterpreted as a pattern.
+struct CoverInitializedName<'alloc> {
+    // The offset of the IdentifierReference in the source.
+    offset: usize,
+    initializer: arena::Box<'alloc, Expression<'alloc>>,
+}
 
-    // BindingIdentifier is inside LexicalDeclaration with const.
-    Const,
+// PropertyDefinition : PropertyName `:` AssignmentExpression
+//
+// where PropName of PropertyName is "__proto__", found after another one in
+// the same ObjectLiteral.
+#[derive(Clone, Copy)]
+struct DuplicateProto {
+    // The offset of the first `__proto__` in the source.
+    first_offset: usize,
+    // The offset of the duplicate `__proto__` in the source.
+    offset: usize,
+}
 
-    // BindingIdentifier is the name of ClassDeclaration.
-    Class,
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum StrictModeViolationKind {
+    // WithStatement : `with` `(` Expression `)` Statement
+    With,
+    // NumericLiteral : LegacyOctalIntegerLiteral
+    // DecimalIntegerLiteral : NonOctalDecimalIntegerLiteral
+    LegacyOctalLiteral,
+    // `eval` or `arguments` as the IdentifierReference of an assignment
+    // target.
+    EvalOrArgumentsAssignment,
 }
 
+// A construct that is an Early Error only in strict mode code.
 #[derive(Debug, PartialEq, Clone, Copy)]
-struct BindingInfo<'alloc> {
-    name: &'alloc str,
-    // The offset of the BindingIdentifier in the source.
+struct StrictModeViolation {
+    kind: StrictModeViolationKind,
+    // The offset of the construct in the source.
     offset: usize,
-    kind: BindingKind,
+}
+
+impl StrictModeViolation {
+    fn to_error<'alloc>(self) -> ParseError<'alloc> {
+        match self.kind {
+            StrictModeViolationKind::With => ParseError::WithInStrictMode(self.offset),
+            StrictModeViolationKind::LegacyOctalLiteral => {
+                ParseError::LegacyOctalInStrictMode(self.offset)
+            }
+            StrictModeViolationKind::EvalOrArgumentsAssignment => {
+                ParseError::InvalidAssignmentTarget
+            }
+        }
+    }
+}
+
+// Sets `is_annex_b_hoisted` of the FunctionDeclarations hoisted by the web
+// legacy compatibility semantics, once the hoisting is decided at the end of
+// the script.
+struct AnnexBHoistingPass {
+    // The sorted offsets of the BindingIdentifiers of the hoisted functions.
+    offsets: Vec<usize>,
+}
+
+impl<'alloc> Pass<'alloc> for AnnexBHoistingPass {
+    fn enter_function(&mut self, ast: &mut Function<'alloc>) {
+        if let Some(name) = &ast.name {
+            if self.offsets.binary_search(&name.loc.start()).is_ok() {
+                ast.is_annex_b_hoisted = true;
+            }
+        }
+    }
+}
+
+// Sets `is_strict` of the LazyFunctions inside class bodies and other strict
+// mode code, once the end of the script is reached.
+struct StrictLazyFunctionsPass {
+    // The sorted and disjoint ranges of the outermost strict mode code.
+    ranges: Vec<(usize, usize)>,
+}
+
+impl<'alloc> Pass<'alloc> for StrictLazyFunctionsPass {
+    fn enter_lazy_function(&mut self, ast: &mut LazyFunction<'alloc>) {
+        let offset = ast.loc.start();
+        let index = self.ranges.partition_point(|&(start, _)| start <= offset);
+        if index > 0 && offset < self.ranges[index - 1].1 {
+            ast.is_strict = true;
+        }
+    }
 }
 
 pub struct AstBuilder<'alloc> {
     pub allocator: &'alloc Bump,
 
-    // The stack of information about BindingIdentifier.
+    // If true, ReturnStatement is allowed at the top level of a script,
+    // like CommonJS modules.
+    pub allow_return_outside_function: bool,
+
+    // If true, the body of each function is checked for Early Errors, and
+    // then dropped in favor of a LazyFunction, to be parsed again with
+    // `parse_lazy_function` when the function gets compiled.
+    pub lazy_inner_functions: bool,
+
+    // True if the source is parsed with the Module goal symbol, where `await`
+    // is a reserved word, or is a function of a module parsed again by
+    // `parse_lazy_function`. Set by the parser from its start state.
+    pub is_module: bool,
+
+    // True if the source is strict mode code, like a function of a class
+    // body parsed again by `parse_lazy_function`.
+    pub strict_mode: bool,
+
+    // The PrivateIdentifiers declared by the classes enclosing the source,
+    // when it's a function parsed again by `parse_lazy_function`.
+    pub enclosing_private_names: Vec<&'alloc str>,
+
+    // The number of function bodies replaced by LazyFunction that the parser
+    // is in. Their statements are dropped as soon as they're parsed, except
+    // for the Directive Prologue and the statement that ends it, which are
+    // needed to check the "use strict" directive, and the last statement.
+    lazy_function_depth: usize,
+
+    // The stack of IdentifierReferences found so far when
+    // `lazy_inner_functions` is set, sorted by offset.
     //
-    // When the parser found BindingIdentifier, the information (`name` and
-    // `offset`) are noted to this vector, and when the parser determined what
-    // kind of binding it is, `kind` is updated.
+    // When the parser reaches the end of a lazy function, the references
+    // inside it are replaced with the first reference to each name it closes
+    // over, which are left for the enclosing function.
+    name_references: Vec<NameReference<'alloc>>,
+
+    // The sorted and disjoint ranges of the outermost class bodies and
+    // bodies with a "use strict" directive found so far when
+    // `lazy_inner_functions` is set. They are strict mode code, and this is
+    // used to mark the LazyFunctions inside them at the end of the script.
+    strict_ranges: Vec<(usize, usize)>,
+
+    // The constructs found so far that are Early Errors in strict mode code.
     //
-    // The bindings are sorted by offset.
+    // Whether the code is strict depends on the "use strict" directive of the
+    // enclosing function or script, or on the enclosing class, which aren't
+    // known yet when the construct is parsed. When the parser reaches the end
+    // of strict mode code, any item inside it is an Early Error.
+    strict_mode_violations: Vec<StrictModeViolation>,
+
+    // The maximum depth of chains of binary operators, member accesses and
+    // calls. These are left-associative, so they don't grow the parser stack,
+    // but they are as deep as they are long in the AST.
+    pub max_nesting_depth: Option<usize>,
+
+    // The depth of the chains of binary operators, member accesses and calls
+    // built so far, keyed by the start and end offsets of the Expression,
+    // which, unlike its address, are kept when the parser copies it for a
+    // checkpoint. An entry is removed when the Expression becomes a part of a
+    // longer chain, and expressions without an entry have depth 1.
+    expression_depths: HashMap<(usize, usize), usize>,
+
+    // If Some, the bindings in it are declared along with the top-level
+    // bindings of the script, and at the end of the script, this is replaced
+    // with all the top-level bindings, sorted by offset. Used by incremental
+    // reparsing, where the bindings of the statements that aren't parsed
+    // again come from the previous parse.
+    pub script_bindings: Option<Vec<BindingInfo<'alloc>>>,
+
+    // The information about the enclosing contexts, used to detect Early
+    // Errors that depend on them.
+    context_metadata: ContextMetadata<'alloc>,
+
+    // The stack of PrivateIdentifiers referenced in MemberExpressions
+    // (`obj.#x`), sorted by offset.
     //
-    // When the parser reaches the end of a scope, all bindings declared within
-    // that scope (not including nested scopes) are fed to an
-    // EarlyErrorsContext to detect Early Errors.
+    // When the parser reaches the end of a ClassBody, references to the
+    // private names declared by that ClassBody are removed. The remaining
+    // references are left there, to be resolved by the enclosing ClassBody.
+    // Any reference left at the end of the script or module is an Early
+    // Error.
+    private_name_references: Vec<PrivateNameReference<'alloc>>,
+
+    // The stack of SuperCall and SuperProperty found so far, sorted by
+    // offset.
     //
-    // When leaving a context that is not one of script/module/function,
-    // lexical items (`kind != BindingKind::Var`) in the corresponding range
-    // are removed, while non-lexical items (`kind == BindingKind::Var`) are
-    // left there, so that VariableDeclarations are propagated to the enclosing
-    // context.
+    // Whether `super` is allowed depends on the enclosing function, which
+    // isn't known yet when `super` is parsed. When the parser reaches the end
+    // of a function, method, field initializer or static block, references
+    // inside it are checked and removed. Arrow functions are transparent, so
+    // their references are left for the enclosing context.
+    super_references: Vec<SuperReference>,
+
+    // The stack of CoverInitializedName found so far, sorted by offset.
     //
-    // FIXME: Once the context stack gets implemented, this structure and
-    //        related methods should be removed and each declaration should be
-    //        fed directly to EarlyErrorsContext.
-    bindings: Vec<BindingInfo<'alloc>>,
+    // When an ObjectLiteral is reinterpreted as an ObjectAssignmentPattern or
+    // a parameter binding, the Initializers of its shorthand properties are
+    // taken from this stack. Any item left at the end of the script or module
+    // was used in an actual ObjectLiteral, which is an Early Error.
+    cover_initialized_names: Vec<CoverInitializedName<'alloc>>,
+
+    // The stack of duplicate `__proto__` properties found so far, sorted by
+    // offset.
+    //
+    // Duplicate `__proto__` is allowed in ObjectAssignmentPattern, so this is
+    // handled in the same way as `cover_initialized_names`.
+    duplicate_protos: Vec<DuplicateProto>,
+}
+
+// Cloning the builder copies the pending information about the enclosing
+// contexts, so that the parser can be restored to a checkpoint.
+impl<'alloc> Clone for AstBuilder<'alloc> {
+    fn clone(&self) -> Self {
+        Self {
+            allocator: self.allocator,
+            allow_return_outside_function: self.allow_return_outside_function,
+            lazy_inner_functions: self.lazy_inner_functions,
+            is_module: self.is_module,
+            strict_mode: self.strict_mode,
+            enclosing_private_names: self.enclosing_private_names.clone(),
+            lazy_function_depth: self.lazy_function_depth,
+            name_references: self.name_references.clone(),
+            strict_ranges: self.strict_ranges.clone(),
+            strict_mode_violations: self.strict_mode_violations.clone(),
+            max_nesting_depth: self.max_nesting_depth,
+            expression_depths: self.expression_depths.clone(),
+            script_bindings: self.script_bindings.clone(),
+            context_metadata: self.context_metadata.clone(),
+            private_name_references: self.private_name_references.clone(),
+            super_references: self.super_references.clone(),
+            cover_initialized_names: self
+                .cover_initialized_names
+                .iter()
+                .map(|item| CoverInitializedName {
+                    offset: item.offset,
+                    initializer: item.initializer.clone_in(self.allocator),
+                })
+                .collect(),
+            duplicate_protos: self.duplicate_protos.clone(),
+        }
+    }
 }
 
 impl<'alloc> AstBuilder<'alloc> {
     pub fn new(allocator: &'alloc Bump) -> Self {
         Self {
             allocator,
-            bindings: Vec::new(),
+            allow_return_outside_function: false,
+            lazy_inner_functions: false,
+            is_module: false,
+            strict_mode: false,
+            enclosing_private_names: Vec::new(),
+            lazy_function_depth: 0,
+            name_references: Vec::new(),
+            strict_ranges: Vec::new(),
+            strict_mode_violations: Vec::new(),
+            max_nesting_depth: None,
+            expression_depths: HashMap::new(),
+            script_bindings: None,
+            context_metadata: ContextMetadata::new(),
+            private_name_references: Vec::new(),
+            super_references: Vec::new(),
+            cover_initialized_names: Vec::new(),
+            duplicate_protos: Vec::new(),
         }
     }
 
@@ -108,17 +323,6 @@ impl<'alloc> AstBuilder<'alloc> {
         vec![in self.allocator; value]
     }
 
-    fn collect_vec_from_results<T, C>(&self, results: C) -> Result<'alloc, arena::Vec<'alloc, T>>
-    where
-        C: IntoIterator<Item = Result<'alloc, T>>,
-    {
-        let mut out = self.new_vec();
-        for result in results {
-            out.push(result?);
-        }
-        Ok(out)
-    }
-
     fn push<T>(&self, list: &mut arena::Vec<'alloc, T>, value: T) {
         list.push(value);
     }
@@ -127,19 +331,64 @@ impl<'alloc> AstBuilder<'alloc> {
         list.append(elements);
     }
 
+    // Return the depth of an Expression that has `operands` as its direct
+    // operands, or an error if that's more than `max_nesting_depth`. `offset`
+    // is the start of the part that makes the Expression too deep.
+    fn chain_depth(
+        &mut self,
+        operands: &[&arena::Box<'alloc, Expression<'alloc>>],
+        offset: usize,
+    ) -> Result<'alloc, usize> {
+        let mut depth = 1;
+        for operand in operands {
+            let loc = operand.get_loc();
+            let operand_depth = self
+                .expression_depths
+                .remove(&(loc.start(), loc.end()))
+                .unwrap_or(1);
+            depth = depth.max(operand_depth + 1);
+        }
+        if let Some(max_nesting_depth) = self.max_nesting_depth {
+            if depth > max_nesting_depth {
+                return Err(ParseError::TooDeeplyNested(offset));
+            }
+        }
+        Ok(depth)
+    }
+
+    fn alloc_chain(
+        &mut self,
+        expression: Expression<'alloc>,
+        depth: usize,
+    ) -> arena::Box<'alloc, Expression<'alloc>> {
+        let loc = expression.get_loc();
+        self.expression_depths.insert((loc.start(), loc.end()), depth);
+        self.alloc(expression)
+    }
+
     // IdentifierReference : Identifier
     pub fn identifier_reference(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Identifier<'alloc>>> {
         self.on_identifier_reference(&token)?;
-        Ok(self.alloc(self.identifier(token)))
+        if token.str_value() == Some("arguments") {
+            self.on_function_reference(FunctionReferenceKind::Arguments, token.loc.start());
+        }
+        let identifier = self.identifier(token);
+        if self.lazy_inner_functions {
+            self.name_references.push(NameReference {
+                name: identifier.value,
+                loc: identifier.loc,
+            });
+        }
+        Ok(self.alloc(identifier))
     }
 
     // BindingIdentifier : Identifier
     pub fn binding_identifier(
         &mut self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, BindingIdentifier<'alloc>>> {
         self.on_binding_identifier(&token)?;
         let loc = token.loc;
@@ -152,7 +401,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // BindingIdentifier : `yield`
     pub fn binding_identifier_yield(
         &mut self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, BindingIdentifier<'alloc>>> {
         self.on_binding_identifier(&token)?;
         let loc = token.loc;
@@ -168,7 +417,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // BindingIdentifier : `await`
     pub fn binding_identifier_await(
         &mut self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, BindingIdentifier<'alloc>>> {
         self.on_binding_identifier(&token)?;
         let loc = token.loc;
@@ -184,21 +433,18 @@ impl<'alloc> AstBuilder<'alloc> {
     // LabelIdentifier : Identifier
     pub fn label_identifier(
         &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Label<'alloc>>> {
         self.on_label_identifier(&token)?;
         let loc = token.loc;
         Ok(self.alloc(Label {
-            value: token.value.unwrap(),
+            value: token.str_value().unwrap(),
             loc,
         }))
     }
 
     // PrimaryExpression : `this`
-    pub fn this_expr(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    pub fn this_expr(&self, token: Token<'alloc>) -> arena::Box<'alloc, Expression<'alloc>> {
         let loc = token.loc;
         self.alloc(Expression::ThisExpression { loc })
     }
@@ -216,10 +462,7 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     // PrimaryExpression : RegularExpressionLiteral
-    pub fn regexp_literal(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    pub fn regexp_literal(&self, token: Token<'alloc>) -> arena::Box<'alloc, Expression<'alloc>> {
         let pattern = token.value.unwrap();
         let global: bool = false;
         let ignore_case: bool = false;
@@ -257,18 +500,20 @@ impl<'alloc> AstBuilder<'alloc> {
                 // invalid ObjectPattern or ArrayPattern syntax?
                 Ok(expression)
             }
-            CoverParenthesized::Parameters(_parameters) => Err(ParseError::NotImplemented(
-                "parenthesized expression with `...` should be a syntax error",
-            )),
+            // `()` and `(a, ...b)` are only valid as arrow function
+            // parameters.
+            CoverParenthesized::Parameters(parameters) => Err(
+                ParseError::ArrowParametersWithoutArrow(parameters.loc.start()),
+            ),
         }
     }
 
     // CoverParenthesizedExpressionAndArrowParameterList : `(` Expression `)`
     pub fn cover_parenthesized_expression(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         expression: arena::Box<'alloc, Expression<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, CoverParenthesized<'alloc>> {
         self.alloc(CoverParenthesized::Expression {
             expression,
@@ -284,7 +529,7 @@ impl<'alloc> AstBuilder<'alloc> {
     /// Used when parsing `([a, b=2]=arr) =>` to reinterpret as parameter bindings
     /// the snippets `a` and `b=2`, which were previously parsed as assignment targets.
     fn assignment_target_maybe_default_to_binding(
-        &self,
+        &mut self,
         target: AssignmentTargetMaybeDefault<'alloc>,
     ) -> Result<'alloc, Parameter<'alloc>> {
         match target {
@@ -303,7 +548,7 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     fn assignment_target_property_to_binding_property(
-        &self,
+        &mut self,
         target: AssignmentTargetProperty<'alloc>,
     ) -> Result<'alloc, BindingProperty<'alloc>> {
         Ok(match target {
@@ -319,13 +564,15 @@ impl<'alloc> AstBuilder<'alloc> {
                 loc: loc2,
             }),
 
-            AssignmentTargetProperty::AssignmentTargetPropertyProperty(
-                AssignmentTargetPropertyProperty { name, binding, loc },
-            ) => BindingProperty::BindingPropertyProperty(BindingPropertyProperty {
-                name,
-                binding: self.assignment_target_maybe_default_to_binding(binding)?,
-                loc,
-            }),
+            AssignmentTargetProperty::AssignmentTargetPropertyProperty(property) => {
+                let AssignmentTargetPropertyProperty { name, binding, loc } = property.unbox();
+                let binding = self.assignment_target_maybe_default_to_binding(binding)?;
+                BindingProperty::BindingPropertyProperty(self.alloc(BindingPropertyProperty {
+                    name,
+                    binding,
+                    loc,
+                }))
+            }
         })
     }
 
@@ -364,7 +611,7 @@ impl<'alloc> AstBuilder<'alloc> {
     /// When parsing `(a = 1, [b, c] = obj) => {}`, the assignment targets `a`
     /// and `[b, c]` are passed to this method.
     fn assignment_target_to_binding(
-        &self,
+        &mut self,
         target: AssignmentTarget<'alloc>,
     ) -> Result<'alloc, Binding<'alloc>> {
         match target {
@@ -393,18 +640,24 @@ impl<'alloc> AstBuilder<'alloc> {
             ) => {
                 let elements: arena::Vec<'alloc, Option<AssignmentTargetMaybeDefault<'alloc>>> =
                     elements;
-                let elements: arena::Vec<'alloc, Option<Parameter<'alloc>>> = self
-                    .collect_vec_from_results(elements.into_iter().map(|maybe_target| {
-                        maybe_target
-                            .map(|target| self.assignment_target_maybe_default_to_binding(target))
-                            .transpose()
-                    }))?;
-                let rest: Option<Result<'alloc, arena::Box<'alloc, Binding<'alloc>>>> = rest.map(
-                    |rest_target| -> Result<'alloc, arena::Box<'alloc, Binding<'alloc>>> {
-                        Ok(self.alloc(self.assignment_target_to_binding(rest_target.unbox())?))
-                    },
-                );
-                let rest: Option<arena::Box<'alloc, Binding<'alloc>>> = rest.transpose()?;
+                let mut parameters: arena::Vec<'alloc, Option<Parameter<'alloc>>> = self.new_vec();
+                for maybe_target in elements {
+                    let parameter = match maybe_target {
+                        Some(target) => {
+                            Some(self.assignment_target_maybe_default_to_binding(target)?)
+                        }
+                        None => None,
+                    };
+                    self.push(&mut parameters, parameter);
+                }
+                let elements = parameters;
+                let rest: Option<arena::Box<'alloc, Binding<'alloc>>> = match rest {
+                    Some(rest_target) => {
+                        let binding = self.assignment_target_to_binding(rest_target.unbox())?;
+                        Some(self.alloc(binding))
+                    }
+                    None => None,
+                };
                 Ok(Binding::BindingPattern(BindingPattern::ArrayBinding(
                     ArrayBinding {
                         elements,
@@ -422,10 +675,12 @@ impl<'alloc> AstBuilder<'alloc> {
                     loc,
                 }),
             ) => {
-                let properties =
-                    self.collect_vec_from_results(properties.into_iter().map(|target| {
-                        self.assignment_target_property_to_binding_property(target)
-                    }))?;
+                let mut binding_properties = self.new_vec();
+                for target in properties {
+                    let property = self.assignment_target_property_to_binding_property(target)?;
+                    self.push(&mut binding_properties, property);
+                }
+                let properties = binding_properties;
 
                 let rest = if let Some(rest_target) = rest {
                     Some(self.assignment_rest_property_to_binding_identifier(rest_target.unbox())?)
@@ -444,7 +699,7 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     fn object_property_to_binding_property(
-        &self,
+        &mut self,
         op: ObjectProperty<'alloc>,
     ) -> Result<'alloc, BindingProperty<'alloc>> {
         match op {
@@ -454,33 +709,48 @@ impl<'alloc> AstBuilder<'alloc> {
                     expression,
                     loc,
                 },
-            )) => Ok(BindingProperty::BindingPropertyProperty(
-                BindingPropertyProperty {
-                    name: property_name,
-                    binding: self.expression_to_parameter(expression.unbox())?,
-                    loc,
-                },
-            )),
+            )) => {
+                let binding = self.expression_to_parameter(expression.unbox())?;
+                Ok(BindingProperty::BindingPropertyProperty(self.alloc(
+                    BindingPropertyProperty {
+                        name: property_name,
+                        binding,
+                        loc,
+                    },
+                )))
+            }
 
             ObjectProperty::NamedObjectProperty(NamedObjectProperty::MethodDefinition(_)) => {
                 Err(ParseError::ObjectPatternWithMethod)
             }
 
-            ObjectProperty::ShorthandProperty(ShorthandProperty {
-                name: IdentifierExpression { name, loc },
-                ..
+            ObjectProperty::ProtoProperty(ProtoProperty {
+                name,
+                expression,
+                loc,
             }) => {
-                // TODO - CoverInitializedName can't be represented in an
-                // ObjectProperty, but we need it here.
-                Ok(BindingProperty::BindingPropertyIdentifier(
-                    BindingPropertyIdentifier {
-                        binding: BindingIdentifier { name, loc },
-                        init: None,
+                self.remove_duplicate_proto(loc.start());
+                let binding = self.expression_to_parameter(expression.unbox())?;
+                Ok(BindingProperty::BindingPropertyProperty(self.alloc(
+                    BindingPropertyProperty {
+                        name: PropertyName::StaticPropertyName(name),
+                        binding,
                         loc,
                     },
-                ))
+                )))
             }
 
+            ObjectProperty::ShorthandProperty(ShorthandProperty {
+                name: IdentifierExpression { name, loc },
+                loc: property_loc,
+            }) => Ok(BindingProperty::BindingPropertyIdentifier(
+                BindingPropertyIdentifier {
+                    binding: BindingIdentifier { name, loc },
+                    init: self.take_cover_initializer(loc.start()),
+                    loc: property_loc,
+                },
+            )),
+
             ObjectProperty::SpreadProperty(_expression) => {
                 Err(ParseError::ObjectPatternWithNonFinalRest)
             }
@@ -513,8 +783,9 @@ impl<'alloc> AstBuilder<'alloc> {
             _ => return None,
         }
 
-        // We do.
-        match properties.pop().unwrap().unbox() {
+        // We do. `arena::Vec::pop` can't be used here: bumpalo 2.x's
+        // implementation indexes past the new length, which is UB.
+        match properties.remove(properties.len() - 1).unbox() {
             ObjectProperty::SpreadProperty(expression) => Some(expression),
             _ => panic!("bug"), // can't happen: we just checked this above
         }
@@ -522,18 +793,19 @@ impl<'alloc> AstBuilder<'alloc> {
 
     /// Refine an *ObjectLiteral* into an *ObjectBindingPattern*.
     fn object_expression_to_object_binding(
-        &self,
+        &mut self,
         object: ObjectExpression<'alloc>,
     ) -> Result<'alloc, ObjectBinding<'alloc>> {
         let mut properties = object.properties;
         let loc = object.loc;
         let rest = self.pop_trailing_spread_property(&mut properties);
+        let mut binding_properties = self.new_vec();
+        for prop in properties {
+            let property = self.object_property_to_binding_property(prop.unbox())?;
+            self.push(&mut binding_properties, property);
+        }
         Ok(ObjectBinding {
-            properties: self.collect_vec_from_results(
-                properties
-                    .into_iter()
-                    .map(|prop| self.object_property_to_binding_property(prop.unbox())),
-            )?,
+            properties: binding_properties,
             rest: rest
                 .map(|expression| self.spread_expression_to_rest_binding(expression))
                 .transpose()?,
@@ -542,17 +814,24 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     fn array_elements_to_parameters(
-        &self,
+        &mut self,
         elements: arena::Vec<'alloc, ArrayExpressionElement<'alloc>>,
     ) -> Result<'alloc, arena::Vec<'alloc, Option<Parameter<'alloc>>>> {
-        self.collect_vec_from_results(elements.into_iter().map(|element| match element {
-                ArrayExpressionElement::Expression(expr) =>
-                    Ok(Some(self.expression_to_parameter(expr.unbox())?)),
-                ArrayExpressionElement::SpreadElement(_expr) =>
+        let mut parameters = self.new_vec();
+        for element in elements {
+            let parameter = match element {
+                ArrayExpressionElement::Expression(expr) => {
+                    Some(self.expression_to_parameter(expr.unbox())?)
+                }
+                ArrayExpressionElement::SpreadElement(_expr) => {
                     // ([...a, b]) => {}
-                    Err(ParseError::ArrayPatternWithNonFinalRest),
-                ArrayExpressionElement::Elision { .. } => Ok(None),
-            }))
+                    return Err(ParseError::ArrayPatternWithNonFinalRest);
+                }
+                ArrayExpressionElement::Elision { .. } => None,
+            };
+            self.push(&mut parameters, parameter);
+        }
+        Ok(parameters)
     }
 
     fn pop_trailing_spread_element(
@@ -565,15 +844,16 @@ impl<'alloc> AstBuilder<'alloc> {
             _ => return None,
         }
 
-        // We do.
-        match elements.pop() {
-            Some(ArrayExpressionElement::SpreadElement(expression)) => Some(expression),
+        // We do. See `pop_trailing_spread_property` for why this doesn't use
+        // `arena::Vec::pop`.
+        match elements.remove(elements.len() - 1) {
+            ArrayExpressionElement::SpreadElement(expression) => Some(expression),
             _ => panic!("bug"), // can't happen: we just checked this above
         }
     }
 
     fn expression_to_binding_no_default(
-        &self,
+        &mut self,
         expression: Expression<'alloc>,
     ) -> Result<'alloc, Binding<'alloc>> {
         match expression {
@@ -610,7 +890,7 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     fn expression_to_parameter(
-        &self,
+        &mut self,
         expression: Expression<'alloc>,
     ) -> Result<'alloc, Parameter<'alloc>> {
         match expression {
@@ -634,7 +914,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // CoverParenthesizedExpressionAndArrowParameterList : `(` Expression `,` `...` BindingIdentifier `)`
     // CoverParenthesizedExpressionAndArrowParameterList : `(` Expression `,` `...` BindingPattern `)`
     pub fn expression_to_parameter_list(
-        &self,
+        &mut self,
         expression: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, arena::Vec<'alloc, Parameter<'alloc>>> {
         // When the production
@@ -653,20 +933,21 @@ impl<'alloc> AstBuilder<'alloc> {
                 ..
             } => {
                 let mut parameters = self.expression_to_parameter_list(left)?;
-                self.push(
-                    &mut parameters,
-                    self.expression_to_parameter(right.unbox())?,
-                );
+                let parameter = self.expression_to_parameter(right.unbox())?;
+                self.push(&mut parameters, parameter);
                 Ok(parameters)
             }
-            other => Ok(self.new_vec_single(self.expression_to_parameter(other)?)),
+            other => {
+                let parameter = self.expression_to_parameter(other)?;
+                Ok(self.new_vec_single(parameter))
+            }
         }
     }
 
     /// Used to convert `async(x, y, ...z)` from a *CallExpression* to async
     /// arrow function parameters.
     fn arguments_to_parameter_list(
-        &self,
+        &mut self,
         arguments: Arguments<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, FormalParameters<'alloc>>> {
         let loc = arguments.loc;
@@ -678,7 +959,8 @@ impl<'alloc> AstBuilder<'alloc> {
             }
             match arg {
                 Argument::Expression(expr) => {
-                    self.push(&mut items, self.expression_to_parameter(expr.unbox())?);
+                    let parameter = self.expression_to_parameter(expr.unbox())?;
+                    self.push(&mut items, parameter);
                 }
                 Argument::SpreadElement(spread_expr) => {
                     rest = Some(self.expression_to_binding_no_default(spread_expr.unbox())?);
@@ -695,10 +977,10 @@ impl<'alloc> AstBuilder<'alloc> {
     // CoverParenthesizedExpressionAndArrowParameterList : `(` Expression `,` `...` BindingPattern `)`
     pub fn cover_arrow_parameter_list(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         parameters: arena::Vec<'alloc, Parameter<'alloc>>,
         rest: Option<arena::Box<'alloc, Binding<'alloc>>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, CoverParenthesized<'alloc>> {
         self.alloc(CoverParenthesized::Parameters(self.alloc(
             FormalParameters {
@@ -710,21 +992,15 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     // Literal : NullLiteral
-    pub fn null_literal(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    pub fn null_literal(&self, token: Token<'alloc>) -> arena::Box<'alloc, Expression<'alloc>> {
         let loc = token.loc;
         self.alloc(Expression::LiteralNullExpression { loc })
     }
 
     // Literal : BooleanLiteral
-    pub fn boolean_literal(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    pub fn boolean_literal(&self, token: Token<'alloc>) -> arena::Box<'alloc, Expression<'alloc>> {
         let loc = token.loc;
-        let s = token.unbox().value.unwrap();
+        let s = token.str_value().unwrap();
         assert!(s == "true" || s == "false");
 
         self.alloc(Expression::LiteralBooleanExpression {
@@ -733,8 +1009,22 @@ impl<'alloc> AstBuilder<'alloc> {
         })
     }
 
-    fn numeric_literal_value(token: arena::Box<'alloc, Token<'alloc>>) -> Result<'alloc, f64> {
-        let s = token.unbox().value.unwrap();
+    fn numeric_literal_value(&mut self, token: Token<'alloc>) -> Result<'alloc, f64> {
+        let s = token.str_value().unwrap();
+
+        // LegacyOctalIntegerLiteral and NonOctalDecimalIntegerLiteral are
+        // numbers of more than one digit starting with `0`.
+        if s.len() > 1 && s.starts_with('0') && s.bytes().all(|b| b.is_ascii_digit()) {
+            self.on_strict_mode_violation(
+                StrictModeViolationKind::LegacyOctalLiteral,
+                token.loc.start(),
+            )?;
+            if s.bytes().all(|b| b < b'8') {
+                return u64::from_str_radix(s, 8)
+                    .map(|value| value as f64)
+                    .map_err(|_| ParseError::NotImplemented("Cannot parse numeric literal"));
+            }
+        }
 
         // BUG: Not all syntax is supported yet.
         s.parse::<f64>()
@@ -743,14 +1033,12 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // Literal : NumericLiteral
     pub fn numeric_literal(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let loc = token.loc;
-        Ok(self.alloc(Expression::LiteralNumericExpression {
-            value: Self::numeric_literal_value(token)?,
-            loc,
-        }))
+        let value = self.numeric_literal_value(token)?;
+        Ok(self.alloc(Expression::LiteralNumericExpression { value, loc }))
     }
 
     // Literal : NumericLiteral
@@ -760,7 +1048,7 @@ impl<'alloc> AstBuilder<'alloc> {
     //   * NonDecimalIntegerLiteralBigIntLiteralSuffix
     pub fn bigint_literal(
         &self,
-        _token: arena::Box<'alloc, Token<'alloc>>,
+        _token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         Err(ParseError::NotImplemented("BigInt"))
     }
@@ -768,15 +1056,9 @@ impl<'alloc> AstBuilder<'alloc> {
     // Literal : StringLiteral
     pub fn string_literal(
         &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let loc = token.loc;
-        // Hack: Prevent emission for scripts with "use strict"
-        // directive.
-        if token.value == Some("use strict") {
-            return Err(ParseError::NotImplemented("use strict directive"));
-        }
-
         Ok(self.alloc(Expression::LiteralStringExpression {
             value: token.value.unwrap(),
             loc,
@@ -786,9 +1068,9 @@ impl<'alloc> AstBuilder<'alloc> {
     // ArrayLiteral : `[` Elision? `]`
     pub fn array_literal_empty(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         elision: Option<arena::Box<'alloc, ArrayExpression<'alloc>>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         self.alloc(Expression::ArrayExpression(match elision {
             None => ArrayExpression {
@@ -805,9 +1087,9 @@ impl<'alloc> AstBuilder<'alloc> {
     // ArrayLiteral : `[` ElementList `]`
     pub fn array_literal(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         mut array: arena::Box<'alloc, ArrayExpression<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         array.loc.set_range(open_token.loc, close_token.loc);
         self.alloc(Expression::ArrayExpression(array.unbox()))
@@ -816,10 +1098,10 @@ impl<'alloc> AstBuilder<'alloc> {
     // ArrayLiteral : `[` ElementList `,` Elision? `]`
     pub fn array_literal_with_trailing_elision(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         mut array: arena::Box<'alloc, ArrayExpression<'alloc>>,
         elision: Option<arena::Box<'alloc, ArrayExpression<'alloc>>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         if let Some(mut more) = elision {
             self.append(&mut array.elements, &mut more.elements);
@@ -907,7 +1189,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // Elision : `,`
     pub fn elision_single(
         &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> arena::Box<'alloc, ArrayExpression<'alloc>> {
         let loc = token.loc;
         self.alloc(ArrayExpression {
@@ -921,7 +1203,7 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn elision_append(
         &self,
         mut array: arena::Box<'alloc, ArrayExpression<'alloc>>,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> arena::Box<'alloc, ArrayExpression<'alloc>> {
         let loc = token.loc;
         self.push(&mut array.elements, ArrayExpressionElement::Elision { loc });
@@ -939,8 +1221,8 @@ impl<'alloc> AstBuilder<'alloc> {
     // ObjectLiteral : `{` `}`
     pub fn object_literal_empty(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         self.alloc(Expression::ObjectExpression(ObjectExpression {
             properties: self.new_vec(),
@@ -951,11 +1233,21 @@ impl<'alloc> AstBuilder<'alloc> {
     // ObjectLiteral : `{` PropertyDefinitionList `}`
     // ObjectLiteral : `{` PropertyDefinitionList `,` `}`
     pub fn object_literal(
-        &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        open_token: Token<'alloc>,
         mut object: arena::Box<'alloc, ObjectExpression<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
+        let mut first_offset = None;
+        for property in object.properties.iter() {
+            if let ObjectProperty::ProtoProperty(ProtoProperty { loc, .. }) = &**property {
+                match first_offset {
+                    None => first_offset = Some(loc.start()),
+                    Some(first_offset) => self.on_duplicate_proto(first_offset, loc.start()),
+                }
+            }
+        }
+
         object.loc.set_range(open_token.loc, close_token.loc);
         self.alloc(Expression::ObjectExpression(object.unbox()))
     }
@@ -1005,6 +1297,20 @@ impl<'alloc> AstBuilder<'alloc> {
     ) -> arena::Box<'alloc, ObjectProperty<'alloc>> {
         let name_loc = name.get_loc();
         let expression_loc = expression.get_loc();
+
+        // `__proto__: value` sets the [[Prototype]] of the object, instead of
+        // defining a property.
+        // https://tc39.es/ecma262/#sec-runtime-semantics-propertydefinitionevaluation
+        if let PropertyName::StaticPropertyName(StaticPropertyName { value, loc }) = *name {
+            if value == "__proto__" {
+                return self.alloc(ObjectProperty::ProtoProperty(ProtoProperty {
+                    name: StaticPropertyName { value, loc },
+                    expression,
+                    loc: SourceLocation::from_parts(name_loc, expression_loc),
+                }));
+            }
+        }
+
         self.alloc(ObjectProperty::NamedObjectProperty(
             NamedObjectProperty::DataProperty(DataProperty {
                 property_name: name.unbox(),
@@ -1016,12 +1322,30 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // PropertyDefinition : MethodDefinition
     pub fn property_definition_method(
-        &self,
+        &mut self,
         method: arena::Box<'alloc, MethodDefinition<'alloc>>,
-    ) -> arena::Box<'alloc, ObjectProperty<'alloc>> {
-        self.alloc(ObjectProperty::NamedObjectProperty(
+    ) -> Result<'alloc, arena::Box<'alloc, ObjectProperty<'alloc>>> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-object-initializer-static-semantics-early-errors
+        //
+        // PropertyDefinition : MethodDefinition
+        //
+        // * It is a Syntax Error if PrivateBoundIdentifiers of
+        //   MethodDefinition is not empty.
+        if let ClassElementName::PrivateFieldName(PrivateIdentifier { value, loc }) =
+            Self::method_definition_name(&method)
+        {
+            return Err(ParseError::PrivateNameInObjectLiteral(value, loc.start()));
+        }
+
+        // * It is a Syntax Error if HasDirectSuper of MethodDefinition is
+        //   true.
+        let (start, end) = Self::method_definition_body_range(&method);
+        self.check_super_references(start, end, false, true)?;
+
+        Ok(self.alloc(ObjectProperty::NamedObjectProperty(
             NamedObjectProperty::MethodDefinition(method.unbox()),
-        ))
+        )))
     }
 
     // PropertyDefinition : `...` AssignmentExpression
@@ -1035,13 +1359,9 @@ impl<'alloc> AstBuilder<'alloc> {
     // LiteralPropertyName : IdentifierName
     pub fn property_name_identifier(
         &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, PropertyName<'alloc>>> {
-        let value = token.value.unwrap();
-        if value == "__proto__" {
-            return Err(ParseError::NotImplemented("__proto__ as property name"));
-        }
-
+        let value = token.str_value().unwrap();
         let loc = token.loc;
         Ok(
             self.alloc(PropertyName::StaticPropertyName(StaticPropertyName {
@@ -1054,13 +1374,11 @@ impl<'alloc> AstBuilder<'alloc> {
     // LiteralPropertyName : StringLiteral
     pub fn property_name_string(
         &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, PropertyName<'alloc>>> {
-        let value = token.value.unwrap();
-        if value == "__proto__" {
-            return Err(ParseError::NotImplemented("__proto__ as property name"));
-        }
-
+        let value = token.value.unwrap().as_str().ok_or(ParseError::NotImplemented(
+            "unpaired surrogates in property names",
+        ))?;
         let loc = token.loc;
         Ok(
             self.alloc(PropertyName::StaticPropertyName(StaticPropertyName {
@@ -1072,13 +1390,14 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // LiteralPropertyName : NumericLiteral
     pub fn property_name_numeric(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, PropertyName<'alloc>>> {
         let loc = token.loc;
+        let value = self.numeric_literal_value(token)?;
         Ok(
             self.alloc(PropertyName::StaticPropertyName(StaticPropertyName {
-                value: self.alloc_str(&format!("{:?}", Self::numeric_literal_value(token)?)),
+                value: self.alloc_str(&format!("{:?}", value)),
                 loc,
             })),
         )
@@ -1091,7 +1410,7 @@ impl<'alloc> AstBuilder<'alloc> {
     //   * NonDecimalIntegerLiteralBigIntLiteralSuffix
     pub fn property_name_bigint(
         &self,
-        _token: arena::Box<'alloc, Token<'alloc>>,
+        _token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, PropertyName<'alloc>>> {
         Err(ParseError::NotImplemented("BigInt"))
     }
@@ -1099,9 +1418,9 @@ impl<'alloc> AstBuilder<'alloc> {
     // ComputedPropertyName : `[` AssignmentExpression `]`
     pub fn computed_property_name(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         expression: arena::Box<'alloc, Expression<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, PropertyName<'alloc>> {
         self.alloc(PropertyName::ComputedPropertyName(ComputedPropertyName {
             expression,
@@ -1111,22 +1430,30 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // CoverInitializedName : IdentifierReference Initializer
     pub fn cover_initialized_name(
-        &self,
-        _name: arena::Box<'alloc, Identifier<'alloc>>,
-        _initializer: arena::Box<'alloc, Expression<'alloc>>,
+        &mut self,
+        name: arena::Box<'alloc, Identifier<'alloc>>,
+        initializer: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, ObjectProperty<'alloc>>> {
-        // Awkward. This needs to be stored somehow until we reach an enclosing
-        // context where it can be reinterpreted as a default value in an
-        // object destructuring assignment pattern.
-        Err(ParseError::NotImplemented(
-            "default initializers in object patterns",
-        ))
+        // The Initializer is only valid once this ObjectLiteral is
+        // reinterpreted as a pattern, and it's stored aside until then.
+        let name_loc = name.loc;
+        let loc = SourceLocation::from_parts(name_loc, initializer.get_loc());
+        self.on_cover_initialized_name(name_loc.start(), initializer);
+        Ok(
+            self.alloc(ObjectProperty::ShorthandProperty(ShorthandProperty {
+                name: IdentifierExpression {
+                    name: name.unbox(),
+                    loc: name_loc,
+                },
+                loc,
+            })),
+        )
     }
 
     // TemplateLiteral : NoSubstitutionTemplate
     pub fn template_literal(
         &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> arena::Box<'alloc, TemplateExpression<'alloc>> {
         let loc = token.loc;
         self.alloc(TemplateExpression {
@@ -1144,7 +1471,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // SubstitutionTemplate : TemplateHead Expression TemplateSpans
     pub fn substitution_template(
         &self,
-        _head: arena::Box<'alloc, Token<'alloc>>,
+        _head: Token<'alloc>,
         _expression: arena::Box<'alloc, Expression<'alloc>>,
         _spans: arena::Box<'alloc, Void>,
     ) -> Result<'alloc, arena::Box<'alloc, TemplateExpression<'alloc>>> {
@@ -1156,7 +1483,7 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn template_spans(
         &self,
         _middle_list: Option<arena::Box<'alloc, Void>>,
-        _tail: arena::Box<'alloc, Token<'alloc>>,
+        _tail: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Void>> {
         Err(ParseError::NotImplemented("template strings"))
     }
@@ -1164,7 +1491,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // TemplateMiddleList : TemplateMiddle Expression
     pub fn template_middle_list_single(
         &self,
-        _middle: arena::Box<'alloc, Token<'alloc>>,
+        _middle: Token<'alloc>,
         _expression: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Void>> {
         Err(ParseError::NotImplemented("template strings"))
@@ -1174,7 +1501,7 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn template_middle_list_append(
         &self,
         _middle_list: arena::Box<'alloc, Void>,
-        _middle: arena::Box<'alloc, Token<'alloc>>,
+        _middle: Token<'alloc>,
         _expression: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Void>> {
         Err(ParseError::NotImplemented("template strings"))
@@ -1183,18 +1510,22 @@ impl<'alloc> AstBuilder<'alloc> {
     // MemberExpression : MemberExpression `[` Expression `]`
     // CallExpression : CallExpression `[` Expression `]`
     pub fn computed_member_expr(
-        &self,
+        &mut self,
         object: arena::Box<'alloc, Expression<'alloc>>,
         expression: arena::Box<'alloc, Expression<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+        close_token: Token<'alloc>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let object_loc = object.get_loc();
-        self.alloc(Expression::MemberExpression(
-            MemberExpression::ComputedMemberExpression(ComputedMemberExpression {
-                object: ExpressionOrSuper::Expression(object),
-                expression,
-                loc: SourceLocation::from_parts(object_loc, close_token.loc),
-            }),
+        let depth = self.chain_depth(&[&object, &expression], expression.get_loc().start())?;
+        Ok(self.alloc_chain(
+            Expression::MemberExpression(MemberExpression::ComputedMemberExpression(
+                ComputedMemberExpression {
+                    object: ExpressionOrSuper::Expression(object),
+                    expression,
+                    loc: SourceLocation::from_parts(object_loc, close_token.loc),
+                },
+            )),
+            depth,
         ))
     }
 
@@ -1202,25 +1533,29 @@ impl<'alloc> AstBuilder<'alloc> {
     // OptionalExpression : CallExpression OptionalChain
     // OptionalExpression : OptionalExpression OptionalChain
     pub fn optional_expr(
-        &self,
+        &mut self,
         object: arena::Box<'alloc, Expression<'alloc>>,
         tail: arena::Box<'alloc, Expression<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let object_loc = object.get_loc();
         let expression_loc = tail.get_loc();
-        self.alloc(Expression::OptionalExpression {
-            object: ExpressionOrSuper::Expression(object),
-            tail,
-            loc: SourceLocation::from_parts(object_loc, expression_loc),
-        })
+        let depth = self.chain_depth(&[&object, &tail], expression_loc.start())?;
+        Ok(self.alloc_chain(
+            Expression::OptionalExpression {
+                object: ExpressionOrSuper::Expression(object),
+                tail,
+                loc: SourceLocation::from_parts(object_loc, expression_loc),
+            },
+            depth,
+        ))
     }
 
     // OptionalChain : `?.` `[` Expression `]`
     pub fn optional_computed_member_expr_tail(
         &self,
-        start_token: arena::Box<'alloc, Token<'alloc>>,
+        start_token: Token<'alloc>,
         expression: arena::Box<'alloc, Expression<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         self.alloc(Expression::OptionalChain(
             OptionalChain::ComputedMemberExpressionTail {
@@ -1233,8 +1568,8 @@ impl<'alloc> AstBuilder<'alloc> {
     // OptionalChain : `?.` Expression
     pub fn optional_static_member_expr_tail(
         &self,
-        start_token: arena::Box<'alloc, Token<'alloc>>,
-        identifier_token: arena::Box<'alloc, Token<'alloc>>,
+        start_token: Token<'alloc>,
+        identifier_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let identifier_token_loc = identifier_token.loc;
         self.alloc(Expression::OptionalChain(
@@ -1248,7 +1583,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // OptionalChain : `?.` Arguments
     pub fn optional_call_expr_tail(
         &self,
-        start_token: arena::Box<'alloc, Token<'alloc>>,
+        start_token: Token<'alloc>,
         arguments: arena::Box<'alloc, Arguments<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let arguments_loc = arguments.loc;
@@ -1269,75 +1604,83 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // OptionalChain : OptionalChain `[` Expression `]`
     pub fn optional_computed_member_expr(
-        &self,
+        &mut self,
         object: arena::Box<'alloc, Expression<'alloc>>,
         expression: arena::Box<'alloc, Expression<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+        close_token: Token<'alloc>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let object_loc = object.get_loc();
-        self.alloc(Expression::OptionalChain(
-            OptionalChain::ComputedMemberExpression(ComputedMemberExpression {
-                object: ExpressionOrSuper::Expression(object),
-                expression,
-                loc: SourceLocation::from_parts(object_loc, close_token.loc),
-            }),
+        let depth = self.chain_depth(&[&object, &expression], expression.get_loc().start())?;
+        Ok(self.alloc_chain(
+            Expression::OptionalChain(OptionalChain::ComputedMemberExpression(
+                ComputedMemberExpression {
+                    object: ExpressionOrSuper::Expression(object),
+                    expression,
+                    loc: SourceLocation::from_parts(object_loc, close_token.loc),
+                },
+            )),
+            depth,
         ))
     }
 
     // OptionalChain : OptionalChain `.` Expression
     pub fn optional_static_member_expr(
-        &self,
+        &mut self,
         object: arena::Box<'alloc, Expression<'alloc>>,
-        identifier_token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+        identifier_token: Token<'alloc>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let object_loc = object.get_loc();
         let identifier_token_loc = identifier_token.loc;
-        self.alloc(Expression::OptionalChain(
-            OptionalChain::StaticMemberExpression(StaticMemberExpression {
-                object: ExpressionOrSuper::Expression(object),
-                property: self.identifier_name(identifier_token),
-                loc: SourceLocation::from_parts(object_loc, identifier_token_loc),
-            }),
+        let depth = self.chain_depth(&[&object], identifier_token_loc.start())?;
+        let property = self.identifier_name(identifier_token);
+        Ok(self.alloc_chain(
+            Expression::OptionalChain(OptionalChain::StaticMemberExpression(
+                StaticMemberExpression {
+                    object: ExpressionOrSuper::Expression(object),
+                    property,
+                    loc: SourceLocation::from_parts(object_loc, identifier_token_loc),
+                },
+            )),
+            depth,
         ))
     }
 
     // OptionalChain : OptionalChain Arguments
     pub fn optional_call_expr(
-        &self,
+        &mut self,
         callee: arena::Box<'alloc, Expression<'alloc>>,
         arguments: arena::Box<'alloc, Arguments<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let callee_loc = callee.get_loc();
         let arguments_loc = arguments.loc;
-        self.alloc(Expression::OptionalChain(OptionalChain::CallExpression(
-            CallExpression {
+        let depth = self.chain_depth(&[&callee], arguments_loc.start())?;
+        Ok(self.alloc_chain(
+            Expression::OptionalChain(OptionalChain::CallExpression(CallExpression {
                 callee: ExpressionOrSuper::Expression(callee),
                 arguments: arguments.unbox(),
                 loc: SourceLocation::from_parts(callee_loc, arguments_loc),
-            },
-        )))
+            })),
+            depth,
+        ))
     }
 
-    fn identifier(&self, token: arena::Box<'alloc, Token<'alloc>>) -> Identifier<'alloc> {
+    fn identifier(&self, token: Token<'alloc>) -> Identifier<'alloc> {
         Identifier {
-            value: token.value.unwrap(),
+            value: token.str_value().unwrap(),
             loc: token.loc,
         }
     }
 
-    fn identifier_name(&self, token: arena::Box<'alloc, Token<'alloc>>) -> IdentifierName<'alloc> {
+    fn identifier_name(&self, token: Token<'alloc>) -> IdentifierName<'alloc> {
         IdentifierName {
-            value: token.value.unwrap(),
+            value: token.str_value().unwrap(),
             loc: token.loc,
         }
     }
 
-    fn private_identifier(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> PrivateIdentifier<'alloc> {
+    fn private_identifier(&self, token: Token<'alloc>) -> PrivateIdentifier<'alloc> {
         PrivateIdentifier {
-            value: token.value.unwrap(),
+            value: token.str_value().unwrap(),
             loc: token.loc,
         }
     }
@@ -1345,36 +1688,45 @@ impl<'alloc> AstBuilder<'alloc> {
     // MemberExpression : MemberExpression `.` IdentifierName
     // CallExpression : CallExpression `.` IdentifierName
     pub fn static_member_expr(
-        &self,
+        &mut self,
         object: arena::Box<'alloc, Expression<'alloc>>,
-        identifier_token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+        identifier_token: Token<'alloc>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let object_loc = object.get_loc();
         let identifier_token_loc = identifier_token.loc;
-        self.alloc(Expression::MemberExpression(
-            MemberExpression::StaticMemberExpression(StaticMemberExpression {
-                object: ExpressionOrSuper::Expression(object),
-                property: self.identifier_name(identifier_token),
-                loc: SourceLocation::from_parts(object_loc, identifier_token_loc),
-            }),
+        let depth = self.chain_depth(&[&object], identifier_token_loc.start())?;
+        let property = self.identifier_name(identifier_token);
+        Ok(self.alloc_chain(
+            Expression::MemberExpression(MemberExpression::StaticMemberExpression(
+                StaticMemberExpression {
+                    object: ExpressionOrSuper::Expression(object),
+                    property,
+                    loc: SourceLocation::from_parts(object_loc, identifier_token_loc),
+                },
+            )),
+            depth,
         ))
     }
 
     // MemberExpression : MemberExpression TemplateLiteral
     // CallExpression : CallExpression TemplateLiteral
     pub fn tagged_template_expr(
-        &self,
+        &mut self,
         tag: arena::Box<'alloc, Expression<'alloc>>,
         mut template_literal: arena::Box<'alloc, TemplateExpression<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        let depth = self.chain_depth(&[&tag], template_literal.loc.start())?;
         template_literal.tag = Some(tag);
-        self.alloc(Expression::TemplateExpression(template_literal.unbox()))
+        Ok(self.alloc_chain(
+            Expression::TemplateExpression(template_literal.unbox()),
+            depth,
+        ))
     }
 
     // MemberExpression : `new` MemberExpression Arguments
     pub fn new_expr_with_arguments(
         &self,
-        new_token: arena::Box<'alloc, Token<'alloc>>,
+        new_token: Token<'alloc>,
         callee: arena::Box<'alloc, Expression<'alloc>>,
         arguments: arena::Box<'alloc, Arguments<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
@@ -1388,29 +1740,36 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // MemberExpression : MemberExpression `.` PrivateIdentifier
     pub fn private_field_expr(
-        &self,
+        &mut self,
         object: arena::Box<'alloc, Expression<'alloc>>,
-        private_identifier: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+        private_identifier: Token<'alloc>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let object_loc = object.get_loc();
         let field_loc = private_identifier.loc;
-        self.alloc(Expression::MemberExpression(
-            MemberExpression::PrivateFieldExpression(PrivateFieldExpression {
-                object,
-                field: self.private_identifier(private_identifier),
-                loc: SourceLocation::from_parts(object_loc, field_loc),
-            }),
+        let depth = self.chain_depth(&[&object], field_loc.start())?;
+        self.on_private_name_reference(&private_identifier);
+        let field = self.private_identifier(private_identifier);
+        Ok(self.alloc_chain(
+            Expression::MemberExpression(MemberExpression::PrivateFieldExpression(
+                PrivateFieldExpression {
+                    object,
+                    field,
+                    loc: SourceLocation::from_parts(object_loc, field_loc),
+                },
+            )),
+            depth,
         ))
     }
 
     // SuperProperty : `super` `[` Expression `]`
     pub fn super_property_computed(
-        &self,
-        super_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        super_token: Token<'alloc>,
         expression: arena::Box<'alloc, Expression<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let super_loc = super_token.loc;
+        self.on_super_reference(SuperKind::Property, super_loc.start());
         self.alloc(Expression::MemberExpression(
             MemberExpression::ComputedMemberExpression(ComputedMemberExpression {
                 object: ExpressionOrSuper::Super { loc: super_loc },
@@ -1422,12 +1781,13 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // SuperProperty : `super` `.` IdentifierName
     pub fn super_property_static(
-        &self,
-        super_token: arena::Box<'alloc, Token<'alloc>>,
-        identifier_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        super_token: Token<'alloc>,
+        identifier_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let super_loc = super_token.loc;
         let identifier_loc = identifier_token.loc;
+        self.on_super_reference(SuperKind::Property, super_loc.start());
         self.alloc(Expression::MemberExpression(
             MemberExpression::StaticMemberExpression(StaticMemberExpression {
                 object: ExpressionOrSuper::Super { loc: super_loc },
@@ -1439,19 +1799,32 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // NewTarget : `new` `.` `target`
     pub fn new_target_expr(
-        &self,
-        new_token: arena::Box<'alloc, Token<'alloc>>,
-        target_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        new_token: Token<'alloc>,
+        target_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
+        self.on_function_reference(FunctionReferenceKind::NewTarget, new_token.loc.start());
         return self.alloc(Expression::NewTargetExpression {
             loc: SourceLocation::from_parts(new_token.loc, target_token.loc),
         });
     }
 
+    // ImportMeta : `import` `.` `meta`
+    pub fn import_meta_expr(
+        &mut self,
+        import_token: Token<'alloc>,
+        meta_token: Token<'alloc>,
+    ) -> arena::Box<'alloc, Expression<'alloc>> {
+        self.on_function_reference(FunctionReferenceKind::ImportMeta, import_token.loc.start());
+        self.alloc(Expression::ImportMetaExpression {
+            loc: SourceLocation::from_parts(import_token.loc, meta_token.loc),
+        })
+    }
+
     // NewExpression : `new` NewExpression
     pub fn new_expr_without_arguments(
         &self,
-        new_token: arena::Box<'alloc, Token<'alloc>>,
+        new_token: Token<'alloc>,
         callee: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let callee_loc = callee.get_loc();
@@ -1459,7 +1832,7 @@ impl<'alloc> AstBuilder<'alloc> {
             callee,
             arguments: Arguments {
                 args: self.new_vec(),
-                loc: SourceLocation::new(callee_loc.end, callee_loc.end),
+                loc: SourceLocation::new(callee_loc.end(), callee_loc.end()),
             },
             loc: SourceLocation::from_parts(new_token.loc, callee_loc),
         })
@@ -1469,27 +1842,32 @@ impl<'alloc> AstBuilder<'alloc> {
     // CoverCallExpressionAndAsyncArrowHead : MemberExpression Arguments
     // CallMemberExpression : MemberExpression Arguments
     pub fn call_expr(
-        &self,
+        &mut self,
         callee: arena::Box<'alloc, Expression<'alloc>>,
         arguments: arena::Box<'alloc, Arguments<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let callee_loc = callee.get_loc();
         let arguments_loc = arguments.loc;
-        self.alloc(Expression::CallExpression(CallExpression {
-            callee: ExpressionOrSuper::Expression(callee),
-            arguments: arguments.unbox(),
-            loc: SourceLocation::from_parts(callee_loc, arguments_loc),
-        }))
+        let depth = self.chain_depth(&[&callee], arguments_loc.start())?;
+        Ok(self.alloc_chain(
+            Expression::CallExpression(CallExpression {
+                callee: ExpressionOrSuper::Expression(callee),
+                arguments: arguments.unbox(),
+                loc: SourceLocation::from_parts(callee_loc, arguments_loc),
+            }),
+            depth,
+        ))
     }
 
     // SuperCall : `super` Arguments
     pub fn super_call(
-        &self,
-        super_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        super_token: Token<'alloc>,
         arguments: arena::Box<'alloc, Arguments<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let super_loc = super_token.loc;
         let arguments_loc = arguments.loc;
+        self.on_super_reference(SuperKind::Call, super_loc.start());
         self.alloc(Expression::CallExpression(CallExpression {
             callee: ExpressionOrSuper::Super { loc: super_loc },
             arguments: arguments.unbox(),
@@ -1500,9 +1878,9 @@ impl<'alloc> AstBuilder<'alloc> {
     // ImportCall : `import` `(` AssignmentExpression `)`
     pub fn import_call(
         &self,
-        import_token: arena::Box<'alloc, Token<'alloc>>,
+        import_token: Token<'alloc>,
         argument: arena::Box<'alloc, Expression<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         self.alloc(Expression::ImportCallExpression {
             argument,
@@ -1513,8 +1891,8 @@ impl<'alloc> AstBuilder<'alloc> {
     // Arguments : `(` `)`
     pub fn arguments_empty(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Arguments<'alloc>> {
         self.alloc(Arguments {
             args: self.new_vec(),
@@ -1548,9 +1926,9 @@ impl<'alloc> AstBuilder<'alloc> {
 
     pub fn arguments(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         mut arguments: arena::Box<'alloc, Arguments<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Arguments<'alloc>> {
         arguments.loc.set_range(open_token.loc, close_token.loc);
         arguments
@@ -1580,9 +1958,9 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // UpdateExpression : LeftHandSideExpression `++`
     pub fn post_increment_expr(
-        &self,
+        &mut self,
         operand: arena::Box<'alloc, Expression<'alloc>>,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        operator_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let operand = self.expression_to_simple_assignment_target(operand)?;
         let operand_loc = operand.get_loc();
@@ -1591,16 +1969,16 @@ impl<'alloc> AstBuilder<'alloc> {
             operator: UpdateOperator::Increment {
                 loc: operator_token.loc,
             },
-            operand,
+            operand: self.alloc(operand),
             loc: SourceLocation::from_parts(operand_loc, operator_token.loc),
         }))
     }
 
     // UpdateExpression : LeftHandSideExpression `--`
     pub fn post_decrement_expr(
-        &self,
+        &mut self,
         operand: arena::Box<'alloc, Expression<'alloc>>,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        operator_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let operand = self.expression_to_simple_assignment_target(operand)?;
         let operand_loc = operand.get_loc();
@@ -1609,15 +1987,15 @@ impl<'alloc> AstBuilder<'alloc> {
             operator: UpdateOperator::Decrement {
                 loc: operator_token.loc,
             },
-            operand,
+            operand: self.alloc(operand),
             loc: SourceLocation::from_parts(operand_loc, operator_token.loc),
         }))
     }
 
     // UpdateExpression : `++` UnaryExpression
     pub fn pre_increment_expr(
-        &self,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        operator_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let operand = self.expression_to_simple_assignment_target(operand)?;
@@ -1627,15 +2005,15 @@ impl<'alloc> AstBuilder<'alloc> {
             operator: UpdateOperator::Increment {
                 loc: operator_token.loc,
             },
-            operand,
+            operand: self.alloc(operand),
             loc: SourceLocation::from_parts(operator_token.loc, operand_loc),
         }))
     }
 
     // UpdateExpression : `--` UnaryExpression
     pub fn pre_decrement_expr(
-        &self,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        operator_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let operand = self.expression_to_simple_assignment_target(operand)?;
@@ -1645,7 +2023,7 @@ impl<'alloc> AstBuilder<'alloc> {
             operator: UpdateOperator::Decrement {
                 loc: operator_token.loc,
             },
-            operand,
+            operand: self.alloc(operand),
             loc: SourceLocation::from_parts(operator_token.loc, operand_loc),
         }))
     }
@@ -1653,7 +2031,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // UnaryExpression : `delete` UnaryExpression
     pub fn delete_expr(
         &self,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        operator_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let operand_loc = operand.get_loc();
@@ -1669,7 +2047,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // UnaryExpression : `void` UnaryExpression
     pub fn void_expr(
         &self,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        operator_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let operand_loc = operand.get_loc();
@@ -1685,7 +2063,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // UnaryExpression : `typeof` UnaryExpression
     pub fn typeof_expr(
         &self,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        operator_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let operand_loc = operand.get_loc();
@@ -1701,7 +2079,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // UnaryExpression : `+` UnaryExpression
     pub fn unary_plus_expr(
         &self,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        operator_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let operand_loc = operand.get_loc();
@@ -1717,7 +2095,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // UnaryExpression : `-` UnaryExpression
     pub fn unary_minus_expr(
         &self,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        operator_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let operand_loc = operand.get_loc();
@@ -1733,7 +2111,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // UnaryExpression : `~` UnaryExpression
     pub fn bitwise_not_expr(
         &self,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        operator_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let operand_loc = operand.get_loc();
@@ -1749,7 +2127,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // UnaryExpression : `!` UnaryExpression
     pub fn logical_not_expr(
         &self,
-        operator_token: arena::Box<'alloc, Token<'alloc>>,
+        operator_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let operand_loc = operand.get_loc();
@@ -1762,88 +2140,82 @@ impl<'alloc> AstBuilder<'alloc> {
         })
     }
 
-    pub fn equals_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn equals_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::Equals { loc: token.loc }
     }
-    pub fn not_equals_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn not_equals_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::NotEquals { loc: token.loc }
     }
-    pub fn strict_equals_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn strict_equals_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::StrictEquals { loc: token.loc }
     }
-    pub fn strict_not_equals_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn strict_not_equals_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::StrictNotEquals { loc: token.loc }
     }
-    pub fn less_than_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn less_than_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::LessThan { loc: token.loc }
     }
-    pub fn less_than_or_equal_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> BinaryOperator {
+    pub fn less_than_or_equal_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::LessThanOrEqual { loc: token.loc }
     }
-    pub fn greater_than_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn greater_than_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::GreaterThan { loc: token.loc }
     }
-    pub fn greater_than_or_equal_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> BinaryOperator {
+    pub fn greater_than_or_equal_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::GreaterThanOrEqual { loc: token.loc }
     }
-    pub fn in_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn in_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::In { loc: token.loc }
     }
-    pub fn instanceof_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn instanceof_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::Instanceof { loc: token.loc }
     }
-    pub fn left_shift_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn left_shift_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::LeftShift { loc: token.loc }
     }
-    pub fn right_shift_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn right_shift_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::RightShift { loc: token.loc }
     }
-    pub fn right_shift_ext_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn right_shift_ext_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::RightShiftExt { loc: token.loc }
     }
-    pub fn add_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn add_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::Add { loc: token.loc }
     }
-    pub fn sub_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn sub_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::Sub { loc: token.loc }
     }
-    pub fn mul_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn mul_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::Mul { loc: token.loc }
     }
-    pub fn div_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn div_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::Div { loc: token.loc }
     }
-    pub fn mod_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn mod_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::Mod { loc: token.loc }
     }
-    pub fn pow_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn pow_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::Pow { loc: token.loc }
     }
-    pub fn comma_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn comma_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::Comma { loc: token.loc }
     }
-    pub fn coalesce_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn coalesce_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::Coalesce { loc: token.loc }
     }
-    pub fn logical_or_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn logical_or_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::LogicalOr { loc: token.loc }
     }
-    pub fn logical_and_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn logical_and_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::LogicalAnd { loc: token.loc }
     }
-    pub fn bitwise_or_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn bitwise_or_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::BitwiseOr { loc: token.loc }
     }
-    pub fn bitwise_xor_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn bitwise_xor_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::BitwiseXor { loc: token.loc }
     }
-    pub fn bitwise_and_op(&self, token: arena::Box<'alloc, Token<'alloc>>) -> BinaryOperator {
+    pub fn bitwise_and_op(&self, token: Token<'alloc>) -> BinaryOperator {
         BinaryOperator::BitwiseAnd { loc: token.loc }
     }
 
@@ -1856,11 +2228,11 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // MultiplicativeExpression : MultiplicativeExpression MultiplicativeOperator ExponentiationExpression
     pub fn multiplicative_expr(
-        &self,
+        &mut self,
         left: arena::Box<'alloc, Expression<'alloc>>,
         operator: arena::Box<'alloc, BinaryOperator>,
         right: arena::Box<'alloc, Expression<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         self.binary_expr(operator.unbox(), left, right)
     }
 
@@ -1886,19 +2258,23 @@ impl<'alloc> AstBuilder<'alloc> {
     // LogicalANDExpression : LogicalANDExpression `&&` BitwiseORExpression
     // LogicalORExpression : LogicalORExpression `||` LogicalANDExpression
     pub fn binary_expr(
-        &self,
+        &mut self,
         operator: BinaryOperator,
         left: arena::Box<'alloc, Expression<'alloc>>,
         right: arena::Box<'alloc, Expression<'alloc>>,
-    ) -> arena::Box<'alloc, Expression<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let left_loc = left.get_loc();
         let right_loc = right.get_loc();
-        self.alloc(Expression::BinaryExpression {
-            operator,
-            left,
-            right,
-            loc: SourceLocation::from_parts(left_loc, right_loc),
-        })
+        let depth = self.chain_depth(&[&left, &right], right_loc.start())?;
+        Ok(self.alloc_chain(
+            Expression::BinaryExpression {
+                operator,
+                left,
+                right,
+                loc: SourceLocation::from_parts(left_loc, right_loc),
+            },
+            depth,
+        ))
     }
 
     // ConditionalExpression : LogicalORExpression `?` AssignmentExpression `:` AssignmentExpression
@@ -1920,24 +2296,28 @@ impl<'alloc> AstBuilder<'alloc> {
 
     /// Refine an *ArrayLiteral* into an *ArrayAssignmentPattern*.
     fn array_expression_to_array_assignment_target(
-        &self,
+        &mut self,
         mut elements: arena::Vec<'alloc, ArrayExpressionElement<'alloc>>,
         loc: SourceLocation,
     ) -> Result<'alloc, ArrayAssignmentTarget<'alloc>> {
         let spread = self.pop_trailing_spread_element(&mut elements);
-        let elements =
-            self.collect_vec_from_results(elements.into_iter().map(|element| match element {
-                ArrayExpressionElement::SpreadElement(_) => Err(ParseError::NotImplemented(
-                    "rest destructuring in array pattern",
-                )),
-                ArrayExpressionElement::Expression(expression) => Ok(Some(
-                    self.expression_to_assignment_target_maybe_default(expression)?,
-                )),
-                ArrayExpressionElement::Elision { .. } => Ok(None),
-            }))?;
-        let rest: Option<Result<'alloc, arena::Box<'alloc, AssignmentTarget<'alloc>>>> =
-            spread.map(|expr| Ok(self.alloc(self.expression_to_assignment_target(expr)?)));
-        let rest = rest.transpose()?;
+        let mut targets = self.new_vec();
+        for element in elements {
+            let target = match element {
+                ArrayExpressionElement::SpreadElement(_) => {
+                    return Err(ParseError::NotImplemented(
+                        "rest destructuring in array pattern",
+                    ))
+                }
+                ArrayExpressionElement::Expression(expression) => {
+                    Some(self.expression_to_assignment_target_maybe_default(expression)?)
+                }
+                ArrayExpressionElement::Elision { .. } => None,
+            };
+            self.push(&mut targets, target);
+        }
+        let elements = targets;
+        let rest = self.spread_expression_to_rest_assignment_target(spread)?;
         Ok(ArrayAssignmentTarget {
             elements,
             rest,
@@ -1946,7 +2326,7 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     fn object_property_to_assignment_target_property(
-        &self,
+        &mut self,
         property: arena::Box<'alloc, ObjectProperty<'alloc>>,
     ) -> Result<'alloc, AssignmentTargetProperty<'alloc>> {
         Ok(match property.unbox() {
@@ -1960,49 +2340,79 @@ impl<'alloc> AstBuilder<'alloc> {
                     expression,
                     loc,
                 },
-            )) => AssignmentTargetProperty::AssignmentTargetPropertyProperty(
-                AssignmentTargetPropertyProperty {
-                    name: property_name,
-                    binding: self.expression_to_assignment_target_maybe_default(expression)?,
-                    loc,
-                },
-            ),
+            )) => {
+                let binding = self.expression_to_assignment_target_maybe_default(expression)?;
+                AssignmentTargetProperty::AssignmentTargetPropertyProperty(self.alloc(
+                    AssignmentTargetPropertyProperty {
+                        name: property_name,
+                        binding,
+                        loc,
+                    },
+                ))
+            }
 
-            ObjectProperty::ShorthandProperty(ShorthandProperty {
-                name: IdentifierExpression { name, loc },
-                ..
+            ObjectProperty::ProtoProperty(ProtoProperty {
+                name,
+                expression,
+                loc,
             }) => {
-                // TODO - support CoverInitializedName
-                AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(
-                    AssignmentTargetPropertyIdentifier {
-                        binding: AssignmentTargetIdentifier { name, loc },
-                        init: None,
+                self.remove_duplicate_proto(loc.start());
+                let binding = self.expression_to_assignment_target_maybe_default(expression)?;
+                AssignmentTargetProperty::AssignmentTargetPropertyProperty(self.alloc(
+                    AssignmentTargetPropertyProperty {
+                        name: PropertyName::StaticPropertyName(name),
+                        binding,
                         loc,
                     },
-                )
+                ))
             }
 
+            ObjectProperty::ShorthandProperty(ShorthandProperty {
+                name: IdentifierExpression { name, loc },
+                loc: property_loc,
+            }) => AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(
+                AssignmentTargetPropertyIdentifier {
+                    binding: AssignmentTargetIdentifier { name, loc },
+                    init: self.take_cover_initializer(loc.start()),
+                    loc: property_loc,
+                },
+            ),
+
             ObjectProperty::SpreadProperty(_expression) => {
                 return Err(ParseError::ObjectPatternWithNonFinalRest)
             }
         })
     }
 
+    /// Refine the trailing spread of an *ArrayLiteral* or *ObjectLiteral* into
+    /// the rest element of the assignment pattern.
+    fn spread_expression_to_rest_assignment_target(
+        &mut self,
+        spread: Option<arena::Box<'alloc, Expression<'alloc>>>,
+    ) -> Result<'alloc, Option<arena::Box<'alloc, AssignmentTarget<'alloc>>>> {
+        Ok(match spread {
+            Some(expr) => {
+                let target = self.expression_to_assignment_target(expr)?;
+                Some(self.alloc(target))
+            }
+            None => None,
+        })
+    }
+
     // Refine an *ObjectLiteral* into an *ObjectAssignmentPattern*.
     fn object_expression_to_object_assignment_target(
-        &self,
+        &mut self,
         mut properties: arena::Vec<'alloc, arena::Box<'alloc, ObjectProperty<'alloc>>>,
         loc: SourceLocation,
     ) -> Result<'alloc, ObjectAssignmentTarget<'alloc>> {
         let spread = self.pop_trailing_spread_property(&mut properties);
-        let properties = self.collect_vec_from_results(
-            properties
-                .into_iter()
-                .map(|p| self.object_property_to_assignment_target_property(p)),
-        )?;
-        let rest: Option<Result<'alloc, arena::Box<'alloc, AssignmentTarget<'alloc>>>> =
-            spread.map(|expr| Ok(self.alloc(self.expression_to_assignment_target(expr)?)));
-        let rest = rest.transpose()?;
+        let mut targets = self.new_vec();
+        for p in properties {
+            let target = self.object_property_to_assignment_target_property(p)?;
+            self.push(&mut targets, target);
+        }
+        let properties = targets;
+        let rest = self.spread_expression_to_rest_assignment_target(spread)?;
         Ok(ObjectAssignmentTarget {
             properties,
             rest,
@@ -2011,7 +2421,7 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     fn expression_to_assignment_target_maybe_default(
-        &self,
+        &mut self,
         expression: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, AssignmentTargetMaybeDefault<'alloc>> {
         Ok(match expression.unbox() {
@@ -2034,7 +2444,7 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     fn expression_to_assignment_target(
-        &self,
+        &mut self,
         expression: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, AssignmentTarget<'alloc>> {
         Ok(match expression.unbox() {
@@ -2061,7 +2471,7 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     fn expression_to_simple_assignment_target(
-        &self,
+        &mut self,
         expression: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, SimpleAssignmentTarget<'alloc>> {
         Ok(match expression.unbox() {
@@ -2074,9 +2484,10 @@ impl<'alloc> AstBuilder<'alloc> {
                 //    code and StringValue of Identifier is "eval" or
                 //    "arguments", return invalid.
                 if name.value == "arguments" || name.value == "eval" {
-                    if self.is_strict()? {
-                        return Err(ParseError::InvalidAssignmentTarget);
-                    }
+                    self.on_strict_mode_violation(
+                        StrictModeViolationKind::EvalOrArgumentsAssignment,
+                        loc.start(),
+                    )?;
                 }
 
                 // 2. Return simple.
@@ -2156,7 +2567,7 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // AssignmentExpression : LeftHandSideExpression `=` AssignmentExpression
     pub fn assignment_expr(
-        &self,
+        &mut self,
         left_hand_side: arena::Box<'alloc, Expression<'alloc>>,
         value: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
@@ -2170,76 +2581,40 @@ impl<'alloc> AstBuilder<'alloc> {
         }))
     }
 
-    pub fn add_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn add_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::Add { loc: token.loc }
     }
-    pub fn sub_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn sub_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::Sub { loc: token.loc }
     }
-    pub fn mul_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn mul_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::Mul { loc: token.loc }
     }
-    pub fn div_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn div_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::Div { loc: token.loc }
     }
-    pub fn mod_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn mod_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::Mod { loc: token.loc }
     }
-    pub fn pow_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn pow_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::Pow { loc: token.loc }
     }
-    pub fn left_shift_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn left_shift_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::LeftShift { loc: token.loc }
     }
-    pub fn right_shift_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn right_shift_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::RightShift { loc: token.loc }
     }
-    pub fn right_shift_ext_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn right_shift_ext_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::RightShiftExt { loc: token.loc }
     }
-    pub fn bitwise_or_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn bitwise_or_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::Or { loc: token.loc }
     }
-    pub fn bitwise_xor_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn bitwise_xor_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::Xor { loc: token.loc }
     }
-    pub fn bitwise_and_assign_op(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> CompoundAssignmentOperator {
+    pub fn bitwise_and_assign_op(&self, token: Token<'alloc>) -> CompoundAssignmentOperator {
         CompoundAssignmentOperator::And { loc: token.loc }
     }
 
@@ -2252,7 +2627,7 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // AssignmentExpression : LeftHandSideExpression AssignmentOperator AssignmentExpression
     pub fn compound_assignment_expr(
-        &self,
+        &mut self,
         left_hand_side: arena::Box<'alloc, Expression<'alloc>>,
         operator: arena::Box<'alloc, CompoundAssignmentOperator>,
         value: arena::Box<'alloc, Expression<'alloc>>,
@@ -2262,7 +2637,50 @@ impl<'alloc> AstBuilder<'alloc> {
         let value_loc = value.get_loc();
         Ok(self.alloc(Expression::CompoundAssignmentExpression {
             operator: operator.unbox(),
-            binding: target,
+            binding: self.alloc(target),
+            expression: value,
+            loc: SourceLocation::from_parts(target_loc, value_loc),
+        }))
+    }
+
+    pub fn logical_and_assign_op(&self, token: Token<'alloc>) -> LogicalAssignmentOperator {
+        LogicalAssignmentOperator::And { loc: token.loc }
+    }
+    pub fn logical_or_assign_op(&self, token: Token<'alloc>) -> LogicalAssignmentOperator {
+        LogicalAssignmentOperator::Or { loc: token.loc }
+    }
+    pub fn coalesce_assign_op(&self, token: Token<'alloc>) -> LogicalAssignmentOperator {
+        LogicalAssignmentOperator::Coalesce { loc: token.loc }
+    }
+
+    pub fn box_logical_assign_op(
+        &self,
+        op: LogicalAssignmentOperator,
+    ) -> arena::Box<'alloc, LogicalAssignmentOperator> {
+        self.alloc(op)
+    }
+
+    // AssignmentExpression : LeftHandSideExpression `&&=` AssignmentExpression
+    // AssignmentExpression : LeftHandSideExpression `||=` AssignmentExpression
+    // AssignmentExpression : LeftHandSideExpression `??=` AssignmentExpression
+    pub fn logical_assignment_expr(
+        &mut self,
+        left_hand_side: arena::Box<'alloc, Expression<'alloc>>,
+        operator: arena::Box<'alloc, LogicalAssignmentOperator>,
+        value: arena::Box<'alloc, Expression<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        // Unlike `=` and the compound assignment operators, the web
+        // compatibility exception for call expressions as assignment targets
+        // does not extend to logical assignment.
+        if let Expression::CallExpression(_) = *left_hand_side {
+            return Err(ParseError::InvalidAssignmentTarget);
+        }
+        let target = self.expression_to_simple_assignment_target(left_hand_side)?;
+        let target_loc = target.get_loc();
+        let value_loc = value.get_loc();
+        Ok(self.alloc(Expression::LogicalAssignmentExpression {
+            operator: operator.unbox(),
+            binding: self.alloc(target),
             expression: value,
             loc: SourceLocation::from_parts(target_loc, value_loc),
         }))
@@ -2283,11 +2701,11 @@ impl<'alloc> AstBuilder<'alloc> {
     // Block : `{` StatementList? `}`
     pub fn block(
         &mut self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         statements: Option<arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Block<'alloc>>> {
-        self.check_block_bindings(open_token.loc.start)?;
+        self.check_block_bindings(open_token.loc.start())?;
 
         Ok(self.alloc(Block {
             statements: match statements {
@@ -2303,9 +2721,9 @@ impl<'alloc> AstBuilder<'alloc> {
     // for Catch
     pub fn catch_block(
         &mut self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         statements: Option<arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Block<'alloc>> {
         // Early Error handling is done in Catch.
 
@@ -2333,7 +2751,15 @@ impl<'alloc> AstBuilder<'alloc> {
         mut list: arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>,
         statement: arena::Box<'alloc, Statement<'alloc>>,
     ) -> arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>> {
-        self.push(&mut list, statement.unbox());
+        // In the body of a lazy function, keep the Directive Prologue, the
+        // statement that ends it, and the last statement, whose end is the end
+        // of the body.
+        let len = list.len();
+        if self.lazy_function_depth > 0 && len >= 2 && !Self::is_directive(&list[len - 2]) {
+            list[len - 1] = statement.unbox();
+        } else {
+            self.push(&mut list, statement.unbox());
+        }
         list
     }
 
@@ -2349,7 +2775,8 @@ impl<'alloc> AstBuilder<'alloc> {
             _ => panic!("unexpected VariableDeclarationKind"),
         };
 
-        self.mark_binding_kind(kind.get_loc().start, None, binding_kind);
+        self.context_metadata
+            .mark_binding_kind(kind.get_loc().start(), None, binding_kind);
 
         // 13.3.1.1 Static Semantics: Early Errors
         if let VariableDeclarationKind::Const { .. } = *kind {
@@ -2387,7 +2814,8 @@ impl<'alloc> AstBuilder<'alloc> {
             VariableDeclarationKind::Const { .. } => BindingKind::Const,
             _ => panic!("unexpected VariableDeclarationKind"),
         };
-        self.mark_binding_kind(kind.get_loc().start, None, binding_kind);
+        self.context_metadata
+            .mark_binding_kind(kind.get_loc().start(), None, binding_kind);
 
         // 13.3.1.1 Static Semantics: Early Errors
         if let VariableDeclarationKind::Const { .. } = *kind {
@@ -2417,25 +2845,19 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     // LetOrConst : `let`
-    pub fn let_kind(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, VariableDeclarationKind> {
+    pub fn let_kind(&self, token: Token<'alloc>) -> arena::Box<'alloc, VariableDeclarationKind> {
         self.alloc(VariableDeclarationKind::Let { loc: token.loc })
     }
 
     // LetOrConst : `const`
-    pub fn const_kind(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, VariableDeclarationKind> {
+    pub fn const_kind(&self, token: Token<'alloc>) -> arena::Box<'alloc, VariableDeclarationKind> {
         self.alloc(VariableDeclarationKind::Const { loc: token.loc })
     }
 
     // VariableStatement : `var` VariableDeclarationList `;`
     pub fn variable_statement(
         &mut self,
-        var_token: arena::Box<'alloc, Token<'alloc>>,
+        var_token: Token<'alloc>,
         declarators: arena::Box<'alloc, arena::Vec<'alloc, VariableDeclarator<'alloc>>>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
         let var_loc = var_token.loc;
@@ -2444,7 +2866,8 @@ impl<'alloc> AstBuilder<'alloc> {
             .expect("There should be at least one declarator")
             .get_loc();
 
-        self.mark_binding_kind(var_loc.start, None, BindingKind::Var);
+        self.context_metadata
+            .mark_binding_kind(var_loc.start(), None, BindingKind::Var);
 
         self.alloc(Statement::VariableDeclarationStatement(
             VariableDeclaration {
@@ -2500,10 +2923,10 @@ impl<'alloc> AstBuilder<'alloc> {
     // ObjectBindingPattern : `{` BindingPropertyList `,` BindingRestProperty? `}`
     pub fn object_binding_pattern(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         properties: arena::Box<'alloc, arena::Vec<'alloc, BindingProperty<'alloc>>>,
         rest: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Binding<'alloc>> {
         self.alloc(Binding::BindingPattern(BindingPattern::ObjectBinding(
             ObjectBinding {
@@ -2525,11 +2948,11 @@ impl<'alloc> AstBuilder<'alloc> {
     // ArrayBindingPattern : `[` BindingElementList `,` Elision? BindingRestElement? `]`
     pub fn array_binding_pattern(
         &self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         mut elements: arena::Box<'alloc, arena::Vec<'alloc, Option<Parameter<'alloc>>>>,
         elision: Option<arena::Box<'alloc, ArrayExpression<'alloc>>>,
         rest: Option<arena::Box<'alloc, Binding<'alloc>>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, Binding<'alloc>> {
         if let Some(elision) = elision {
             for _ in 0..elision.elements.len() {
@@ -2637,13 +3060,13 @@ impl<'alloc> AstBuilder<'alloc> {
     ) -> arena::Box<'alloc, BindingProperty<'alloc>> {
         let name_loc = name.get_loc();
         let binding_loc = binding.get_loc();
-        self.alloc(BindingProperty::BindingPropertyProperty(
+        self.alloc(BindingProperty::BindingPropertyProperty(self.alloc(
             BindingPropertyProperty {
                 name: name.unbox(),
                 binding: binding.unbox(),
                 loc: SourceLocation::from_parts(name_loc, binding_loc),
             },
-        ))
+        )))
     }
 
     // BindingElement : BindingPattern Initializer?
@@ -2696,10 +3119,7 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     // EmptyStatement : `;`
-    pub fn empty_statement(
-        &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, Statement<'alloc>> {
+    pub fn empty_statement(&self, token: Token<'alloc>) -> arena::Box<'alloc, Statement<'alloc>> {
         self.alloc(Statement::EmptyStatement { loc: token.loc })
     }
 
@@ -2715,7 +3135,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // IfStatement : `if` `(` Expression `)` Statement
     pub fn if_statement(
         &self,
-        if_token: arena::Box<'alloc, Token<'alloc>>,
+        if_token: Token<'alloc>,
         test: arena::Box<'alloc, Expression<'alloc>>,
         consequent: arena::Box<'alloc, Statement<'alloc>>,
         alternate: Option<arena::Box<'alloc, Statement<'alloc>>>,
@@ -2754,16 +3174,17 @@ impl<'alloc> AstBuilder<'alloc> {
         // https://tc39.es/ecma262/#sec-functiondeclarations-in-ifstatement-statement-clauses
         //
         // This production only applies when parsing non-strict code.
-        if self.is_strict()? {
-            return Err(ParseError::FunctionDeclInSingleStatement);
-        }
-
+        //
+        // Whether the code is strict isn't known yet, and the
+        // FunctionDeclaration is rejected by check_strict_block_functions
+        // when the parser reaches the end of strict mode code.
+        //
         // Code matching this production is processed as if each matching
         // occurrence of FunctionDeclaration[?Yield, ?Await, ~Default] was the
         // sole StatementListItem of a BlockStatement occupying that position
         // in the source code. The semantics of such a synthetic BlockStatement
         // includes the web legacy compatibility semantics specified in B.3.3.
-        self.check_block_bindings(fun_loc.start)?;
+        self.check_block_bindings_with(fun_loc.start(), true)?;
 
         Ok(self.alloc(Statement::BlockStatement {
             block: Block {
@@ -2775,21 +3196,16 @@ impl<'alloc> AstBuilder<'alloc> {
         }))
     }
 
-    fn is_strict(&self) -> Result<'alloc, bool> {
-        Err(ParseError::NotImplemented(
-            "strict-mode-only early error is not yet supported",
-        ))
-    }
-
     // IterationStatement : `do` Statement `while` `(` Expression `)` `;`
     pub fn do_while_statement(
-        &self,
-        do_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        do_token: Token<'alloc>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
         test: arena::Box<'alloc, Expression<'alloc>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
         self.check_single_statement(&stmt)?;
+        self.resolve_unlabelled_jumps(do_token.loc.start(), close_token.loc.end(), true);
 
         Ok(self.alloc(Statement::DoWhileStatement {
             block: stmt,
@@ -2800,14 +3216,15 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // IterationStatement : `while` `(` Expression `)` Statement
     pub fn while_statement(
-        &self,
-        while_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        while_token: Token<'alloc>,
         test: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
         self.check_single_statement(&stmt)?;
 
         let stmt_loc = stmt.get_loc();
+        self.resolve_unlabelled_jumps(while_token.loc.start(), stmt_loc.end(), true);
         Ok(self.alloc(Statement::WhileStatement {
             test,
             block: stmt,
@@ -2818,8 +3235,8 @@ impl<'alloc> AstBuilder<'alloc> {
     // IterationStatement : `for` `(` [lookahead != 'let'] Expression? `;` Expression? `;` Expression? `)` Statement
     // IterationStatement : `for` `(` `var` VariableDeclarationList `;` Expression? `;` Expression? `)` Statement
     pub fn for_statement(
-        &self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        for_token: Token<'alloc>,
         init: Option<VariableDeclarationOrExpression<'alloc>>,
         test: Option<arena::Box<'alloc, Expression<'alloc>>>,
         update: Option<arena::Box<'alloc, Expression<'alloc>>>,
@@ -2832,7 +3249,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // IterationStatement : `for` `(` ForLexicalDeclaration Expression? `;` Expression? `)` Statement
     pub fn for_statement_lexical(
         &mut self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
+        for_token: Token<'alloc>,
         init: VariableDeclarationOrExpression<'alloc>,
         test: Option<arena::Box<'alloc, Expression<'alloc>>>,
         update: Option<arena::Box<'alloc, Expression<'alloc>>>,
@@ -2844,14 +3261,15 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     pub fn for_statement_common(
-        &self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        for_token: Token<'alloc>,
         init: Option<VariableDeclarationOrExpression<'alloc>>,
         test: Option<arena::Box<'alloc, Expression<'alloc>>>,
         update: Option<arena::Box<'alloc, Expression<'alloc>>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
         let stmt_loc = stmt.get_loc();
+        self.resolve_unlabelled_jumps(for_token.loc.start(), stmt_loc.end(), true);
         Ok(self.alloc(Statement::ForStatement {
             init,
             test,
@@ -2870,7 +3288,7 @@ impl<'alloc> AstBuilder<'alloc> {
 
     pub fn for_var_declaration(
         &mut self,
-        var_token: arena::Box<'alloc, Token<'alloc>>,
+        var_token: Token<'alloc>,
         declarators: arena::Box<'alloc, arena::Vec<'alloc, VariableDeclarator<'alloc>>>,
     ) -> VariableDeclarationOrExpression<'alloc> {
         let var_loc = var_token.loc;
@@ -2879,7 +3297,11 @@ impl<'alloc> AstBuilder<'alloc> {
             .expect("There should be at least one declarator")
             .get_loc();
 
-        self.mark_binding_kind(var_loc.start, Some(declarator_loc.end), BindingKind::Var);
+        self.context_metadata.mark_binding_kind(
+            var_loc.start(),
+            Some(declarator_loc.end()),
+            BindingKind::Var,
+        );
 
         VariableDeclarationOrExpression::VariableDeclaration(VariableDeclaration {
             kind: VariableDeclarationKind::Var { loc: var_loc },
@@ -2903,8 +3325,8 @@ impl<'alloc> AstBuilder<'alloc> {
     //
     // IterationStatement :  `for` `(` `var` BindingIdentifier Initializer `in` Expression `)` Statement
     pub fn for_in_statement(
-        &self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        for_token: Token<'alloc>,
         left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
@@ -2916,7 +3338,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // IterationStatement : `for` `(` ForDeclaration `in` Expression `)` Statement
     pub fn for_in_statement_lexical(
         &mut self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
+        for_token: Token<'alloc>,
         left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
@@ -2927,15 +3349,16 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     pub fn for_in_statement_common(
-        &self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        for_token: Token<'alloc>,
         left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
         let stmt_loc = stmt.get_loc();
+        self.resolve_unlabelled_jumps(for_token.loc.start(), stmt_loc.end(), true);
         Ok(self.alloc(Statement::ForInStatement {
-            left,
+            left: self.alloc(left),
             right,
             block: stmt,
             loc: SourceLocation::from_parts(for_token.loc, stmt_loc),
@@ -2944,7 +3367,7 @@ impl<'alloc> AstBuilder<'alloc> {
 
     pub fn for_in_or_of_var_declaration(
         &mut self,
-        var_token: arena::Box<'alloc, Token<'alloc>>,
+        var_token: Token<'alloc>,
         binding: arena::Box<'alloc, Binding<'alloc>>,
         init: Option<arena::Box<'alloc, Expression<'alloc>>>,
     ) -> VariableDeclarationOrAssignmentTarget<'alloc> {
@@ -2955,7 +3378,11 @@ impl<'alloc> AstBuilder<'alloc> {
             None => binding_loc,
         };
 
-        self.mark_binding_kind(binding_loc.start, Some(binding_loc.end), BindingKind::Var);
+        self.context_metadata.mark_binding_kind(
+            binding_loc.start(),
+            Some(binding_loc.end()),
+            BindingKind::Var,
+        );
 
         VariableDeclarationOrAssignmentTarget::VariableDeclaration(VariableDeclaration {
             kind: VariableDeclarationKind::Var { loc: var_loc },
@@ -2969,7 +3396,7 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     pub fn for_assignment_target(
-        &self,
+        &mut self,
         expression: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, VariableDeclarationOrAssignmentTarget<'alloc>> {
         Ok(VariableDeclarationOrAssignmentTarget::AssignmentTarget(
@@ -2987,8 +3414,8 @@ impl<'alloc> AstBuilder<'alloc> {
     // IterationStatement : `for` `(` [lookahead != 'let'] LeftHandSideExpression `of` AssignmentExpression `)` Statement
     // IterationStatement : `for` `(` `var` ForBinding `of` AssignmentExpression `)` Statement
     pub fn for_of_statement(
-        &self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        for_token: Token<'alloc>,
         left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
@@ -3000,7 +3427,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // IterationStatement : `for` `(` ForDeclaration `of` AssignmentExpression `)` Statement
     pub fn for_of_statement_lexical(
         &mut self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
+        for_token: Token<'alloc>,
         left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
@@ -3011,15 +3438,16 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     pub fn for_of_statement_common(
-        &self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        for_token: Token<'alloc>,
         left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
         let stmt_loc = stmt.get_loc();
+        self.resolve_unlabelled_jumps(for_token.loc.start(), stmt_loc.end(), true);
         Ok(self.alloc(Statement::ForOfStatement {
-            left,
+            left: self.alloc(left),
             right,
             block: stmt,
             loc: SourceLocation::from_parts(for_token.loc, stmt_loc),
@@ -3029,9 +3457,9 @@ impl<'alloc> AstBuilder<'alloc> {
     // IterationStatement : `for` `await` `(` [lookahead != 'let'] LeftHandSideExpression `of` AssignmentExpression `)` Statement
     // IterationStatement : `for` `await` `(` `var` ForBinding `of` AssignmentExpression `)` Statement
     pub fn for_await_of_statement(
-        &self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
-        left: VariableDeclarationOrAssignmentTarget,
+        &mut self,
+        for_token: Token<'alloc>,
+        left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
@@ -3042,8 +3470,8 @@ impl<'alloc> AstBuilder<'alloc> {
     // IterationStatement : `for` `await` `(` ForDeclaration `of` AssignmentExpression `)` Statement
     pub fn for_await_of_statement_lexical(
         &mut self,
-        for_token: arena::Box<'alloc, Token<'alloc>>,
-        left: VariableDeclarationOrAssignmentTarget,
+        for_token: Token<'alloc>,
+        left: VariableDeclarationOrAssignmentTarget<'alloc>,
         right: arena::Box<'alloc, Expression<'alloc>>,
         stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
@@ -3053,15 +3481,21 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     pub fn for_await_of_statement_common(
-        &self,
-        _for_token: arena::Box<'alloc, Token<'alloc>>,
-        _left: VariableDeclarationOrAssignmentTarget,
-        _right: arena::Box<'alloc, Expression<'alloc>>,
-        _stmt: arena::Box<'alloc, Statement<'alloc>>,
+        &mut self,
+        for_token: Token<'alloc>,
+        left: VariableDeclarationOrAssignmentTarget<'alloc>,
+        right: arena::Box<'alloc, Expression<'alloc>>,
+        stmt: arena::Box<'alloc, Statement<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
-        Err(ParseError::NotImplemented(
-            "for await statement (missing from AST)",
-        ))
+        let stmt_loc = stmt.get_loc();
+        self.resolve_unlabelled_jumps(for_token.loc.start(), stmt_loc.end(), true);
+        self.on_function_reference(FunctionReferenceKind::ForAwait, for_token.loc.start());
+        Ok(self.alloc(Statement::ForAwaitOfStatement {
+            left: self.alloc(left),
+            right,
+            block: stmt,
+            loc: SourceLocation::from_parts(for_token.loc, stmt_loc),
+        }))
     }
 
     // ForDeclaration : LetOrConst ForBinding => ForDeclaration($0, $1)
@@ -3076,7 +3510,8 @@ impl<'alloc> AstBuilder<'alloc> {
             _ => panic!("unexpected VariableDeclarationKind"),
         };
 
-        self.mark_binding_kind(kind.get_loc().start, None, binding_kind);
+        self.context_metadata
+            .mark_binding_kind(kind.get_loc().start(), None, binding_kind);
 
         let kind_loc = kind.get_loc();
         let binding_loc = binding.get_loc();
@@ -3107,11 +3542,16 @@ impl<'alloc> AstBuilder<'alloc> {
     // ContinueStatement : `continue` `;`
     // ContinueStatement : `continue` LabelIdentifier `;`
     pub fn continue_statement(
-        &self,
-        continue_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        continue_token: Token<'alloc>,
         label: Option<arena::Box<'alloc, Label<'alloc>>>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
         let continue_loc = continue_token.loc;
+        self.on_jump(
+            JumpKind::Continue,
+            label.as_ref().map(|l| l.value),
+            continue_loc.start(),
+        );
         let loc = match label {
             Some(ref label) => SourceLocation::from_parts(continue_loc, label.loc),
             None => continue_loc,
@@ -3125,11 +3565,16 @@ impl<'alloc> AstBuilder<'alloc> {
     // BreakStatement : `break` `;`
     // BreakStatement : `break` LabelIdentifier `;`
     pub fn break_statement(
-        &self,
-        break_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        break_token: Token<'alloc>,
         label: Option<arena::Box<'alloc, Label<'alloc>>>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
         let break_loc = break_token.loc;
+        self.on_jump(
+            JumpKind::Break,
+            label.as_ref().map(|l| l.value),
+            break_loc.start(),
+        );
         let loc = match label {
             Some(ref label) => SourceLocation::from_parts(break_loc, label.loc),
             None => break_loc,
@@ -3143,11 +3588,12 @@ impl<'alloc> AstBuilder<'alloc> {
     // ReturnStatement : `return` `;`
     // ReturnStatement : `return` Expression `;`
     pub fn return_statement(
-        &self,
-        return_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        return_token: Token<'alloc>,
         expression: Option<arena::Box<'alloc, Expression<'alloc>>>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
         let return_loc = return_token.loc;
+        self.on_function_reference(FunctionReferenceKind::Return, return_loc.start());
         let loc = match expression {
             Some(ref expression) => SourceLocation::from_parts(return_loc, expression.get_loc()),
             None => return_loc,
@@ -3157,23 +3603,30 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // WithStatement : `with` `(` Expression `)` Statement
     pub fn with_statement(
-        &self,
-        with_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        with_token: Token<'alloc>,
         object: arena::Box<'alloc, Expression<'alloc>>,
         body: arena::Box<'alloc, Statement<'alloc>>,
-    ) -> arena::Box<'alloc, Statement<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-with-statement-static-semantics-early-errors
+        //
+        // * It is a Syntax Error if the source text matched by this
+        //   production is contained in strict mode code.
+        self.on_strict_mode_violation(StrictModeViolationKind::With, with_token.loc.start())?;
+
         let body_loc = body.get_loc();
-        self.alloc(Statement::WithStatement {
+        Ok(self.alloc(Statement::WithStatement {
             object,
             body,
             loc: SourceLocation::from_parts(with_token.loc, body_loc),
-        })
+        }))
     }
 
     // SwitchStatement : `switch` `(` Expression `)` CaseBlock
     pub fn switch_statement(
-        &self,
-        switch_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        switch_token: Token<'alloc>,
         discriminant_expr: arena::Box<'alloc, Expression<'alloc>>,
         mut cases: arena::Box<'alloc, Statement<'alloc>>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
@@ -3182,13 +3635,13 @@ impl<'alloc> AstBuilder<'alloc> {
                 discriminant, loc, ..
             } => {
                 *discriminant = discriminant_expr;
-                (*loc).start = switch_token.loc.start;
+                loc.set_range(switch_token.loc, *loc);
             }
             Statement::SwitchStatementWithDefault {
                 discriminant, loc, ..
             } => {
                 *discriminant = discriminant_expr;
-                (*loc).start = switch_token.loc.start;
+                loc.set_range(switch_token.loc, *loc);
             }
             _ => {
                 // The grammar ensures that the parser always passes a valid
@@ -3196,17 +3649,21 @@ impl<'alloc> AstBuilder<'alloc> {
                 panic!("invalid argument: argument 2 must be a SwitchStatement");
             }
         }
+
+        let loc = cases.get_loc();
+        self.resolve_unlabelled_jumps(loc.start(), loc.end(), false);
+
         cases
     }
 
     // CaseBlock : `{` CaseClauses? `}`
     pub fn case_block(
         &mut self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         cases: Option<arena::Box<'alloc, arena::Vec<'alloc, SwitchCase<'alloc>>>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
-        self.check_case_block_binding(open_token.loc.start)?;
+        self.check_case_block_binding(open_token.loc.start())?;
 
         Ok(self.alloc(Statement::SwitchStatement {
             // This will be overwritten once the enclosing switch statement
@@ -3227,13 +3684,13 @@ impl<'alloc> AstBuilder<'alloc> {
     // CaseBlock : `{` CaseClauses DefaultClause CaseClauses `}`
     pub fn case_block_with_default(
         &mut self,
-        open_token: arena::Box<'alloc, Token<'alloc>>,
+        open_token: Token<'alloc>,
         pre_default_cases: Option<arena::Box<'alloc, arena::Vec<'alloc, SwitchCase<'alloc>>>>,
         default_case: arena::Box<'alloc, SwitchDefault<'alloc>>,
         post_default_cases: Option<arena::Box<'alloc, arena::Vec<'alloc, SwitchCase<'alloc>>>>,
-        close_token: arena::Box<'alloc, Token<'alloc>>,
+        close_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
-        self.check_case_block_binding(open_token.loc.start)?;
+        self.check_case_block_binding(open_token.loc.start())?;
 
         Ok(self.alloc(Statement::SwitchStatementWithDefault {
             // This will be overwritten once the enclosing switch statement
@@ -3245,7 +3702,7 @@ impl<'alloc> AstBuilder<'alloc> {
                 None => self.new_vec(),
                 Some(boxed) => boxed.unbox(),
             },
-            default_case: default_case.unbox(),
+            default_case,
             post_default_cases: match post_default_cases {
                 None => self.new_vec(),
                 Some(boxed) => boxed.unbox(),
@@ -3277,9 +3734,9 @@ impl<'alloc> AstBuilder<'alloc> {
     // CaseClause : `case` Expression `:` StatementList
     pub fn case_clause(
         &self,
-        case_token: arena::Box<'alloc, Token<'alloc>>,
+        case_token: Token<'alloc>,
         expression: arena::Box<'alloc, Expression<'alloc>>,
-        colon_token: arena::Box<'alloc, Token<'alloc>>,
+        colon_token: Token<'alloc>,
         statements: Option<arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>>,
     ) -> arena::Box<'alloc, SwitchCase<'alloc>> {
         let case_loc = case_token.loc;
@@ -3306,8 +3763,8 @@ impl<'alloc> AstBuilder<'alloc> {
     // DefaultClause : `default` `:` StatementList
     pub fn default_clause(
         &self,
-        default_token: arena::Box<'alloc, Token<'alloc>>,
-        colon_token: arena::Box<'alloc, Token<'alloc>>,
+        default_token: Token<'alloc>,
+        colon_token: Token<'alloc>,
         statements: Option<arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>>,
     ) -> arena::Box<'alloc, SwitchDefault<'alloc>> {
         let default_loc = default_token.loc;
@@ -3331,23 +3788,25 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // LabelledStatement : LabelIdentifier `:` LabelledItem
     pub fn labelled_statement(
-        &self,
+        &mut self,
         label: arena::Box<'alloc, Label<'alloc>>,
         body: arena::Box<'alloc, Statement<'alloc>>,
-    ) -> arena::Box<'alloc, Statement<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Statement<'alloc>>> {
         let label_loc = label.loc;
         let body_loc = body.get_loc();
-        self.alloc(Statement::LabeledStatement {
+        self.check_labelled_statement(label.value, label_loc.start(), &body, body_loc.end())?;
+
+        Ok(self.alloc(Statement::LabeledStatement {
             label: label.unbox(),
             body,
             loc: SourceLocation::from_parts(label_loc, body_loc),
-        })
+        }))
     }
 
     // ThrowStatement : `throw` Expression `;`
     pub fn throw_statement(
         &self,
-        throw_token: arena::Box<'alloc, Token<'alloc>>,
+        throw_token: Token<'alloc>,
         expression: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
         let expression_loc = expression.get_loc();
@@ -3362,7 +3821,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // TryStatement : `try` Block Catch Finally
     pub fn try_statement(
         &self,
-        try_token: arena::Box<'alloc, Token<'alloc>>,
+        try_token: Token<'alloc>,
         body: arena::Box<'alloc, Block<'alloc>>,
         catch_clause: Option<arena::Box<'alloc, CatchClause<'alloc>>>,
         finally_block: Option<arena::Box<'alloc, Block<'alloc>>>,
@@ -3373,7 +3832,7 @@ impl<'alloc> AstBuilder<'alloc> {
                 let catch_clause_loc = catch_clause.loc;
                 self.alloc(Statement::TryCatchStatement {
                     body: body.unbox(),
-                    catch_clause: catch_clause.unbox(),
+                    catch_clause,
                     loc: SourceLocation::from_parts(try_loc, catch_clause_loc),
                 })
             }
@@ -3381,8 +3840,8 @@ impl<'alloc> AstBuilder<'alloc> {
                 let finally_block_loc = finally_block.loc;
                 self.alloc(Statement::TryFinallyStatement {
                     body: body.unbox(),
-                    catch_clause: catch_clause.map(|boxed| boxed.unbox()),
-                    finalizer: finally_block.unbox(),
+                    catch_clause,
+                    finalizer: finally_block,
                     loc: SourceLocation::from_parts(try_loc, finally_block_loc),
                 })
             }
@@ -3397,7 +3856,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // Catch : `catch` `(` CatchParameter `)` Block
     pub fn catch(
         &mut self,
-        catch_token: arena::Box<'alloc, Token<'alloc>>,
+        catch_token: Token<'alloc>,
         binding: arena::Box<'alloc, Binding<'alloc>>,
         body: arena::Box<'alloc, Block<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, CatchClause<'alloc>>> {
@@ -3421,13 +3880,13 @@ impl<'alloc> AstBuilder<'alloc> {
     // Catch : `catch` `(` CatchParameter `)` Block
     pub fn catch_no_param(
         &mut self,
-        catch_token: arena::Box<'alloc, Token<'alloc>>,
+        catch_token: Token<'alloc>,
         body: arena::Box<'alloc, Block<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, CatchClause<'alloc>>> {
         let catch_loc = catch_token.loc;
         let body_loc = body.loc;
 
-        self.check_catch_no_param_bindings(catch_loc.start)?;
+        self.check_catch_no_param_bindings(catch_loc.start())?;
 
         Ok(self.alloc(CatchClause {
             binding: None,
@@ -3439,31 +3898,33 @@ impl<'alloc> AstBuilder<'alloc> {
     // DebuggerStatement : `debugger` `;`
     pub fn debugger_statement(
         &self,
-        token: arena::Box<'alloc, Token<'alloc>>,
+        token: Token<'alloc>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
         self.alloc(Statement::DebuggerStatement { loc: token.loc })
     }
 
     pub fn function_decl(&mut self, f: Function<'alloc>) -> arena::Box<'alloc, Statement<'alloc>> {
-        self.mark_binding_kind(f.loc.start, None, BindingKind::Function);
+        self.context_metadata
+            .mark_binding_kind(f.loc.start(), None, BindingKind::Function);
 
-        self.alloc(Statement::FunctionDeclaration(f))
+        self.alloc(Statement::FunctionDeclaration(self.alloc(f)))
     }
 
     pub fn async_or_generator_decl(
         &mut self,
         f: Function<'alloc>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
-        self.mark_binding_kind(f.loc.start, None, BindingKind::AsyncOrGenerator);
+        self.context_metadata
+            .mark_binding_kind(f.loc.start(), None, BindingKind::AsyncOrGenerator);
 
-        self.alloc(Statement::FunctionDeclaration(f))
+        self.alloc(Statement::FunctionDeclaration(self.alloc(f)))
     }
 
     pub fn function_expr(&mut self, f: Function<'alloc>) -> arena::Box<'alloc, Expression<'alloc>> {
-        let index = self.find_first_binding(f.loc.start);
-        self.pop_bindings_from(index);
+        let index = self.context_metadata.find_first_binding(f.loc.start());
+        self.context_metadata.pop_bindings_from(index);
 
-        self.alloc(Expression::FunctionExpression(f))
+        self.alloc(Expression::FunctionExpression(self.alloc(f)))
     }
 
     // FunctionDeclaration : `function` BindingIdentifier `(` FormalParameters `)` `{` FunctionBody `}`
@@ -3471,32 +3932,46 @@ impl<'alloc> AstBuilder<'alloc> {
     // FunctionExpression : `function` BindingIdentifier? `(` FormalParameters `)` `{` FunctionBody `}`
     pub fn function(
         &mut self,
-        function_token: arena::Box<'alloc, Token<'alloc>>,
+        function_token: Token<'alloc>,
         name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
-        param_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_open_token: Token<'alloc>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
-        param_close_token: arena::Box<'alloc, Token<'alloc>>,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_close_token: Token<'alloc>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
+        body_close_token: Token<'alloc>,
     ) -> Result<'alloc, Function<'alloc>> {
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
+        let loc = SourceLocation::from_parts(function_token.loc, body_close_loc);
+        let lazy = self.lazy_function(loc, param_open_loc.start(), &body);
 
         let is_simple = Self::is_params_simple(&params);
-        self.check_function_bindings(is_simple, param_open_loc.start, param_close_loc.end)?;
+        self.check_function_bindings(is_simple, param_open_loc.start(), param_close_loc.end())?;
+        self.check_use_strict_directive(is_simple, &body.statements)?;
+
+        // Ordinary functions don't have a [[HomeObject]], and aren't class
+        // constructors.
+        self.check_super_references(param_open_loc.start(), body_close_loc.end(), false, false)?;
+        self.check_function_jumps(body_open_token.loc.start(), body_close_loc.end())?;
+        self.check_function_references(param_open_loc.start(), body_close_loc.end(), false)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
+        if lazy.is_some() {
+            body.statements = self.new_vec();
+        }
 
         Ok(Function {
             name: name.map(|b| b.unbox()),
             is_async: false,
             is_generator: false,
+            is_annex_b_hoisted: false,
             params: params.unbox(),
             body: body.unbox(),
-            loc: SourceLocation::from_parts(function_token.loc, body_close_loc),
+            lazy,
+            loc,
         })
     }
 
@@ -3505,32 +3980,47 @@ impl<'alloc> AstBuilder<'alloc> {
     // AsyncFunctionExpression : `async` `function` `(` FormalParameters `)` `{` AsyncFunctionBody `}`
     pub fn async_function(
         &mut self,
-        async_token: arena::Box<'alloc, Token<'alloc>>,
+        async_token: Token<'alloc>,
         name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
-        param_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_open_token: Token<'alloc>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
-        param_close_token: arena::Box<'alloc, Token<'alloc>>,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_close_token: Token<'alloc>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
+        body_close_token: Token<'alloc>,
     ) -> Result<'alloc, Function<'alloc>> {
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
+        let loc = SourceLocation::from_parts(async_token.loc, body_close_loc);
+        let lazy = self.lazy_function(loc, param_open_loc.start(), &body);
 
         let is_simple = Self::is_params_simple(&params);
-        self.check_function_bindings(is_simple, param_open_loc.start, param_close_loc.end)?;
+        self.check_function_bindings(is_simple, param_open_loc.start(), param_close_loc.end())?;
+        self.check_use_strict_directive(is_simple, &body.statements)?;
+
+        // Ordinary functions don't have a [[HomeObject]], and aren't class
+        // constructors.
+        self.check_super_references(param_open_loc.start(), body_close_loc.end(), false, false)?;
+        self.check_function_jumps(body_open_token.loc.start(), body_close_loc.end())?;
+        self.check_async_function_parameters(param_open_loc.start(), param_close_loc.end())?;
+        self.check_function_references(param_open_loc.start(), body_close_loc.end(), true)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
+        if lazy.is_some() {
+            body.statements = self.new_vec();
+        }
 
         Ok(Function {
             name: name.map(|b| b.unbox()),
             is_async: true,
             is_generator: false,
+            is_annex_b_hoisted: false,
             params: params.unbox(),
             body: body.unbox(),
-            loc: SourceLocation::from_parts(async_token.loc, body_close_loc),
+            lazy,
+            loc,
         })
     }
 
@@ -3539,32 +4029,46 @@ impl<'alloc> AstBuilder<'alloc> {
     // GeneratorExpression : `function` `*` BindingIdentifier? `(` FormalParameters `)` `{` GeneratorBody `}`
     pub fn generator(
         &mut self,
-        function_token: arena::Box<'alloc, Token<'alloc>>,
+        function_token: Token<'alloc>,
         name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
-        param_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_open_token: Token<'alloc>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
-        param_close_token: arena::Box<'alloc, Token<'alloc>>,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_close_token: Token<'alloc>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
+        body_close_token: Token<'alloc>,
     ) -> Result<'alloc, Function<'alloc>> {
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
+        let loc = SourceLocation::from_parts(function_token.loc, body_close_loc);
+        let lazy = self.lazy_function(loc, param_open_loc.start(), &body);
 
         let is_simple = Self::is_params_simple(&params);
-        self.check_function_bindings(is_simple, param_open_loc.start, param_close_loc.end)?;
+        self.check_function_bindings(is_simple, param_open_loc.start(), param_close_loc.end())?;
+        self.check_use_strict_directive(is_simple, &body.statements)?;
+
+        // Ordinary functions don't have a [[HomeObject]], and aren't class
+        // constructors.
+        self.check_super_references(param_open_loc.start(), body_close_loc.end(), false, false)?;
+        self.check_function_jumps(body_open_token.loc.start(), body_close_loc.end())?;
+        self.check_function_references(param_open_loc.start(), body_close_loc.end(), false)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
+        if lazy.is_some() {
+            body.statements = self.new_vec();
+        }
 
         Ok(Function {
             name: name.map(|b| b.unbox()),
             is_async: false,
             is_generator: true,
+            is_annex_b_hoisted: false,
             params: params.unbox(),
             body: body.unbox(),
-            loc: SourceLocation::from_parts(function_token.loc, body_close_loc),
+            lazy,
+            loc,
         })
     }
 
@@ -3573,32 +4077,47 @@ impl<'alloc> AstBuilder<'alloc> {
     // AsyncGeneratorExpression : `async` `function` `*` BindingIdentifier? `(` FormalParameters `)` `{` AsyncGeneratorBody `}`
     pub fn async_generator(
         &mut self,
-        async_token: arena::Box<'alloc, Token<'alloc>>,
+        async_token: Token<'alloc>,
         name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
-        param_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_open_token: Token<'alloc>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
-        param_close_token: arena::Box<'alloc, Token<'alloc>>,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_close_token: Token<'alloc>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
+        body_close_token: Token<'alloc>,
     ) -> Result<'alloc, Function<'alloc>> {
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
+        let loc = SourceLocation::from_parts(async_token.loc, body_close_loc);
+        let lazy = self.lazy_function(loc, param_open_loc.start(), &body);
 
         let is_simple = Self::is_params_simple(&params);
-        self.check_function_bindings(is_simple, param_open_loc.start, param_close_loc.end)?;
+        self.check_function_bindings(is_simple, param_open_loc.start(), param_close_loc.end())?;
+        self.check_use_strict_directive(is_simple, &body.statements)?;
+
+        // Ordinary functions don't have a [[HomeObject]], and aren't class
+        // constructors.
+        self.check_super_references(param_open_loc.start(), body_close_loc.end(), false, false)?;
+        self.check_function_jumps(body_open_token.loc.start(), body_close_loc.end())?;
+        self.check_async_function_parameters(param_open_loc.start(), param_close_loc.end())?;
+        self.check_function_references(param_open_loc.start(), body_close_loc.end(), true)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
+        if lazy.is_some() {
+            body.statements = self.new_vec();
+        }
 
         Ok(Function {
             name: name.map(|b| b.unbox()),
             is_async: true,
             is_generator: true,
+            is_annex_b_hoisted: false,
             params: params.unbox(),
             body: body.unbox(),
-            loc: SourceLocation::from_parts(async_token.loc, body_close_loc),
+            lazy,
+            loc,
         })
     }
 
@@ -3656,16 +4175,40 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // FunctionBody : FunctionStatementList
     pub fn function_body(
-        &self,
+        &mut self,
         statements: arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>,
-    ) -> arena::Box<'alloc, FunctionBody<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, FunctionBody<'alloc>>> {
+        // A function whose body has a "use strict" directive is strict mode
+        // code.
+        if Self::use_strict_directive_offset(&statements).is_some() {
+            let start = statements.first().unwrap().get_loc().start();
+            let end = statements.last().unwrap().get_loc().end();
+            self.check_strict_mode_violations(start, end)?;
+            self.check_strict_block_functions(start, end)?;
+            if self.lazy_inner_functions {
+                self.on_strict_range(start, end);
+            }
+        }
+
         // TODO: Directives
-        self.alloc(FunctionBody {
+        Ok(self.alloc(FunctionBody {
             directives: self.new_vec(),
             statements: statements.unbox(),
             // This will be overwritten once the enclosing function gets parsed.
             loc: SourceLocation::default(),
-        })
+        }))
+    }
+
+    // The `{` token that starts the body of a FunctionDeclaration,
+    // FunctionExpression, generator or async function.
+    pub fn function_body_start(
+        &mut self,
+        token: Token<'alloc>,
+    ) -> arena::Box<'alloc, Token<'alloc>> {
+        if self.lazy_inner_functions {
+            self.lazy_function_depth += 1;
+        }
+        self.alloc(token)
     }
 
     // FunctionStatementList : StatementList?
@@ -3685,14 +4228,17 @@ impl<'alloc> AstBuilder<'alloc> {
         params: arena::Box<'alloc, FormalParameters<'alloc>>,
         body: arena::Box<'alloc, ArrowExpressionBody<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
-        self.check_unique_function_bindings(params.loc.start, params.loc.end)?;
+        self.check_unique_function_bindings(params.loc.start(), params.loc.end())?;
+        self.check_arrow_use_strict_directive(&params, &body)?;
 
         let params_loc = params.loc;
         let body_loc = body.get_loc();
+        self.check_function_jumps(body_loc.start(), body_loc.end())?;
+        self.check_arrow_function_references(body_loc.start(), body_loc.end(), false)?;
 
         Ok(self.alloc(Expression::ArrowExpression {
             is_async: false,
-            params: params.unbox(),
+            params,
             body: body.unbox(),
             loc: SourceLocation::from_parts(params_loc, body_loc),
         }))
@@ -3715,16 +4261,28 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // ArrowParameters : CoverParenthesizedExpressionAndArrowParameterList
     pub fn uncover_arrow_parameters(
-        &self,
+        &mut self,
         covered: arena::Box<'alloc, CoverParenthesized<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, FormalParameters<'alloc>>> {
         Ok(match covered.unbox() {
-            CoverParenthesized::Expression { expression, loc } => self.alloc(FormalParameters {
-                items: self.expression_to_parameter_list(expression)?,
-                rest: None,
-                loc,
-            }),
-            CoverParenthesized::Parameters(parameters) => parameters,
+            CoverParenthesized::Expression { expression, loc } => {
+                let items = self.expression_to_parameter_list(expression)?;
+                let params = self.alloc(FormalParameters {
+                    items,
+                    rest: None,
+                    loc,
+                });
+                self.declare_cover_parameters(&params);
+                params
+            }
+            CoverParenthesized::Parameters(parameters) => {
+                // The rest parameter is parsed as BindingIdentifier or
+                // BindingPattern and is already noted to the stack.
+                for item in parameters.items.iter() {
+                    self.declare_cover_parameter(item);
+                }
+                parameters
+            }
         })
     }
 
@@ -3739,75 +4297,82 @@ impl<'alloc> AstBuilder<'alloc> {
     // ConciseBody : `{` FunctionBody `}`
     pub fn concise_body_block(
         &self,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
+        body_close_token: Token<'alloc>,
     ) -> arena::Box<'alloc, ArrowExpressionBody<'alloc>> {
         body.loc
             .set_range(body_open_token.loc, body_close_token.loc);
         self.alloc(ArrowExpressionBody::FunctionBody(body.unbox()))
     }
 
-    // MethodDefinition : PropertyName `(` UniqueFormalParameters `)` `{` FunctionBody `}`
+    // MethodDefinition : ClassElementName `(` UniqueFormalParameters `)` `{` FunctionBody `}`
     pub fn method_definition(
         &mut self,
-        name: arena::Box<'alloc, PropertyName<'alloc>>,
-        param_open_token: arena::Box<'alloc, Token<'alloc>>,
+        name: arena::Box<'alloc, ClassElementName<'alloc>>,
+        param_open_token: Token<'alloc>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
-        param_close_token: arena::Box<'alloc, Token<'alloc>>,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_close_token: Token<'alloc>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
+        body_close_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, MethodDefinition<'alloc>>> {
         let name_loc = name.get_loc();
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
 
-        self.check_unique_function_bindings(param_open_loc.start, param_close_loc.end)?;
+        self.check_unique_function_bindings(param_open_loc.start(), param_close_loc.end())?;
+        self.check_use_strict_directive(Self::is_params_simple(&params), &body.statements)?;
+        self.check_function_jumps(body_open_token.loc.start(), body_close_loc.end())?;
+        self.check_function_references(param_open_loc.start(), body_close_loc.end(), false)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
 
-        Ok(self.alloc(MethodDefinition::Method(Method {
+        Ok(self.alloc(MethodDefinition::Method(self.alloc(Method {
             name: name.unbox(),
             is_async: false,
             is_generator: false,
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(name_loc, body_close_loc),
-        })))
+        }))))
     }
 
-    // MethodDefinition : `get` PropertyName `(` `)` `{` FunctionBody `}`
+    // MethodDefinition : `get` ClassElementName `(` `)` `{` FunctionBody `}`
     pub fn getter(
-        &self,
-        get_token: arena::Box<'alloc, Token<'alloc>>,
-        name: arena::Box<'alloc, PropertyName<'alloc>>,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        get_token: Token<'alloc>,
+        name: arena::Box<'alloc, ClassElementName<'alloc>>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, MethodDefinition<'alloc>> {
+        body_close_token: Token<'alloc>,
+    ) -> Result<'alloc, arena::Box<'alloc, MethodDefinition<'alloc>>> {
         let body_close_loc = body_close_token.loc;
+        self.check_use_strict_directive(true, &body.statements)?;
+        self.check_function_jumps(body_open_token.loc.start(), body_close_loc.end())?;
+        self.check_function_references(body_open_token.loc.start(), body_close_loc.end(), false)?;
+
         body.loc.set_range(body_open_token.loc, body_close_loc);
-        self.alloc(MethodDefinition::Getter(Getter {
+        Ok(self.alloc(MethodDefinition::Getter(self.alloc(Getter {
             property_name: name.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(get_token.loc, body_close_loc),
-        }))
+        }))))
     }
 
-    // MethodDefinition : `set` PropertyName `(` PropertySetParameterList `)` `{` FunctionBody `}`
+    // MethodDefinition : `set` ClassElementName `(` PropertySetParameterList `)` `{` FunctionBody `}`
     pub fn setter(
         &mut self,
-        set_token: arena::Box<'alloc, Token<'alloc>>,
-        name: arena::Box<'alloc, PropertyName<'alloc>>,
-        param_open_token: arena::Box<'alloc, Token<'alloc>>,
+        set_token: Token<'alloc>,
+        name: arena::Box<'alloc, ClassElementName<'alloc>>,
+        param_open_token: Token<'alloc>,
         mut parameter: arena::Box<'alloc, Parameter<'alloc>>,
-        param_close_token: arena::Box<'alloc, Token<'alloc>>,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_close_token: Token<'alloc>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
+        body_close_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, MethodDefinition<'alloc>>> {
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
@@ -3815,54 +4380,64 @@ impl<'alloc> AstBuilder<'alloc> {
 
         // A setter only has one parameter, but it can be a destructuring
         // pattern, so it is still possible to flunk this check.
-        self.check_unique_function_bindings(param_open_loc.start, param_close_loc.end)?;
+        self.check_unique_function_bindings(param_open_loc.start(), param_close_loc.end())?;
+        let is_simple = matches!(
+            &*parameter,
+            Parameter::Binding(Binding::BindingIdentifier(_))
+        );
+        self.check_use_strict_directive(is_simple, &body.statements)?;
+        self.check_function_jumps(body_open_token.loc.start(), body_close_loc.end())?;
+        self.check_function_references(param_open_loc.start(), body_close_loc.end(), false)?;
 
         parameter.set_loc(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
-        Ok(self.alloc(MethodDefinition::Setter(Setter {
+        Ok(self.alloc(MethodDefinition::Setter(self.alloc(Setter {
             property_name: name.unbox(),
             param: parameter.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(set_token.loc, body_close_loc),
-        })))
+        }))))
     }
 
-    // GeneratorMethod : `*` PropertyName `(` UniqueFormalParameters `)` `{` GeneratorBody `}`
+    // GeneratorMethod : `*` ClassElementName `(` UniqueFormalParameters `)` `{` GeneratorBody `}`
     pub fn generator_method(
         &mut self,
-        generator_token: arena::Box<'alloc, Token<'alloc>>,
-        name: arena::Box<'alloc, PropertyName<'alloc>>,
-        param_open_token: arena::Box<'alloc, Token<'alloc>>,
+        generator_token: Token<'alloc>,
+        name: arena::Box<'alloc, ClassElementName<'alloc>>,
+        param_open_token: Token<'alloc>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
-        param_close_token: arena::Box<'alloc, Token<'alloc>>,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_close_token: Token<'alloc>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
+        body_close_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, MethodDefinition<'alloc>>> {
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
 
-        self.check_unique_function_bindings(param_open_loc.start, param_close_loc.end)?;
+        self.check_unique_function_bindings(param_open_loc.start(), param_close_loc.end())?;
+        self.check_use_strict_directive(Self::is_params_simple(&params), &body.statements)?;
+        self.check_function_jumps(body_open_token.loc.start(), body_close_loc.end())?;
+        self.check_function_references(param_open_loc.start(), body_close_loc.end(), false)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
 
-        Ok(self.alloc(MethodDefinition::Method(Method {
+        Ok(self.alloc(MethodDefinition::Method(self.alloc(Method {
             name: name.unbox(),
             is_async: false,
             is_generator: true,
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(generator_token.loc, body_close_loc),
-        })))
+        }))))
     }
 
     // YieldExpression : `yield`
     // YieldExpression : `yield` AssignmentExpression
     pub fn yield_expr(
         &self,
-        yield_token: arena::Box<'alloc, Token<'alloc>>,
+        yield_token: Token<'alloc>,
         operand: Option<arena::Box<'alloc, Expression<'alloc>>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let yield_loc = yield_token.loc;
@@ -3880,7 +4455,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // YieldExpression : `yield` `*` AssignmentExpression
     pub fn yield_star_expr(
         &self,
-        yield_token: arena::Box<'alloc, Token<'alloc>>,
+        yield_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
         let yield_loc = yield_token.loc;
@@ -3891,55 +4466,60 @@ impl<'alloc> AstBuilder<'alloc> {
         })
     }
 
-    // AsyncGeneratorMethod ::= "async" "*" PropertyName "(" UniqueFormalParameters ")" "{" AsyncGeneratorBody "}"
+    // AsyncGeneratorMethod ::= "async" "*" ClassElementName "(" UniqueFormalParameters ")" "{" AsyncGeneratorBody "}"
     pub fn async_generator_method(
         &mut self,
-        async_token: arena::Box<'alloc, Token<'alloc>>,
-        name: arena::Box<'alloc, PropertyName<'alloc>>,
-        param_open_token: arena::Box<'alloc, Token<'alloc>>,
+        async_token: Token<'alloc>,
+        name: arena::Box<'alloc, ClassElementName<'alloc>>,
+        param_open_token: Token<'alloc>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
-        param_close_token: arena::Box<'alloc, Token<'alloc>>,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_close_token: Token<'alloc>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
+        body_close_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, MethodDefinition<'alloc>>> {
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
 
-        self.check_unique_function_bindings(param_open_loc.start, param_close_loc.end)?;
+        self.check_unique_function_bindings(param_open_loc.start(), param_close_loc.end())?;
+        self.check_use_strict_directive(Self::is_params_simple(&params), &body.statements)?;
+        self.check_function_jumps(body_open_token.loc.start(), body_close_loc.end())?;
+        self.check_async_function_parameters(param_open_loc.start(), param_close_loc.end())?;
+        self.check_function_references(param_open_loc.start(), body_close_loc.end(), true)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
 
-        Ok(self.alloc(MethodDefinition::Method(Method {
+        Ok(self.alloc(MethodDefinition::Method(self.alloc(Method {
             name: name.unbox(),
             is_async: true,
             is_generator: true,
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(async_token.loc, body_close_loc),
-        })))
+        }))))
     }
 
     // ClassDeclaration : `class` BindingIdentifier ClassTail
     // ClassDeclaration : `class` ClassTail
     pub fn class_declaration(
         &mut self,
-        class_token: arena::Box<'alloc, Token<'alloc>>,
+        class_token: Token<'alloc>,
         name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
         tail: arena::Box<'alloc, ClassExpression<'alloc>>,
     ) -> arena::Box<'alloc, Statement<'alloc>> {
         let class_loc = class_token.loc;
 
-        self.mark_binding_kind(class_loc.start, None, BindingKind::Class);
+        self.context_metadata
+            .mark_binding_kind(class_loc.start(), None, BindingKind::Class);
 
         let tail = tail.unbox();
         let tail_loc = tail.loc;
         self.alloc(Statement::ClassDeclaration(ClassDeclaration {
             name: match name {
                 None => {
-                    let loc = SourceLocation::new(class_loc.end, class_loc.end);
+                    let loc = SourceLocation::new(class_loc.end(), class_loc.end());
                     BindingIdentifier {
                         name: Identifier {
                             value: "default",
@@ -3959,38 +4539,59 @@ impl<'alloc> AstBuilder<'alloc> {
     // ClassExpression : `class` BindingIdentifier? ClassTail
     pub fn class_expression(
         &mut self,
-        class_token: arena::Box<'alloc, Token<'alloc>>,
+        class_token: Token<'alloc>,
         name: Option<arena::Box<'alloc, BindingIdentifier<'alloc>>>,
         mut tail: arena::Box<'alloc, ClassExpression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
-        let index = self.find_first_binding(class_token.loc.start);
-        self.pop_bindings_from(index);
+        let index = self
+            .context_metadata
+            .find_first_binding(class_token.loc.start());
+        self.context_metadata.pop_bindings_from(index);
 
         tail.name = name.map(|boxed| boxed.unbox());
-        tail.loc.start = class_token.loc.start;
+        tail.loc = SourceLocation::from_parts(class_token.loc, tail.loc);
         self.alloc(Expression::ClassExpression(tail.unbox()))
     }
 
     // ClassTail : ClassHeritage? `{` ClassBody? `}`
     pub fn class_tail(
-        &self,
+        &mut self,
         heritage: Option<arena::Box<'alloc, Expression<'alloc>>>,
+        body_open_token: Token<'alloc>,
         body: Option<
             arena::Box<'alloc, arena::Vec<'alloc, arena::Box<'alloc, ClassElement<'alloc>>>>,
         >,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, ClassExpression<'alloc>> {
-        self.alloc(ClassExpression {
+        body_close_token: Token<'alloc>,
+    ) -> Result<'alloc, arena::Box<'alloc, ClassExpression<'alloc>>> {
+        let elements = match body {
+            None => self.new_vec(),
+            Some(boxed) => boxed.unbox(),
+        };
+
+        self.check_class_private_names(&elements, body_open_token.loc.start())?;
+        self.check_class_super_references(&elements, heritage.is_some())?;
+
+        // All parts of a ClassDeclaration or a ClassExpression are strict
+        // mode code.
+        let start = heritage
+            .as_ref()
+            .map_or(body_open_token.loc.start(), |heritage| {
+                heritage.get_loc().start()
+            });
+        self.check_strict_mode_violations(start, body_close_token.loc.end())?;
+        self.check_strict_block_functions(start, body_close_token.loc.end())?;
+        if self.lazy_inner_functions {
+            self.on_strict_range(start, body_close_token.loc.end());
+        }
+
+        Ok(self.alloc(ClassExpression {
             name: None,
             super_: heritage,
-            elements: match body {
-                None => self.new_vec(),
-                Some(boxed) => boxed.unbox(),
-            },
+            elements,
             // `start` of this will be overwritten once the enclosing class
             // gets parsed.
             loc: body_close_token.loc,
-        })
+        }))
     }
 
     // ClassElementList : ClassElementList ClassElement
@@ -4008,20 +4609,38 @@ impl<'alloc> AstBuilder<'alloc> {
 
     // FieldDefinition : ClassElementName Initializer?
     pub fn class_field_definition(
-        &self,
+        &mut self,
         name: arena::Box<'alloc, ClassElementName<'alloc>>,
         init: Option<arena::Box<'alloc, Expression<'alloc>>>,
-    ) -> arena::Box<'alloc, ClassElement<'alloc>> {
+    ) -> Result<'alloc, arena::Box<'alloc, ClassElement<'alloc>>> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-class-definitions-static-semantics-early-errors
+        //
+        // FieldDefinition : ClassElementName Initializer?
+        //
+        // * It is a Syntax Error if PropName of ClassElementName is
+        //   "constructor".
+        if let Some((value, offset)) = Self::class_element_prop_name(&name) {
+            if value == "constructor" {
+                return Err(ParseError::InvalidFieldName(value, offset));
+            }
+        }
+
         let name_loc = name.get_loc();
         let loc = match &init {
             None => name_loc,
-            Some(expr) => SourceLocation::from_parts(name_loc, expr.get_loc()),
+            Some(expr) => {
+                let init_loc = expr.get_loc();
+                self.check_class_initializer_references(init_loc.start(), init_loc.end())?;
+                SourceLocation::from_parts(name_loc, init_loc)
+            }
         };
-        self.alloc(ClassElement::FieldDefinition {
+        Ok(self.alloc(ClassElement::FieldDefinition {
+            is_static: false,
             name: name.unbox(),
             init,
             loc,
-        })
+        }))
     }
 
     // ClassElementName : PropertyName
@@ -4038,11 +4657,26 @@ impl<'alloc> AstBuilder<'alloc> {
     // ClassElementName : PrivateIdentifier
     pub fn class_element_name_private(
         &self,
-        private_identifier: arena::Box<'alloc, Token<'alloc>>,
-    ) -> arena::Box<'alloc, ClassElementName<'alloc>> {
-        self.alloc(ClassElementName::PrivateFieldName(
+        private_identifier: Token<'alloc>,
+    ) -> Result<'alloc, arena::Box<'alloc, ClassElementName<'alloc>>> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-class-definitions-static-semantics-early-errors
+        //
+        // ClassElementName : PrivateIdentifier
+        //
+        // * It is a Syntax Error if StringValue of PrivateIdentifier is
+        //   "#constructor".
+        let name = private_identifier.str_value().unwrap();
+        if name == "#constructor" {
+            return Err(ParseError::InvalidPrivateName(
+                name,
+                private_identifier.loc.start(),
+            ));
+        }
+
+        Ok(self.alloc(ClassElementName::PrivateFieldName(
             self.private_identifier(private_identifier),
-        ))
+        )))
     }
 
     // ClassElement : MethodDefinition
@@ -4069,17 +4703,74 @@ impl<'alloc> AstBuilder<'alloc> {
     // ClassElement : `static` MethodDefinition
     pub fn class_element_static(
         &self,
-        static_token: arena::Box<'alloc, Token<'alloc>>,
+        static_token: Token<'alloc>,
         method: arena::Box<'alloc, MethodDefinition<'alloc>>,
-    ) -> arena::Box<'alloc, arena::Vec<'alloc, arena::Box<'alloc, ClassElement<'alloc>>>> {
+    ) -> Result<
+        'alloc,
+        arena::Box<'alloc, arena::Vec<'alloc, arena::Box<'alloc, ClassElement<'alloc>>>>,
+    > {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-class-definitions-static-semantics-early-errors
+        //
+        // ClassElement : `static` MethodDefinition
+        //
+        // * It is a Syntax Error if PropName of MethodDefinition is
+        //   "prototype".
+        if let Some((value, offset)) =
+            Self::class_element_prop_name(Self::method_definition_name(&method))
+        {
+            if value == "prototype" {
+                return Err(ParseError::InvalidStaticMethodName(value, offset));
+            }
+        }
+
         let method_loc = method.get_loc();
-        self.alloc(
+        Ok(self.alloc(
             self.new_vec_single(self.alloc(ClassElement::MethodDefinition {
                 is_static: true,
                 method: method.unbox(),
                 loc: SourceLocation::from_parts(static_token.loc, method_loc),
             })),
-        )
+        ))
+    }
+
+    // ClassElement : `static` FieldDefinition `;`
+    pub fn class_element_static_field(
+        &self,
+        static_token: Token<'alloc>,
+        mut field: arena::Box<'alloc, ClassElement<'alloc>>,
+    ) -> Result<
+        'alloc,
+        arena::Box<'alloc, arena::Vec<'alloc, arena::Box<'alloc, ClassElement<'alloc>>>>,
+    > {
+        match &mut *field {
+            ClassElement::FieldDefinition {
+                is_static,
+                name,
+                loc,
+                ..
+            } => {
+                // Static Semantics: Early Errors
+                // https://tc39.es/ecma262/#sec-class-definitions-static-semantics-early-errors
+                //
+                // ClassElement : `static` FieldDefinition `;`
+                //
+                // * It is a Syntax Error if PropName of FieldDefinition is
+                //   "prototype" or "constructor".
+                //
+                // "constructor" is already rejected by FieldDefinition.
+                if let Some((value, offset)) = Self::class_element_prop_name(name) {
+                    if value == "prototype" {
+                        return Err(ParseError::InvalidFieldName(value, offset));
+                    }
+                }
+
+                *is_static = true;
+                loc.set_range(static_token.loc, *loc);
+            }
+            _ => panic!("FieldDefinition should be passed"),
+        }
+        Ok(self.class_element_to_vec(field))
     }
 
     // ClassElement : `;`
@@ -4089,43 +4780,85 @@ impl<'alloc> AstBuilder<'alloc> {
         self.alloc(self.new_vec())
     }
 
-    // AsyncMethod : `async` PropertyName `(` UniqueFormalParameters `)` `{` AsyncFunctionBody `}`
+    // ClassStaticBlock : `static` `{` ClassStaticBlockStatementList `}`
+    //
+    // ClassStaticBlockStatementList is parsed with [~Await], so `await` can't
+    // appear as an AwaitExpression (ContainsAwait), and `await` used as an
+    // identifier is rejected by the identifier Early Errors.
+    pub fn class_static_block(
+        &mut self,
+        static_token: Token<'alloc>,
+        body_open_token: Token<'alloc>,
+        statements: arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>,
+        body_close_token: Token<'alloc>,
+    ) -> Result<'alloc, arena::Box<'alloc, ClassElement<'alloc>>> {
+        // The static block has its own var scope, and the same
+        // LexicallyDeclaredNames/VarDeclaredNames early errors as a
+        // function body without parameters.
+        let body_start = body_open_token.loc.start();
+        self.check_unique_function_bindings(body_start, body_start)?;
+        self.check_function_jumps(body_start, body_close_token.loc.end())?;
+        self.check_class_initializer_references(body_start, body_close_token.loc.end())?;
+
+        Ok(self.alloc(ClassElement::StaticBlock {
+            statements: statements.unbox(),
+            loc: SourceLocation::from_parts(static_token.loc, body_close_token.loc),
+        }))
+    }
+
+    // ClassStaticBlockStatementList : StatementList?
+    pub fn class_static_block_statement_list(
+        &self,
+        statements: Option<arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>>,
+    ) -> arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>> {
+        match statements {
+            Some(statements) => statements,
+            None => self.alloc(self.new_vec()),
+        }
+    }
+
+    // AsyncMethod : `async` ClassElementName `(` UniqueFormalParameters `)` `{` AsyncFunctionBody `}`
     pub fn async_method(
         &mut self,
-        async_token: arena::Box<'alloc, Token<'alloc>>,
-        name: arena::Box<'alloc, PropertyName<'alloc>>,
-        param_open_token: arena::Box<'alloc, Token<'alloc>>,
+        async_token: Token<'alloc>,
+        name: arena::Box<'alloc, ClassElementName<'alloc>>,
+        param_open_token: Token<'alloc>,
         mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
-        param_close_token: arena::Box<'alloc, Token<'alloc>>,
-        body_open_token: arena::Box<'alloc, Token<'alloc>>,
+        param_close_token: Token<'alloc>,
+        body_open_token: Token<'alloc>,
         mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
-        body_close_token: arena::Box<'alloc, Token<'alloc>>,
+        body_close_token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, MethodDefinition<'alloc>>> {
         let param_open_loc = param_open_token.loc;
         let param_close_loc = param_close_token.loc;
         let body_close_loc = body_close_token.loc;
 
-        self.check_unique_function_bindings(param_open_loc.start, param_close_loc.end)?;
+        self.check_unique_function_bindings(param_open_loc.start(), param_close_loc.end())?;
+        self.check_use_strict_directive(Self::is_params_simple(&params), &body.statements)?;
+        self.check_function_jumps(body_open_token.loc.start(), body_close_loc.end())?;
+        self.check_async_function_parameters(param_open_loc.start(), param_close_loc.end())?;
+        self.check_function_references(param_open_loc.start(), body_close_loc.end(), true)?;
 
         params.loc.set_range(param_open_loc, param_close_loc);
         body.loc.set_range(body_open_token.loc, body_close_loc);
 
-        Ok(self.alloc(MethodDefinition::Method(Method {
+        Ok(self.alloc(MethodDefinition::Method(self.alloc(Method {
             name: name.unbox(),
             is_async: true,
             is_generator: false,
             params: params.unbox(),
             body: body.unbox(),
             loc: SourceLocation::from_parts(async_token.loc, body_close_loc),
-        })))
+        }))))
     }
 
     // AwaitExpression : `await` UnaryExpression
     pub fn await_expr(
-        &self,
-        await_token: arena::Box<'alloc, Token<'alloc>>,
+        &mut self,
+        await_token: Token<'alloc>,
         operand: arena::Box<'alloc, Expression<'alloc>>,
     ) -> arena::Box<'alloc, Expression<'alloc>> {
+        self.on_function_reference(FunctionReferenceKind::Await, await_token.loc.start());
         let operand_loc = operand.get_loc();
         self.alloc(Expression::AwaitExpression {
             expression: operand,
@@ -4137,18 +4870,21 @@ impl<'alloc> AstBuilder<'alloc> {
     // AsyncArrowFunction : CoverCallExpressionAndAsyncArrowHead `=>` AsyncConciseBody
     pub fn async_arrow_function_bare(
         &mut self,
-        async_token: arena::Box<'alloc, Token<'alloc>>,
+        async_token: Token<'alloc>,
         identifier: arena::Box<'alloc, BindingIdentifier<'alloc>>,
         body: arena::Box<'alloc, ArrowExpressionBody<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let params = self.arrow_parameters_bare(identifier);
 
-        self.check_unique_function_bindings(params.loc.start, params.loc.end)?;
+        self.check_unique_function_bindings(params.loc.start(), params.loc.end())?;
+        self.check_arrow_use_strict_directive(&params, &body)?;
 
         let body_loc = body.get_loc();
+        self.check_function_jumps(body_loc.start(), body_loc.end())?;
+        self.check_arrow_function_references(body_loc.start(), body_loc.end(), true)?;
         Ok(self.alloc(Expression::ArrowExpression {
             is_async: true,
-            params: params.unbox(),
+            params,
             body: body.unbox(),
             loc: SourceLocation::from_parts(async_token.loc, body_loc),
         }))
@@ -4161,12 +4897,15 @@ impl<'alloc> AstBuilder<'alloc> {
     ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
         let (params, call_loc) = self.async_arrow_parameters(params)?;
 
-        self.check_unique_function_bindings(params.loc.start, params.loc.end)?;
+        self.check_unique_function_bindings(params.loc.start(), params.loc.end())?;
+        self.check_arrow_use_strict_directive(&params, &body)?;
 
         let body_loc = body.get_loc();
+        self.check_function_jumps(body_loc.start(), body_loc.end())?;
+        self.check_arrow_function_references(body_loc.start(), body_loc.end(), true)?;
         Ok(self.alloc(Expression::ArrowExpression {
             is_async: true,
-            params: params.unbox(),
+            params,
             body: body.unbox(),
             loc: SourceLocation::from_parts(call_loc, body_loc),
         }))
@@ -4176,7 +4915,7 @@ impl<'alloc> AstBuilder<'alloc> {
     //
     // This is used to convert the Expression that is produced by parsing a CoverCallExpressionAndAsyncArrowHead
     fn async_arrow_parameters(
-        &self,
+        &mut self,
         call_expression: arena::Box<'alloc, Expression<'alloc>>,
     ) -> Result<'alloc, (arena::Box<'alloc, FormalParameters<'alloc>>, SourceLocation)> {
         match call_expression.unbox() {
@@ -4207,7 +4946,9 @@ impl<'alloc> AstBuilder<'alloc> {
                     }
                 }
 
-                Ok((self.arguments_to_parameter_list(arguments)?, loc))
+                let params = self.arguments_to_parameter_list(arguments)?;
+                self.declare_cover_parameters(&params);
+                Ok((params, loc))
             }
             _ => {
                 // The grammar ensures that the parser always passes
@@ -4223,9 +4964,37 @@ impl<'alloc> AstBuilder<'alloc> {
         script: Option<arena::Box<'alloc, Script<'alloc>>>,
     ) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
         self.check_script_bindings()?;
+        self.check_private_name_references()?;
+        self.check_super_references(0, usize::MAX, false, false)?;
+        self.check_function_jumps(0, usize::MAX)?;
+        self.check_top_level_references(self.allow_return_outside_function, false)?;
+        self.check_cover_initialized_names()?;
+        self.check_duplicate_protos()?;
+
+        // A script with a "use strict" directive is strict mode code.
+        if let Some(script) = &script {
+            if Self::use_strict_directive_offset(&script.statements).is_some() {
+                self.check_strict_mode_violations(0, usize::MAX)?;
+                self.check_strict_block_functions(0, usize::MAX)?;
+                if self.lazy_inner_functions {
+                    self.on_strict_range(0, usize::MAX);
+                }
+            }
+        }
+
+        let offsets = self.pop_hoisted_block_function_offsets();
+        let strict_pass = self.take_strict_lazy_functions_pass();
 
         Ok(match script {
-            Some(script) => script,
+            Some(mut script) => {
+                if !offsets.is_empty() {
+                    AnnexBHoistingPass { offsets }.visit_script(&mut script);
+                }
+                if let Some(mut pass) = strict_pass {
+                    pass.visit_script(&mut script);
+                }
+                script
+            }
             None => self.alloc(Script {
                 directives: self.new_vec(),
                 statements: self.new_vec(),
@@ -4260,10 +5029,141 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn module(
         &mut self,
         body: Option<arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>>,
-    ) -> Result<'alloc, arena::Box<'alloc, arena::Vec<'alloc, Statement<'alloc>>>> {
+    ) -> Result<'alloc, arena::Box<'alloc, Module<'alloc>>> {
         self.check_module_bindings()?;
+        self.check_private_name_references()?;
+        self.check_super_references(0, usize::MAX, false, false)?;
+        self.check_function_jumps(0, usize::MAX)?;
+        let is_async = self.has_top_level_await();
+        self.check_top_level_references(false, true)?;
+        self.check_cover_initialized_names()?;
+        self.check_duplicate_protos()?;
+
+        // Module code is always strict mode code.
+        self.check_strict_block_functions(0, usize::MAX)?;
+        self.context_metadata.pop_block_functions();
+
+        let statements = match body {
+            Some(statements) => statements.unbox(),
+            None => self.new_vec(),
+        };
+        let loc = if statements.is_empty() {
+            SourceLocation::default()
+        } else {
+            SourceLocation::from_parts(
+                statements.first().unwrap().get_loc(),
+                statements.last().unwrap().get_loc(),
+            )
+        };
+
+        // ImportDeclaration and ExportDeclaration aren't supported yet, so
+        // all items are statements.
+        // TODO: directives
+        let mut items = self.new_vec();
+        for statement in statements {
+            items.push(ModuleItems::Statement(self.alloc(statement)));
+        }
+        Ok(self.alloc(Module {
+            is_async,
+            directives: self.new_vec(),
+            items,
+            loc,
+        }))
+    }
+
+    // StandaloneExpression : Expression
+    pub fn standalone_expression(
+        &mut self,
+        mut expression: arena::Box<'alloc, Expression<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+        self.check_script_bindings()?;
+        self.check_private_name_references()?;
+        self.check_super_references(0, usize::MAX, false, false)?;
+        self.check_function_jumps(0, usize::MAX)?;
+        self.check_top_level_references(false, self.is_module)?;
+        self.check_cover_initialized_names()?;
+        self.check_duplicate_protos()?;
+
+        if self.strict_mode {
+            self.check_strict_block_functions(0, usize::MAX)?;
+        }
+        let offsets = self.pop_hoisted_block_function_offsets();
+        if !offsets.is_empty() {
+            AnnexBHoistingPass { offsets }.visit_expression(&mut expression);
+        }
+        if let Some(mut pass) = self.take_strict_lazy_functions_pass() {
+            pass.visit_expression(&mut expression);
+        }
+        Ok(expression)
+    }
+
+    // StandaloneFormalParameters : FormalParameters
+    //
+    // The parameters are checked as if they were the parameters of an
+    // ordinary function with an empty body, like the first arguments of
+    // `new Function(...)`.
+    pub fn standalone_formal_parameters(
+        &mut self,
+        mut params: arena::Box<'alloc, FormalParameters<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, FormalParameters<'alloc>>> {
+        let is_simple = Self::is_params_simple(&params);
+        self.check_function_bindings(is_simple, 0, usize::MAX)?;
+        self.check_private_name_references()?;
+        self.check_super_references(0, usize::MAX, false, false)?;
+        self.check_function_references(0, usize::MAX, false)?;
+        self.check_top_level_references(false, false)?;
+        self.check_cover_initialized_names()?;
+        self.check_duplicate_protos()?;
+
+        let first = params.items.first().map(|param| param.get_loc());
+        let last = match &params.rest {
+            Some(rest) => Some(rest.get_loc()),
+            None => params.items.last().map(|param| param.get_loc()),
+        };
+        if let (Some(first), Some(last)) = (first, last) {
+            params.loc = SourceLocation::from_parts(first, last);
+        }
+
+        let offsets = self.pop_hoisted_block_function_offsets();
+        if !offsets.is_empty() {
+            AnnexBHoistingPass { offsets }.visit_formal_parameters(&mut params);
+        }
+        if let Some(mut pass) = self.take_strict_lazy_functions_pass() {
+            pass.visit_formal_parameters(&mut params);
+        }
+        Ok(params)
+    }
+
+    // StandaloneFunctionBody : FunctionBody
+    //
+    // The body is checked as if it were the body of an ordinary function
+    // without parameters, like the last argument of `new Function(...)`.
+    pub fn standalone_function_body(
+        &mut self,
+        mut body: arena::Box<'alloc, FunctionBody<'alloc>>,
+    ) -> Result<'alloc, arena::Box<'alloc, FunctionBody<'alloc>>> {
+        self.check_function_bindings(true, 0, 0)?;
+        self.check_use_strict_directive(true, &body.statements)?;
+        self.check_private_name_references()?;
+        self.check_super_references(0, usize::MAX, false, false)?;
+        self.check_function_jumps(0, usize::MAX)?;
+        self.check_function_references(0, usize::MAX, false)?;
+        self.check_top_level_references(false, false)?;
+        self.check_cover_initialized_names()?;
+        self.check_duplicate_protos()?;
+
+        if let (Some(first), Some(last)) = (body.statements.first(), body.statements.last()) {
+            body.loc = SourceLocation::from_parts(first.get_loc(), last.get_loc());
+        }
 
-        Ok(body.unwrap_or_else(|| self.alloc(self.new_vec())))
+        let offsets = self.pop_hoisted_block_function_offsets();
+        if !offsets.is_empty() {
+            AnnexBHoistingPass { offsets }.visit_function_body(&mut body);
+        }
+        if let Some(mut pass) = self.take_strict_lazy_functions_pass() {
+            pass.visit_function_body(&mut body);
+        }
+        Ok(body)
     }
 
     // ModuleItemList : ModuleItem
@@ -4289,7 +5189,7 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn import_declaration(
         &self,
         _import_clause: Option<arena::Box<'alloc, Void>>,
-        _module_specifier: arena::Box<'alloc, Token<'alloc>>,
+        _module_specifier: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Void>> {
         Err(ParseError::NotImplemented("import"))
     }
@@ -4342,7 +5242,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // ImportSpecifier : IdentifierName `as` ImportedBinding
     pub fn import_specifier_renaming(
         &self,
-        _original_name: arena::Box<'alloc, Token<'alloc>>,
+        _original_name: Token<'alloc>,
         _local_name: arena::Box<'alloc, BindingIdentifier<'alloc>>,
     ) -> Result<'alloc, arena::Box<'alloc, Void>> {
         Err(ParseError::NotImplemented("import"))
@@ -4351,7 +5251,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // ModuleSpecifier : StringLiteral
     pub fn module_specifier(
         &self,
-        _token: arena::Box<'alloc, Token<'alloc>>,
+        _token: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Token<'alloc>>> {
         Err(ParseError::NotImplemented("import"))
     }
@@ -4359,7 +5259,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // ExportDeclaration : `export` `*` FromClause `;`
     pub fn export_all_from(
         &self,
-        _module_specifier: arena::Box<'alloc, Token<'alloc>>,
+        _module_specifier: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Void>> {
         Err(ParseError::NotImplemented("export"))
     }
@@ -4368,7 +5268,7 @@ impl<'alloc> AstBuilder<'alloc> {
     pub fn export_set_from(
         &self,
         _export_clause: arena::Box<'alloc, Void>,
-        _module_specifier: arena::Box<'alloc, Token<'alloc>>,
+        _module_specifier: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Void>> {
         Err(ParseError::NotImplemented("export"))
     }
@@ -4439,7 +5339,7 @@ impl<'alloc> AstBuilder<'alloc> {
     // ExportSpecifier : IdentifierName
     pub fn export_specifier(
         &self,
-        _identifier: arena::Box<'alloc, Token<'alloc>>,
+        _identifier: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Void>> {
         Err(ParseError::NotImplemented("export"))
     }
@@ -4447,29 +5347,22 @@ impl<'alloc> AstBuilder<'alloc> {
     // ExportSpecifier : IdentifierName `as` IdentifierName
     pub fn export_specifier_renaming(
         &self,
-        _local_name: arena::Box<'alloc, Token<'alloc>>,
-        _exported_name: arena::Box<'alloc, Token<'alloc>>,
+        _local_name: Token<'alloc>,
+        _exported_name: Token<'alloc>,
     ) -> Result<'alloc, arena::Box<'alloc, Void>> {
         Err(ParseError::NotImplemented("export"))
     }
 
     // Check Early Error for BindingIdentifier and note binding info to the
     // stack.
-    fn on_binding_identifier(
-        &mut self,
-        token: &arena::Box<'alloc, Token<'alloc>>,
-    ) -> Result<'alloc, ()> {
+    fn on_binding_identifier(&mut self, token: &Token<'alloc>) -> Result<'alloc, ()> {
         let context = IdentifierEarlyErrorsContext::new();
         context.check_binding_identifier(token)?;
 
-        let name = token.value.unwrap();
-        let offset = token.loc.start;
-
-        if let Some(info) = self.bindings.last() {
-            debug_assert!(info.offset < offset);
-        }
+        let name = token.str_value().unwrap();
+        let offset = token.loc.start();
 
-        self.bindings.push(BindingInfo {
+        self.context_metadata.push_binding(BindingInfo {
             name,
             offset,
             kind: BindingKind::Unknown,
@@ -4479,135 +5372,1034 @@ impl<'alloc> AstBuilder<'alloc> {
     }
 
     // Check Early Error for IdentifierReference.
-    fn on_identifier_reference(
-        &self,
-        token: &arena::Box<'alloc, Token<'alloc>>,
-    ) -> Result<'alloc, ()> {
+    fn on_identifier_reference(&self, token: &Token<'alloc>) -> Result<'alloc, ()> {
         let context = IdentifierEarlyErrorsContext::new();
         context.check_identifier_reference(token)
     }
 
     // Check Early Error for LabelIdentifier.
-    fn on_label_identifier(&self, token: &arena::Box<'alloc, Token<'alloc>>) -> Result<'alloc, ()> {
+    fn on_label_identifier(&self, token: &Token<'alloc>) -> Result<'alloc, ()> {
         let context = IdentifierEarlyErrorsContext::new();
         context.check_label_identifier(token)
     }
 
-    // Update the binding kind of all names declared in a specific range of the
-    // source (and not in any nested scope). This is used e.g. when the parser
-    // reaches the end of a VariableStatement to mark all the variables as Var
-    // bindings.
-    //
-    // It's necessary because the current parser only calls AstBuilder methods
-    // at the end of each production, not at the beginning.
-    //
-    // Bindings inside `StatementList` must be marked using this method before
-    // we reach the end of its scope.
-    fn mark_binding_kind(&mut self, from: usize, to: Option<usize>, kind: BindingKind) {
-        for info in self.bindings.iter_mut().rev() {
-            if info.offset < from {
-                break;
-            }
+    // Note PrivateIdentifier reference info to the stack.
+    fn on_private_name_reference(&mut self, token: &Token<'alloc>) {
+        let name = token.str_value().unwrap();
+        let loc = token.loc;
 
-            if to.is_none() || info.offset < to.unwrap() {
-                info.kind = kind;
-            }
+        if let Some(info) = self.private_name_references.last() {
+            debug_assert!(info.loc.start() < loc.start());
         }
+
+        self.private_name_references
+            .push(PrivateNameReference { name, loc });
     }
 
-    // Returns the index of the first binding at/after `offset` source position.
-    fn find_first_binding(&mut self, offset: usize) -> usize {
-        let mut i = self.bindings.len();
-        for info in self.bindings.iter_mut().rev() {
-            if info.offset < offset {
+    // Note the range of a class body or of code with a "use strict"
+    // directive, which replaces the ranges nested in it.
+    fn on_strict_range(&mut self, start: usize, end: usize) {
+        while let Some(&(last_start, _)) = self.strict_ranges.last() {
+            if last_start < start {
                 break;
             }
-            i -= 1;
+            self.strict_ranges.pop();
         }
-        i
+        self.strict_ranges.push((start, end));
     }
 
-    // Remove all bindings after `index`-th item.
+    // Note a construct that is an Early Error in strict mode code.
     //
-    // This should be called when leaving function/script/module.
-    fn pop_bindings_from(&mut self, index: usize) {
-        self.bindings.truncate(index)
+    // Unless the whole source is strict mode code, the construct is checked
+    // by check_strict_mode_violations when the parser reaches the end of the
+    // enclosing strict mode code, if any.
+    fn on_strict_mode_violation(
+        &mut self,
+        kind: StrictModeViolationKind,
+        offset: usize,
+    ) -> Result<'alloc, ()> {
+        let violation = StrictModeViolation { kind, offset };
+        if self.strict_mode || self.is_module {
+            return Err(violation.to_error());
+        }
+        self.strict_mode_violations.push(violation);
+        Ok(())
     }
 
-    // Remove lexical bindings after `index`-th item,
-    // while keeping var bindings.
-    //
-    // This should be called when leaving block.
-    fn pop_lexical_bindings_from(&mut self, index: usize) {
-        let len = self.bindings.len();
-        let mut i = index;
+    // Returns the pass that marks the LazyFunctions in class bodies and other
+    // strict mode code as strict, if there is any such code.
+    fn take_strict_lazy_functions_pass(&mut self) -> Option<StrictLazyFunctionsPass> {
+        if self.strict_ranges.is_empty() {
+            return None;
+        }
+        Some(StrictLazyFunctionsPass {
+            ranges: std::mem::take(&mut self.strict_ranges),
+        })
+    }
 
-        while i < len && self.bindings[i].kind == BindingKind::Var {
-            i += 1;
+    // Returns PropName of ClassElementName and its offset, unless it's a
+    // ComputedPropertyName or a PrivateIdentifier, which have no PropName.
+    fn class_element_prop_name(name: &ClassElementName<'alloc>) -> Option<(&'alloc str, usize)> {
+        match name {
+            ClassElementName::StaticPropertyName(StaticPropertyName { value, loc }) => {
+                Some((*value, loc.start()))
+            }
+            _ => None,
+        }
+    }
+
+    // Returns the name of MethodDefinition.
+    fn method_definition_name<'a>(
+        method: &'a MethodDefinition<'alloc>,
+    ) -> &'a ClassElementName<'alloc> {
+        match method {
+            MethodDefinition::Method(method) => &method.name,
+            MethodDefinition::Getter(getter) => &getter.property_name,
+            MethodDefinition::Setter(setter) => &setter.property_name,
+        }
+    }
+
+    // Check private names declared in ClassBody, and resolve references to
+    // them found since `start_of_body_offset`.
+    fn check_class_private_names(
+        &mut self,
+        elements: &arena::Vec<'alloc, arena::Box<'alloc, ClassElement<'alloc>>>,
+        start_of_body_offset: usize,
+    ) -> Result<'alloc, ()> {
+        let mut context = ClassBodyEarlyErrorsContext::new();
+
+        for element in elements.iter() {
+            let (name, kind, is_static) = match &**element {
+                ClassElement::FieldDefinition {
+                    name, is_static, ..
+                } => (name, PrivateNameKind::Field, *is_static),
+                ClassElement::MethodDefinition { method, is_static, .. } => {
+                    let kind = match method {
+                        MethodDefinition::Method(_) => PrivateNameKind::Method,
+                        MethodDefinition::Getter(_) => PrivateNameKind::Getter,
+                        MethodDefinition::Setter(_) => PrivateNameKind::Setter,
+                    };
+                    (Self::method_definition_name(method), kind, *is_static)
+                }
+                ClassElement::StaticBlock { .. } => continue,
+            };
+
+            if let ClassElementName::PrivateFieldName(PrivateIdentifier { value, loc }) = name {
+                context.declare_private_name(value, kind, is_static, loc.start())?;
+            }
+        }
+
+        // Keep unresolved references, in order, for the enclosing ClassBody.
+        let len = self.private_name_references.len();
+        let mut i = len;
+        for info in self.private_name_references.iter().rev() {
+            if info.loc.start() < start_of_body_offset {
+                break;
+            }
+            i -= 1;
         }
 
         let mut j = i;
         while j < len {
-            if self.bindings[j].kind == BindingKind::Var {
-                self.bindings[i] = self.bindings[j];
+            let info = self.private_name_references[j];
+            if !context.is_private_name_declared(info.name) {
+                self.private_name_references[i] = info;
                 i += 1;
             }
             j += 1;
         }
 
-        self.bindings.truncate(i)
+        self.private_name_references.truncate(i);
+
+        Ok(())
     }
 
-    // Declare bindings to Block-like context, where function declarations
-    // are lexical.
-    fn declare_block<T>(&self, context: &mut T, index: usize) -> Result<'alloc, ()>
-    where
-        T: LexicalEarlyErrorsContext<'alloc> + VarEarlyErrorsContext<'alloc>,
-    {
-        for info in self.bindings.iter().skip(index) {
-            match info.kind {
-                BindingKind::Var => {
-                    context.declare_var(info.name, DeclarationKind::Var, info.offset)?;
-                }
-                BindingKind::Function => {
-                    context.declare_lex(
-                        info.name,
-                        DeclarationKind::LexicalFunction,
-                        info.offset,
-                    )?;
-                }
-                BindingKind::AsyncOrGenerator => {
-                    context.declare_lex(
-                        info.name,
-                        DeclarationKind::LexicalAsyncOrGenerator,
-                        info.offset,
-                    )?;
-                }
-                BindingKind::Let => {
-                    context.declare_lex(info.name, DeclarationKind::Let, info.offset)?;
-                }
-                BindingKind::Const => {
-                    context.declare_lex(info.name, DeclarationKind::Const, info.offset)?;
-                }
-                BindingKind::Class => {
-                    context.declare_lex(info.name, DeclarationKind::Class, info.offset)?;
-                }
-                _ => {
-                    panic!("Unexpected binding found {:?}", info);
-                }
-            }
+    // Check that all PrivateIdentifier references were resolved by some
+    // enclosing ClassBody.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-scripts-static-semantics-early-errors
+    // https://tc39.es/ecma262/#sec-module-semantics-static-semantics-early-errors
+    //
+    // * It is a Syntax Error if AllPrivateIdentifiersValid of StatementList
+    //   (or ModuleItemList) with argument « » is false.
+    //
+    // References to the PrivateIdentifiers of the classes enclosing a function
+    // parsed again were resolved by the first parse.
+    fn check_private_name_references(&mut self) -> Result<'alloc, ()> {
+        let enclosing_private_names = &self.enclosing_private_names;
+        self.private_name_references
+            .retain(|info| !enclosing_private_names.contains(&info.name));
+        if let Some(info) = self.private_name_references.first() {
+            return Err(ParseError::UndeclaredPrivateName(info.name, info.loc.start()));
         }
 
         Ok(())
     }
 
-    // Check bindings in Block.
-    fn check_block_bindings(&mut self, start_of_block_offset: usize) -> Result<'alloc, ()> {
-        let mut context = BlockEarlyErrorsContext::new();
-        let index = self.find_first_binding(start_of_block_offset);
-        self.declare_block(&mut context, index)?;
-        self.pop_lexical_bindings_from(index);
-
+    // Note CoverInitializedName to the stack.
+    //
+    // CoverInitializedName is reduced after its Initializer, so items inside
+    // the Initializer may already be on the stack.
+    fn on_cover_initialized_name(
+        &mut self,
+        offset: usize,
+        initializer: arena::Box<'alloc, Expression<'alloc>>,
+    ) {
+        let index = self
+            .cover_initialized_names
+            .iter()
+            .rposition(|info| info.offset < offset)
+            .map_or(0, |i| i + 1);
+        self.cover_initialized_names.insert(
+            index,
+            CoverInitializedName {
+                offset,
+                initializer,
+            },
+        );
+    }
+
+    // Remove the Initializer of the CoverInitializedName whose
+    // IdentifierReference is at `offset`, if any, from the stack.
+    fn take_cover_initializer(
+        &mut self,
+        offset: usize,
+    ) -> Option<arena::Box<'alloc, Expression<'alloc>>> {
+        let index = self
+            .cover_initialized_names
+            .binary_search_by_key(&offset, |info| info.offset)
+            .ok()?;
+        Some(self.cover_initialized_names.remove(index).initializer)
+    }
+
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-object-initializer-static-semantics-early-errors
+    //
+    // PropertyDefinition : CoverInitializedName
+    //
+    // * Always throw a Syntax Error if code matches this production.
+    fn check_cover_initialized_names(&mut self) -> Result<'alloc, ()> {
+        if let Some(info) = self.cover_initialized_names.first() {
+            return Err(ParseError::InvalidCoverInitializedName(info.offset));
+        }
+
+        Ok(())
+    }
+
+    // Note a duplicate `__proto__` to the stack.
+    //
+    // ObjectLiteral is reduced after its properties, so items inside the
+    // properties may already be on the stack.
+    fn on_duplicate_proto(&mut self, first_offset: usize, offset: usize) {
+        let index = self
+            .duplicate_protos
+            .iter()
+            .rposition(|info| info.offset < offset)
+            .map_or(0, |i| i + 1);
+        self.duplicate_protos.insert(
+            index,
+            DuplicateProto {
+                first_offset,
+                offset,
+            },
+        );
+    }
+
+    // Remove the duplicate `__proto__` at `offset`, if any, from the stack.
+    fn remove_duplicate_proto(&mut self, offset: usize) {
+        if let Ok(index) = self
+            .duplicate_protos
+            .binary_search_by_key(&offset, |info| info.offset)
+        {
+            self.duplicate_protos.remove(index);
+        }
+    }
+
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-object-initializer-static-semantics-early-errors
+    //
+    // ObjectLiteral : `{` PropertyDefinitionList `}`
+    // ObjectLiteral : `{` PropertyDefinitionList `,` `}`
+    //
+    // * It is a Syntax Error if PropertyNameList of PropertyDefinitionList
+    //   contains any duplicate entries for "__proto__" and at least two of
+    //   those entries were obtained from productions of the form
+    //   PropertyDefinition : PropertyName `:` AssignmentExpression. This rule
+    //   is not applied if this ObjectLiteral is contained within a
+    //   PrimaryExpression that is being parsed as the
+    //   CoverParenthesizedExpressionAndArrowParameterList or
+    //   CoverCallExpressionAndAsyncArrowHead.
+    //
+    // The rule isn't applied to an ObjectLiteral reinterpreted as an
+    // ObjectAssignmentPattern either.
+    fn check_duplicate_protos(&mut self) -> Result<'alloc, ()> {
+        if let Some(info) = self.duplicate_protos.first() {
+            return Err(ParseError::DuplicateProto(info.first_offset, info.offset));
+        }
+
+        Ok(())
+    }
+
+    // Note SuperCall or SuperProperty to the stack.
+    //
+    // SuperCall is reduced after its Arguments, so references inside the
+    // Arguments may already be on the stack.
+    fn on_super_reference(&mut self, kind: SuperKind, offset: usize) {
+        let index = self
+            .super_references
+            .iter()
+            .rposition(|info| info.offset < offset)
+            .map_or(0, |i| i + 1);
+        self.super_references
+            .insert(index, SuperReference { kind, offset });
+    }
+
+    // Note BreakStatement or ContinueStatement to the stack.
+    fn on_jump(&mut self, kind: JumpKind, label: Option<&'alloc str>, offset: usize) {
+        self.context_metadata.push_jump(JumpInfo {
+            kind,
+            label,
+            offset,
+        });
+    }
+
+    // Remove BreakStatement without label found in the range [start, end) of
+    // the source, and also ContinueStatement without label if the range is
+    // an IterationStatement.
+    //
+    // Jumps in nested functions have already been removed, so the remaining
+    // ones target this statement.
+    fn resolve_unlabelled_jumps(&mut self, start: usize, end: usize, is_iteration: bool) {
+        self.context_metadata.remove_jumps_in(start, end, |info| {
+            info.label.is_none() && (info.kind == JumpKind::Break || is_iteration)
+        });
+    }
+
+    // Returns true if the statement is an IterationStatement, possibly
+    // wrapped in LabelledStatements.
+    fn is_labelled_iteration_statement(statement: &Statement<'alloc>) -> bool {
+        match statement {
+            Statement::DoWhileStatement { .. }
+            | Statement::WhileStatement { .. }
+            | Statement::ForStatement { .. }
+            | Statement::ForInStatement { .. }
+            | Statement::ForOfStatement { .. }
+            | Statement::ForAwaitOfStatement { .. } => true,
+            Statement::LabeledStatement { body, .. } => Self::is_labelled_iteration_statement(body),
+            _ => false,
+        }
+    }
+
+    // Check the label of LabelledStatement ending at `end`, and resolve the
+    // jumps targeting it.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-labelled-statements-static-semantics-early-errors
+    // https://tc39.es/ecma262/#sec-static-semantics-containsduplicatelabels
+    // https://tc39.es/ecma262/#sec-static-semantics-containsundefinedcontinuetarget
+    //
+    // * It is a Syntax Error if ContainsDuplicateLabels of StatementList
+    //   with argument « » is true.
+    // * It is a Syntax Error if ContainsUndefinedContinueTarget of
+    //   StatementList with arguments « » and « » is true.
+    fn check_labelled_statement(
+        &mut self,
+        name: &'alloc str,
+        offset: usize,
+        body: &Statement<'alloc>,
+        end: usize,
+    ) -> Result<'alloc, ()> {
+        if let Some(info) = self
+            .context_metadata
+            .labels_in(offset, end)
+            .iter()
+            .find(|info| info.name == name)
+        {
+            return Err(ParseError::DuplicateLabel(name, offset, info.offset));
+        }
+
+        let is_iteration = Self::is_labelled_iteration_statement(body);
+        if !is_iteration {
+            if let Some(info) = self
+                .context_metadata
+                .jumps_in(offset, end)
+                .iter()
+                .find(|info| info.kind == JumpKind::Continue && info.label == Some(name))
+            {
+                return Err(ParseError::ContinueToNonIterationLabel(
+                    name,
+                    info.offset,
+                    offset,
+                ));
+            }
+        }
+
+        self.context_metadata
+            .remove_jumps_in(offset, end, |info| info.label == Some(name));
+        self.context_metadata.push_label(LabelInfo { name, offset });
+
+        Ok(())
+    }
+
+    // Check that all jumps found in the range [start, end) of the source
+    // have a target, when reaching the end of a function body, static block,
+    // script or module. Labels can't be referred from outside of it, and
+    // are removed.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-function-definitions-static-semantics-early-errors
+    // https://tc39.es/ecma262/#sec-scripts-static-semantics-early-errors
+    //
+    // * It is a Syntax Error if ContainsUndefinedBreakTarget of
+    //   StatementList with argument « » is true.
+    // * It is a Syntax Error if ContainsUndefinedContinueTarget of
+    //   StatementList with arguments « » and « » is true.
+    //
+    // https://tc39.es/ecma262/#sec-break-statement-static-semantics-early-errors
+    // https://tc39.es/ecma262/#sec-continue-statement-static-semantics-early-errors
+    //
+    // * It is a Syntax Error if this BreakStatement is not nested, directly
+    //   or indirectly (but not crossing function or static initialization
+    //   block boundaries), within an IterationStatement or a
+    //   SwitchStatement.
+    // * It is a Syntax Error if this ContinueStatement is not nested,
+    //   directly or indirectly (but not crossing function or static
+    //   initialization block boundaries), within an IterationStatement.
+    fn check_function_jumps(&mut self, start: usize, end: usize) -> Result<'alloc, ()> {
+        if let Some(info) = self.context_metadata.jumps_in(start, end).first() {
+            return Err(match (info.kind, info.label) {
+                (_, Some(name)) => ParseError::UndefinedLabel(name, info.offset),
+                (JumpKind::Break, None) => ParseError::BreakOutsideOfLoop(info.offset),
+                (JumpKind::Continue, None) => ParseError::ContinueOutsideOfLoop(info.offset),
+            });
+        }
+
+        self.context_metadata.pop_labels_in(start, end);
+
+        Ok(())
+    }
+
+    // Note ReturnStatement, NewTarget, IdentifierReference `arguments`,
+    // AwaitExpression, `for await` or ImportMeta to the stack.
+    fn on_function_reference(&mut self, kind: FunctionReferenceKind, offset: usize) {
+        self.context_metadata
+            .push_function_reference(FunctionReference { kind, offset });
+    }
+
+    // Resolve the function references found in the range [start, end) of
+    // the source, when reaching the end of a function other than an arrow
+    // function. `import.meta` is left for the script or module.
+    fn check_function_references(
+        &mut self,
+        start: usize,
+        end: usize,
+        is_async: bool,
+    ) -> Result<'alloc, ()> {
+        if !is_async {
+            self.check_await_references(start, end)?;
+        }
+
+        self.context_metadata
+            .remove_function_references_in(start, end, |info| {
+                info.kind != FunctionReferenceKind::ImportMeta
+            });
+
+        Ok(())
+    }
+
+    // Resolve `return`, `await` and `for await` found in the range
+    // [start, end) of the source, when reaching the end of an arrow
+    // function. `new.target` and `arguments` are left for the enclosing
+    // function.
+    fn check_arrow_function_references(
+        &mut self,
+        start: usize,
+        end: usize,
+        is_async: bool,
+    ) -> Result<'alloc, ()> {
+        if !is_async {
+            self.check_await_references(start, end)?;
+        }
+
+        self.context_metadata
+            .remove_function_references_in(start, end, |info| match info.kind {
+                FunctionReferenceKind::Return
+                | FunctionReferenceKind::Await
+                | FunctionReferenceKind::ForAwait => true,
+                FunctionReferenceKind::NewTarget
+                | FunctionReferenceKind::Arguments
+                | FunctionReferenceKind::ImportMeta => false,
+            });
+
+        Ok(())
+    }
+
+    // Check `await` and `for await` found in the range [start, end) of the
+    // source, outside of any async function.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-for-in-and-for-of-statements-static-semantics-early-errors
+    //
+    // `for await` is parsed only with [+Await].
+    //
+    // In a script, `await` is an IdentifierReference there, which isn't
+    // supported by the parser. For example `await(x)` is a CallExpression.
+    // In a module, `await` is a reserved word, so it's an error.
+    fn check_await_references(&self, start: usize, end: usize) -> Result<'alloc, ()> {
+        for info in self.context_metadata.function_references_in(start, end) {
+            match info.kind {
+                FunctionReferenceKind::Await if self.is_module => {
+                    return Err(ParseError::AwaitOutsideOfAsyncFunction(info.offset));
+                }
+                FunctionReferenceKind::Await => {
+                    return Err(ParseError::NotImplemented("await as identifier"));
+                }
+                FunctionReferenceKind::ForAwait => {
+                    return Err(ParseError::ForAwaitOutsideOfAsyncFunction(info.offset));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-async-function-definitions-static-semantics-early-errors
+    // https://tc39.es/ecma262/#sec-async-generator-function-definitions-static-semantics-early-errors
+    //
+    // * It is a Syntax Error if FormalParameters Contains AwaitExpression is
+    //   true.
+    fn check_async_function_parameters(&self, start: usize, end: usize) -> Result<'alloc, ()> {
+        for info in self.context_metadata.function_references_in(start, end) {
+            if info.kind == FunctionReferenceKind::Await {
+                return Err(ParseError::AwaitInFormalParameters(info.offset));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Check `return`, `new.target` and `arguments` found in the range
+    // [start, end) of the source, when reaching the end of a field
+    // initializer or a static block.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-class-definitions-static-semantics-early-errors
+    //
+    // FieldDefinition : ClassElementName Initializer?
+    //
+    // * It is a Syntax Error if Initializer is present and ContainsArguments
+    //   of Initializer is true.
+    //
+    // ClassStaticBlockBody : ClassStaticBlockStatementList
+    //
+    // * It is a Syntax Error if ContainsArguments of
+    //   ClassStaticBlockStatementList is true.
+    //
+    // `return` can't appear in ClassStaticBlockStatementList, which is
+    // parsed with [~Return].
+    fn check_class_initializer_references(
+        &mut self,
+        start: usize,
+        end: usize,
+    ) -> Result<'alloc, ()> {
+        self.check_await_references(start, end)?;
+
+        for info in self.context_metadata.function_references_in(start, end) {
+            match info.kind {
+                FunctionReferenceKind::Return => {
+                    return Err(ParseError::ReturnOutsideOfFunction(info.offset));
+                }
+                FunctionReferenceKind::Arguments => {
+                    return Err(ParseError::ArgumentsInClassInitializer(info.offset));
+                }
+                FunctionReferenceKind::NewTarget
+                | FunctionReferenceKind::Await
+                | FunctionReferenceKind::ForAwait
+                | FunctionReferenceKind::ImportMeta => {}
+            }
+        }
+
+        self.context_metadata
+            .remove_function_references_in(start, end, |info| {
+                info.kind != FunctionReferenceKind::ImportMeta
+            });
+
+        Ok(())
+    }
+
+    // Check the function references left at the end of the script or
+    // module.
+    //
+    // `return` can't appear in ScriptBody and ModuleBody, which are parsed
+    // with [~Return], unless `allow_return_outside_function` is set for the
+    // script.
+    //
+    // `await` and `for await` are allowed at the top level of a module,
+    // which is parsed with [+Await].
+    //
+    // `import.meta` is parsed only with the Module goal symbol.
+    // https://tc39.es/ecma262/#sec-meta-properties
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-scripts-static-semantics-early-errors
+    // https://tc39.es/ecma262/#sec-module-semantics-static-semantics-early-errors
+    //
+    // * It is a Syntax Error if StatementList (or ModuleItemList) Contains
+    //   NewTarget unless the source text containing NewTarget is eval code
+    //   that is being processed by a direct eval.
+    fn check_top_level_references(
+        &mut self,
+        allow_return: bool,
+        is_module: bool,
+    ) -> Result<'alloc, ()> {
+        for info in self.context_metadata.function_references_in(0, usize::MAX) {
+            match info.kind {
+                FunctionReferenceKind::Await if !is_module => {
+                    return Err(ParseError::NotImplemented("await as identifier"));
+                }
+                FunctionReferenceKind::ForAwait if !is_module => {
+                    return Err(ParseError::ForAwaitOutsideOfAsyncFunction(info.offset));
+                }
+                FunctionReferenceKind::Return if !allow_return => {
+                    return Err(ParseError::ReturnOutsideOfFunction(info.offset));
+                }
+                FunctionReferenceKind::NewTarget => {
+                    return Err(ParseError::NewTargetOutsideOfFunction(info.offset));
+                }
+                FunctionReferenceKind::ImportMeta if !is_module => {
+                    return Err(ParseError::ImportMetaOutsideOfModule(info.offset));
+                }
+                _ => {}
+            }
+        }
+
+        self.context_metadata
+            .remove_function_references_in(0, usize::MAX, |_| true);
+
+        Ok(())
+    }
+
+    // True if `await` or `for await` is found outside of any function, when
+    // reaching the end of a module. The references in functions are already
+    // removed at this point.
+    fn has_top_level_await(&self) -> bool {
+        self.context_metadata
+            .function_references_in(0, usize::MAX)
+            .iter()
+            .any(|info| {
+                matches!(
+                    info.kind,
+                    FunctionReferenceKind::Await | FunctionReferenceKind::ForAwait
+                )
+            })
+    }
+
+    // Returns the LazyFunction that replaces the body of the function at
+    // `loc`, or None if the function should be built eagerly.
+    //
+    // The names the function closes over are the names it references that
+    // aren't declared in the scope of the function itself, in the order of
+    // their first reference. Names declared in nested scopes aren't excluded,
+    // so this may contain more names than the function actually closes over.
+    //
+    // This must be called before the bindings of the function are removed
+    // from the stack.
+    fn lazy_function(
+        &mut self,
+        loc: SourceLocation,
+        param_open_offset: usize,
+        body: &FunctionBody<'alloc>,
+    ) -> Option<LazyFunction<'alloc>> {
+        if !self.lazy_inner_functions {
+            return None;
+        }
+        self.lazy_function_depth -= 1;
+
+        let index = self.context_metadata.find_first_binding(param_open_offset);
+        let declared_names: Vec<&str> = self
+            .context_metadata
+            .bindings_from(index)
+            .iter()
+            .map(|info| info.name)
+            .collect();
+
+        let mut closed_over_names: arena::Vec<'alloc, Identifier<'alloc>> = self.new_vec();
+        let first = self
+            .name_references
+            .partition_point(|info| info.loc.start() < loc.start());
+        let mut i = first;
+        for j in first..self.name_references.len() {
+            let info = self.name_references[j];
+            // `arguments` always refers to the arguments object of a function
+            // inside the lazy function.
+            if info.name == "arguments"
+                || declared_names.contains(&info.name)
+                || closed_over_names.iter().any(|name| name.value == info.name)
+            {
+                continue;
+            }
+            closed_over_names.push(Identifier {
+                value: info.name,
+                loc: info.loc,
+            });
+            self.name_references[i] = info;
+            i += 1;
+        }
+        self.name_references.truncate(i);
+
+        // PrivateIdentifiers are resolved by the enclosing classes, which
+        // aren't available when the function is parsed again.
+        let mut private_names: arena::Vec<'alloc, PrivateIdentifier<'alloc>> = self.new_vec();
+        for info in self
+            .private_name_references
+            .iter()
+            .filter(|info| loc.start() <= info.loc.start())
+        {
+            if !private_names.iter().any(|name| name.value == info.name) {
+                private_names.push(PrivateIdentifier {
+                    value: info.name,
+                    loc: info.loc,
+                });
+            }
+        }
+
+        // Module code is always strict mode code, and so is a function with a
+        // "use strict" directive. Functions in class bodies and in other
+        // strict mode code are marked at the end of the script.
+        let is_strict = self.is_module
+            || self.strict_mode
+            || Self::use_strict_directive_offset(&body.statements).is_some();
+        Some(LazyFunction {
+            closed_over_names,
+            private_names,
+            is_strict,
+            is_module: self.is_module,
+            loc,
+        })
+    }
+
+    // Pop all block-level functions, and return the sorted offsets of the
+    // ones hoisted by Annex B.3.3.
+    fn pop_hoisted_block_function_offsets(&mut self) -> Vec<usize> {
+        self.context_metadata
+            .pop_block_functions()
+            .iter()
+            .filter(|info| info.state == BlockFunctionState::Hoisted)
+            .map(|info| info.offset)
+            .collect()
+    }
+
+    // Returns the range of the source that is evaluated in the scope of the
+    // method itself, excluding the ClassElementName (a ComputedPropertyName
+    // is evaluated in the enclosing scope).
+    fn method_definition_body_range(method: &MethodDefinition<'alloc>) -> (usize, usize) {
+        let name_end = Self::method_definition_name(method).get_loc().end();
+        (name_end, method.get_loc().end())
+    }
+
+    // Check SuperCall and SuperProperty found in the range [start, end) of
+    // the source, and remove them from the stack.
+    fn check_super_references(
+        &mut self,
+        start: usize,
+        end: usize,
+        allow_super_call: bool,
+        allow_super_property: bool,
+    ) -> Result<'alloc, ()> {
+        let from = self
+            .super_references
+            .iter()
+            .position(|info| info.offset >= start)
+            .unwrap_or(self.super_references.len());
+        let to = self.super_references[from..]
+            .iter()
+            .position(|info| info.offset >= end)
+            .map_or(self.super_references.len(), |i| from + i);
+
+        for info in &self.super_references[from..to] {
+            match info.kind {
+                SuperKind::Call if !allow_super_call => {
+                    return Err(ParseError::UnexpectedSuperCall(info.offset));
+                }
+                SuperKind::Property if !allow_super_property => {
+                    return Err(ParseError::UnexpectedSuperProperty(info.offset));
+                }
+                _ => {}
+            }
+        }
+
+        self.super_references.drain(from..to);
+
+        Ok(())
+    }
+
+    // Check the constructor of the ClassBody, and SuperCall and SuperProperty
+    // found inside its methods, field initializers and static blocks.
+    //
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-class-definitions-static-semantics-early-errors
+    //
+    // ClassElement : MethodDefinition
+    //
+    // * It is a Syntax Error if PropName of MethodDefinition is not
+    //   "constructor" and HasDirectSuper of MethodDefinition is true.
+    //
+    // ClassElement : `static` MethodDefinition
+    //
+    // * It is a Syntax Error if HasDirectSuper of MethodDefinition is true.
+    //
+    // FieldDefinition : ClassElementName Initializer?
+    //
+    // * It is a Syntax Error if Initializer is present and Initializer
+    //   Contains SuperCall is true.
+    //
+    // ClassStaticBlockBody : ClassStaticBlockStatementList
+    //
+    // * It is a Syntax Error if ClassStaticBlockStatementList Contains
+    //   SuperCall is true.
+    //
+    // ClassTail : ClassHeritage? `{` ClassBody `}`
+    //
+    // * It is a Syntax Error if ClassHeritage is not present and the
+    //   following algorithm returns true:
+    //   1. Let constructor be ConstructorMethod of ClassBody.
+    //   2. If constructor is empty, return false.
+    //   3. Return HasDirectSuper of constructor.
+    fn check_class_super_references(
+        &mut self,
+        elements: &arena::Vec<'alloc, arena::Box<'alloc, ClassElement<'alloc>>>,
+        has_heritage: bool,
+    ) -> Result<'alloc, ()> {
+        let mut context = ClassBodyEarlyErrorsContext::new();
+
+        for element in elements.iter() {
+            match &**element {
+                ClassElement::MethodDefinition {
+                    is_static, method, ..
+                } => {
+                    let name = Self::method_definition_name(method);
+                    let is_constructor = !is_static
+                        && match name {
+                            ClassElementName::StaticPropertyName(StaticPropertyName {
+                                value,
+                                ..
+                            }) => *value == "constructor",
+                            _ => false,
+                        };
+                    if is_constructor {
+                        let is_special_method = match method {
+                            MethodDefinition::Method(method) => {
+                                method.is_async || method.is_generator
+                            }
+                            _ => true,
+                        };
+                        context.declare_constructor(is_special_method, name.get_loc().start())?;
+                    }
+
+                    let (start, end) = Self::method_definition_body_range(method);
+                    self.check_super_references(
+                        start,
+                        end,
+                        is_constructor && has_heritage,
+                        true,
+                    )?;
+                }
+                ClassElement::FieldDefinition {
+                    init: Some(init), ..
+                } => {
+                    let loc = init.get_loc();
+                    self.check_super_references(loc.start(), loc.end(), false, true)?;
+                }
+                ClassElement::FieldDefinition { init: None, .. } => {}
+                ClassElement::StaticBlock { loc, .. } => {
+                    self.check_super_references(loc.start(), loc.end(), false, true)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Declare bindings to Block-like context, where function declarations
+    // are lexical.
+    fn declare_block<T>(&self, context: &mut T, index: usize) -> Result<'alloc, ()>
+    where
+        T: LexicalEarlyErrorsContext<'alloc> + VarEarlyErrorsContext<'alloc>,
+    {
+        for info in self.context_metadata.bindings_from(index) {
+            match info.kind {
+                BindingKind::Var => {
+                    context.declare_var(info.name, DeclarationKind::Var, info.offset)?;
+                }
+                BindingKind::Function => {
+                    context.declare_lex(
+                        info.name,
+                        DeclarationKind::LexicalFunction,
+                        info.offset,
+                    )?;
+                }
+                BindingKind::AsyncOrGenerator => {
+                    context.declare_lex(
+                        info.name,
+                        DeclarationKind::LexicalAsyncOrGenerator,
+                        info.offset,
+                    )?;
+                }
+                BindingKind::Let => {
+                    context.declare_lex(info.name, DeclarationKind::Let, info.offset)?;
+                }
+                BindingKind::Const => {
+                    context.declare_lex(info.name, DeclarationKind::Const, info.offset)?;
+                }
+                BindingKind::Class => {
+                    context.declare_lex(info.name, DeclarationKind::Class, info.offset)?;
+                }
+                _ => {
+                    panic!("Unexpected binding found {:?}", info);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Declare the pending FunctionDeclarations in nested blocks to the
+    // context, as if they were replaced with VariableStatements.
+    //
+    // Changes to FunctionDeclarationInstantiation
+    // https://tc39.es/ecma262/#sec-web-compat-functiondeclarationinstantiation
+    //
+    // * If replacing the FunctionDeclaration f with a VariableStatement that
+    //   has F as a BindingIdentifier would not produce any Early Errors for
+    //   func and F is not an element of parameterNames, then
+    //   NOTE: A var binding for F is only instantiated here if it is neither
+    //         a VarDeclaredName, the name of a formal parameter, or another
+    //         FunctionDeclaration.
+    //
+    // If it would produce an Early Error in any of the enclosing contexts,
+    // the function isn't hoisted.
+    fn declare_block_functions<T>(&mut self, context: &mut T, start_offset: usize)
+    where
+        T: VarEarlyErrorsContext<'alloc>,
+    {
+        self.context_metadata
+            .update_pending_block_functions(start_offset, |info| {
+                match context.declare_var(
+                    info.name,
+                    DeclarationKind::VarForAnnexBLexicalFunction,
+                    info.offset,
+                ) {
+                    Ok(()) => BlockFunctionState::Pending,
+                    Err(_) => BlockFunctionState::NotHoisted,
+                }
+            });
+    }
+
+    // Decide the hoisting of the pending FunctionDeclarations in nested
+    // blocks, when the parser reaches the end of a function or script.
+    fn hoist_block_functions<T>(
+        &mut self,
+        context: &mut T,
+        start_offset: usize,
+        param_names: &[&'alloc str],
+    ) where
+        T: VarEarlyErrorsContext<'alloc>,
+    {
+        self.context_metadata
+            .update_pending_block_functions(start_offset, |info| {
+                if param_names.contains(&info.name) {
+                    return BlockFunctionState::NotHoisted;
+                }
+                match context.declare_var(
+                    info.name,
+                    DeclarationKind::VarForAnnexBLexicalFunction,
+                    info.offset,
+                ) {
+                    Ok(()) => BlockFunctionState::Hoisted,
+                    Err(_) => BlockFunctionState::NotHoisted,
+                }
+            });
+    }
+
+    // Block-level FunctionDeclarations in strict mode code.
+    //
+    // FunctionDeclarations in IfStatement Statement Clauses, and duplicate
+    // FunctionDeclarations in a Block or CaseBlock are allowed only in
+    // non-strict code. FunctionDeclarations in strict mode code are never
+    // hoisted.
+    //
+    // Changes to Block Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-block-duplicates-allowed-static-semantics
+    //
+    // Changes to switch Statement Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-switch-duplicates-allowed-static-semantics
+    fn check_strict_block_functions(&mut self, start: usize, end: usize) -> Result<'alloc, ()> {
+        let mut first_offsets = HashMap::new();
+        for info in self.context_metadata.block_functions_in(start, end) {
+            if info.is_single_statement {
+                return Err(ParseError::FunctionDeclInSingleStatement);
+            }
+
+            if let Some(first_offset) =
+                first_offsets.insert((info.block_offset, info.name), info.offset)
+            {
+                return Err(ParseError::DuplicateBinding(
+                    info.name,
+                    DeclarationKind::LexicalFunction,
+                    first_offset,
+                    DeclarationKind::LexicalFunction,
+                    info.offset,
+                ));
+            }
+        }
+
+        self.context_metadata.unhoist_block_functions_in(start, end);
+
+        Ok(())
+    }
+
+    // Reject the constructs noted by on_strict_mode_violation in the range
+    // [start, end) of strict mode code.
+    fn check_strict_mode_violations(&self, start: usize, end: usize) -> Result<'alloc, ()> {
+        match self
+            .strict_mode_violations
+            .iter()
+            .filter(|violation| start <= violation.offset && violation.offset < end)
+            .min_by_key(|violation| violation.offset)
+        {
+            Some(violation) => Err(violation.to_error()),
+            None => Ok(()),
+        }
+    }
+
+    // Check bindings in Block.
+    fn check_block_bindings(&mut self, start_of_block_offset: usize) -> Result<'alloc, ()> {
+        self.check_block_bindings_with(start_of_block_offset, false)
+    }
+
+    // Check bindings in Block, or the synthetic Block for FunctionDeclaration
+    // in IfStatement clause if `is_single_statement` is true.
+    fn check_block_bindings_with(
+        &mut self,
+        start_of_block_offset: usize,
+        is_single_statement: bool,
+    ) -> Result<'alloc, ()> {
+        let mut context = BlockEarlyErrorsContext::new();
+        let index = self
+            .context_metadata
+            .find_first_binding(start_of_block_offset);
+        self.declare_block(&mut context, index)?;
+        self.declare_block_functions(&mut context, start_of_block_offset);
+        self.context_metadata.push_block_functions_from(
+            index,
+            start_of_block_offset,
+            is_single_statement,
+        );
+        self.context_metadata.pop_lexical_bindings_from(index);
+
         Ok(())
     }
 
@@ -4618,7 +6410,7 @@ impl<'alloc> AstBuilder<'alloc> {
         from: usize,
         to: usize,
     ) -> Result<'alloc, ()> {
-        for info in self.bindings.iter().skip(from).take(to - from) {
+        for info in self.context_metadata.bindings_from_to(from, to) {
             match info.kind {
                 BindingKind::Let => {
                     context.declare_lex(info.name, DeclarationKind::Let, info.offset)?;
@@ -4641,7 +6433,7 @@ impl<'alloc> AstBuilder<'alloc> {
         context: &mut LexicalForBodyEarlyErrorsContext<'alloc>,
         index: usize,
     ) -> Result<'alloc, ()> {
-        for info in self.bindings.iter().skip(index) {
+        for info in self.context_metadata.bindings_from(index) {
             match info.kind {
                 BindingKind::Var => {
                     context.declare_var(info.name, DeclarationKind::Var, info.offset)?;
@@ -4659,13 +6451,16 @@ impl<'alloc> AstBuilder<'alloc> {
     fn check_lexical_for_bindings(&mut self, bindings_loc: &SourceLocation) -> Result<'alloc, ()> {
         let mut head_context = LexicalForHeadEarlyErrorsContext::new();
 
-        let head_index = self.find_first_binding(bindings_loc.start);
-        let body_index = self.find_first_binding(bindings_loc.end);
+        let head_index = self
+            .context_metadata
+            .find_first_binding(bindings_loc.start());
+        let body_index = self.context_metadata.find_first_binding(bindings_loc.end());
         self.declare_lexical_for_head(&mut head_context, head_index, body_index)?;
 
         let mut body_context = LexicalForBodyEarlyErrorsContext::new(head_context);
         self.declare_lexical_for_body(&mut body_context, body_index)?;
-        self.pop_lexical_bindings_from(head_index);
+        self.declare_block_functions(&mut body_context, bindings_loc.end());
+        self.context_metadata.pop_lexical_bindings_from(head_index);
 
         Ok(())
     }
@@ -4674,9 +6469,14 @@ impl<'alloc> AstBuilder<'alloc> {
     fn check_case_block_binding(&mut self, start_of_block_offset: usize) -> Result<'alloc, ()> {
         let mut context = CaseBlockEarlyErrorsContext::new();
 
-        let index = self.find_first_binding(start_of_block_offset);
+        let index = self
+            .context_metadata
+            .find_first_binding(start_of_block_offset);
         self.declare_block(&mut context, index)?;
-        self.pop_lexical_bindings_from(index);
+        self.declare_block_functions(&mut context, start_of_block_offset);
+        self.context_metadata
+            .push_block_functions_from(index, start_of_block_offset, false);
+        self.context_metadata.pop_lexical_bindings_from(index);
 
         Ok(())
     }
@@ -4686,7 +6486,7 @@ impl<'alloc> AstBuilder<'alloc> {
     where
         T: ParameterEarlyErrorsContext<'alloc>,
     {
-        for info in self.bindings.iter().skip(from).take(to - from) {
+        for info in self.context_metadata.bindings_from_to(from, to) {
             context.declare(info.name, info.offset)?;
         }
 
@@ -4705,25 +6505,33 @@ impl<'alloc> AstBuilder<'alloc> {
             CatchParameterEarlyErrorsContext::new_with_binding_pattern()
         };
 
-        let param_index = self.find_first_binding(bindings_loc.start);
-        let body_index = self.find_first_binding(bindings_loc.end);
+        let param_index = self
+            .context_metadata
+            .find_first_binding(bindings_loc.start());
+        let body_index = self.context_metadata.find_first_binding(bindings_loc.end());
         self.declare_param(&mut param_context, param_index, body_index)?;
 
         let mut block_context = CatchBlockEarlyErrorsContext::new(param_context);
         self.declare_block(&mut block_context, body_index)?;
-        self.pop_lexical_bindings_from(param_index);
+        self.declare_block_functions(&mut block_context, bindings_loc.start());
+        self.context_metadata
+            .push_block_functions_from(body_index, bindings_loc.end(), false);
+        self.context_metadata.pop_lexical_bindings_from(param_index);
 
         Ok(())
     }
 
     // Check bindings in Catch with no parameter and Block.
     fn check_catch_no_param_bindings(&mut self, catch_offset: usize) -> Result<'alloc, ()> {
-        let body_index = self.find_first_binding(catch_offset);
+        let body_index = self.context_metadata.find_first_binding(catch_offset);
 
         let param_context = CatchParameterEarlyErrorsContext::new_with_binding_identifier();
         let mut block_context = CatchBlockEarlyErrorsContext::new(param_context);
         self.declare_block(&mut block_context, body_index)?;
-        self.pop_lexical_bindings_from(body_index);
+        self.declare_block_functions(&mut block_context, catch_offset);
+        self.context_metadata
+            .push_block_functions_from(body_index, catch_offset, false);
+        self.context_metadata.pop_lexical_bindings_from(body_index);
 
         Ok(())
     }
@@ -4734,7 +6542,7 @@ impl<'alloc> AstBuilder<'alloc> {
     where
         T: LexicalEarlyErrorsContext<'alloc> + VarEarlyErrorsContext<'alloc>,
     {
-        for info in self.bindings.iter().skip(index) {
+        for info in self.context_metadata.bindings_from(index) {
             match info.kind {
                 BindingKind::Var => {
                     context.declare_var(info.name, DeclarationKind::Var, info.offset)?;
@@ -4777,13 +6585,24 @@ impl<'alloc> AstBuilder<'alloc> {
             FormalParametersEarlyErrorsContext::new_non_simple()
         };
 
-        let param_index = self.find_first_binding(start_of_param_offset);
-        let body_index = self.find_first_binding(end_of_param_offset);
+        let param_index = self
+            .context_metadata
+            .find_first_binding(start_of_param_offset);
+        let body_index = self
+            .context_metadata
+            .find_first_binding(end_of_param_offset);
         self.declare_param(&mut param_context, param_index, body_index)?;
 
         let mut body_context = FunctionBodyEarlyErrorsContext::new(param_context);
         self.declare_script_or_function(&mut body_context, body_index)?;
-        self.pop_bindings_from(param_index);
+        let param_names: Vec<_> = self
+            .context_metadata
+            .bindings_from_to(param_index, body_index)
+            .iter()
+            .map(|info| info.name)
+            .collect();
+        self.hoist_block_functions(&mut body_context, start_of_param_offset, &param_names);
+        self.context_metadata.pop_bindings_from(param_index);
 
         Ok(())
     }
@@ -4796,22 +6615,39 @@ impl<'alloc> AstBuilder<'alloc> {
     ) -> Result<'alloc, ()> {
         let mut param_context = UniqueFormalParametersEarlyErrorsContext::new();
 
-        let param_index = self.find_first_binding(start_of_param_offset);
-        let body_index = self.find_first_binding(end_of_param_offset);
+        let param_index = self
+            .context_metadata
+            .find_first_binding(start_of_param_offset);
+        let body_index = self
+            .context_metadata
+            .find_first_binding(end_of_param_offset);
         self.declare_param(&mut param_context, param_index, body_index)?;
 
         let mut body_context = UniqueFunctionBodyEarlyErrorsContext::new(param_context);
         self.declare_script_or_function(&mut body_context, body_index)?;
-        self.pop_bindings_from(param_index);
+        let param_names: Vec<_> = self
+            .context_metadata
+            .bindings_from_to(param_index, body_index)
+            .iter()
+            .map(|info| info.name)
+            .collect();
+        self.hoist_block_functions(&mut body_context, start_of_param_offset, &param_names);
+        self.context_metadata.pop_bindings_from(param_index);
 
         Ok(())
     }
 
     // Check bindings in Script.
     fn check_script_bindings(&mut self) -> Result<'alloc, ()> {
+        if let Some(bindings) = &self.script_bindings {
+            self.context_metadata.merge_bindings(bindings);
+            self.script_bindings = Some(self.context_metadata.bindings_from(0).to_vec());
+        }
+
         let mut context = ScriptEarlyErrorsContext::new();
         self.declare_script_or_function(&mut context, 0)?;
-        self.pop_bindings_from(0);
+        self.hoist_block_functions(&mut context, 0, &[]);
+        self.context_metadata.pop_bindings_from(0);
 
         Ok(())
     }
@@ -4820,7 +6656,7 @@ impl<'alloc> AstBuilder<'alloc> {
     fn check_module_bindings(&mut self) -> Result<'alloc, ()> {
         let mut context = ModuleEarlyErrorsContext::new();
         self.declare_script_or_function(&mut context, 0)?;
-        self.pop_bindings_from(0);
+        self.context_metadata.pop_bindings_from(0);
 
         Ok(())
     }
@@ -4846,6 +6682,152 @@ impl<'alloc> AstBuilder<'alloc> {
         true
     }
 
+    // Note the names bound by arrow function parameters, which were parsed
+    // as IdentifierReferences in CoverParenthesizedExpressionAndArrowParameterList
+    // or CoverCallExpressionAndAsyncArrowHead, to the bindings stack, so that
+    // they're checked like the parameters of any other function.
+    fn declare_cover_parameters(&mut self, params: &FormalParameters<'alloc>) {
+        for item in params.items.iter() {
+            self.declare_cover_parameter(item);
+        }
+        if let Some(rest) = &params.rest {
+            self.declare_cover_binding(rest);
+        }
+    }
+
+    fn declare_cover_parameter(&mut self, param: &Parameter<'alloc>) {
+        match param {
+            Parameter::Binding(binding) => self.declare_cover_binding(binding),
+            Parameter::BindingWithDefault(BindingWithDefault { binding, .. }) => {
+                self.declare_cover_binding(binding)
+            }
+        }
+    }
+
+    fn declare_cover_binding(&mut self, binding: &Binding<'alloc>) {
+        match binding {
+            Binding::BindingIdentifier(identifier) => {
+                self.declare_cover_binding_identifier(identifier)
+            }
+            Binding::BindingPattern(BindingPattern::ArrayBinding(ArrayBinding {
+                elements,
+                rest,
+                ..
+            })) => {
+                for element in elements.iter().flatten() {
+                    self.declare_cover_parameter(element);
+                }
+                if let Some(rest) = rest {
+                    self.declare_cover_binding(rest);
+                }
+            }
+            Binding::BindingPattern(BindingPattern::ObjectBinding(ObjectBinding {
+                properties,
+                rest,
+                ..
+            })) => {
+                for property in properties.iter() {
+                    match property {
+                        BindingProperty::BindingPropertyIdentifier(BindingPropertyIdentifier {
+                            binding,
+                            ..
+                        }) => self.declare_cover_binding_identifier(binding),
+                        BindingProperty::BindingPropertyProperty(property) => {
+                            self.declare_cover_parameter(&property.binding)
+                        }
+                    }
+                }
+                if let Some(rest) = rest {
+                    self.declare_cover_binding_identifier(rest);
+                }
+            }
+        }
+    }
+
+    fn declare_cover_binding_identifier(&mut self, identifier: &BindingIdentifier<'alloc>) {
+        self.context_metadata.insert_binding(BindingInfo {
+            name: identifier.name.value,
+            offset: identifier.loc.start(),
+            kind: BindingKind::Unknown,
+        });
+    }
+
+    // True if `statement` is an ExpressionStatement consisting of a
+    // StringLiteral, which is a Directive if it's in a Directive Prologue.
+    fn is_directive(statement: &Statement<'alloc>) -> bool {
+        match statement {
+            Statement::ExpressionStatement(expression) => {
+                matches!(**expression, Expression::LiteralStringExpression { .. })
+            }
+            _ => false,
+        }
+    }
+
+    // Returns the offset of the "use strict" directive in the Directive
+    // Prologue of `statements`, if any.
+    //
+    // NOTE: A parenthesized string literal is also an ExpressionStatement
+    //       consisting of a StringLiteral in the AST, and can't be told apart
+    //       from a directive here.
+    fn use_strict_directive_offset(statements: &[Statement<'alloc>]) -> Option<usize> {
+        for statement in statements {
+            match statement {
+                Statement::ExpressionStatement(expression) => match &**expression {
+                    Expression::LiteralStringExpression { value, loc } => {
+                        // The directive can't contain escape sequences or
+                        // line continuations.
+                        if *value == "use strict" && loc.end() - loc.start() == value.len() + 2 {
+                            return Some(loc.start());
+                        }
+                    }
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+
+        None
+    }
+
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-function-definitions-static-semantics-early-errors
+    // https://tc39.es/ecma262/#sec-method-definitions-static-semantics-early-errors
+    //
+    // * It is a Syntax Error if FunctionBodyContainsUseStrict of FunctionBody
+    //   is true and IsSimpleParameterList of FormalParameters is false.
+    fn check_use_strict_directive(
+        &self,
+        is_simple: bool,
+        statements: &[Statement<'alloc>],
+    ) -> Result<'alloc, ()> {
+        if let Some(offset) = Self::use_strict_directive_offset(statements) {
+            if !is_simple {
+                return Err(ParseError::UseStrictWithNonSimpleParameters(offset));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Static Semantics: Early Errors
+    // https://tc39.es/ecma262/#sec-arrow-function-definitions-static-semantics-early-errors
+    // https://tc39.es/ecma262/#sec-async-arrow-function-definitions-static-semantics-early-errors
+    //
+    // * It is a Syntax Error if FunctionBodyContainsUseStrict of ConciseBody
+    //   is true and IsSimpleParameterList of ArrowParameters is false.
+    fn check_arrow_use_strict_directive(
+        &self,
+        params: &FormalParameters<'alloc>,
+        body: &ArrowExpressionBody<'alloc>,
+    ) -> Result<'alloc, ()> {
+        match body {
+            ArrowExpressionBody::FunctionBody(body) => {
+                self.check_use_strict_directive(Self::is_params_simple(params), &body.statements)
+            }
+            ArrowExpressionBody::Expression(_) => Ok(()),
+        }
+    }
+
     // Static Semantics: Early Errors
     // https://tc39.es/ecma262/#sec-if-statement-static-semantics-early-errors
     // https://tc39.es/ecma262/#sec-semantics-static-semantics-early-errors
@@ -0,0 +1,502 @@
+//! Information about the contexts (functions, blocks, labelled statements,
+//! etc.) that enclose the syntax being parsed.
+//!
+//! The parser only calls AstBuilder methods at the end of each production,
+//! not at the beginning. So when it finds a BindingIdentifier, a `break` or
+//! a `return`, it doesn't yet know which context it belongs to.
+//!
+//! Instead, the information is noted to one of the stacks in
+//! `ContextMetadata`, each sorted by offset. When the parser reaches the end
+//! of a context, the items found in the context's range of the source are
+//! checked, and removed or left there for the enclosing context.
+
+use std::ops::Range;
+
+// The kind of BindingIdentifier found while parsing.
+//
+// At the point of finding a BindingIdentifier, we don't know what kind of
+// binding it is. So it's marked as `Unknown`.
+//
+// Once the parser reaches the end of a declaration, bindings found in the
+// range are marked as corresponding kind.
+//
+// This is a separate enum than `DeclarationKind` for the following reason:
+//   * `DeclarationKind` is determined only when the parser reaches the end of
+//     the entire context, not each declaration
+//   * As long as `BindingKind` is known for each binding, we can map it to
+//     `DeclarationKind`
+//   * `DeclarationKind::CatchParameter` and some others don't to be marked
+//     this way, because `AstBuilder` knows where they are in the `bindings`
+//     vector.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BindingKind {
+    // The initial state.
+    // BindingIdentifier is found, and haven't yet marked as any kind.
+    Unknown,
+
+    // BindingIdentifier is inside VariableDeclaration.
+    Var,
+
+    // BindingIdentifier is the name of FunctionDeclaration.
+    Function,
+
+    // BindingIdentifier is the name of GeneratorDeclaration,
+    // AsyncFunctionDeclaration, or AsyncGeneratorDeclaration.
+    AsyncOrGenerator,
+
+    // BindingIdentifier is inside LexicalDeclaration with let.
+    Let,
+
+    // BindingIdentifier is inside LexicalDeclaration with const.
+    Const,
+
+    // BindingIdentifier is the name of ClassDeclaration.
+    Class,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BindingInfo<'alloc> {
+    pub name: &'alloc str,
+    // The offset of the BindingIdentifier in the source.
+    pub offset: usize,
+    pub kind: BindingKind,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JumpKind {
+    Break,
+    Continue,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct JumpInfo<'alloc> {
+    pub kind: JumpKind,
+    pub label: Option<&'alloc str>,
+    // The offset of the `break` or `continue` token in the source.
+    pub offset: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LabelInfo<'alloc> {
+    pub name: &'alloc str,
+    // The offset of the LabelIdentifier in the source.
+    pub offset: usize,
+}
+
+// Syntax whose validity depends on the enclosing function.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FunctionReferenceKind {
+    // ReturnStatement
+    Return,
+
+    // NewTarget : `new` `.` `target`
+    NewTarget,
+
+    // IdentifierReference whose StringValue is "arguments".
+    Arguments,
+
+    // AwaitExpression : `await` UnaryExpression
+    Await,
+
+    // IterationStatement : `for` `await` `(` ... `of` ... `)` Statement
+    ForAwait,
+
+    // ImportMeta : `import` `.` `meta`
+    //
+    // This depends only on the goal symbol, and is left for the script or
+    // module.
+    ImportMeta,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FunctionReference {
+    pub kind: FunctionReferenceKind,
+    // The offset of the first token of the syntax in the source.
+    pub offset: usize,
+}
+
+// The state of a FunctionDeclaration that is directly contained in a Block,
+// CaseClause or DefaultClause, with respect to the hoisting in the web legacy
+// compatibility semantics.
+// https://tc39.es/ecma262/#sec-block-level-function-declarations-web-legacy-compatibility-semantics
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockFunctionState {
+    // The parser hasn't yet reached the end of the enclosing function or
+    // script, and no conflicting lexical binding has been found so far.
+    Pending,
+
+    // The function is also bound as a var in the enclosing function or
+    // script.
+    Hoisted,
+
+    // Replacing the FunctionDeclaration with a VariableStatement would
+    // produce an Early Error, or the code is strict mode code.
+    NotHoisted,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BlockFunctionInfo<'alloc> {
+    pub name: &'alloc str,
+    // The offset of the BindingIdentifier of the FunctionDeclaration in the
+    // source.
+    pub offset: usize,
+    // The offset of the start of the Block, CaseBlock or the IfStatement
+    // clause that directly contains the FunctionDeclaration, used to detect
+    // duplicate declarations in strict mode code.
+    pub block_offset: usize,
+    // True if the FunctionDeclaration is an IfStatement clause, which is
+    // allowed only in non-strict code.
+    pub is_single_statement: bool,
+    pub state: BlockFunctionState,
+}
+
+trait HasOffset {
+    fn offset(&self) -> usize;
+}
+
+impl<'alloc> HasOffset for BindingInfo<'alloc> {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'alloc> HasOffset for JumpInfo<'alloc> {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'alloc> HasOffset for LabelInfo<'alloc> {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl HasOffset for FunctionReference {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'alloc> HasOffset for BlockFunctionInfo<'alloc> {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+// Insert `item` to the offset-sorted `items`.
+//
+// Most items are found in source order, but e.g. a ReturnStatement is
+// reduced after its Expression, so it can be inserted before items found
+// inside it.
+fn insert_sorted<T: HasOffset>(items: &mut Vec<T>, item: T) {
+    let offset = item.offset();
+    let index = items
+        .iter()
+        .rposition(|other| other.offset() < offset)
+        .map_or(0, |i| i + 1);
+    items.insert(index, item);
+}
+
+// Returns the range of indices of the items in the offset-sorted `items`,
+// found in the range [start, end) of the source.
+fn index_range<T: HasOffset>(items: &[T], start: usize, end: usize) -> Range<usize> {
+    let from = items.partition_point(|item| item.offset() < start);
+    let to = from + items[from..].partition_point(|item| item.offset() < end);
+    from..to
+}
+
+// Remove the items found in the range [start, end) of the source, for which
+// `f` returns true.
+fn remove_in<T, F>(items: &mut Vec<T>, start: usize, end: usize, f: F)
+where
+    T: HasOffset,
+    F: Fn(&T) -> bool,
+{
+    items.retain(|item| {
+        let offset = item.offset();
+        !(start <= offset && offset < end && f(item))
+    });
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ContextMetadata<'alloc> {
+    // The stack of information about BindingIdentifier.
+    //
+    // When the parser found BindingIdentifier, the information (`name` and
+    // `offset`) are noted to this vector, and when the parser determined what
+    // kind of binding it is, `kind` is updated.
+    //
+    // When the parser reaches the end of a scope, all bindings declared
+    // within that scope (not including nested scopes) are fed to an
+    // EarlyErrorsContext to detect Early Errors.
+    //
+    // When leaving a context that is not one of script/module/function,
+    // lexical items (`kind != BindingKind::Var`) in the corresponding range
+    // are removed, while non-lexical items (`kind == BindingKind::Var`) are
+    // left there, so that VariableDeclarations are propagated to the
+    // enclosing context.
+    bindings: Vec<BindingInfo<'alloc>>,
+
+    // The stack of LabelledStatements in the enclosing functions, used to
+    // detect duplicate nested labels. Labels are removed when the parser
+    // reaches the end of the function.
+    labels: Vec<LabelInfo<'alloc>>,
+
+    // The stack of BreakStatement and ContinueStatement whose target hasn't
+    // been found yet.
+    //
+    // When the parser reaches the end of an IterationStatement,
+    // SwitchStatement or LabelledStatement, the jumps inside it that target
+    // it are removed. Any jump left at the end of a function, static block,
+    // script or module doesn't have a valid target.
+    jumps: Vec<JumpInfo<'alloc>>,
+
+    // The stack of `return`, `new.target`, `arguments`, `await` and
+    // `for await`.
+    //
+    // When the parser reaches the end of a function, the references inside
+    // it are removed, except for `new.target` and `arguments` inside arrow
+    // functions, which refer to the enclosing function.
+    function_references: Vec<FunctionReference>,
+
+    // The stack of FunctionDeclarations directly contained in blocks.
+    //
+    // When the parser reaches the end of a block, function declarations in
+    // it are added to this vector, and the ones from nested blocks are
+    // checked against the bindings of the block. When the parser reaches the
+    // end of a function or script, the hoisting of the pending ones is
+    // decided. The items are left there until the end of the script or
+    // module, so that they can be rejected if the enclosing code turns out
+    // to be strict mode code.
+    block_functions: Vec<BlockFunctionInfo<'alloc>>,
+}
+
+impl<'alloc> ContextMetadata<'alloc> {
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+            labels: Vec::new(),
+            jumps: Vec::new(),
+            function_references: Vec::new(),
+            block_functions: Vec::new(),
+        }
+    }
+
+    pub fn push_binding(&mut self, binding: BindingInfo<'alloc>) {
+        if let Some(info) = self.bindings.last() {
+            debug_assert!(info.offset < binding.offset);
+        }
+
+        self.bindings.push(binding);
+    }
+
+    // Insert a binding found before the last binding in the stack, e.g. an
+    // arrow function parameter which was first parsed as an
+    // IdentifierReference.
+    pub fn insert_binding(&mut self, binding: BindingInfo<'alloc>) {
+        insert_sorted(&mut self.bindings, binding);
+    }
+
+    // Insert bindings found outside of the source being parsed, e.g. in the
+    // parts of a script that aren't parsed again. Both the stack and
+    // `bindings` are sorted by offset.
+    pub fn merge_bindings(&mut self, bindings: &[BindingInfo<'alloc>]) {
+        let mut merged = Vec::with_capacity(self.bindings.len() + bindings.len());
+        let mut others = bindings.iter().peekable();
+        for info in self.bindings.drain(..) {
+            while let Some(other) = others.next_if(|other| other.offset < info.offset) {
+                merged.push(*other);
+            }
+            merged.push(info);
+        }
+        merged.extend(others);
+        self.bindings = merged;
+    }
+
+    // Update the binding kind of all names declared in a specific range of
+    // the source (and not in any nested scope). This is used e.g. when the
+    // parser reaches the end of a VariableStatement to mark all the
+    // variables as Var bindings.
+    //
+    // Bindings inside `StatementList` must be marked using this method
+    // before we reach the end of its scope.
+    pub fn mark_binding_kind(&mut self, from: usize, to: Option<usize>, kind: BindingKind) {
+        for info in self.bindings.iter_mut().rev() {
+            if info.offset < from {
+                break;
+            }
+
+            if to.is_none() || info.offset < to.unwrap() {
+                info.kind = kind;
+            }
+        }
+    }
+
+    // Returns the index of the first binding at/after `offset` source
+    // position.
+    pub fn find_first_binding(&self, offset: usize) -> usize {
+        self.bindings.partition_point(|info| info.offset < offset)
+    }
+
+    // Returns the bindings from `index`-th item.
+    pub fn bindings_from(&self, index: usize) -> &[BindingInfo<'alloc>] {
+        &self.bindings[index..]
+    }
+
+    // Returns the bindings from `from`-th item to before `to`-th item.
+    pub fn bindings_from_to(&self, from: usize, to: usize) -> &[BindingInfo<'alloc>] {
+        &self.bindings[from..to]
+    }
+
+    // Remove all bindings after `index`-th item.
+    //
+    // This should be called when leaving function/script/module.
+    pub fn pop_bindings_from(&mut self, index: usize) {
+        self.bindings.truncate(index)
+    }
+
+    // Remove lexical bindings after `index`-th item,
+    // while keeping var bindings.
+    //
+    // This should be called when leaving block.
+    pub fn pop_lexical_bindings_from(&mut self, index: usize) {
+        let len = self.bindings.len();
+        let mut i = index;
+
+        while i < len && self.bindings[i].kind == BindingKind::Var {
+            i += 1;
+        }
+
+        let mut j = i;
+        while j < len {
+            if self.bindings[j].kind == BindingKind::Var {
+                self.bindings[i] = self.bindings[j];
+                i += 1;
+            }
+            j += 1;
+        }
+
+        self.bindings.truncate(i)
+    }
+
+    pub fn push_label(&mut self, label: LabelInfo<'alloc>) {
+        insert_sorted(&mut self.labels, label);
+    }
+
+    // Returns the labels found in the range [start, end) of the source.
+    pub fn labels_in(&self, start: usize, end: usize) -> &[LabelInfo<'alloc>] {
+        &self.labels[index_range(&self.labels, start, end)]
+    }
+
+    // Remove the labels found in the range [start, end) of the source.
+    pub fn pop_labels_in(&mut self, start: usize, end: usize) {
+        self.labels.drain(index_range(&self.labels, start, end));
+    }
+
+    pub fn push_jump(&mut self, jump: JumpInfo<'alloc>) {
+        if let Some(info) = self.jumps.last() {
+            debug_assert!(info.offset < jump.offset);
+        }
+
+        self.jumps.push(jump);
+    }
+
+    // Returns the jumps found in the range [start, end) of the source.
+    pub fn jumps_in(&self, start: usize, end: usize) -> &[JumpInfo<'alloc>] {
+        &self.jumps[index_range(&self.jumps, start, end)]
+    }
+
+    // Remove the jumps found in the range [start, end) of the source, for
+    // which `f` returns true.
+    pub fn remove_jumps_in<F>(&mut self, start: usize, end: usize, f: F)
+    where
+        F: Fn(&JumpInfo<'alloc>) -> bool,
+    {
+        remove_in(&mut self.jumps, start, end, f);
+    }
+
+    pub fn push_function_reference(&mut self, reference: FunctionReference) {
+        insert_sorted(&mut self.function_references, reference);
+    }
+
+    // Returns the function references found in the range [start, end) of
+    // the source.
+    pub fn function_references_in(&self, start: usize, end: usize) -> &[FunctionReference] {
+        &self.function_references[index_range(&self.function_references, start, end)]
+    }
+
+    // Remove the function references found in the range [start, end) of the
+    // source, for which `f` returns true.
+    pub fn remove_function_references_in<F>(&mut self, start: usize, end: usize, f: F)
+    where
+        F: Fn(&FunctionReference) -> bool,
+    {
+        remove_in(&mut self.function_references, start, end, f);
+    }
+
+    // Note the FunctionDeclarations from `index`-th binding, which are
+    // directly contained in the block starting at `block_offset`.
+    //
+    // This should be called when leaving block, before removing the lexical
+    // bindings.
+    pub fn push_block_functions_from(
+        &mut self,
+        index: usize,
+        block_offset: usize,
+        is_single_statement: bool,
+    ) {
+        for info in self.bindings[index..].iter() {
+            if info.kind == BindingKind::Function {
+                insert_sorted(
+                    &mut self.block_functions,
+                    BlockFunctionInfo {
+                        name: info.name,
+                        offset: info.offset,
+                        block_offset,
+                        is_single_statement,
+                        state: BlockFunctionState::Pending,
+                    },
+                );
+            }
+        }
+    }
+
+    // Update the state of the pending FunctionDeclarations found at/after
+    // `offset` source position, with the state returned by `f`.
+    pub fn update_pending_block_functions<F>(&mut self, offset: usize, mut f: F)
+    where
+        F: FnMut(&BlockFunctionInfo<'alloc>) -> BlockFunctionState,
+    {
+        let from = self
+            .block_functions
+            .partition_point(|info| info.offset < offset);
+        for info in self.block_functions[from..].iter_mut() {
+            if info.state == BlockFunctionState::Pending {
+                info.state = f(info);
+            }
+        }
+    }
+
+    // Returns the FunctionDeclarations directly contained in blocks, found
+    // in the range [start, end) of the source.
+    pub fn block_functions_in(&self, start: usize, end: usize) -> &[BlockFunctionInfo<'alloc>] {
+        &self.block_functions[index_range(&self.block_functions, start, end)]
+    }
+
+    // Mark all FunctionDeclarations found in the range [start, end) of the
+    // source as not hoisted.
+    pub fn unhoist_block_functions_in(&mut self, start: usize, end: usize) {
+        let range = index_range(&self.block_functions, start, end);
+        for info in self.block_functions[range].iter_mut() {
+            info.state = BlockFunctionState::NotHoisted;
+        }
+    }
+
+    // Remove all FunctionDeclarations directly contained in blocks.
+    //
+    // This should be called when leaving script/module.
+    pub fn pop_block_functions(&mut self) -> Vec<BlockFunctionInfo<'alloc>> {
+        std::mem::take(&mut self.block_functions)
+    }
+}
@@ -18,7 +18,8 @@ pub enum DeclarationKind {
     LexicalFunction,
     LexicalAsyncOrGenerator,
 
-    // This is used after parsing the entire function/script body.
+    // This is used when leaving the blocks, function and script enclosing a
+    // block-level FunctionDeclaration, to check whether it can be hoisted.
     VarForAnnexBLexicalFunction,
 
     CatchParameter,
@@ -2,7 +2,6 @@ use crate::parser_tables_generated::TerminalId;
 use crate::DeclarationKind;
 use crate::ParseError;
 use crate::Token;
-use ast::arena;
 use std::collections::HashMap;
 use std::marker::PhantomData;
 
@@ -76,34 +75,31 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
     }
      */
 
-    fn is_arguments_identifier(token: &arena::Box<'alloc, Token<'alloc>>) -> bool {
+    fn is_arguments_identifier(token: &Token<'alloc>) -> bool {
         return (token.terminal_id == TerminalId::Name
             || token.terminal_id == TerminalId::NameWithEscape)
-            && token.value.unwrap() == "arguments";
+            && token.str_value().unwrap() == "arguments";
     }
 
-    fn is_eval_identifier(token: &arena::Box<'alloc, Token<'alloc>>) -> bool {
+    fn is_eval_identifier(token: &Token<'alloc>) -> bool {
         return (token.terminal_id == TerminalId::Name
             || token.terminal_id == TerminalId::NameWithEscape)
-            && token.value.unwrap() == "eval";
+            && token.str_value().unwrap() == "eval";
     }
 
-    fn is_yield_identifier(token: &arena::Box<'alloc, Token<'alloc>>) -> bool {
+    fn is_yield_identifier(token: &Token<'alloc>) -> bool {
         return token.terminal_id == TerminalId::Yield
             || (token.terminal_id == TerminalId::NameWithEscape
-                && token.value.unwrap() == "yield");
+                && token.str_value().unwrap() == "yield");
     }
 
-    fn is_await_identifier(token: &arena::Box<'alloc, Token<'alloc>>) -> bool {
+    fn is_await_identifier(token: &Token<'alloc>) -> bool {
         return token.terminal_id == TerminalId::Await
             || (token.terminal_id == TerminalId::NameWithEscape
-                && token.value.unwrap() == "await");
+                && token.str_value().unwrap() == "await");
     }
 
-    pub fn check_binding_identifier(
-        &self,
-        token: &arena::Box<'alloc, Token<'alloc>>,
-    ) -> EarlyErrorsResult<'alloc> {
+    pub fn check_binding_identifier(&self, token: &Token<'alloc>) -> EarlyErrorsResult<'alloc> {
         if Self::is_arguments_identifier(token) || Self::is_eval_identifier(token) {
             // Static Semantics: Early Errors
             // https://tc39.es/ecma262/#sec-identifiers-static-semantics-early-errors
@@ -114,8 +110,8 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
             //   production is contained in strict mode code and the
             //   StringValue of Identifier is "arguments" or "eval".
             if self.is_strict()? {
-                let name = token.value.unwrap();
-                let offset = token.loc.start;
+                let name = token.str_value().unwrap();
+                let offset = token.loc.start();
                 return Err(ParseError::InvalidIdentifier(name.clone(), offset));
             }
 
@@ -145,10 +141,7 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
         self.check_identifier(token)
     }
 
-    pub fn check_label_identifier(
-        &self,
-        token: &arena::Box<'alloc, Token<'alloc>>,
-    ) -> EarlyErrorsResult<'alloc> {
+    pub fn check_label_identifier(&self, token: &Token<'alloc>) -> EarlyErrorsResult<'alloc> {
         if Self::is_yield_identifier(token) {
             return self.check_yield_common(token);
         }
@@ -160,10 +153,7 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
         self.check_identifier(token)
     }
 
-    pub fn check_identifier_reference(
-        &self,
-        token: &arena::Box<'alloc, Token<'alloc>>,
-    ) -> EarlyErrorsResult<'alloc> {
+    pub fn check_identifier_reference(&self, token: &Token<'alloc>) -> EarlyErrorsResult<'alloc> {
         if Self::is_yield_identifier(token) {
             return self.check_yield_common(token);
         }
@@ -175,10 +165,7 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
         self.check_identifier(token)
     }
 
-    fn check_yield_common(
-        &self,
-        _token: &arena::Box<'alloc, Token<'alloc>>,
-    ) -> EarlyErrorsResult<'alloc> {
+    fn check_yield_common(&self, _token: &Token<'alloc>) -> EarlyErrorsResult<'alloc> {
         // Static Semantics: Early Errors
         // https://tc39.es/ecma262/#sec-identifiers-static-semantics-early-errors
         //
@@ -212,7 +199,7 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
         //
         // if self.is_strict()? {
         //     return Err(ParseError::InvalidIdentifier(
-        //         token.value.unwrap().clone(),
+        //         token.str_value().unwrap().clone(),
         //         offset,
         //     ));
         // }
@@ -220,10 +207,7 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
         // Ok(())
     }
 
-    fn check_await_common(
-        &self,
-        _token: &arena::Box<'alloc, Token<'alloc>>,
-    ) -> EarlyErrorsResult<'alloc> {
+    fn check_await_common(&self, _token: &Token<'alloc>) -> EarlyErrorsResult<'alloc> {
         // Static Semantics: Early Errors
         // https://tc39.es/ecma262/#sec-identifiers-static-semantics-early-errors
         //
@@ -255,7 +239,7 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
         //
         // if self.is_module()? {
         //     return Err(ParseError::InvalidIdentifier(
-        //         token.value.unwrap().clone(),
+        //         token.str_value().unwrap().clone(),
         //         offset,
         //     ));
         // }
@@ -263,13 +247,10 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
         // Ok(())
     }
 
-    fn check_identifier(
-        &self,
-        token: &arena::Box<'alloc, Token<'alloc>>,
-    ) -> EarlyErrorsResult<'alloc> {
+    fn check_identifier(&self, token: &Token<'alloc>) -> EarlyErrorsResult<'alloc> {
         match token.terminal_id {
             TerminalId::NameWithEscape => {
-                let name = token.value.unwrap();
+                let name = token.str_value().unwrap();
                 match name {
                     "implements" | "interface" | "let" | "package" | "private" | "protected"
                     | "public" | "static" => {
@@ -285,7 +266,7 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
                         // NOTE: "yield" case is handled in
                         //       `check_yield_common`.
                         if self.is_strict()? {
-                            let offset = token.loc.start;
+                            let offset = token.loc.start();
                             return Err(ParseError::InvalidIdentifier(name.clone(), offset));
                         }
                     }
@@ -301,7 +282,7 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
                         //   IdentifierName is the same String value as the
                         //   StringValue of any ReservedWord except for yield
                         //   or await.
-                        let offset = token.loc.start;
+                        let offset = token.loc.start();
                         return Err(ParseError::InvalidIdentifier(name.clone(), offset));
                     }
 
@@ -325,8 +306,8 @@ impl<'alloc> IdentifierEarlyErrorsContext<'alloc> {
                 //
                 // NOTE: "yield" case is handled in `check_yield_common`.
                 if self.is_strict()? {
-                    let name = token.value.unwrap();
-                    let offset = token.loc.start;
+                    let name = token.str_value().unwrap();
+                    let offset = token.loc.start();
                     return Err(ParseError::InvalidIdentifier(name.clone(), offset));
                 }
             }
@@ -543,12 +524,6 @@ impl<'alloc> BlockEarlyErrorsContext<'alloc> {
             _ => false,
         }
     }
-
-    fn is_strict(&self) -> Result<bool, ParseError<'alloc>> {
-        Err(ParseError::NotImplemented(
-            "strict-mode-only early error is not yet supported",
-        ))
-    }
 }
 
 impl<'alloc> LexicalEarlyErrorsContext<'alloc> for BlockEarlyErrorsContext<'alloc> {
@@ -579,8 +554,12 @@ impl<'alloc> LexicalEarlyErrorsContext<'alloc> for BlockEarlyErrorsContext<'allo
             //   source code matching this production is not strict mode
             //   code and the duplicate entries are only bound by
             //   FunctionDeclarations **.
-            if !(!self.is_strict()?
-                && info.kind == DeclarationKind::LexicalFunction
+            //
+            // Whether the code is strict isn't known yet when the parser
+            // reaches the end of the block. Duplicate FunctionDeclarations
+            // are accepted here, and AstBuilder reports them when it reaches
+            // the end of strict mode code.
+            if !(info.kind == DeclarationKind::LexicalFunction
                 && kind == DeclarationKind::LexicalFunction)
             {
                 return Err(ParseError::DuplicateBinding(
@@ -769,7 +748,7 @@ impl<'alloc> InternalForBodyEarlyErrorsContext<'alloc> {
             // VarDeclaredNames of Statement
             //
             // See Block::is_supported_var for the details.
-            DeclarationKind::Var => true,
+            DeclarationKind::Var | DeclarationKind::VarForAnnexBLexicalFunction => true,
 
             _ => false,
         }
@@ -879,12 +858,6 @@ impl<'alloc> CaseBlockEarlyErrorsContext<'alloc> {
     fn is_supported_var(kind: DeclarationKind) -> bool {
         BlockEarlyErrorsContext::is_supported_var(kind)
     }
-
-    fn is_strict(&self) -> Result<bool, ParseError<'alloc>> {
-        Err(ParseError::NotImplemented(
-            "strict-mode-only early error is not yet supported",
-        ))
-    }
 }
 
 impl<'alloc> LexicalEarlyErrorsContext<'alloc> for CaseBlockEarlyErrorsContext<'alloc> {
@@ -913,8 +886,12 @@ impl<'alloc> LexicalEarlyErrorsContext<'alloc> for CaseBlockEarlyErrorsContext<'
             //   CaseBlock contains any duplicate entries, ** unless the source
             //   code matching this production is not strict mode code and the
             //   duplicate entries are only bound by FunctionDeclarations **.
-            if !(!self.is_strict()?
-                && info.kind == DeclarationKind::LexicalFunction
+            //
+            // Whether the code is strict isn't known yet when the parser
+            // reaches the end of the block. Duplicate FunctionDeclarations
+            // are accepted here, and AstBuilder reports them when it reaches
+            // the end of strict mode code.
+            if !(info.kind == DeclarationKind::LexicalFunction
                 && kind == DeclarationKind::LexicalFunction)
             {
                 return Err(ParseError::DuplicateBinding(
@@ -1744,6 +1721,133 @@ impl<'alloc> VarEarlyErrorsContext<'alloc> for UniqueFunctionBodyEarlyErrorsCont
     }
 }
 
+// ===========================================================================
+// Class Definitions
+// https://tc39.es/ecma262/#sec-class-definitions
+// ===========================================================================
+
+// The kind of ClassElement that declares a private name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrivateNameKind {
+    Field,
+    Method,
+    Getter,
+    Setter,
+
+    // A getter and a setter with the same name have been declared.
+    GetterSetter,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PrivateNameInfo {
+    kind: PrivateNameKind,
+    is_static: bool,
+    offset: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ClassBodyEarlyErrorsContext<'alloc> {
+    private_bound_names: HashMap<Name<'alloc>, PrivateNameInfo>,
+
+    // The offset of the ClassElementName of the constructor, if any.
+    constructor_offset: Option<usize>,
+}
+
+impl<'alloc> ClassBodyEarlyErrorsContext<'alloc> {
+    pub fn new() -> Self {
+        Self {
+            private_bound_names: HashMap::new(),
+            constructor_offset: None,
+        }
+    }
+
+    // Declare a non-static MethodDefinition whose PropName is "constructor".
+    pub fn declare_constructor(
+        &mut self,
+        is_special_method: bool,
+        offset: usize,
+    ) -> EarlyErrorsResult<'alloc> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-class-definitions-static-semantics-early-errors
+        //
+        // ClassElement : MethodDefinition
+        //
+        // * It is a Syntax Error if PropName of MethodDefinition is
+        //   "constructor" and SpecialMethod of MethodDefinition is true.
+        if is_special_method {
+            return Err(ParseError::InvalidConstructor(offset));
+        }
+
+        // ClassBody : ClassElementList
+        //
+        // * It is a Syntax Error if PrototypePropertyNameList of
+        //   ClassElementList contains more than one occurrence of
+        //   "constructor".
+        if let Some(prev_offset) = self.constructor_offset {
+            return Err(ParseError::DuplicateConstructor(prev_offset, offset));
+        }
+
+        self.constructor_offset = Some(offset);
+
+        Ok(())
+    }
+
+    pub fn declare_private_name(
+        &mut self,
+        name: Name<'alloc>,
+        kind: PrivateNameKind,
+        is_static: bool,
+        offset: usize,
+    ) -> EarlyErrorsResult<'alloc> {
+        // Static Semantics: Early Errors
+        // https://tc39.es/ecma262/#sec-class-definitions-static-semantics-early-errors
+        //
+        // ClassBody : ClassElementList
+        //
+        // * It is a Syntax Error if PrivateBoundIdentifiers of
+        //   ClassElementList contains any duplicate entries, unless the name
+        //   is used once for a getter and once for a setter and in no other
+        //   entries, and the getter and setter are either both static or both
+        //   non-static.
+        if let Some(info) = self.private_bound_names.get_mut(&name) {
+            let is_accessor_pair = match (info.kind, kind) {
+                (PrivateNameKind::Getter, PrivateNameKind::Setter)
+                | (PrivateNameKind::Setter, PrivateNameKind::Getter) => {
+                    info.is_static == is_static
+                }
+                _ => false,
+            };
+            if is_accessor_pair {
+                info.kind = PrivateNameKind::GetterSetter;
+                return Ok(());
+            }
+
+            return Err(ParseError::DuplicatePrivateName(name, info.offset, offset));
+        }
+
+        self.private_bound_names.insert(
+            name,
+            PrivateNameInfo {
+                kind,
+                is_static,
+                offset,
+            },
+        );
+
+        Ok(())
+    }
+
+    // Static Semantics: AllPrivateIdentifiersValid
+    // https://tc39.es/ecma262/#sec-static-semantics-allprivateidentifiersvalid
+    //
+    // Returns true if `name` is one of the PrivateBoundIdentifiers of the
+    // ClassBody. References that aren't are resolved against the enclosing
+    // ClassBody, if any.
+    pub fn is_private_name_declared(&self, name: Name<'alloc>) -> bool {
+        self.private_bound_names.contains_key(&name)
+    }
+}
+
 // ===========================================================================
 // Scripts
 // https://tc39.es/ecma262/#sec-scripts
@@ -7,6 +7,7 @@ use std::{convert::Infallible, error::Error, fmt};
 pub enum ParseError<'alloc> {
     // Lexical errors
     IllegalCharacter(char),
+    UnpairedSurrogate(u16),
     InvalidEscapeSequence,
     UnterminatedString,
     UnterminatedRegExp,
@@ -28,25 +29,75 @@ pub enum ParseError<'alloc> {
     ObjectPatternWithMethod,
     ObjectPatternWithNonFinalRest,
     ObjectBindingPatternWithInvalidRest,
+    InvalidCoverInitializedName(usize),
+    DuplicateProto(usize, usize),
 
     // 14.8 Async arrow function definitions
     ArrowHeadInvalid,
     ArrowParametersWithNonFinalRest,
+    ArrowParametersWithoutArrow(usize),
 
     DuplicateBinding(&'alloc str, DeclarationKind, usize, DeclarationKind, usize),
     DuplicateExport(&'alloc str, usize, usize),
     MissingExport(&'alloc str, usize),
+    UseStrictWithNonSimpleParameters(usize),
+
+    // Strict mode code
+    WithInStrictMode(usize),
+    LegacyOctalInStrictMode(usize),
+
+    // Labels and jump targets
+    DuplicateLabel(&'alloc str, usize, usize),
+    UndefinedLabel(&'alloc str, usize),
+    ContinueToNonIterationLabel(&'alloc str, usize, usize),
+    BreakOutsideOfLoop(usize),
+    ContinueOutsideOfLoop(usize),
+
+    // Function-only syntax
+    ReturnOutsideOfFunction(usize),
+    NewTargetOutsideOfFunction(usize),
+    ArgumentsInClassInitializer(usize),
+    AwaitOutsideOfAsyncFunction(usize),
+    ForAwaitOutsideOfAsyncFunction(usize),
+    AwaitInFormalParameters(usize),
+    ImportMetaOutsideOfModule(usize),
+
+    // Class definitions
+    DuplicatePrivateName(&'alloc str, usize, usize),
+    UndeclaredPrivateName(&'alloc str, usize),
+    InvalidPrivateName(&'alloc str, usize),
+    PrivateNameInObjectLiteral(&'alloc str, usize),
+    DuplicateConstructor(usize, usize),
+    InvalidConstructor(usize),
+    InvalidFieldName(&'alloc str, usize),
+    InvalidStaticMethodName(&'alloc str, usize),
+    UnexpectedSuperCall(usize),
+    UnexpectedSuperProperty(usize),
 
     // Annex B. FunctionDeclarations in IfStatement Statement Clauses
     // https://tc39.es/ecma262/#sec-functiondeclarations-in-ifstatement-statement-clauses
     FunctionDeclInSingleStatement,
     LabelledFunctionDeclInSingleStatement,
+
+    // Resource limits set in the options of the parse
+    TooDeeplyNested(usize),
+    TooManyTokens(usize),
+    Cancelled,
+
+    // The source has offsets too large for SourceLocation, which only happens
+    // with the `compact_locations` feature of the AST.
+    SourceTooLong,
+
+    // The range [start, end) of a TextEdit is reversed, goes past the end of
+    // the source, or splits a character.
+    InvalidTextEdit(usize, usize),
 }
 
 impl<'alloc> ParseError<'alloc> {
     pub fn message(&self) -> String {
         match self {
             ParseError::IllegalCharacter(c) => format!("illegal character: {:?}", c),
+            ParseError::UnpairedSurrogate(unit) => format!("unpaired surrogate: \\u{{{:x}}}", unit),
             ParseError::InvalidEscapeSequence => format!("invalid escape sequence"),
             ParseError::UnterminatedString => format!("unterminated string literal"),
             ParseError::UnterminatedRegExp => format!("unterminated regexp literal"),
@@ -74,12 +125,21 @@ impl<'alloc> ParseError<'alloc> {
             ParseError::ObjectBindingPatternWithInvalidRest => format!(
                 "the expression after `...` in this object pattern must be a single identifier"
             ),
+            ParseError::InvalidCoverInitializedName(_) => format!(
+                "a shorthand property with an initializer (`{{a = 1}}`) is only allowed in a destructuring pattern"
+            ),
+            ParseError::DuplicateProto(_, _) => {
+                format!("duplicate __proto__ property in object literal")
+            }
             ParseError::ArrowHeadInvalid => format!(
                 "unexpected `=>` after function call (parentheses around the arrow function may help)"
             ),
             ParseError::ArrowParametersWithNonFinalRest => format!(
                 "arrow function parameters can have a rest element (`...x`) only at the end"
             ),
+            ParseError::ArrowParametersWithoutArrow(_) => {
+                format!("expected `=>` after arrow function parameters")
+            }
             ParseError::DuplicateBinding(name, kind1, _, kind2, _) => format!(
                 "redeclaration of {} '{}' with {}",
                 kind1.to_str(),
@@ -94,12 +154,103 @@ impl<'alloc> ParseError<'alloc> {
                 "local binding for export '{}' not found",
                 name,
             ),
+            ParseError::UseStrictWithNonSimpleParameters(_) => format!(
+                "\"use strict\" not allowed in function with default, destructuring or rest parameters"
+            ),
+            ParseError::WithInStrictMode(_) => {
+                String::from("with statements are not allowed in strict mode code")
+            }
+            ParseError::LegacyOctalInStrictMode(_) => {
+                String::from("numbers starting with 0 are not allowed in strict mode code")
+            }
+            ParseError::DuplicateLabel(name, _, _) => format!(
+                "label '{}' is already declared by an enclosing statement",
+                name,
+            ),
+            ParseError::UndefinedLabel(name, _) => format!(
+                "label '{}' not found",
+                name,
+            ),
+            ParseError::ContinueToNonIterationLabel(name, _, _) => format!(
+                "`continue` can only target a label of a loop, but '{}' isn't",
+                name,
+            ),
+            ParseError::BreakOutsideOfLoop(_) => {
+                format!("`break` must be inside a loop or `switch`")
+            }
+            ParseError::ContinueOutsideOfLoop(_) => {
+                format!("`continue` must be inside a loop")
+            }
+            ParseError::ReturnOutsideOfFunction(_) => {
+                format!("`return` must be inside a function")
+            }
+            ParseError::NewTargetOutsideOfFunction(_) => {
+                format!("`new.target` must be inside a function")
+            }
+            ParseError::ArgumentsInClassInitializer(_) => format!(
+                "`arguments` can't be used in class field initializers and static blocks"
+            ),
+            ParseError::AwaitOutsideOfAsyncFunction(_) => {
+                format!("`await` must be inside an async function or at the top level of a module")
+            }
+            ParseError::ForAwaitOutsideOfAsyncFunction(_) => {
+                format!("`for await` must be inside an async function or a module")
+            }
+            ParseError::AwaitInFormalParameters(_) => {
+                format!("`await` can't be used in the parameters of an async function")
+            }
+            ParseError::ImportMetaOutsideOfModule(_) => {
+                format!("`import.meta` must be inside a module")
+            }
+            ParseError::DuplicatePrivateName(name, _, _) => format!(
+                "duplicate private name '{}'",
+                name,
+            ),
+            ParseError::UndeclaredPrivateName(name, _) => format!(
+                "reference to undeclared private name '{}'",
+                name,
+            ),
+            ParseError::InvalidPrivateName(name, _) => format!(
+                "'{}' is not a valid private name",
+                name,
+            ),
+            ParseError::PrivateNameInObjectLiteral(name, _) => format!(
+                "private name '{}' can only be used in a class body",
+                name,
+            ),
+            ParseError::DuplicateConstructor(_, _) => {
+                format!("a class can only have one constructor")
+            }
+            ParseError::InvalidConstructor(_) => format!(
+                "class constructor can't be a getter, setter, generator or async method"
+            ),
+            ParseError::InvalidFieldName(name, _) => {
+                format!("class fields can't be named {:?}", name)
+            }
+            ParseError::InvalidStaticMethodName(name, _) => {
+                format!("static methods can't be named {:?}", name)
+            }
+            ParseError::UnexpectedSuperCall(_) => {
+                format!("`super()` is only valid in derived class constructors")
+            }
+            ParseError::UnexpectedSuperProperty(_) => {
+                format!("`super` property access is only valid in methods")
+            }
             ParseError::FunctionDeclInSingleStatement => format!(
                 "function declarations can't appear in single-statement context"
             ),
             ParseError::LabelledFunctionDeclInSingleStatement => format!(
                 "functions can only be labelled inside blocks"
             ),
+            ParseError::TooDeeplyNested(_) => {
+                String::from("too many nested expressions or statements")
+            }
+            ParseError::TooManyTokens(_) => String::from("too many tokens"),
+            ParseError::Cancelled => String::from("parsing was cancelled"),
+            ParseError::SourceTooLong => String::from("the source is too long"),
+            ParseError::InvalidTextEdit(start, end) => {
+                format!("invalid range for a text edit: {}..{}", start, end)
+            }
         }
     }
 }
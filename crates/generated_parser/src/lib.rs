@@ -1,9 +1,11 @@
 //! Generated parts of a JS parser.
 
 mod ast_builder;
+mod context_stack;
 mod declaration_kind;
 mod early_errors;
 mod error;
+mod loaded_tables;
 mod parser_tables_generated;
 mod stack_value_generated;
 mod token;
@@ -11,11 +13,15 @@ mod token;
 extern crate jsparagus_ast as ast;
 
 pub use ast_builder::AstBuilder;
+pub use context_stack::{BindingInfo, BindingKind};
 pub use declaration_kind::DeclarationKind;
 pub use error::{ParseError, Result};
+pub use loaded_tables::LoadedTables;
 pub use parser_tables_generated::{
-    reduce, ErrorCode, NonterminalId, ParserTables, TerminalId, START_STATE_MODULE,
-    START_STATE_SCRIPT, TABLES,
+    reduce, ErrorCode, NonterminalId, ParserTables, SpecialCase, TerminalId, NONTERMINAL_NAMES,
+    REDUCE_KEYS, START_STATES, START_STATE_MODULE, START_STATE_SCRIPT,
+    START_STATE_STANDALONE_EXPRESSION, START_STATE_STANDALONE_FORMAL_PARAMETERS,
+    START_STATE_STANDALONE_FUNCTION_BODY, TABLES, TERMINAL_NAMES,
 };
 pub use stack_value_generated::StackValue;
-pub use token::Token;
+pub use token::{InlineToken, Token};
@@ -0,0 +1,412 @@
+//! Parse tables loaded at run time, from a file written by the parser
+//! generator, e.g.
+//!
+//! ```text
+//! python3 -m js_parser.generate_js_parser_tables -o es.jsparagus_tables
+//! ```
+//!
+//! The tables can be generated from a modified grammar, without regenerating
+//! and rebuilding this crate, as long as the grammar has the same terminals,
+//! and each of its productions is also in the compiled grammar, with the same
+//! reduce action. Productions are matched by their text, e.g.
+//! `Script ::= ScriptBody => script(Some($0))`, which determines the code
+//! run when they are reduced. So new syntax can be tried out if it builds
+//! the same AST as existing syntax.
+
+use crate::parser_tables_generated::{
+    ErrorCode, ParserTables, SpecialCase, TerminalId, ACCEPT, ERROR, NONTERMINAL_NAMES,
+    REDUCE_KEYS, SPECIAL_CASE_TAG, START_STATES, TABLES, TERMINAL_NAMES,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::{FromStr, Lines};
+
+// The version of the format, written by RustParserWriter.emit_tables_file in
+// jsparagus/emit/rust.py.
+const VERSION: &str = "2";
+
+pub struct LoadedTables {
+    state_count: usize,
+    action_width: usize,
+    goto_width: usize,
+    action_rows: Vec<(u32, i16)>,
+    action_entries: Vec<(u16, i16)>,
+    goto_rows: Vec<(u32, u16)>,
+    goto_entries: Vec<(u16, u16)>,
+    special_cases: Vec<SpecialCase>,
+    error_codes: Vec<Option<ErrorCode>>,
+    reduce_simulator: Vec<(usize, usize)>,
+    reduce_actions: Vec<usize>,
+
+    // The start states of the compiled tables, like START_STATE_SCRIPT, and
+    // the states of these tables for the same goal nonterminals.
+    start_states: Vec<(usize, usize)>,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse_value<T: FromStr>(value: &str, field: &str) -> io::Result<T> {
+    value
+        .parse()
+        .map_err(|_| invalid(format!("invalid value in {}: {}", field, value)))
+}
+
+// The lines of a tables file. Each field is a line with its name, the number
+// of values, and the values, separated by spaces.
+struct Fields<'a> {
+    lines: Lines<'a>,
+}
+
+impl<'a> Fields<'a> {
+    fn line(&mut self, name: &str) -> io::Result<&'a str> {
+        self.lines
+            .next()
+            .ok_or_else(|| invalid(format!("missing {}", name)))
+    }
+
+    fn strings(&mut self, name: &str) -> io::Result<Vec<&'a str>> {
+        let mut words = self.line(name)?.split_whitespace();
+        if words.next() != Some(name) {
+            return Err(invalid(format!("expected {}", name)));
+        }
+        let len: usize = parse_value(words.next().unwrap_or(""), name)?;
+        let values: Vec<_> = words.collect();
+        if values.len() != len {
+            return Err(invalid(format!("wrong number of values in {}", name)));
+        }
+        Ok(values)
+    }
+
+    fn values<T: FromStr>(&mut self, name: &str) -> io::Result<Vec<T>> {
+        self.strings(name)?
+            .into_iter()
+            .map(|value| parse_value(value, name))
+            .collect()
+    }
+}
+
+// Check that all the lookups in a compressed table, with `row_count` rows of
+// `width` entries, are in bounds.
+fn check_compressed(
+    name: &str,
+    row_count: usize,
+    width: usize,
+    base: &[u32],
+    check: &[u16],
+    default: usize,
+) -> io::Result<()> {
+    let max_base = base.iter().cloned().max().unwrap_or(0) as usize;
+    if base.len() != row_count || default != row_count || max_base + width > check.len() {
+        return Err(invalid(format!("inconsistent sizes of the {} table", name)));
+    }
+    Ok(())
+}
+
+// The number of states, productions and special cases, that the entries of
+// the tables refer to by index.
+struct Limits {
+    state_count: usize,
+    prod_count: usize,
+    special_case_count: usize,
+}
+
+impl Limits {
+    fn check_state(&self, state: usize, field: &str) -> io::Result<()> {
+        if state >= self.state_count {
+            return Err(invalid(format!("invalid state {} in {}", state, field)));
+        }
+        Ok(())
+    }
+
+    // Check an action in the encoding described in jsparagus/emit/rust.py.
+    fn check_action(&self, action: i64, field: &str) -> io::Result<()> {
+        if action >= 0 {
+            self.check_state(action as usize, field)
+        } else if action > ACCEPT {
+            self.check_production((-action - 1) as usize, field)
+        } else if action >= ERROR {
+            Ok(())
+        } else {
+            self.check_special_case((action - SPECIAL_CASE_TAG) as usize, field)
+        }
+    }
+
+    fn check_production(&self, prod: usize, field: &str) -> io::Result<()> {
+        if prod >= self.prod_count {
+            return Err(invalid(format!("invalid production {} in {}", prod, field)));
+        }
+        Ok(())
+    }
+
+    fn check_special_case(&self, index: usize, field: &str) -> io::Result<()> {
+        if index >= self.special_case_count {
+            return Err(invalid(format!(
+                "invalid special case {} in {}",
+                index, field
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl LoadedTables {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut fields = Fields {
+            lines: text.lines(),
+        };
+
+        if fields.line("header")? != format!("jsparagus-tables {}", VERSION) {
+            return Err(invalid(String::from(
+                "not a parse tables file, or not the current version",
+            )));
+        }
+
+        // The lexer produces the TerminalId of the compiled tables.
+        let terminals = fields.strings("terminals")?;
+        if terminals[..] != TERMINAL_NAMES[..] {
+            return Err(invalid(String::from(
+                "the terminals differ from the compiled grammar",
+            )));
+        }
+        let action_width = terminals.len();
+        let nonterminals = fields.strings("nonterminals")?;
+        let goto_width = nonterminals.len();
+
+        let start_state_names = fields.strings("start_states")?;
+        let mut start_states = Vec::new();
+        let mut goal_nts = Vec::new();
+        for &(name, compiled_state) in START_STATES.iter() {
+            let state = start_state_names
+                .chunks(2)
+                .find(|pair| pair[0] == name && pair.len() == 2)
+                .ok_or_else(|| invalid(format!("missing start state for {}", name)))?[1];
+            start_states.push((compiled_state, parse_value(state, "start_states")?));
+            let goal_nt = nonterminals
+                .iter()
+                .position(|&nt| nt == name)
+                .ok_or_else(|| invalid(format!("missing goal nonterminal {}", name)))?;
+            goal_nts.push(goal_nt);
+        }
+
+        let action_base = fields.values("action_base")?;
+        let action_check = fields.values("action_check")?;
+        let action_values: Vec<i16> = fields.values("action_values")?;
+        let action_default: Vec<i16> = fields.values("action_default")?;
+        let goto_base = fields.values("goto_base")?;
+        let goto_check = fields.values("goto_check")?;
+        let goto_values: Vec<u16> = fields.values("goto_values")?;
+        let goto_default: Vec<u16> = fields.values("goto_default")?;
+
+        let state_count = action_default.len();
+        check_compressed(
+            "action",
+            state_count,
+            action_width,
+            &action_base,
+            &action_check,
+            action_default.len(),
+        )?;
+        check_compressed(
+            "goto",
+            goto_width,
+            state_count,
+            &goto_base,
+            &goto_check,
+            goto_default.len(),
+        )?;
+        if action_values.len() != action_check.len() || goto_values.len() != goto_check.len() {
+            return Err(invalid(String::from("inconsistent sizes of the tables")));
+        }
+
+        let special_cases: Vec<i64> = fields.values("special_cases")?;
+        let special_cases = special_cases
+            .chunks(2)
+            .map(|pair| match *pair {
+                [same_line, new_line] => Ok(SpecialCase {
+                    same_line,
+                    new_line,
+                }),
+                _ => Err(invalid(String::from(
+                    "special_cases must have two actions for each case",
+                ))),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let error_codes = fields
+            .strings("error_codes")?
+            .into_iter()
+            .map(|name| match name {
+                "-" => Ok(None),
+                "Asi" => Ok(Some(ErrorCode::Asi)),
+                "DoWhileAsi" => Ok(Some(ErrorCode::DoWhileAsi)),
+                _ => Err(invalid(format!("unknown error code {}", name))),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        if error_codes.len() != state_count {
+            return Err(invalid(String::from("wrong number of error codes")));
+        }
+
+        let compiled_prods: HashMap<&str, usize> = REDUCE_KEYS
+            .iter()
+            .enumerate()
+            .map(|(prod, &key)| (key, prod))
+            .collect();
+        let mut production_line = fields.line("productions")?.split_whitespace();
+        if production_line.next() != Some("productions") {
+            return Err(invalid(String::from("expected productions")));
+        }
+        let prod_count: usize = parse_value(production_line.next().unwrap_or(""), "productions")?;
+        let mut reduce_simulator = Vec::with_capacity(prod_count);
+        let mut reduce_actions = Vec::with_capacity(prod_count);
+        for _ in 0..prod_count {
+            let mut parts = fields.line("production")?.splitn(3, ' ');
+            let num_pops = parse_value(parts.next().unwrap_or(""), "productions")?;
+            let nt: usize = parse_value(parts.next().unwrap_or(""), "productions")?;
+            let key = parts.next().unwrap_or("");
+            if nt >= goto_width {
+                return Err(invalid(format!("invalid nonterminal for {}", key)));
+            }
+            let compiled_prod = compiled_prods.get(key).ok_or_else(|| {
+                invalid(format!(
+                    "no reduce action in the compiled grammar for {}",
+                    key
+                ))
+            })?;
+            // The reduce action pops as many values, and pushes the same
+            // nonterminal, as the compiled production.
+            let (compiled_num_pops, compiled_nt) = TABLES.reduce_simulator[*compiled_prod];
+            if num_pops != compiled_num_pops || nonterminals[nt] != NONTERMINAL_NAMES[compiled_nt] {
+                return Err(invalid(format!(
+                    "the production {} differs from the compiled grammar",
+                    key
+                )));
+            }
+            reduce_simulator.push((num_pops, nt));
+            reduce_actions.push(*compiled_prod);
+        }
+
+        // The parser indexes its own tables with the entries, so check them
+        // all here, rather than panicking in the middle of a parse.
+        let limits = Limits {
+            state_count,
+            prod_count,
+            special_case_count: special_cases.len(),
+        };
+        for &(_, state) in &start_states {
+            limits.check_state(state, "start_states")?;
+        }
+        for &action in &action_values {
+            limits.check_action(action as i64, "action_values")?;
+        }
+        for &action in &action_default {
+            limits.check_action(action as i64, "action_default")?;
+        }
+        for &state in &goto_values {
+            limits.check_state(state as usize, "goto_values")?;
+        }
+        for &state in &goto_default {
+            limits.check_state(state as usize, "goto_default")?;
+        }
+        for case in &special_cases {
+            limits.check_action(case.same_line, "special_cases")?;
+            limits.check_action(case.new_line, "special_cases")?;
+            if case.same_line == ACCEPT || case.new_line == ACCEPT {
+                return Err(invalid(String::from("ACCEPT in special_cases")));
+            }
+        }
+
+        let loaded = LoadedTables {
+            state_count,
+            action_width,
+            goto_width,
+            action_rows: action_base.into_iter().zip(action_default).collect(),
+            action_entries: action_check.into_iter().zip(action_values).collect(),
+            goto_rows: goto_base.into_iter().zip(goto_default).collect(),
+            goto_entries: goto_check.into_iter().zip(goto_values).collect(),
+            special_cases,
+            error_codes,
+            reduce_simulator,
+            reduce_actions,
+            start_states,
+        };
+        loaded.check_actions(&goal_nts)?;
+        Ok(loaded)
+    }
+
+    // Check the actions that the parser only handles in some states:
+    // accepting the input, and shifting an ErrorToken.
+    fn check_actions(&self, goal_nts: &[usize]) -> io::Result<()> {
+        let tables = self.tables();
+        let goal_states: Vec<usize> = self
+            .start_states
+            .iter()
+            .zip(goal_nts)
+            .map(|(&(_, state), &nt)| tables.goto(state, nt))
+            .collect();
+        let end = TerminalId::End as usize;
+        let error_token = TerminalId::ErrorToken as usize;
+        for state in 0..self.state_count {
+            for t in 0..self.action_width {
+                let action = tables.action(state, t);
+                if action == ACCEPT && (t != end || !goal_states.contains(&state)) {
+                    return Err(invalid(format!(
+                        "ACCEPT in state {}, which is not a goal state",
+                        state
+                    )));
+                }
+            }
+
+            // After an error, the parser reduces and then shifts an
+            // ErrorToken, and recovers as described by the error code.
+            let action = tables.action(state, error_token);
+            if action < ERROR {
+                return Err(invalid(format!(
+                    "special case for ErrorToken in state {}",
+                    state
+                )));
+            }
+            if action >= 0 && self.error_codes[state].is_none() {
+                return Err(invalid(format!(
+                    "missing error code for state {}, which shifts an ErrorToken",
+                    state
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn tables(&self) -> ParserTables<'_> {
+        ParserTables {
+            state_count: self.state_count,
+            action_width: self.action_width,
+            special_cases: &self.special_cases,
+            error_codes: &self.error_codes,
+            reduce_simulator: &self.reduce_simulator,
+            reduce_actions: Some(&self.reduce_actions),
+            goto_width: self.goto_width,
+            action_rows: &self.action_rows,
+            action_entries: &self.action_entries,
+            goto_rows: &self.goto_rows,
+            goto_entries: &self.goto_entries,
+        }
+    }
+
+    /// The state of these tables for the same goal nonterminal as
+    /// `compiled_state`, one of the start states of the compiled tables,
+    /// e.g. START_STATE_SCRIPT.
+    pub fn start_state(&self, compiled_state: usize) -> usize {
+        self.start_states
+            .iter()
+            .find(|&&(compiled, _)| compiled == compiled_state)
+            .expect("not a start state of the compiled tables")
+            .1
+    }
+}
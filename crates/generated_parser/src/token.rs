@@ -1,5 +1,7 @@
 use crate::parser_tables_generated::TerminalId;
-use ast::SourceLocation;
+use ast::{arena::CloneIn, wtf8::Wtf8, SourceLocation};
+use bumpalo::Bump;
+use std::convert::TryFrom;
 
 /// An ECMAScript input token. The lexer discards input matching *WhiteSpace*,
 /// *LineTerminator*, and *Comment*. The remaining input elements are called
@@ -36,14 +38,25 @@ pub struct Token<'a> {
     ///
     /// For a string literal, the string characters, after decoding
     /// *EscapeSequence*s and removing *LineContinuation*s (the SV of the
-    /// literal, in standardese).
+    /// literal, in standardese). This may contain unpaired surrogates, which
+    /// is why the value is `Wtf8` rather than `str`.
     ///
     /// For all other tokens (including template literal parts), the content is
     /// unspecified for now. TODO.
-    pub value: Option<&'a str>,
+    pub value: Option<&'a Wtf8>,
 }
 
-impl Token<'_> {
+impl<'a> Token<'a> {
+    /// The value of a token that can't contain unpaired surrogates, such as
+    /// a name or a numeric literal.
+    pub fn str_value(&self) -> Option<&'a str> {
+        self.value.map(|value| {
+            value
+                .as_str()
+                .expect("only string, template and regexp literals have unpaired surrogates")
+        })
+    }
+
     pub fn basic_token(terminal_id: TerminalId, loc: SourceLocation) -> Self {
         Self {
             terminal_id,
@@ -53,3 +66,49 @@ impl Token<'_> {
         }
     }
 }
+
+impl<'alloc> CloneIn<'alloc> for Token<'alloc> {
+    fn clone_in(&self, _allocator: &'alloc Bump) -> Self {
+        self.clone()
+    }
+}
+
+/// A token without a `value`, such as a punctuator, packed small enough to be
+/// stored in a `StackValue` without allocating it in the arena.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InlineToken {
+    terminal_id: TerminalId,
+    is_on_new_line: bool,
+    start: u32,
+    end: u32,
+}
+
+impl InlineToken {
+    /// Returns None if the token has a value, or its offsets don't fit.
+    pub fn new(token: &Token) -> Option<Self> {
+        if token.value.is_some() {
+            return None;
+        }
+        Some(Self {
+            terminal_id: token.terminal_id,
+            is_on_new_line: token.is_on_new_line,
+            start: u32::try_from(token.loc.start()).ok()?,
+            end: u32::try_from(token.loc.end()).ok()?,
+        })
+    }
+
+    pub fn to_token<'a>(self) -> Token<'a> {
+        Token {
+            terminal_id: self.terminal_id,
+            loc: SourceLocation::new(self.start as usize, self.end as usize),
+            is_on_new_line: self.is_on_new_line,
+            value: None,
+        }
+    }
+}
+
+impl<'alloc> CloneIn<'alloc> for InlineToken {
+    fn clone_in(&self, _allocator: &'alloc Bump) -> Self {
+        *self
+    }
+}
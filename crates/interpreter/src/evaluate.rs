@@ -8,6 +8,7 @@ use std::fmt;
 use std::rc::Rc;
 
 use crate::globals::create_global;
+use crate::host::{ModuleLoader, NoModuleLoader};
 use crate::object::Object;
 use crate::value::{to_boolean, to_number, JSValue};
 
@@ -58,11 +59,19 @@ impl Helpers for EmitResult {
 }
 
 pub fn evaluate(emit: &EmitResult) -> Result<JSValue, EvalError> {
+    evaluate_with_module_loader(emit, &mut NoModuleLoader)
+}
+
+pub fn evaluate_with_module_loader(
+    emit: &EmitResult,
+    loader: &mut dyn ModuleLoader,
+) -> Result<JSValue, EvalError> {
     let mut pc = 0;
     let mut stack = Vec::new();
     let mut rval = JSValue::Undefined;
 
     let global = create_global();
+    let mut import_meta = None;
 
     loop {
         let op = match Opcode::try_from(emit.bytecode[pc]) {
@@ -110,6 +119,14 @@ pub fn evaluate(emit: &EmitResult) -> Result<JSValue, EvalError> {
                 stack.pop().ok_or(EvalError::EmptyStack)?;
             }
 
+            Opcode::PopN => {
+                let n = emit.read_u16(pc + 1) as usize;
+                if stack.len() < n {
+                    return Err(EvalError::EmptyStack);
+                }
+                stack.truncate(stack.len() - n);
+            }
+
             Opcode::SetRval => {
                 rval = stack.pop().ok_or(EvalError::EmptyStack)?;
             }
@@ -277,6 +294,24 @@ pub fn evaluate(emit: &EmitResult) -> Result<JSValue, EvalError> {
                 stack.push(JSValue::String(emit.read_atom(pc + 1)));
             }
 
+            Opcode::DynamicImport => {
+                let specifier = stack.pop().ok_or(EvalError::EmptyStack)?;
+                stack.push(loader.import_module_dynamically(&specifier)?);
+            }
+
+            Opcode::ImportMeta => {
+                // The object is created on the first evaluation, and reused
+                // after that.
+                let meta = import_meta.get_or_insert_with(|| {
+                    let mut meta = Object::new();
+                    for (name, value) in loader.import_meta_properties() {
+                        meta.set(name, value);
+                    }
+                    Rc::new(RefCell::new(meta))
+                });
+                stack.push(JSValue::Object(meta.clone()));
+            }
+
             Opcode::True => stack.push(JSValue::Boolean(true)),
             Opcode::False => stack.push(JSValue::Boolean(false)),
             Opcode::Undefined => stack.push(JSValue::Undefined),
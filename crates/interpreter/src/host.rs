@@ -0,0 +1,28 @@
+use crate::evaluate::EvalError;
+use crate::value::JSValue;
+
+/// Hooks the embedding provides to load modules.
+pub trait ModuleLoader {
+    /// HostImportModuleDynamically, called for `import(specifier)`. The
+    /// returned value is the result of the ImportCall.
+    ///
+    /// TODO: The result should be a promise, once promises are supported.
+    fn import_module_dynamically(&mut self, specifier: &JSValue) -> Result<JSValue, EvalError>;
+
+    /// HostGetImportMetaProperties, called when `import.meta` is evaluated.
+    /// The properties are defined on the `import.meta` object.
+    fn import_meta_properties(&mut self) -> Vec<(String, JSValue)> {
+        Vec::new()
+    }
+}
+
+/// The ModuleLoader used when the embedding doesn't provide one.
+pub struct NoModuleLoader;
+
+impl ModuleLoader for NoModuleLoader {
+    fn import_module_dynamically(&mut self, _specifier: &JSValue) -> Result<JSValue, EvalError> {
+        Err(EvalError::NotImplemented(
+            "dynamic import without a module loader".to_owned(),
+        ))
+    }
+}
@@ -1,5 +1,6 @@
 mod evaluate;
 mod globals;
+mod host;
 mod object;
 mod value;
 
@@ -13,5 +14,6 @@ extern crate jsparagus_ast as ast;
 #[cfg(test)]
 extern crate jsparagus_parser as parser;
 
-pub use evaluate::{evaluate, EvalError};
+pub use evaluate::{evaluate, evaluate_with_module_loader, EvalError};
+pub use host::{ModuleLoader, NoModuleLoader};
 pub use value::JSValue;
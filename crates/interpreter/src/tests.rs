@@ -1,20 +1,35 @@
 use bumpalo::Bump;
-use emitter::{emit, EmitOptions};
-use parser::{parse_script, ParseOptions};
+use emitter::{emit, EmitOptions, EmitResult};
+use parser::{parse_module, parse_script, ParseOptions};
 
-use crate::{evaluate, EvalError, JSValue};
+use crate::{evaluate, evaluate_with_module_loader, EvalError, JSValue, ModuleLoader};
 
-fn try_evaluate(source: &str) -> Result<JSValue, EvalError> {
+fn emit_script(source: &str) -> EmitResult {
     let alloc = &Bump::new();
     let parse_options = ParseOptions::new();
     let parse_result = parse_script(alloc, source, &parse_options).expect("Failed to parse");
     let emit_options = EmitOptions::new();
-    let emit_result = emit(
+    emit(
         &mut ast::types::Program::Script(parse_result.unbox()),
         &emit_options,
     )
-    .expect("Should work!");
-    evaluate(&emit_result)
+    .expect("Should work!")
+}
+
+fn emit_module(source: &str) -> EmitResult {
+    let alloc = &Bump::new();
+    let parse_options = ParseOptions::new();
+    let parse_result = parse_module(alloc, source, &parse_options).expect("Failed to parse");
+    let emit_options = EmitOptions::new();
+    emit(
+        &mut ast::types::Program::Module(parse_result.unbox()),
+        &emit_options,
+    )
+    .expect("Should work!")
+}
+
+fn try_evaluate(source: &str) -> Result<JSValue, EvalError> {
+    evaluate(&emit_script(source))
 }
 
 #[test]
@@ -116,3 +131,96 @@ fn test_call() {
         _ => panic!("wrong result"),
     }
 }
+
+#[test]
+fn test_optional_chain() {
+    match try_evaluate("o = {b: 1}; o?.b") {
+        Ok(JSValue::Number(n)) if n == 1.0 => (),
+        _ => panic!("wrong result"),
+    }
+
+    match try_evaluate("o = null; o?.b.c") {
+        Ok(JSValue::Undefined) => (),
+        _ => panic!("wrong result"),
+    }
+
+    match try_evaluate("o = {}; o.f?.(1)") {
+        Ok(JSValue::Undefined) => (),
+        _ => panic!("wrong result"),
+    }
+
+    match try_evaluate("Math.sqrt?.(36)") {
+        Ok(JSValue::Number(n)) if n == 6.0 => (),
+        _ => panic!("wrong result"),
+    }
+
+    match try_evaluate("o = undefined; o?.b?.c") {
+        Ok(JSValue::Undefined) => (),
+        _ => panic!("wrong result"),
+    }
+}
+
+struct TestModuleLoader {
+    requested: Vec<String>,
+}
+
+impl ModuleLoader for TestModuleLoader {
+    fn import_module_dynamically(&mut self, specifier: &JSValue) -> Result<JSValue, EvalError> {
+        match specifier {
+            JSValue::String(s) => {
+                self.requested.push(s.clone());
+                Ok(JSValue::Number(self.requested.len() as f64))
+            }
+            _ => Err(EvalError::NotImplemented("non-string specifier".to_owned())),
+        }
+    }
+
+    fn import_meta_properties(&mut self) -> Vec<(String, JSValue)> {
+        vec![("url".to_owned(), JSValue::String("test.mjs".to_owned()))]
+    }
+}
+
+#[test]
+fn test_dynamic_import() {
+    let mut loader = TestModuleLoader {
+        requested: Vec::new(),
+    };
+    match evaluate_with_module_loader(&emit_script("import('a'); import('b')"), &mut loader) {
+        Ok(JSValue::Number(n)) if n == 2.0 => (),
+        _ => panic!("wrong result"),
+    }
+    assert_eq!(loader.requested, vec!["a".to_owned(), "b".to_owned()]);
+
+    match try_evaluate("import('a')") {
+        Err(EvalError::NotImplemented(_)) => (),
+        _ => panic!("wrong result"),
+    }
+}
+
+#[test]
+fn test_import_meta() {
+    let mut loader = TestModuleLoader {
+        requested: Vec::new(),
+    };
+    match evaluate_with_module_loader(&emit_module("import.meta.url"), &mut loader) {
+        Ok(JSValue::String(ref s)) if s == "test.mjs" => (),
+        _ => panic!("wrong result"),
+    }
+
+    match evaluate(&emit_module("import.meta.url")) {
+        Ok(JSValue::Undefined) => (),
+        _ => panic!("wrong result"),
+    }
+}
+
+#[test]
+fn test_top_level_await() {
+    let result = emit_module("await 1");
+    assert!(result.is_async);
+
+    // Suspending the module isn't supported yet.
+    match evaluate(&result) {
+        Err(EvalError::NotImplemented(_)) => (),
+        _ => panic!("wrong result"),
+    }
+}
@@ -0,0 +1,312 @@
+//! Incremental reparsing of scripts after text edits.
+//!
+//! The granularity is the top-level statement: statements before and after
+//! an edit are taken from the previous parse, and only the statements in
+//! between are parsed again. The parser starts at the beginning of the last
+//! statement before the edit, and stops as soon as the tokens parsed so far
+//! form complete statements and the next token starts one of the old
+//! statements after the edit.
+
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::source::{LocationUnit, SourceChars};
+use crate::ParseOptions;
+use ast::{
+    arena, source_location::SourceLocation, source_location_accessor::SourceLocationAccessor,
+    types::Script, visit::Pass,
+};
+use bumpalo::{collections::String, Bump};
+use generated_parser::{
+    BindingInfo, BindingKind, ParseError, Result, TerminalId, START_STATE_SCRIPT,
+};
+
+/// Replacement of the text in the range [start, end) of a source, in UTF-8
+/// bytes.
+pub struct TextEdit<'a> {
+    pub start: usize,
+    pub end: usize,
+    pub text: &'a str,
+}
+
+/// A parsed script, which can be updated after an edit with `reparse_script`.
+pub struct IncrementalScript<'alloc> {
+    pub source: &'alloc str,
+    pub script: arena::Box<'alloc, Script<'alloc>>,
+
+    // The offsets of the first tokens of the top-level statements. The
+    // locations of the statements themselves don't include everything, e.g.
+    // the semicolon of an ExpressionStatement.
+    statement_starts: Vec<usize>,
+
+    // The top-level bindings of the script, sorted by offset. The ones from
+    // the reused statements are checked against the ones from the statements
+    // that are parsed again.
+    bindings: Vec<BindingInfo<'alloc>>,
+}
+
+// Adds `delta` to all source locations.
+struct ShiftLocations {
+    delta: isize,
+}
+
+impl<'alloc> Pass<'alloc> for ShiftLocations {
+    fn visit_source_location(&mut self, loc: &mut SourceLocation) {
+        // Some nodes without any source (e.g. empty FunctionBody) have the
+        // default location, which is never a real location after an edit.
+        if *loc != SourceLocation::default() {
+            *loc = SourceLocation::new(
+                shift(loc.start(), self.delta),
+                shift(loc.end(), self.delta),
+            );
+        }
+    }
+}
+
+fn shift(offset: usize, delta: isize) -> usize {
+    (offset as isize + delta) as usize
+}
+
+fn check_options<'alloc>(options: &ParseOptions) -> Result<'alloc, ()> {
+    if options.location_unit != LocationUnit::Utf8 {
+        return Err(ParseError::NotImplemented(
+            "incremental reparsing with locations in UTF-16",
+        ));
+    }
+    if options.tables.is_some() {
+        return Err(ParseError::NotImplemented(
+            "incremental reparsing with parse tables loaded at run time",
+        ));
+    }
+    Ok(())
+}
+
+fn new_parser<'alloc>(allocator: &'alloc Bump, options: &ParseOptions) -> Parser<'alloc> {
+    let mut parser = crate::new_parser(allocator, options, START_STATE_SCRIPT);
+    parser.handler_mut().script_bindings = Some(Vec::new());
+    parser
+}
+
+// Write the tokens from `tokens` to `parser` until the end of the source, or
+// until the parser is at the start of a statement and the next token starts
+// at one of the offsets in `stop_offsets`, in which case the index of that
+// offset is returned. The offsets of the statements are added to
+// `statement_starts`.
+fn write_statements<'alloc>(
+    parser: &mut Parser<'alloc>,
+    tokens: &mut Lexer<'alloc>,
+    statement_starts: &mut Vec<usize>,
+    stop_offsets: &[usize],
+) -> Result<'alloc, Option<usize>> {
+    loop {
+        let t = tokens.next(parser)?;
+        if t.terminal_id == TerminalId::End {
+            return Ok(None);
+        }
+        if parser.is_at_script_statement_start(&t) {
+            if let Ok(index) = stop_offsets.binary_search(&t.loc.start()) {
+                return Ok(Some(index));
+            }
+            statement_starts.push(t.loc.start());
+        }
+        parser.write_token(&t)?;
+    }
+}
+
+// Returns the sorted names of the top-level lexical declarations in the
+// range [start, end) of the source.
+fn lexical_names<'alloc>(
+    bindings: &[BindingInfo<'alloc>],
+    start: usize,
+    end: usize,
+) -> Vec<&'alloc str> {
+    let mut names: Vec<_> = bindings
+        .iter()
+        .filter(|info| start <= info.offset && info.offset < end)
+        .filter(|info| {
+            matches!(
+                info.kind,
+                BindingKind::Let | BindingKind::Const | BindingKind::Class
+            )
+        })
+        .map(|info| info.name)
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+/// Parse `source` as a Script, keeping the information needed to reparse it
+/// after an edit.
+pub fn parse_script_incrementally<'alloc>(
+    allocator: &'alloc Bump,
+    source: &'alloc str,
+    options: &ParseOptions,
+) -> Result<'alloc, IncrementalScript<'alloc>> {
+    check_options(options)?;
+
+    let mut parser = new_parser(allocator, options);
+    let mut tokens = Lexer::new(allocator, source);
+    let mut statement_starts = Vec::new();
+    write_statements(&mut parser, &mut tokens, &mut statement_starts, &[])?;
+    let script = parser.close(tokens.offset())?.to_ast()?;
+    let bindings = parser.handler_mut().script_bindings.take().unwrap();
+
+    Ok(IncrementalScript {
+        source,
+        script,
+        statement_starts,
+        bindings,
+    })
+}
+
+/// Apply `edit` to the source of `previous`, and parse the result, reusing
+/// the top-level statements not affected by the edit. Source locations of the
+/// reused statements after the edit are adjusted.
+///
+/// The result is the same as `parse_script_incrementally` on the edited
+/// source. The range of `edit` must be in the source of `previous`, on
+/// character boundaries, or ParseError::InvalidTextEdit is returned.
+pub fn reparse_script<'alloc>(
+    allocator: &'alloc Bump,
+    previous: IncrementalScript<'alloc>,
+    edit: &TextEdit,
+    options: &ParseOptions,
+) -> Result<'alloc, IncrementalScript<'alloc>> {
+    check_options(options)?;
+
+    let old_source = previous.source;
+    if edit.start > edit.end
+        || !old_source.is_char_boundary(edit.start)
+        || !old_source.is_char_boundary(edit.end)
+    {
+        return Err(ParseError::InvalidTextEdit(edit.start, edit.end));
+    }
+    let mut text = String::with_capacity_in(
+        old_source.len() - (edit.end - edit.start) + edit.text.len(),
+        allocator,
+    );
+    text.push_str(&old_source[..edit.start]);
+    text.push_str(edit.text);
+    text.push_str(&old_source[edit.end..]);
+    let source = text.into_bump_str();
+
+    match reparse_statements(allocator, previous, edit, source, options) {
+        Some(result) => Ok(result),
+        // The edited part couldn't be parsed separately, or it changes how
+        // the other statements are parsed. Errors are also reported from
+        // here, so that they are the same as with a full parse.
+        None => parse_script_incrementally(allocator, source, options),
+    }
+}
+
+fn reparse_statements<'alloc>(
+    allocator: &'alloc Bump,
+    previous: IncrementalScript<'alloc>,
+    edit: &TextEdit,
+    source: &'alloc str,
+    options: &ParseOptions,
+) -> Option<IncrementalScript<'alloc>> {
+    let old_script = previous.script.unbox();
+    let old_starts = previous.statement_starts;
+    let delta = edit.text.len() as isize - (edit.end - edit.start) as isize;
+
+    // Statements before the edit are reused, except the last one, which may
+    // continue into the edited text, e.g. when a semicolon is removed.
+    let prefix_len = old_starts
+        .iter()
+        .skip(1)
+        .take_while(|&&offset| offset <= edit.start)
+        .count()
+        .saturating_sub(1);
+    let start = match prefix_len {
+        0 => 0,
+        _ => old_starts[prefix_len],
+    };
+
+    // The statements after the edit, where the parser can stop.
+    let first_suffix_index = old_starts
+        .iter()
+        .take_while(|&&offset| offset < edit.end)
+        .count();
+    let stop_offsets: Vec<_> = old_starts[first_suffix_index..]
+        .iter()
+        .map(|&offset| shift(offset, delta))
+        .collect();
+
+    let mut parser = new_parser(allocator, options);
+    let chars = SourceChars::with_range(source, LocationUnit::Utf8, start, source.len());
+    let mut tokens = Lexer::with_chars(allocator, chars);
+    let mut statement_starts = old_starts[..prefix_len].to_vec();
+    let suffix_index = write_statements(
+        &mut parser,
+        &mut tokens,
+        &mut statement_starts,
+        &stop_offsets,
+    )
+    .ok()?
+    .map_or(old_starts.len(), |index| first_suffix_index + index);
+
+    // The end of the reparsed part, in the old source and in the new source.
+    let (old_end, end) = match old_starts.get(suffix_index) {
+        Some(&offset) => (offset, shift(offset, delta)),
+        None => (usize::MAX, source.len()),
+    };
+
+    let old_bindings = previous.bindings;
+    let mut outer_bindings: Vec<_> = old_bindings
+        .iter()
+        .filter(|info| info.offset < start)
+        .cloned()
+        .collect();
+    outer_bindings.extend(
+        old_bindings
+            .iter()
+            .filter(|info| info.offset >= old_end)
+            .map(|info| BindingInfo {
+                offset: shift(info.offset, delta),
+                ..*info
+            }),
+    );
+    parser.handler_mut().script_bindings = Some(outer_bindings);
+
+    let middle: arena::Box<Script> = parser.close(end).ok()?.to_ast().ok()?;
+    let bindings = parser.handler_mut().script_bindings.take().unwrap();
+
+    // Top-level lexical declarations affect the Annex B hoisting of
+    // functions in blocks, which has been decided for the reused statements.
+    if lexical_names(&old_bindings, start, old_end) != lexical_names(&bindings, start, end) {
+        return None;
+    }
+
+    let mut statements = arena::Vec::new_in(allocator);
+    let mut old_statements = old_script.statements.into_iter();
+    statements.extend(old_statements.by_ref().take(prefix_len));
+    statements.extend(middle.unbox().statements);
+    let mut shift_locations = ShiftLocations { delta };
+    for mut statement in old_statements.skip(suffix_index - prefix_len) {
+        shift_locations.visit_statement(&mut statement);
+        statements.push(statement);
+    }
+    statement_starts.extend(
+        old_starts[suffix_index..]
+            .iter()
+            .map(|&offset| shift(offset, delta)),
+    );
+
+    let loc = match (statements.first(), statements.last()) {
+        (Some(first), Some(last)) => SourceLocation::from_parts(first.get_loc(), last.get_loc()),
+        _ => SourceLocation::default(),
+    };
+    Some(IncrementalScript {
+        source,
+        script: arena::alloc(
+            allocator,
+            Script {
+                directives: old_script.directives,
+                statements,
+                loc,
+            },
+        ),
+        statement_starts,
+        bindings,
+    })
+}
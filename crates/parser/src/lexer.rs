@@ -1,68 +1,104 @@
 //! JavaScript lexer.
 
 use crate::parser::Parser;
+use crate::source::{LocationUnit, SourceChars, SourceText};
+use ast::atom_table::AtomTable;
+use ast::wtf8::{Wtf8, Wtf8Buf};
 use ast::SourceLocation;
-use bumpalo::{collections::String, Bump};
+use bumpalo::Bump;
 use generated_parser::{ParseError, Result, TerminalId, Token};
 use std::convert::TryFrom;
-use std::str::Chars;
 use unic_ucd_ident::{is_id_continue, is_id_start};
 
-pub struct Lexer<'alloc> {
+pub struct Lexer<'alloc, S: ?Sized + SourceText = str> {
     allocator: &'alloc Bump,
 
-    /// Length of the input text, in UTF-8 bytes.
-    source_length: usize,
-
     /// Iterator over the remaining not-yet-parsed input.
-    chars: Chars<'alloc>,
+    chars: SourceChars<'alloc, S>,
 
     /// True if the current position is before the first
     /// token of a line (or on a line with no tokens).
     is_on_new_line: bool,
+
+    /// True if the offsets of the input might not fit in a SourceLocation.
+    too_long: bool,
+
+    /// Names that can't be borrowed from the source, interned so that each
+    /// is only allocated once. Shared by clones of this lexer, and by the
+    /// other lexers of a ParseSession.
+    atoms: &'alloc AtomTable<'alloc>,
+}
+
+// Not derived, as that would require `S: Clone`.
+impl<'alloc, S: ?Sized + SourceText> Clone for Lexer<'alloc, S> {
+    fn clone(&self) -> Self {
+        Self {
+            allocator: self.allocator,
+            chars: self.chars.clone(),
+            is_on_new_line: self.is_on_new_line,
+            too_long: self.too_long,
+            atoms: self.atoms,
+        }
+    }
 }
 
-impl<'alloc> Lexer<'alloc> {
-    pub fn new(allocator: &'alloc Bump, chars: Chars<'alloc>) -> Lexer<'alloc> {
-        Self::with_offset(allocator, chars, 0)
+impl<'alloc, S: ?Sized + SourceText> Lexer<'alloc, S> {
+    /// Create a lexer for the whole `source`, with source locations in UTF-8
+    /// bytes.
+    pub fn new(allocator: &'alloc Bump, source: &'alloc S) -> Self {
+        Self::with_unit(allocator, source, LocationUnit::Utf8)
     }
 
-    /// Create a lexer for a part of a JS script or module. `offset` is the
-    /// total length of all previous parts, in bytes; source locations for
-    /// tokens created by the new lexer start counting from this number.
-    pub fn with_offset(
+    /// Create a lexer for the whole `source`, with source locations counted
+    /// in `unit`.
+    pub fn with_unit(allocator: &'alloc Bump, source: &'alloc S, unit: LocationUnit) -> Self {
+        Self::with_chars(allocator, SourceChars::new(source, unit))
+    }
+
+    /// Create a lexer for a part of a JS script or module, starting at the
+    /// given position of `chars`. Source locations for tokens created by the
+    /// new lexer are relative to the whole source.
+    pub fn with_chars(allocator: &'alloc Bump, chars: SourceChars<'alloc, S>) -> Self {
+        let atoms = allocator.alloc(AtomTable::new_in(allocator));
+        Self::with_atoms(allocator, atoms, chars)
+    }
+
+    /// Like `with_chars`, but names are interned in `atoms`, which can be
+    /// shared with other lexers.
+    pub fn with_atoms(
         allocator: &'alloc Bump,
-        chars: Chars<'alloc>,
-        offset: usize,
-    ) -> Lexer<'alloc> {
-        let source_length = offset + chars.as_str().len();
+        atoms: &'alloc AtomTable<'alloc>,
+        chars: SourceChars<'alloc, S>,
+    ) -> Self {
         Lexer {
             allocator,
-            source_length,
+            too_long: !SourceLocation::offset_fits(chars.max_offset()),
             chars,
             is_on_new_line: true,
+            atoms,
         }
     }
 
     fn is_looking_at(&self, s: &str) -> bool {
-        self.chars.as_str().starts_with(s)
+        self.chars.starts_with(s)
     }
 
     pub fn offset(&self) -> usize {
-        self.source_length - self.chars.as_str().len()
+        self.chars.offset()
     }
 
     fn peek(&self) -> Option<char> {
-        self.chars.as_str().chars().next()
+        self.chars.peek()
     }
 
     fn double_peek(&self) -> Option<char> {
-        let mut chars = self.chars.as_str().chars();
-        chars.next();
-        chars.next()
+        self.chars.peek_second()
     }
 
     pub fn next<'parser>(&mut self, parser: &Parser<'parser>) -> Result<'alloc, Token<'alloc>> {
+        if self.too_long {
+            return Err(ParseError::SourceTooLong);
+        }
         let (loc, value, terminal_id) = self.advance_impl(parser)?;
         let is_on_new_line = self.is_on_new_line;
         self.is_on_new_line = false;
@@ -75,12 +111,33 @@ impl<'alloc> Lexer<'alloc> {
     }
 
     fn unexpected_err(&mut self) -> ParseError<'alloc> {
-        if let Some(ch) = self.peek() {
+        if let Some(unit) = self.chars.peek_unpaired_surrogate() {
+            ParseError::UnpairedSurrogate(unit)
+        } else if let Some(ch) = self.peek() {
             ParseError::IllegalCharacter(ch)
         } else {
             ParseError::UnexpectedEnd
         }
     }
+
+    /// The error for `ch`, the character just returned by `self.chars.next()`,
+    /// which can't start a token here. An unpaired surrogate is reported as
+    /// its code unit rather than the U+FFFD it was decoded as.
+    fn illegal_character_err(&self, ch: char) -> ParseError<'alloc> {
+        match self.chars.unpaired_surrogate_before() {
+            Some(unit) => ParseError::UnpairedSurrogate(unit),
+            None => ParseError::IllegalCharacter(ch),
+        }
+    }
+
+    /// Push `ch`, the character just returned by `self.chars.next()`, to
+    /// `text`, keeping the code unit of an unpaired surrogate.
+    fn push_source_char(&self, text: &mut Wtf8Buf<'alloc>, ch: char) {
+        match self.chars.unpaired_surrogate_before() {
+            Some(unit) => text.push_code_unit(unit),
+            None => text.push(ch),
+        }
+    }
 }
 
 // ----------------------------------------------------------------------------
@@ -138,7 +195,7 @@ const PS: char = '\u{2029}';
 //     MultiLineComment
 //     SingleLineComment
 
-impl<'alloc> Lexer<'alloc> {
+impl<'alloc, S: ?Sized + SourceText> Lexer<'alloc, S> {
     /// Skip a *MultiLineComment*.
     ///
     /// ```text
@@ -166,7 +223,13 @@ impl<'alloc> Lexer<'alloc> {
     /// use `is_on_new_line` for that.)
     ///
     fn skip_multi_line_comment(&mut self, builder: &mut AutoCow<'alloc>) -> Result<'alloc, ()> {
-        while let Some(ch) = self.chars.next() {
+        loop {
+            self.chars
+                .skip_ascii_while(|byte| byte != b'*' && byte != b'\r' && byte != b'\n');
+            let ch = match self.chars.next() {
+                Some(ch) => ch,
+                None => break,
+            };
             match ch {
                 '*' if self.peek() == Some('/') => {
                     self.chars.next();
@@ -196,7 +259,13 @@ impl<'alloc> Lexer<'alloc> {
     ///     SourceCharacter but not LineTerminator
     /// ```
     fn skip_single_line_comment(&mut self, builder: &mut AutoCow<'alloc>) {
-        while let Some(ch) = self.chars.next() {
+        loop {
+            self.chars
+                .skip_ascii_while(|byte| byte != b'\r' && byte != b'\n');
+            let ch = match self.chars.next() {
+                Some(ch) => ch,
+                None => break,
+            };
             match ch {
                 CR | LF | LS | PS => break,
                 _ => continue,
@@ -247,13 +316,121 @@ fn is_identifier_start(c: char) -> bool {
 fn is_identifier_part(c: char) -> bool {
     // Escaped case is handled separately.
     if c.is_ascii() {
-        c == '$' || c == '_' || c.is_ascii_alphanumeric()
+        is_ascii_identifier_part(c as u8)
     } else {
         is_id_continue(c) || c == ZWNJ || c == ZWJ
     }
 }
 
-impl<'alloc> Lexer<'alloc> {
+fn is_ascii_identifier_part(byte: u8) -> bool {
+    byte == b'$' || byte == b'_' || byte.is_ascii_alphanumeric()
+}
+
+/// The keywords, contextual keywords and literals that look like
+/// identifiers, with their `TerminalId`.
+const KEYWORDS: [(&str, TerminalId); 53] = [
+    ("as", TerminalId::As),
+    ("async", TerminalId::Async),
+    ("await", TerminalId::Await),
+    ("break", TerminalId::Break),
+    ("case", TerminalId::Case),
+    ("catch", TerminalId::Catch),
+    ("class", TerminalId::Class),
+    ("const", TerminalId::Const),
+    ("continue", TerminalId::Continue),
+    ("debugger", TerminalId::Debugger),
+    ("default", TerminalId::Default),
+    ("delete", TerminalId::Delete),
+    ("do", TerminalId::Do),
+    ("else", TerminalId::Else),
+    ("export", TerminalId::Export),
+    ("extends", TerminalId::Extends),
+    ("finally", TerminalId::Finally),
+    ("for", TerminalId::For),
+    ("from", TerminalId::From),
+    ("function", TerminalId::Function),
+    ("get", TerminalId::Get),
+    ("if", TerminalId::If),
+    ("implements", TerminalId::Implements),
+    ("import", TerminalId::Import),
+    ("in", TerminalId::In),
+    ("instanceof", TerminalId::Instanceof),
+    ("interface", TerminalId::Interface),
+    ("let", TerminalId::Let),
+    ("meta", TerminalId::Meta),
+    ("new", TerminalId::New),
+    ("of", TerminalId::Of),
+    ("package", TerminalId::Package),
+    ("private", TerminalId::Private),
+    ("protected", TerminalId::Protected),
+    ("public", TerminalId::Public),
+    ("return", TerminalId::Return),
+    ("set", TerminalId::Set),
+    ("static", TerminalId::Static),
+    ("super", TerminalId::Super),
+    ("switch", TerminalId::Switch),
+    ("target", TerminalId::Target),
+    ("this", TerminalId::This),
+    ("throw", TerminalId::Throw),
+    ("try", TerminalId::Try),
+    ("typeof", TerminalId::Typeof),
+    ("var", TerminalId::Var),
+    ("void", TerminalId::Void),
+    ("while", TerminalId::While),
+    ("with", TerminalId::With),
+    ("yield", TerminalId::Yield),
+    ("null", TerminalId::NullLiteral),
+    ("true", TerminalId::BooleanLiteral),
+    ("false", TerminalId::BooleanLiteral),
+];
+
+const MIN_KEYWORD_LEN: usize = 2;
+const MAX_KEYWORD_LEN: usize = 10;
+
+// Keywords are looked up in a table with one keyword per slot, using a
+// perfect hash of their length and first, second and last bytes. The
+// multiplier was found by trying random ones, and keyword_table checks that
+// there are no collisions when compiling.
+const KEYWORD_HASH_MULTIPLIER: u64 = 0x0823_3bb2_b5ef_0605;
+const KEYWORD_TABLE_BITS: u32 = 7;
+
+const fn keyword_hash(bytes: &[u8]) -> usize {
+    let key = bytes.len() as u64
+        | (bytes[0] as u64) << 8
+        | (bytes[1] as u64) << 16
+        | (bytes[bytes.len() - 1] as u64) << 24;
+    (key.wrapping_mul(KEYWORD_HASH_MULTIPLIER) >> (64 - KEYWORD_TABLE_BITS)) as usize
+}
+
+const fn keyword_table() -> [Option<(&'static str, TerminalId)>; 1 << KEYWORD_TABLE_BITS] {
+    let mut table = [None; 1 << KEYWORD_TABLE_BITS];
+    let mut i = 0;
+    while i < KEYWORDS.len() {
+        let keyword = KEYWORDS[i].0.as_bytes();
+        assert!(MIN_KEYWORD_LEN <= keyword.len() && keyword.len() <= MAX_KEYWORD_LEN);
+        let slot = keyword_hash(keyword);
+        assert!(table[slot].is_none(), "keyword hash collision");
+        table[slot] = Some(KEYWORDS[i]);
+        i += 1;
+    }
+    table
+}
+
+static KEYWORD_TABLE: [Option<(&str, TerminalId)>; 1 << KEYWORD_TABLE_BITS] = keyword_table();
+
+/// The `TerminalId` of `text` if it's one of the KEYWORDS.
+fn keyword(text: &str) -> Option<TerminalId> {
+    let bytes = text.as_bytes();
+    if bytes.len() < MIN_KEYWORD_LEN || bytes.len() > MAX_KEYWORD_LEN {
+        return None;
+    }
+    match KEYWORD_TABLE[keyword_hash(bytes)] {
+        Some((keyword, id)) if keyword == text => Some(id),
+        _ => None,
+    }
+}
+
+impl<'alloc, S: ?Sized + SourceText> Lexer<'alloc, S> {
     /// Scan the rest of an IdentifierName, having already parsed the initial
     /// IdentifierStart and stored it in `builder`.
     ///
@@ -271,7 +448,16 @@ impl<'alloc> Lexer<'alloc> {
         &mut self,
         mut builder: AutoCow<'alloc>,
     ) -> Result<'alloc, (bool, &'alloc str)> {
-        while let Some(ch) = self.peek() {
+        loop {
+            // Runs of ASCII characters don't need the Unicode tables.
+            let run_start = self.chars.position();
+            self.chars.skip_ascii_while(is_ascii_identifier_part);
+            builder.push_matching_run(self, run_start);
+
+            let ch = match self.peek() {
+                Some(ch) => ch,
+                None => break,
+            };
             if !is_identifier_part(ch) {
                 if ch == '\\' {
                     self.chars.next();
@@ -292,7 +478,7 @@ impl<'alloc> Lexer<'alloc> {
             builder.push_matching(ch);
         }
         let has_different = builder.has_different();
-        Ok((has_different, builder.finish(&self)))
+        Ok((has_different, builder.finish_name(self)))
     }
 
     fn identifier_name(&mut self, mut builder: AutoCow<'alloc>) -> Result<'alloc, &'alloc str> {
@@ -321,7 +507,7 @@ impl<'alloc> Lexer<'alloc> {
                     }
 
                     other => {
-                        return Err(ParseError::IllegalCharacter(other));
+                        return Err(self.illegal_character_err(other));
                     }
                 }
                 self.identifier_name_tail(builder)
@@ -353,11 +539,12 @@ impl<'alloc> Lexer<'alloc> {
     /// StringValue of it. For example, if the source string is "\u{79}ield",
     /// the result is `TerminalId::NameWithEscape`, and the StringValue is
     /// "yield".
-    fn identifier_tail(
+    fn identifier_tail<'parser>(
         &mut self,
+        parser: &Parser<'parser>,
         start: usize,
         builder: AutoCow<'alloc>,
-    ) -> Result<'alloc, (SourceLocation, Option<&'alloc str>, TerminalId)> {
+    ) -> Result<'alloc, (SourceLocation, Option<&'alloc Wtf8>, TerminalId)> {
         let (has_different, text) = self.identifier_name_tail(builder)?;
 
         // https://tc39.es/ecma262/#sec-keywords-and-reserved-words
@@ -372,83 +559,99 @@ impl<'alloc> Lexer<'alloc> {
             // consumer.
             TerminalId::NameWithEscape
         } else {
-            match &text as &str {
-                "as" => TerminalId::As,
-                "async" => {
-                    //TerminalId::Async
-                    return Err(ParseError::NotImplemented(
-                        "async cannot be handled in parser due to multiple lookahead",
-                    ));
+            match keyword(text) {
+                Some(TerminalId::Async) => {
+                    // `async` is unambiguous only when it's followed by
+                    // `function` on the same line.
+                    if !self.is_looking_at_function_on_same_line() {
+                        return Err(ParseError::NotImplemented(
+                            "async cannot be handled in parser due to multiple lookahead",
+                        ));
+                    }
+                    TerminalId::Async
                 }
-                "await" => {
-                    //TerminalId::Await
-                    return Err(ParseError::NotImplemented(
-                        "await cannot be handled in parser",
-                    ));
+                Some(TerminalId::Await) if parser.is_module() => {
+                    // `await` is a reserved word in module code, so it can
+                    // only be an AwaitExpression, `for await`, or an
+                    // IdentifierName such as a property name.
+                    if !parser.can_accept_terminal(TerminalId::Await) {
+                        return Err(ParseError::AwaitOutsideOfAsyncFunction(start));
+                    }
+                    TerminalId::Await
+                }
+                Some(TerminalId::Await) => {
+                    // The grammar doesn't accept `await` as an
+                    // IdentifierReference. Whether AwaitExpression and
+                    // `for await` are allowed there is checked by AstBuilder.
+                    if !parser.can_accept_terminal(TerminalId::Await)
+                        || !self.is_looking_at_await_operand()
+                    {
+                        return Err(ParseError::NotImplemented(
+                            "await cannot be handled in parser",
+                        ));
+                    }
+                    TerminalId::Await
                 }
-                "break" => TerminalId::Break,
-                "case" => TerminalId::Case,
-                "catch" => TerminalId::Catch,
-                "class" => TerminalId::Class,
-                "const" => TerminalId::Const,
-                "continue" => TerminalId::Continue,
-                "debugger" => TerminalId::Debugger,
-                "default" => TerminalId::Default,
-                "delete" => TerminalId::Delete,
-                "do" => TerminalId::Do,
-                "else" => TerminalId::Else,
-                "export" => TerminalId::Export,
-                "extends" => TerminalId::Extends,
-                "finally" => TerminalId::Finally,
-                "for" => TerminalId::For,
-                "from" => TerminalId::From,
-                "function" => TerminalId::Function,
-                "get" => TerminalId::Get,
-                "if" => TerminalId::If,
-                "implements" => TerminalId::Implements,
-                "import" => TerminalId::Import,
-                "in" => TerminalId::In,
-                "instanceof" => TerminalId::Instanceof,
-                "interface" => TerminalId::Interface,
-                "let" => {
-                    //TerminalId::Let,
+                Some(TerminalId::Let) => {
                     return Err(ParseError::NotImplemented(
                         "let cannot be handled in parser due to multiple lookahead",
                     ));
                 }
-                "new" => TerminalId::New,
-                "of" => TerminalId::Of,
-                "package" => TerminalId::Package,
-                "private" => TerminalId::Private,
-                "protected" => TerminalId::Protected,
-                "public" => TerminalId::Public,
-                "return" => TerminalId::Return,
-                "set" => TerminalId::Set,
-                "static" => TerminalId::Static,
-                "super" => TerminalId::Super,
-                "switch" => TerminalId::Switch,
-                "target" => TerminalId::Target,
-                "this" => TerminalId::This,
-                "throw" => TerminalId::Throw,
-                "try" => TerminalId::Try,
-                "typeof" => TerminalId::Typeof,
-                "var" => TerminalId::Var,
-                "void" => TerminalId::Void,
-                "while" => TerminalId::While,
-                "with" => TerminalId::With,
-                "yield" => {
-                    //TerminalId::Yield
+                Some(TerminalId::Yield) => {
                     return Err(ParseError::NotImplemented(
                         "yield cannot be handled in parser",
                     ));
                 }
-                "null" => TerminalId::NullLiteral,
-                "true" | "false" => TerminalId::BooleanLiteral,
-                _ => TerminalId::Name,
+                Some(id) => id,
+                None => TerminalId::Name,
             }
         };
 
-        Ok((SourceLocation::new(start, self.offset()), Some(text), id))
+        Ok((
+            SourceLocation::new(start, self.offset()),
+            Some(Wtf8::new(text)),
+            id,
+        ))
+    }
+
+    /// True if the rest of the input starts with the `function` keyword,
+    /// with only spaces and tabs before it.
+    fn is_looking_at_function_on_same_line(&self) -> bool {
+        let mut rest = self.chars.clone();
+        while let Some(SP) | Some(TAB) = rest.clone().next() {
+            rest.next();
+        }
+        if !rest.starts_with("function") {
+            return false;
+        }
+        !matches!(rest.nth("function".len()), Some(c) if is_identifier_part(c))
+    }
+
+    /// True if the rest of the input can start the operand of an
+    /// AwaitExpression, or the `(` of `for await`. Otherwise `await` is an
+    /// identifier, as in `await;` or `await = 1`.
+    fn is_looking_at_await_operand(&self) -> bool {
+        match self.chars.clone().find(|c| !c.is_whitespace()) {
+            Some(c) => !matches!(
+                c,
+                ';' | ','
+                    | ')'
+                    | ']'
+                    | '}'
+                    | ':'
+                    | '?'
+                    | '='
+                    | '*'
+                    | '%'
+                    | '&'
+                    | '|'
+                    | '^'
+                    | '<'
+                    | '>'
+                    | '.'
+            ),
+            None => false,
+        }
     }
 
     /// ```text
@@ -459,11 +662,11 @@ impl<'alloc> Lexer<'alloc> {
         &mut self,
         start: usize,
         builder: AutoCow<'alloc>,
-    ) -> Result<'alloc, (SourceLocation, Option<&'alloc str>, TerminalId)> {
+    ) -> Result<'alloc, (SourceLocation, Option<&'alloc Wtf8>, TerminalId)> {
         let name = self.identifier_name(builder)?;
         Ok((
             SourceLocation::new(start, self.offset()),
-            Some(name),
+            Some(Wtf8::new(name)),
             TerminalId::PrivateIdentifier,
         ))
     }
@@ -480,10 +683,13 @@ impl<'alloc> Lexer<'alloc> {
                 return Err(ParseError::InvalidEscapeSequence);
             }
         }
-        self.unicode_escape_sequence_after_backslash_and_u()
+        let value = self.unicode_escape_sequence_after_backslash_and_u()?;
+        Self::code_point_to_char(value)
     }
 
-    fn unicode_escape_sequence_after_backslash_and_u(&mut self) -> Result<'alloc, char> {
+    /// Scan a UnicodeEscapeSequence, having already consumed the `\u`, and
+    /// return its code point, which may be a surrogate.
+    fn unicode_escape_sequence_after_backslash_and_u(&mut self) -> Result<'alloc, u32> {
         let value = match self.peek() {
             Some('{') => {
                 self.chars.next();
@@ -509,7 +715,7 @@ enum NumericType {
     BigInt,
 }
 
-impl<'alloc> Lexer<'alloc> {
+impl<'alloc, S: ?Sized + SourceText> Lexer<'alloc, S> {
     // ------------------------------------------------------------------------
     // 11.8.3 Numeric Literals
 
@@ -598,30 +804,25 @@ impl<'alloc> Lexer<'alloc> {
             Some(c @ '0'..='9') => Ok(c as u32 - '0' as u32),
             Some(c @ 'a'..='f') => Ok(10 + (c as u32 - 'a' as u32)),
             Some(c @ 'A'..='F') => Ok(10 + (c as u32 - 'A' as u32)),
-            Some(other) => Err(ParseError::IllegalCharacter(other)),
+            Some(other) => Err(self.illegal_character_err(other)),
         }
     }
 
+    /// Names can't contain surrogates, even as escape sequences.
     fn code_point_to_char(value: u32) -> Result<'alloc, char> {
-        if 0xd800 <= value && value <= 0xdfff {
-            Err(ParseError::NotImplemented(
-                "unicode escape sequences (surrogates)",
-            ))
-        } else {
-            char::try_from(value).map_err(|_| ParseError::InvalidEscapeSequence)
-        }
+        char::try_from(value).map_err(|_| ParseError::InvalidEscapeSequence)
     }
 
     /// ```text
     /// Hex4Digits ::
     ///     HexDigit HexDigit HexDigit HexDigit
     /// ```
-    fn hex_4_digits(&mut self) -> Result<'alloc, char> {
+    fn hex_4_digits(&mut self) -> Result<'alloc, u32> {
         let mut value = 0;
         for _ in 0..4 {
             value = (value << 4) | self.hex_digit()?;
         }
-        Self::code_point_to_char(value)
+        Ok(value)
     }
 
     /// ```text
@@ -632,7 +833,7 @@ impl<'alloc> Lexer<'alloc> {
     ///    HexDigit
     ///    HexDigits HexDigit
     /// ```
-    fn code_point(&mut self) -> Result<'alloc, char> {
+    fn code_point(&mut self) -> Result<'alloc, u32> {
         let mut value = self.hex_digit()?;
 
         loop {
@@ -652,7 +853,7 @@ impl<'alloc> Lexer<'alloc> {
             }
         }
 
-        Self::code_point_to_char(value)
+        Ok(value)
     }
 
     /// Scan a NumericLiteral (defined in 11.8.3, extended by B.1.1) after
@@ -850,16 +1051,25 @@ impl<'alloc> Lexer<'alloc> {
                 // NonOctalDigit :: one of
                 //     `8` `9`
                 //
-
-                // TODO: implement `strict_mode` check
-                // let strict_mode = true;
-                // if !strict_mode {
-                //     // TODO: Distinguish between Octal and NonOctalDecimal.
-                //     // TODO: Support NonOctalDecimal followed by a decimal
-                //     //       point and/or ExponentPart.
-                //     self.decimal_digits()?;
-                // }
-                return Err(ParseError::NotImplemented("LegacyOctalIntegerLiteral"));
+                // Whether the code is strict isn't known yet, and the
+                // AstBuilder rejects both literals when the parser reaches the
+                // end of strict mode code.
+                let mut is_octal = true;
+                while let Some(c) = self.peek() {
+                    match c {
+                        '0'..='7' => {}
+                        '8' | '9' => is_octal = false,
+                        _ => break,
+                    }
+                    self.chars.next();
+                }
+                if !is_octal && matches!(self.peek(), Some('.') | Some('e') | Some('E')) {
+                    // TODO: Support NonOctalDecimal followed by a decimal
+                    //       point and/or ExponentPart.
+                    return Err(ParseError::NotImplemented(
+                        "NonOctalDecimalIntegerLiteral with a fraction or exponent",
+                    ));
+                }
             }
 
             _ => {}
@@ -964,7 +1174,7 @@ impl<'alloc> Lexer<'alloc> {
     /// FourToSeven :: one of
     ///     `4` `5` `6` `7`
     /// ```
-    fn escape_sequence(&mut self, text: &mut String<'alloc>) -> Result<'alloc, ()> {
+    fn escape_sequence(&mut self, text: &mut Wtf8Buf<'alloc>) -> Result<'alloc, ()> {
         match self.chars.next() {
             None => {
                 return Err(ParseError::UnterminatedString);
@@ -1030,8 +1240,10 @@ impl<'alloc> Lexer<'alloc> {
                 }
 
                 'u' => {
-                    let c = self.unicode_escape_sequence_after_backslash_and_u()?;
-                    text.push(c);
+                    // A surrogate is kept as a code unit of the string value,
+                    // and joined with the other half if they're paired.
+                    let value = self.unicode_escape_sequence_after_backslash_and_u()?;
+                    text.push_code_point(value);
                 }
 
                 '0' => {
@@ -1069,7 +1281,7 @@ impl<'alloc> Lexer<'alloc> {
                     // "\8" and "\9" are invalid per spec, but SpiderMonkey and
                     // V8 accept them, and JSC accepts them in non-strict mode.
                     // "\8" is "8" and "\9" is "9".
-                    text.push(other);
+                    self.push_source_char(text, other);
                 }
             },
         }
@@ -1107,7 +1319,7 @@ impl<'alloc> Lexer<'alloc> {
     fn string_literal(
         &mut self,
         delimiter: char,
-    ) -> Result<'alloc, (SourceLocation, Option<&'alloc str>, TerminalId)> {
+    ) -> Result<'alloc, (SourceLocation, Option<&'alloc Wtf8>, TerminalId)> {
         let offset = self.offset() - 1;
         let mut builder = AutoCow::new(&self);
         loop {
@@ -1142,7 +1354,7 @@ impl<'alloc> Lexer<'alloc> {
                     //     DecimalDigit
                     //     `x`
                     //     `u`
-                    builder.push_matching(other);
+                    builder.push_matching_source_char(self, other);
                 }
             }
         }
@@ -1153,13 +1365,13 @@ impl<'alloc> Lexer<'alloc> {
 
     fn regular_expression_backslash_sequence(
         &mut self,
-        text: &mut String<'alloc>,
+        text: &mut Wtf8Buf<'alloc>,
     ) -> Result<'alloc, ()> {
         text.push('\\');
         match self.chars.next() {
             None | Some(CR) | Some(LF) | Some(LS) | Some(PS) => Err(ParseError::UnterminatedRegExp),
             Some(c) => {
-                text.push(c);
+                self.push_source_char(text, c);
                 Ok(())
             }
         }
@@ -1169,7 +1381,7 @@ impl<'alloc> Lexer<'alloc> {
     fn regular_expression_literal(
         &mut self,
         builder: &mut AutoCow<'alloc>,
-    ) -> Result<'alloc, (SourceLocation, Option<&'alloc str>, TerminalId)> {
+    ) -> Result<'alloc, (SourceLocation, Option<&'alloc Wtf8>, TerminalId)> {
         let offset = self.offset();
 
         loop {
@@ -1196,7 +1408,7 @@ impl<'alloc> Lexer<'alloc> {
                                 self.regular_expression_backslash_sequence(text)?;
                             }
                             Some(ch) => {
-                                builder.push_matching(ch);
+                                builder.push_matching_source_char(self, ch);
                             }
                         }
                     }
@@ -1207,7 +1419,7 @@ impl<'alloc> Lexer<'alloc> {
                     self.regular_expression_backslash_sequence(text)?;
                 }
                 Some(ch) => {
-                    builder.push_matching(ch);
+                    builder.push_matching_source_char(self, ch);
                 }
             }
         }
@@ -1233,7 +1445,9 @@ impl<'alloc> Lexer<'alloc> {
             .map(|x| 1 << ((*x as u8) - ('a' as u8)))
             .sum();
         let mut flag_text_set: u32 = 0;
-        for ch in flag_text.finish(&self).chars() {
+        for &byte in flag_text.finish(self).as_bytes() {
+            // Only ASCII characters are pushed to `flag_text`.
+            let ch = byte as char;
             if !ch.is_ascii_lowercase() {
                 return Err(ParseError::NotImplemented(
                     "Unexpected flag in regular expression literal",
@@ -1292,7 +1506,7 @@ impl<'alloc> Lexer<'alloc> {
         start: usize,
         subst: TerminalId,
         tail: TerminalId,
-    ) -> Result<'alloc, (SourceLocation, Option<&'alloc str>, TerminalId)> {
+    ) -> Result<'alloc, (SourceLocation, Option<&'alloc Wtf8>, TerminalId)> {
         let mut builder = AutoCow::new(&self);
         while let Some(ch) = self.chars.next() {
             // TemplateCharacter ::
@@ -1341,7 +1555,7 @@ impl<'alloc> Lexer<'alloc> {
                 let text = builder.get_mut_string_without_current_ascii_char(&self);
                 self.escape_sequence(text)?;
             } else {
-                builder.push_matching(ch);
+                builder.push_matching_source_char(self, ch);
             }
         }
         Err(ParseError::UnterminatedString)
@@ -1350,7 +1564,7 @@ impl<'alloc> Lexer<'alloc> {
     fn advance_impl<'parser>(
         &mut self,
         parser: &Parser<'parser>,
-    ) -> Result<'alloc, (SourceLocation, Option<&'alloc str>, TerminalId)> {
+    ) -> Result<'alloc, (SourceLocation, Option<&'alloc Wtf8>, TerminalId)> {
         let mut builder = AutoCow::new(&self);
         let mut start = self.offset();
         while let Some(c) = self.chars.next() {
@@ -1381,6 +1595,7 @@ impl<'alloc> Lexer<'alloc> {
                     // with category "Space_Separator" (Zs). New Unicode
                     // standards may add characters to this set. This should therefore be
                     // implemented using the Unicode database somehow.
+                    self.chars.skip_ascii_while(|byte| byte == b' ' || byte == b'\t');
                     builder = AutoCow::new(&self);
                     start = self.offset();
                     continue;
@@ -1471,7 +1686,13 @@ impl<'alloc> Lexer<'alloc> {
                 '&' => match self.peek() {
                     Some('&') => {
                         self.chars.next();
-                        return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::LogicalAnd));
+                        match self.peek() {
+                            Some('=') => {
+                                self.chars.next();
+                                return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::LogicalAndAssign));
+                            }
+                            _ => return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::LogicalAnd)),
+                        }
                     }
                     Some('=') => {
                         self.chars.next();
@@ -1681,7 +1902,13 @@ impl<'alloc> Lexer<'alloc> {
                 '|' => match self.peek() {
                     Some('|') => {
                         self.chars.next();
-                        return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::LogicalOr));
+                        match self.peek() {
+                            Some('=') => {
+                                self.chars.next();
+                                return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::LogicalOrAssign));
+                            }
+                            _ => return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::LogicalOr)),
+                        }
                     }
                     Some('=') => {
                         self.chars.next();
@@ -1693,7 +1920,13 @@ impl<'alloc> Lexer<'alloc> {
                 '?' => match self.peek() {
                     Some('?') => {
                         self.chars.next();
-                        return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::Coalesce));
+                        match self.peek() {
+                            Some('=') => {
+                                self.chars.next();
+                                return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::CoalesceAssign));
+                            }
+                            _ => return Ok((SourceLocation::new(start, self.offset()), None, TerminalId::Coalesce)),
+                        }
                     }
                     Some('.') => {
                         if let Some('0'..='9') = self.double_peek() {
@@ -1718,7 +1951,7 @@ impl<'alloc> Lexer<'alloc> {
                 // Idents
                 '$' | '_' | 'a'..='z' | 'A'..='Z' => {
                     builder.push_matching(c);
-                    return self.identifier_tail(start, builder);
+                    return self.identifier_tail(parser, start, builder);
                 }
 
                 '\\' => {
@@ -1730,7 +1963,7 @@ impl<'alloc> Lexer<'alloc> {
                     }
                     builder.push_different(value);
 
-                    return self.identifier_tail(start, builder);
+                    return self.identifier_tail(parser, start, builder);
                 }
 
                 '#' => {
@@ -1751,11 +1984,11 @@ impl<'alloc> Lexer<'alloc> {
 
                 other if is_identifier_start(other) => {
                     builder.push_matching(other);
-                    return self.identifier_tail(start, builder);
+                    return self.identifier_tail(parser, start, builder);
                 }
 
                 other => {
-                    return Err(ParseError::IllegalCharacter(other));
+                    return Err(self.illegal_character_err(other));
                 }
             }
         }
@@ -1768,14 +2001,15 @@ impl<'alloc> Lexer<'alloc> {
 }
 
 struct AutoCow<'alloc> {
-    start: &'alloc str,
-    value: Option<String<'alloc>>,
+    /// Position of the start of the text in the source, in source units.
+    start: usize,
+    value: Option<Wtf8Buf<'alloc>>,
 }
 
 impl<'alloc> AutoCow<'alloc> {
-    fn new(lexer: &Lexer<'alloc>) -> Self {
+    fn new<S: ?Sized + SourceText>(lexer: &Lexer<'alloc, S>) -> Self {
         AutoCow {
-            start: lexer.chars.as_str(),
+            start: lexer.chars.position(),
             value: None,
         }
     }
@@ -1787,6 +2021,34 @@ impl<'alloc> AutoCow<'alloc> {
         }
     }
 
+    // Push a char that matches lexer.chars.next(), which may be an unpaired
+    // surrogate.
+    fn push_matching_source_char<S: ?Sized + SourceText>(
+        &mut self,
+        lexer: &Lexer<'alloc, S>,
+        c: char,
+    ) {
+        if let Some(text) = &mut self.value {
+            lexer.push_source_char(text, c);
+        }
+    }
+
+    // Push the characters from `start` to the current position of the lexer,
+    // which have been skipped without calling push_matching.
+    fn push_matching_run<S: ?Sized + SourceText>(
+        &mut self,
+        lexer: &Lexer<'alloc, S>,
+        start: usize,
+    ) {
+        if let Some(text) = &mut self.value {
+            text.push_wtf8(
+                lexer
+                    .chars
+                    .slice(lexer.allocator, start, lexer.chars.position()),
+            );
+        }
+    }
+
     // Push a different character than lexer.chars.next().
     // force_allocation_without_current_ascii_char must be called before this.
     fn push_different(&mut self, c: char) {
@@ -1794,25 +2056,30 @@ impl<'alloc> AutoCow<'alloc> {
         self.value.as_mut().unwrap().push(c)
     }
 
-    // Force allocation of a String, excluding the current ASCII character,
+    // Force allocation of a Wtf8Buf, excluding the current ASCII character,
     // and return the reference to it
-    fn get_mut_string_without_current_ascii_char<'b>(
+    fn get_mut_string_without_current_ascii_char<'b, S: ?Sized + SourceText>(
         &'b mut self,
-        lexer: &'_ Lexer<'alloc>,
-    ) -> &'b mut String<'alloc> {
+        lexer: &'_ Lexer<'alloc, S>,
+    ) -> &'b mut Wtf8Buf<'alloc> {
         self.force_allocation_without_current_ascii_char(lexer);
         self.value.as_mut().unwrap()
     }
 
-    // Force allocation of a String, excluding the current ASCII character.
-    fn force_allocation_without_current_ascii_char(&mut self, lexer: &'_ Lexer<'alloc>) {
+    // Force allocation of a Wtf8Buf, excluding the current ASCII character.
+    fn force_allocation_without_current_ascii_char<S: ?Sized + SourceText>(
+        &mut self,
+        lexer: &'_ Lexer<'alloc, S>,
+    ) {
         if self.value.is_some() {
             return;
         }
 
-        self.value = Some(String::from_str_in(
-            &self.start[..self.start.len() - lexer.chars.as_str().len() - 1],
+        // An ASCII character takes one unit in all supported encodings.
+        self.value = Some(lexer.chars.slice_to_buf(
             lexer.allocator,
+            self.start,
+            lexer.chars.position() - 1,
         ));
     }
 
@@ -1822,18 +2089,41 @@ impl<'alloc> AutoCow<'alloc> {
         self.value.is_some()
     }
 
-    fn finish(&mut self, lexer: &Lexer<'alloc>) -> &'alloc str {
+    fn finish<S: ?Sized + SourceText>(&mut self, lexer: &Lexer<'alloc, S>) -> &'alloc Wtf8 {
         match self.value.take() {
-            Some(arena_string) => arena_string.into_bump_str(),
-            None => &self.start[..self.start.len() - lexer.chars.as_str().len()],
+            Some(buf) => buf.into_bump_wtf8(),
+            None => lexer
+                .chars
+                .slice(lexer.allocator, self.start, lexer.chars.position()),
+        }
+    }
+
+    // Just like finish, but for an IdentifierName, which is interned unless
+    // it can be borrowed from the source.
+    fn finish_name<S: ?Sized + SourceText>(&mut self, lexer: &Lexer<'alloc, S>) -> &'alloc str {
+        let atoms = lexer.atoms;
+        match self.value.take() {
+            Some(buf) => {
+                // Escape sequences in names can't be surrogates.
+                let name = buf.as_wtf8().as_str().unwrap();
+                atoms.get(atoms.intern(name))
+            }
+            None => lexer
+                .chars
+                .slice_atom(atoms, self.start, lexer.chars.position()),
         }
     }
 
     // Just like finish, but without pushing current char.
-    fn finish_without_push(&mut self, lexer: &Lexer<'alloc>) -> &'alloc str {
+    fn finish_without_push<S: ?Sized + SourceText>(
+        &mut self,
+        lexer: &Lexer<'alloc, S>,
+    ) -> &'alloc Wtf8 {
         match self.value.take() {
-            Some(arena_string) => arena_string.into_bump_str(),
-            None => &self.start[..self.start.len() - lexer.chars.as_str().len() - 1],
+            Some(buf) => buf.into_bump_wtf8(),
+            None => lexer
+                .chars
+                .slice(lexer.allocator, self.start, lexer.chars.position() - 1),
         }
     }
 }
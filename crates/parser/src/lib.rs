@@ -1,8 +1,12 @@
 #![cfg_attr(feature = "unstable", feature(test))]
 
+mod incremental;
 mod lexer;
 mod parser;
+mod session;
 mod simulator;
+mod source;
+mod speculative;
 
 #[cfg(test)]
 mod tests;
@@ -13,49 +17,216 @@ extern crate jsparagus_generated_parser as generated_parser;
 use crate::parser::Parser;
 use ast::{
     arena,
-    types::{Module, Script},
+    atom_table::AtomTable,
+    types::{Expression, FormalParameters, Function, FunctionBody, LazyFunction, Module, Script},
 };
 use bumpalo;
 use generated_parser::{
-    AstBuilder, StackValue, TerminalId, START_STATE_MODULE, START_STATE_SCRIPT, TABLES,
+    AstBuilder, StackValue, TerminalId, START_STATE_MODULE, START_STATE_SCRIPT,
+    START_STATE_STANDALONE_EXPRESSION, START_STATE_STANDALONE_FORMAL_PARAMETERS,
+    START_STATE_STANDALONE_FUNCTION_BODY,
 };
-pub use generated_parser::{ParseError, Result};
+pub use generated_parser::{LoadedTables, ParseError, Result};
+pub use incremental::{parse_script_incrementally, reparse_script, IncrementalScript, TextEdit};
 use lexer::Lexer;
+use source::SourceChars;
+pub use session::ParseSession;
+pub use source::{LocationUnit, SourceText};
+pub use speculative::{Checkpoint, SpeculativeParser};
+use std::rc::Rc;
 
-pub struct ParseOptions {}
+pub struct ParseOptions {
+    // Allow ReturnStatement at the top level of a script, like CommonJS
+    // modules.
+    pub allow_return_outside_function: bool,
+
+    // Check function bodies for syntax and Early Errors, but replace them
+    // with LazyFunction instead of building their ASTs. Use
+    // `parse_lazy_function` to get the full AST later.
+    pub lazy_inner_functions: bool,
+
+    // The unit of offsets in SourceLocation.
+    pub location_unit: LocationUnit,
+
+    // The maximum depth of the parser stack, which grows with each level of
+    // nested brackets, operators and statements, and the maximum length of
+    // chains of left-associative binary operators, member accesses and calls,
+    // like `a + b + c` or `a.b().c`. Deeper input is rejected with
+    // ParseError::TooDeeplyNested, instead of overflowing the native stack
+    // later, in the recursive passes over the AST.
+    pub max_nesting_depth: Option<usize>,
+
+    // The maximum number of tokens, above which the parse fails with
+    // ParseError::TooManyTokens.
+    pub max_tokens: Option<usize>,
+
+    // Called every few hundred tokens. If it returns true, the parse fails
+    // with ParseError::Cancelled.
+    pub is_cancelled: Option<Rc<dyn Fn() -> bool>>,
+
+    // Parse tables to use instead of the compiled ones, e.g. generated from
+    // a modified grammar and loaded with `LoadedTables::from_file`.
+    pub tables: Option<Rc<LoadedTables>>,
+}
 impl ParseOptions {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            allow_return_outside_function: false,
+            lazy_inner_functions: false,
+            location_unit: LocationUnit::Utf8,
+            max_nesting_depth: Some(DEFAULT_MAX_NESTING_DEPTH),
+            max_tokens: None,
+            is_cancelled: None,
+            tables: None,
+        }
     }
 }
 
-pub fn parse_script<'alloc>(
+/// The default `ParseOptions::max_nesting_depth`.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 1000;
+
+pub fn parse_script<'alloc, S: ?Sized + SourceText>(
     allocator: &'alloc bumpalo::Bump,
-    source: &'alloc str,
-    _options: &ParseOptions,
+    source: &'alloc S,
+    options: &ParseOptions,
 ) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
-    Ok(parse(allocator, source, START_STATE_SCRIPT)?.to_ast()?)
+    Ok(parse(allocator, source, options, START_STATE_SCRIPT)?.to_ast()?)
 }
 
-pub fn parse_module<'alloc>(
+pub fn parse_module<'alloc, S: ?Sized + SourceText>(
     allocator: &'alloc bumpalo::Bump,
-    source: &'alloc str,
-    _options: &ParseOptions,
+    source: &'alloc S,
+    options: &ParseOptions,
 ) -> Result<'alloc, arena::Box<'alloc, Module<'alloc>>> {
-    Ok(parse(allocator, source, START_STATE_MODULE)?.to_ast()?)
+    Ok(parse(allocator, source, options, START_STATE_MODULE)?.to_ast()?)
+}
+
+/// Parse `source` as a single Expression, outside of any function, with the
+/// Script goal.
+pub fn parse_expression<'alloc, S: ?Sized + SourceText>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc S,
+    options: &ParseOptions,
+) -> Result<'alloc, arena::Box<'alloc, Expression<'alloc>>> {
+    Ok(parse(
+        allocator,
+        source,
+        options,
+        START_STATE_STANDALONE_EXPRESSION,
+    )?
+    .to_ast()?)
+}
+
+/// Parse `source` as the FormalParameters of an ordinary function, like the
+/// parameter arguments of `new Function(...)`.
+pub fn parse_formal_parameters<'alloc, S: ?Sized + SourceText>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc S,
+    options: &ParseOptions,
+) -> Result<'alloc, arena::Box<'alloc, FormalParameters<'alloc>>> {
+    Ok(parse(
+        allocator,
+        source,
+        options,
+        START_STATE_STANDALONE_FORMAL_PARAMETERS,
+    )?
+    .to_ast()?)
+}
+
+/// Parse `source` as the FunctionBody of an ordinary function without
+/// parameters, like the body argument of `new Function(...)`.
+pub fn parse_function_body<'alloc, S: ?Sized + SourceText>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc S,
+    options: &ParseOptions,
+) -> Result<'alloc, arena::Box<'alloc, FunctionBody<'alloc>>> {
+    Ok(parse(
+        allocator,
+        source,
+        options,
+        START_STATE_STANDALONE_FUNCTION_BODY,
+    )?
+    .to_ast()?)
+}
+
+/// Fully parse the function replaced by `lazy` when `source` was parsed with
+/// `ParseOptions::lazy_inner_functions`. `source` and the `location_unit` of
+/// `options` must be the same as in that parse.
+///
+/// The function is parsed in the context recorded in `lazy`: as module code,
+/// strict mode code, and with the PrivateIdentifiers of the enclosing classes.
+/// Functions nested inside it are parsed eagerly, and source locations in the
+/// result are relative to the whole `source`.
+pub fn parse_lazy_function<'alloc, S: ?Sized + SourceText>(
+    allocator: &'alloc bumpalo::Bump,
+    source: &'alloc S,
+    lazy: &LazyFunction<'alloc>,
+    options: &ParseOptions,
+) -> Result<'alloc, arena::Box<'alloc, Function<'alloc>>> {
+    let options = ParseOptions {
+        location_unit: options.location_unit,
+        max_nesting_depth: options.max_nesting_depth,
+        max_tokens: options.max_tokens,
+        is_cancelled: options.is_cancelled.clone(),
+        tables: options.tables.clone(),
+        ..ParseOptions::new()
+    };
+    let chars =
+        SourceChars::with_range(source, options.location_unit, lazy.loc.start(), lazy.loc.end());
+    let tokens = Lexer::with_chars(allocator, chars);
+
+    let mut builder = new_builder(allocator, &options);
+    builder.is_module = lazy.is_module;
+    builder.strict_mode = lazy.is_strict;
+    builder.enclosing_private_names = lazy.private_names.iter().map(|name| name.value).collect();
+
+    // Both FunctionDeclaration and FunctionExpression have the same syntax
+    // as a FunctionExpression.
+    let expression: arena::Box<'alloc, Expression<'alloc>> =
+        parse_tokens(builder, tokens, &options, START_STATE_STANDALONE_EXPRESSION)?.to_ast()?;
+    match expression.unbox() {
+        Expression::FunctionExpression(function) => Ok(function),
+        _ => Err(ParseError::AstError(String::from(
+            "LazyFunction doesn't point to a function",
+        ))),
+    }
 }
 
-fn parse<'alloc>(
+fn parse<'alloc, S: ?Sized + SourceText>(
     allocator: &'alloc bumpalo::Bump,
-    source: &'alloc str,
+    source: &'alloc S,
+    options: &ParseOptions,
     start_state: usize,
 ) -> Result<'alloc, StackValue<'alloc>> {
-    let mut tokens = Lexer::new(allocator, source.chars());
-
-    TABLES.check();
+    let atoms = allocator.alloc(AtomTable::new_in(allocator));
+    parse_with_atoms(allocator, atoms, source, options, start_state)
+}
 
-    let mut parser = Parser::new(AstBuilder::new(allocator), start_state);
+// Names that can't be borrowed from `source` are interned in `atoms`, which
+// can be shared by several parses.
+fn parse_with_atoms<'alloc, S: ?Sized + SourceText>(
+    allocator: &'alloc bumpalo::Bump,
+    atoms: &'alloc AtomTable<'alloc>,
+    source: &'alloc S,
+    options: &ParseOptions,
+    start_state: usize,
+) -> Result<'alloc, StackValue<'alloc>> {
+    let chars = SourceChars::new(source, options.location_unit);
+    parse_tokens(
+        new_builder(allocator, options),
+        Lexer::with_atoms(allocator, atoms, chars),
+        options,
+        start_state,
+    )
+}
 
+fn parse_tokens<'alloc, S: ?Sized + SourceText>(
+    builder: AstBuilder<'alloc>,
+    mut tokens: Lexer<'alloc, S>,
+    options: &ParseOptions,
+    start_state: usize,
+) -> Result<'alloc, StackValue<'alloc>> {
+    let mut parser = new_parser_with(builder, options, start_state);
     loop {
         let t = tokens.next(&parser)?;
         if t.terminal_id == TerminalId::End {
@@ -66,12 +237,41 @@ fn parse<'alloc>(
     parser.close(tokens.offset())
 }
 
-pub fn is_partial_script<'alloc>(
+fn new_parser<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    options: &ParseOptions,
+    start_state: usize,
+) -> Parser<'alloc> {
+    new_parser_with(new_builder(allocator, options), options, start_state)
+}
+
+fn new_builder<'alloc>(
+    allocator: &'alloc bumpalo::Bump,
+    options: &ParseOptions,
+) -> AstBuilder<'alloc> {
+    let mut builder = AstBuilder::new(allocator);
+    builder.allow_return_outside_function = options.allow_return_outside_function;
+    builder.lazy_inner_functions = options.lazy_inner_functions;
+    builder.max_nesting_depth = options.max_nesting_depth;
+    builder
+}
+
+fn new_parser_with<'alloc>(
+    builder: AstBuilder<'alloc>,
+    options: &ParseOptions,
+    start_state: usize,
+) -> Parser<'alloc> {
+    let mut parser = Parser::with_tables(builder, start_state, options.tables.clone());
+    parser.set_limits(options);
+    parser
+}
+
+pub fn is_partial_script<'alloc, S: ?Sized + SourceText>(
     allocator: &'alloc bumpalo::Bump,
-    source: &'alloc str,
+    source: &'alloc S,
 ) -> Result<'alloc, bool> {
     let mut parser = Parser::new(AstBuilder::new(allocator), START_STATE_SCRIPT);
-    let mut tokens = Lexer::new(allocator, source.chars());
+    let mut tokens = Lexer::new(allocator, source);
     loop {
         let t = tokens.next(&parser)?;
         if t.terminal_id == TerminalId::End {
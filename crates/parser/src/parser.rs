@@ -1,15 +1,23 @@
 use crate::simulator::Simulator;
-use ast::SourceLocation;
+use crate::ParseOptions;
+use ast::{arena::CloneIn, SourceLocation};
+use bumpalo::Bump;
 use generated_parser::{
-    reduce, AstBuilder, ErrorCode, ParseError, Result, StackValue, TerminalId, Token, TABLES,
+    reduce, AstBuilder, ErrorCode, InlineToken, LoadedTables, NonterminalId, ParseError,
+    ParserTables, Result, StackValue, TerminalId, Token, START_STATE_MODULE, START_STATE_SCRIPT,
+    TABLES,
 };
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-const SPECIAL_CASE_MASK: i64 = 0x3fff_ffff_ffff_ffff;
-const ACCEPT: i64 = 0x_bfff_ffff_ffff_ffff_u64 as i64;
+// The encoding of the actions in the parse tables, described in
+// jsparagus/emit/rust.py.
+const SPECIAL_CASE_MASK: i64 = 0x3fff;
+const ACCEPT: i64 = -0x4000;
 const ERROR: i64 = ACCEPT - 1;
 
 #[derive(Clone, Copy)]
-pub(crate) struct Action(i64);
+pub(crate) struct Action(pub(crate) i64);
 
 impl Action {
     pub(crate) fn is_shift(self) -> bool {
@@ -47,53 +55,180 @@ impl Action {
     }
 }
 
+// How many tokens are written between two calls to the `is_cancelled`
+// callback.
+const CANCELLATION_CHECK_INTERVAL: usize = 256;
+
 pub struct Parser<'alloc> {
     state_stack: Vec<usize>,
     node_stack: Vec<StackValue<'alloc>>,
     handler: AstBuilder<'alloc>,
+
+    // Resource limits from ParseOptions.
+    max_nesting_depth: Option<usize>,
+    max_tokens: Option<usize>,
+    is_cancelled: Option<Rc<dyn Fn() -> bool>>,
+
+    // The number of tokens written so far.
+    token_count: usize,
+
+    // Parse tables to use instead of TABLES.
+    loaded_tables: Option<Rc<LoadedTables>>,
+
+    undo_log: UndoLog<'alloc>,
+}
+
+// What is needed to restore the checkpoints of a Parser.
+struct UndoLog<'alloc> {
+    // The values and states replaced since the oldest checkpoint that can
+    // still be restored, in order. Each is logged with its index on its
+    // stack, the first time it is popped or overwritten after a checkpoint.
+    entries: Vec<Undo<'alloc>>,
+
+    // The number of values and states at the bottom of the stacks that are
+    // unchanged since the last checkpoint, and so are shared with it.
+    shared_nodes: usize,
+    shared_states: usize,
+
+    // The checkpoints that can still be restored, oldest first. Restoring a
+    // checkpoint discards the ones taken after it.
+    checkpoint_ids: Vec<usize>,
+}
+
+// Checkpoint ids are unique across parsers, so that a checkpoint can't be
+// restored to another parser.
+static NEXT_CHECKPOINT_ID: AtomicUsize = AtomicUsize::new(0);
+
+enum Undo<'alloc> {
+    Node(usize, StackValue<'alloc>),
+    State(usize, usize),
+}
+
+/// The state of a Parser saved by `Parser::checkpoint`.
+///
+/// The values on the stack aren't copied when the checkpoint is taken.
+/// Instead, the parser logs each of them the first time it's popped, and
+/// gives the reduce action a deep copy, which the action can modify.
+/// Restoring the checkpoint puts the logged values back.
+#[derive(Clone)]
+pub struct ParserCheckpoint<'alloc> {
+    id: usize,
+    node_count: usize,
+    state_count: usize,
+    undo_len: usize,
+    handler: AstBuilder<'alloc>,
+    token_count: usize,
+}
+
+// A function rather than a method, so that the tables can be borrowed at the
+// same time as other fields of the Parser.
+fn tables_of(loaded_tables: &Option<Rc<LoadedTables>>) -> ParserTables<'_> {
+    match loaded_tables {
+        Some(loaded_tables) => loaded_tables.tables(),
+        None => TABLES,
+    }
 }
 
 impl<'alloc> Parser<'alloc> {
     pub fn new(handler: AstBuilder<'alloc>, entry_state: usize) -> Self {
-        TABLES.check();
-        assert!(entry_state < TABLES.state_count);
+        Self::with_tables(handler, entry_state, None)
+    }
+
+    /// Create a parser that uses `loaded_tables` if present, instead of the
+    /// compiled tables. `entry_state` is one of the start states of the
+    /// compiled tables, e.g. START_STATE_SCRIPT, either way.
+    pub fn with_tables(
+        mut handler: AstBuilder<'alloc>,
+        entry_state: usize,
+        loaded_tables: Option<Rc<LoadedTables>>,
+    ) -> Self {
+        if entry_state == START_STATE_MODULE {
+            handler.is_module = true;
+        }
+        let entry_state = match &loaded_tables {
+            Some(loaded_tables) => loaded_tables.start_state(entry_state),
+            None => entry_state,
+        };
+        let tables = tables_of(&loaded_tables);
+        tables.check();
+        assert!(entry_state < tables.state_count);
 
         Self {
             state_stack: vec![entry_state],
             node_stack: vec![],
             handler,
+            max_nesting_depth: None,
+            max_tokens: None,
+            is_cancelled: None,
+            token_count: 0,
+            loaded_tables,
+            undo_log: UndoLog::new(),
+        }
+    }
+
+    pub fn set_limits(&mut self, options: &ParseOptions) {
+        self.max_nesting_depth = options.max_nesting_depth;
+        self.max_tokens = options.max_tokens;
+        self.is_cancelled = options.is_cancelled.clone();
+    }
+
+    fn check_limits(&mut self, token: &Token<'alloc>) -> Result<'alloc, ()> {
+        self.token_count += 1;
+        if let Some(max_tokens) = self.max_tokens {
+            if self.token_count > max_tokens {
+                return Err(ParseError::TooManyTokens(token.loc.start()));
+            }
+        }
+        // `usize::is_multiple_of` would require Rust 1.87.
+        #[allow(clippy::manual_is_multiple_of)]
+        if self.token_count % CANCELLATION_CHECK_INTERVAL == 0 {
+            if let Some(is_cancelled) = &self.is_cancelled {
+                if is_cancelled() {
+                    return Err(ParseError::Cancelled);
+                }
+            }
         }
+        Ok(())
     }
 
     fn state(&self) -> usize {
         *self.state_stack.last().unwrap()
     }
 
+    fn tables(&self) -> ParserTables<'_> {
+        tables_of(&self.loaded_tables)
+    }
+
     fn action(&self, t: TerminalId) -> Action {
-        Self::action_at_state(t, self.state())
+        Self::action_at_state(&self.tables(), t, self.state())
     }
 
-    pub(crate) fn action_at_state(t: TerminalId, state: usize) -> Action {
+    pub(crate) fn action_at_state(tables: &ParserTables, t: TerminalId, state: usize) -> Action {
         let t = t as usize;
-        debug_assert!(t < TABLES.action_width);
-        debug_assert!(state < TABLES.state_count);
-        Action(TABLES.action_table[state * TABLES.action_width + t])
+        debug_assert!(t < tables.action_width);
+        debug_assert!(state < tables.state_count);
+        Action(tables.action(state, t))
     }
 
     fn reduce_all(&mut self, t: TerminalId, mut action: Action) -> Result<'alloc, Action> {
-        let tables = TABLES;
+        let tables = tables_of(&self.loaded_tables);
         while action.is_reduce() {
             let prod_index = action.reduce_prod_index();
-            let nt = reduce(&mut self.handler, prod_index, &mut self.node_stack)?;
-            debug_assert!((nt as usize) < tables.goto_width);
+            let reduce_action = tables.reduce_action(prod_index);
+            let (num_pops, nt) = tables.reduce_simulator[prod_index];
+            self.undo_log
+                .unshare_nodes(&mut self.node_stack, num_pops, self.handler.allocator);
+            reduce(&mut self.handler, reduce_action, &mut self.node_stack)?;
+            debug_assert!(nt < tables.goto_width);
             debug_assert!(self.state_stack.len() >= self.node_stack.len());
+            self.undo_log
+                .unshare_states(&self.state_stack, self.node_stack.len());
             self.state_stack.truncate(self.node_stack.len());
             let prev_state = *self.state_stack.last().unwrap();
-            let state_after =
-                tables.goto_table[prev_state * tables.goto_width + nt as usize] as usize;
+            let state_after = tables.goto(prev_state, nt);
             debug_assert!(state_after < tables.state_count);
             self.state_stack.push(state_after);
-            action = self.action(t);
+            action = Self::action_at_state(&tables, t, state_after);
         }
 
         debug_assert_eq!(self.state_stack.len(), self.node_stack.len() + 1);
@@ -101,18 +236,29 @@ impl<'alloc> Parser<'alloc> {
     }
 
     pub fn write_token(&mut self, token: &Token<'alloc>) -> Result<'alloc, ()> {
+        self.check_limits(token)?;
+
         // Loop for error-handling. The normal path through this code reaches
         // the `return` statement.
         let mut action = self.action(token.terminal_id);
         loop {
             action = self.reduce_all(token.terminal_id, action)?;
             if action.is_shift() {
-                self.node_stack
-                    .push(StackValue::Token(self.handler.alloc(token.clone())));
+                if let Some(max_nesting_depth) = self.max_nesting_depth {
+                    if self.state_stack.len() >= max_nesting_depth {
+                        return Err(ParseError::TooDeeplyNested(token.loc.start()));
+                    }
+                }
+                let value = match InlineToken::new(token) {
+                    Some(inline) => StackValue::InlineToken(inline),
+                    None => StackValue::Token(self.handler.alloc(token.clone())),
+                };
+                self.node_stack.push(value);
                 self.state_stack.push(action.shift_state());
                 return Ok(());
             } else if action.is_special_case() {
-                action = Action(TABLES.special_cases[action.special_case_index()](token));
+                let special_case = self.tables().special_cases[action.special_case_index()];
+                action = Action(special_case.action(token));
             } else {
                 assert!(action.is_error());
                 self.try_error_handling(token)?;
@@ -122,6 +268,10 @@ impl<'alloc> Parser<'alloc> {
     }
 
     pub fn close(&mut self, position: usize) -> Result<'alloc, StackValue<'alloc>> {
+        // The reduce actions for the goal symbols pop the whole AST, which
+        // would be copied for the checkpoints, so they are discarded instead.
+        self.undo_log = UndoLog::new();
+
         // Loop for error-handling.
         loop {
             let mut action = self.action(TerminalId::End);
@@ -154,7 +304,7 @@ impl<'alloc> Parser<'alloc> {
         action = self.reduce_all(TerminalId::ErrorToken, action)?;
         if action.is_shift() {
             let state = *self.state_stack.last().unwrap();
-            let error_code = TABLES.error_codes[state]
+            let error_code = self.tables().error_codes[state]
                 .as_ref()
                 .expect("state that accepts an ErrorToken must have an error_code")
                 .clone();
@@ -182,6 +332,8 @@ impl<'alloc> Parser<'alloc> {
                     // Don't actually push an ErrorToken onto the stack here. Treat the
                     // ErrorToken as having been consumed and move to the recovered
                     // state.
+                    self.undo_log
+                        .unshare_states(&self.state_stack, self.state_stack.len() - 1);
                     *self.state_stack.last_mut().unwrap() = next_state;
                     Ok(())
                 } else {
@@ -190,6 +342,8 @@ impl<'alloc> Parser<'alloc> {
             }
             ErrorCode::DoWhileAsi => {
                 // do-while always succeeds in inserting a semicolon.
+                self.undo_log
+                    .unshare_states(&self.state_stack, self.state_stack.len() - 1);
                 *self.state_stack.last_mut().unwrap() = next_state;
                 Ok(())
             }
@@ -197,7 +351,7 @@ impl<'alloc> Parser<'alloc> {
     }
 
     fn simulator(&self) -> Simulator {
-        Simulator::new(&self.state_stack)
+        Simulator::new(&self.state_stack, self.tables())
     }
 
     pub fn can_accept_terminal(&self, t: TerminalId) -> bool {
@@ -207,8 +361,127 @@ impl<'alloc> Parser<'alloc> {
             .is_ok()
     }
 
+    /// Return true if the tokens written so far form complete items of the
+    /// top-level StatementList of a Script, and `token` starts a new one.
+    pub fn is_at_script_statement_start(&self, token: &Token<'alloc>) -> bool {
+        // START_STATE_SCRIPT and NonterminalId are those of the compiled
+        // tables.
+        if self.state_stack[0] != START_STATE_SCRIPT || self.loaded_tables.is_some() {
+            return false;
+        }
+
+        let statement_list_state =
+            TABLES.goto(START_STATE_SCRIPT, NonterminalId::StatementList as usize);
+        match self.simulator().reduce_before_shift(token) {
+            Ok((0, state)) => state == START_STATE_SCRIPT,
+            Ok((1, state)) => state == statement_list_state,
+            _ => false,
+        }
+    }
+
+    /// True if the parser was created with the Module goal symbol.
+    pub fn is_module(&self) -> bool {
+        self.handler.is_module
+    }
+
+    pub fn handler_mut(&mut self) -> &mut AstBuilder<'alloc> {
+        &mut self.handler
+    }
+
     /// Return true if self.close() would succeed.
     pub fn can_close(&self) -> bool {
         self.simulator().close(0).is_ok()
     }
+
+    /// Save the current state, to be restored with `restore`. This takes
+    /// constant time, apart from copying the information about the enclosing
+    /// contexts kept by the AstBuilder.
+    pub fn checkpoint(&mut self) -> ParserCheckpoint<'alloc> {
+        let id = NEXT_CHECKPOINT_ID.fetch_add(1, Ordering::Relaxed);
+        let log = &mut self.undo_log;
+        log.checkpoint_ids.push(id);
+        log.shared_nodes = self.node_stack.len();
+        log.shared_states = self.state_stack.len();
+        ParserCheckpoint {
+            id,
+            node_count: self.node_stack.len(),
+            state_count: self.state_stack.len(),
+            undo_len: log.entries.len(),
+            handler: self.handler.clone(),
+            token_count: self.token_count,
+        }
+    }
+
+    /// Go back to the state saved in `checkpoint`, which must come from this
+    /// parser. The checkpoints taken after it can't be restored anymore, and
+    /// neither can any checkpoint after `close`.
+    pub fn restore(&mut self, checkpoint: ParserCheckpoint<'alloc>) {
+        let log = &mut self.undo_log;
+        let index = log
+            .checkpoint_ids
+            .iter()
+            .position(|&id| id == checkpoint.id)
+            .expect("checkpoint was discarded, or comes from another parser");
+        log.checkpoint_ids.truncate(index + 1);
+
+        while log.entries.len() > checkpoint.undo_len {
+            match log.entries.pop().unwrap() {
+                Undo::Node(index, value) => {
+                    self.node_stack.truncate(index);
+                    self.node_stack.push(value);
+                }
+                Undo::State(index, state) => {
+                    self.state_stack.truncate(index);
+                    self.state_stack.push(state);
+                }
+            }
+        }
+        self.node_stack.truncate(checkpoint.node_count);
+        self.state_stack.truncate(checkpoint.state_count);
+        debug_assert_eq!(self.node_stack.len(), checkpoint.node_count);
+        debug_assert_eq!(self.state_stack.len(), checkpoint.state_count);
+        log.shared_nodes = checkpoint.node_count;
+        log.shared_states = checkpoint.state_count;
+        self.handler = checkpoint.handler;
+        self.token_count = checkpoint.token_count;
+    }
+}
+
+impl<'alloc> UndoLog<'alloc> {
+    fn new() -> Self {
+        Self {
+            entries: vec![],
+            shared_nodes: 0,
+            shared_states: 0,
+            checkpoint_ids: vec![],
+        }
+    }
+
+    // Before the top `num_pops` values are popped, log the ones shared with a
+    // checkpoint, and replace them with deep copies. The time taken is
+    // proportional to their size, but each value is copied at most once per
+    // checkpoint.
+    fn unshare_nodes(
+        &mut self,
+        node_stack: &mut [StackValue<'alloc>],
+        num_pops: usize,
+        allocator: &'alloc Bump,
+    ) {
+        let start = node_stack.len() - num_pops;
+        for index in (start..self.shared_nodes).rev() {
+            let copy = node_stack[index].clone_in(allocator);
+            let value = std::mem::replace(&mut node_stack[index], copy);
+            self.entries.push(Undo::Node(index, value));
+        }
+        self.shared_nodes = self.shared_nodes.min(start);
+    }
+
+    // Before the states from `start` up are popped or overwritten, log the
+    // ones shared with a checkpoint.
+    fn unshare_states(&mut self, state_stack: &[usize], start: usize) {
+        for index in (start..self.shared_states).rev() {
+            self.entries.push(Undo::State(index, state_stack[index]));
+        }
+        self.shared_states = self.shared_states.min(start);
+    }
 }
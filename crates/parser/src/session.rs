@@ -0,0 +1,104 @@
+//! Parsing many sources in turn, reusing memory.
+
+use crate::parse_with_atoms;
+use crate::source::SourceText;
+use crate::ParseOptions;
+use ast::{
+    arena,
+    atom_table::AtomTable,
+    types::{Module, Script},
+};
+use bumpalo::Bump;
+use generated_parser::{Result, START_STATE_MODULE, START_STATE_SCRIPT};
+use std::cell::Cell;
+use std::ptr::NonNull;
+
+/// Owns the arena that ASTs are allocated in, so that it can be reused for
+/// one source after another, like the driver does for a directory.
+///
+/// The names that have to be copied out of the sources are interned in an
+/// `AtomTable` shared by all the parses until the next `reset`, so a name
+/// found in several sources is only allocated once.
+///
+/// The AST holds these names as strings, not as `Atom`s: names that can be
+/// borrowed from the source aren't interned at all, and the emitter's
+/// `get_atom_index` keeps its own table of strings for each script.
+pub struct ParseSession {
+    allocator: Bump,
+
+    /// The AtomTable, allocated in `allocator` by the first parse since the
+    /// last reset.
+    atoms: Cell<Option<NonNull<AtomTable<'static>>>>,
+}
+
+impl ParseSession {
+    pub fn new() -> Self {
+        Self {
+            allocator: Bump::new(),
+            atoms: Cell::new(None),
+        }
+    }
+
+    pub fn allocator(&self) -> &Bump {
+        &self.allocator
+    }
+
+    /// The AtomTable shared by the parses since the last reset.
+    pub fn atoms<'alloc>(&'alloc self) -> &'alloc AtomTable<'alloc> {
+        if let Some(atoms) = self.atoms.get() {
+            // The table is in the arena, which is only reset through
+            // `&mut self`, after clearing `self.atoms`.
+            return unsafe { atoms.cast::<AtomTable<'alloc>>().as_ref() };
+        }
+        let atoms: &'alloc AtomTable<'alloc> =
+            self.allocator.alloc(AtomTable::new_in(&self.allocator));
+        self.atoms.set(Some(NonNull::from(atoms).cast()));
+        atoms
+    }
+
+    pub fn parse_script<'alloc, S: ?Sized + SourceText>(
+        &'alloc self,
+        source: &'alloc S,
+        options: &ParseOptions,
+    ) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
+        let atoms = self.atoms();
+        let result = parse_with_atoms(&self.allocator, atoms, source, options, START_STATE_SCRIPT)?;
+        Ok(result.to_ast()?)
+    }
+
+    pub fn parse_module<'alloc, S: ?Sized + SourceText>(
+        &'alloc self,
+        source: &'alloc S,
+        options: &ParseOptions,
+    ) -> Result<'alloc, arena::Box<'alloc, Module<'alloc>>> {
+        let atoms = self.atoms();
+        let result = parse_with_atoms(&self.allocator, atoms, source, options, START_STATE_MODULE)?;
+        Ok(result.to_ast()?)
+    }
+
+    /// Free everything parsed so far, including the AtomTable. The largest
+    /// block of memory of the arena is kept for the next parse, instead of
+    /// being returned to the system allocator.
+    pub fn reset(&mut self) {
+        self.atoms.set(None);
+        self.allocator.reset();
+    }
+
+    /// Bytes allocated in the arena since the last reset, not counting the
+    /// unused space at the end of each block.
+    pub fn allocated_bytes(&mut self) -> usize {
+        let mut total = 0;
+        // Only the lengths of the blocks are looked at, never their contents.
+        unsafe {
+            self.allocator
+                .each_allocated_chunk(|chunk| total += chunk.len());
+        }
+        total
+    }
+}
+
+impl Default for ParseSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
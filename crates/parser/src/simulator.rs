@@ -6,42 +6,42 @@
 
 use crate::parser::{Action, Parser};
 use ast::SourceLocation;
-use generated_parser::{ErrorCode, Result, TerminalId, Token, TABLES};
+use generated_parser::{ErrorCode, ParserTables, Result, TerminalId, Token};
 
 pub struct Simulator<'parser> {
     sp: usize,
     state: usize,
     state_stack: &'parser [usize],
+    tables: ParserTables<'parser>,
 }
 
 impl<'parser> Simulator<'parser> {
-    pub fn new(state_stack: &'parser [usize]) -> Simulator {
+    pub fn new(state_stack: &'parser [usize], tables: ParserTables<'parser>) -> Simulator<'parser> {
         let sp = state_stack.len() - 1;
         Simulator {
             sp,
             state: state_stack[sp],
             state_stack,
+            tables,
         }
     }
 
     fn action(&self, t: TerminalId) -> Action {
-        Parser::action_at_state(t, self.state)
+        Parser::action_at_state(&self.tables, t, self.state)
     }
 
     // Simulate the action of Parser::reduce_all without calling any AstBuilder
     // methods or modifying the Parser state. Naturally, "early errors"
     // detected by AstBuilder methods are not caught.
-    fn reduce_all(&mut self, t: TerminalId) -> Action {
-        let mut action = self.action(t);
+    fn reduce_all(&mut self, t: TerminalId, mut action: Action) -> Action {
         while action.is_reduce() {
             let prod_index = action.reduce_prod_index();
-            let (num_pops, nt) = TABLES.reduce_simulator[prod_index];
-            debug_assert!((nt as usize) < TABLES.goto_width);
+            let (num_pops, nt) = self.tables.reduce_simulator[prod_index];
+            debug_assert!(nt < self.tables.goto_width);
             self.sp -= num_pops;
             let prev_state = self.state_stack[self.sp];
-            let state_after =
-                TABLES.goto_table[prev_state * TABLES.goto_width + nt as usize] as usize;
-            debug_assert!(state_after < TABLES.state_count);
+            let state_after = self.tables.goto(prev_state, nt);
+            debug_assert!(state_after < self.tables.state_count);
             self.sp += 1;
             self.state = state_after;
             action = self.action(t);
@@ -49,16 +49,31 @@ impl<'parser> Simulator<'parser> {
         action
     }
 
-    pub fn write_token<'alloc>(mut self, token: &Token<'alloc>) -> Result<'alloc, ()> {
+    pub fn write_token<'alloc>(self, token: &Token<'alloc>) -> Result<'alloc, ()> {
+        self.reduce_before_shift(token).map(|_| ())
+    }
+
+    // Simulate the reductions done by Parser::write_token before `token` is
+    // shifted, and return the index and the state of the top of the stack at
+    // that point.
+    pub fn reduce_before_shift<'alloc>(
+        mut self,
+        token: &Token<'alloc>,
+    ) -> Result<'alloc, (usize, usize)> {
         // Loop for error-handling. The normal path through this code reaches
         // the `return` statement.
+        let mut action = self.action(token.terminal_id);
         loop {
-            let action = self.reduce_all(token.terminal_id);
+            action = self.reduce_all(token.terminal_id, action);
             if action.is_shift() {
-                return Ok(());
+                return Ok((self.sp, self.state));
+            } else if action.is_special_case() {
+                let special_case = self.tables.special_cases[action.special_case_index()];
+                action = Action(special_case.action(token));
             } else {
                 assert!(action.is_error());
                 self.try_error_handling(token)?;
+                action = self.action(token.terminal_id);
             }
         }
     }
@@ -66,7 +81,7 @@ impl<'parser> Simulator<'parser> {
     pub fn close(mut self, position: usize) -> Result<'static, ()> {
         // Loop for error-handling.
         loop {
-            let action = self.reduce_all(TerminalId::End);
+            let action = self.reduce_all(TerminalId::End, self.action(TerminalId::End));
             if action.is_accept() {
                 assert_eq!(self.sp, 1);
                 return Ok(());
@@ -82,9 +97,9 @@ impl<'parser> Simulator<'parser> {
     fn try_error_handling<'alloc>(&mut self, t: &Token<'alloc>) -> Result<'alloc, ()> {
         assert!(t.terminal_id != TerminalId::ErrorToken);
 
-        let action = self.reduce_all(TerminalId::ErrorToken);
+        let action = self.reduce_all(TerminalId::ErrorToken, self.action(TerminalId::ErrorToken));
         if action.is_shift() {
-            let error_code = TABLES.error_codes[self.state]
+            let error_code = self.tables.error_codes[self.state]
                 .as_ref()
                 .expect("state that accepts an ErrorToken must have an error_code")
                 .clone();
@@ -0,0 +1,385 @@
+//! Source text in the encodings accepted by the lexer.
+
+use ast::atom_table::AtomTable;
+use ast::wtf8::{Wtf8, Wtf8Buf};
+use bumpalo::{collections::String, Bump};
+use std::char;
+
+/// The unit in which offsets in `SourceLocation` are counted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LocationUnit {
+    /// Bytes of the UTF-8 encoding of the source.
+    Utf8,
+
+    /// Code units of the UTF-16 encoding of the source. For Latin-1 source,
+    /// this is the same as the index of the byte.
+    Utf16,
+}
+
+/// A buffer of JS source text.
+///
+/// The lexer reads the source one character at a time, so embedders don't
+/// have to transcode the whole buffer to UTF-8 before parsing it.
+pub trait SourceText {
+    /// The unit in which the buffer is indexed.
+    const NATIVE_UNIT: LocationUnit;
+
+    /// The length of the buffer, in its own units.
+    fn len_units(&self) -> usize;
+
+    /// Decode the character starting at `index`, and return it with the
+    /// number of units it takes. `index` must be less than `len_units()`.
+    ///
+    /// Unpaired surrogates are decoded as U+FFFD REPLACEMENT CHARACTER; use
+    /// `unpaired_surrogate_at` to tell them apart from that character.
+    fn decode_at(&self, index: usize) -> (char, usize);
+
+    /// Return the code unit at `index` if it's a surrogate that isn't part of
+    /// a pair. Only UTF-16 source can contain them.
+    fn unpaired_surrogate_at(&self, _index: usize) -> Option<u16> {
+        None
+    }
+
+    /// Return the unit at `index` if it's an ASCII character, without
+    /// decoding it. ASCII characters take one unit in all encodings.
+    /// `index` must be less than `len_units()`.
+    fn ascii_at(&self, index: usize) -> Option<u8>;
+
+    /// Return the text in the range [start, end) without copying, if the
+    /// encoding allows it.
+    fn try_as_str(&self, start: usize, end: usize) -> Option<&str>;
+}
+
+/// UTF-8 source.
+impl SourceText for str {
+    const NATIVE_UNIT: LocationUnit = LocationUnit::Utf8;
+
+    fn len_units(&self) -> usize {
+        self.len()
+    }
+
+    fn decode_at(&self, index: usize) -> (char, usize) {
+        let c = self[index..].chars().next().unwrap();
+        (c, c.len_utf8())
+    }
+
+    fn ascii_at(&self, index: usize) -> Option<u8> {
+        let byte = self.as_bytes()[index];
+        if byte.is_ascii() {
+            Some(byte)
+        } else {
+            None
+        }
+    }
+
+    fn try_as_str(&self, start: usize, end: usize) -> Option<&str> {
+        Some(&self[start..end])
+    }
+}
+
+/// UTF-16 source, possibly containing unpaired surrogates.
+impl SourceText for [u16] {
+    const NATIVE_UNIT: LocationUnit = LocationUnit::Utf16;
+
+    fn len_units(&self) -> usize {
+        self.len()
+    }
+
+    fn decode_at(&self, index: usize) -> (char, usize) {
+        let end = usize::min(index + 2, self.len());
+        match char::decode_utf16(self[index..end].iter().cloned()).next() {
+            Some(Ok(c)) => (c, c.len_utf16()),
+            _ => (char::REPLACEMENT_CHARACTER, 1),
+        }
+    }
+
+    fn unpaired_surrogate_at(&self, index: usize) -> Option<u16> {
+        let unit = self[index];
+        let paired = match unit {
+            0xd800..=0xdbff => matches!(self.get(index + 1), Some(0xdc00..=0xdfff)),
+            0xdc00..=0xdfff => index > 0 && matches!(self[index - 1], 0xd800..=0xdbff),
+            _ => return None,
+        };
+        if paired {
+            None
+        } else {
+            Some(unit)
+        }
+    }
+
+    fn ascii_at(&self, index: usize) -> Option<u8> {
+        let unit = self[index];
+        if unit < 0x80 {
+            Some(unit as u8)
+        } else {
+            None
+        }
+    }
+
+    fn try_as_str(&self, _start: usize, _end: usize) -> Option<&str> {
+        None
+    }
+}
+
+/// Latin-1 source. Each byte is the code point of a character.
+impl SourceText for [u8] {
+    const NATIVE_UNIT: LocationUnit = LocationUnit::Utf16;
+
+    fn len_units(&self) -> usize {
+        self.len()
+    }
+
+    fn decode_at(&self, index: usize) -> (char, usize) {
+        (self[index] as char, 1)
+    }
+
+    fn ascii_at(&self, index: usize) -> Option<u8> {
+        let byte = self[index];
+        if byte.is_ascii() {
+            Some(byte)
+        } else {
+            None
+        }
+    }
+
+    fn try_as_str(&self, start: usize, end: usize) -> Option<&str> {
+        // ASCII is the common subset of Latin-1 and UTF-8.
+        let bytes = &self[start..end];
+        if bytes.is_ascii() {
+            std::str::from_utf8(bytes).ok()
+        } else {
+            None
+        }
+    }
+}
+
+/// An iterator over the characters of a range of `SourceText`, which also
+/// keeps track of the offset in `LocationUnit`.
+pub struct SourceChars<'alloc, S: ?Sized + SourceText> {
+    source: &'alloc S,
+
+    /// Index of the next character in `source`, in source units.
+    position: usize,
+
+    /// Index of the end of the range in `source`, in source units.
+    end: usize,
+
+    /// Offset of the next character, in `unit`.
+    offset: usize,
+
+    unit: LocationUnit,
+}
+
+// Not derived, as that would require `S: Clone`.
+impl<'alloc, S: ?Sized + SourceText> Clone for SourceChars<'alloc, S> {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source,
+            position: self.position,
+            end: self.end,
+            offset: self.offset,
+            unit: self.unit,
+        }
+    }
+}
+
+impl<'alloc, S: ?Sized + SourceText> SourceChars<'alloc, S> {
+    pub fn new(source: &'alloc S, unit: LocationUnit) -> Self {
+        Self {
+            source,
+            position: 0,
+            end: source.len_units(),
+            offset: 0,
+            unit,
+        }
+    }
+
+    /// Restrict the iterator to the characters of `source` in the range
+    /// [start, end), in `unit`.
+    pub fn with_range(source: &'alloc S, unit: LocationUnit, start: usize, end: usize) -> Self {
+        let mut chars = Self::new(source, unit);
+        if unit == S::NATIVE_UNIT {
+            chars.position = start;
+            chars.offset = start;
+            chars.end = end;
+            return chars;
+        }
+
+        while chars.offset < start && chars.next().is_some() {}
+        chars.offset = start;
+        let mut rest = chars.clone();
+        while rest.offset < end && rest.next().is_some() {}
+        chars.end = rest.position;
+        chars
+    }
+
+    /// The index of the next character in the source, in source units.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The offset of the next character, in `LocationUnit`.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// An upper bound on the offsets of the remaining characters.
+    pub fn max_offset(&self) -> usize {
+        // A unit of UTF-16 or Latin-1 takes at most 3 bytes in UTF-8, and
+        // no encoding takes fewer units than UTF-16.
+        let ratio = if self.unit == S::NATIVE_UNIT || self.unit == LocationUnit::Utf16 {
+            1
+        } else {
+            3
+        };
+        (self.end - self.position)
+            .saturating_mul(ratio)
+            .saturating_add(self.offset)
+    }
+
+    /// Return the next character without advancing.
+    pub fn peek(&self) -> Option<char> {
+        self.char_at(self.position)
+    }
+
+    /// Return the character after the next one without advancing.
+    pub fn peek_second(&self) -> Option<char> {
+        if self.position < self.end && self.source.ascii_at(self.position).is_some() {
+            return self.char_at(self.position + 1);
+        }
+        let mut rest = self.clone();
+        rest.next();
+        rest.next()
+    }
+
+    fn char_at(&self, index: usize) -> Option<char> {
+        if index >= self.end {
+            return None;
+        }
+        match self.source.ascii_at(index) {
+            Some(byte) => Some(byte as char),
+            None => Some(self.source.decode_at(index).0),
+        }
+    }
+
+    /// Skip the ASCII characters for which `predicate` is true, and stop at
+    /// the first other character. This is faster than calling `next()` for
+    /// each of them, as ASCII characters take one unit in both the source
+    /// and the offsets.
+    pub fn skip_ascii_while<F: Fn(u8) -> bool>(&mut self, predicate: F) {
+        let start = self.position;
+        while self.position < self.end {
+            match self.source.ascii_at(self.position) {
+                Some(byte) if predicate(byte) => self.position += 1,
+                _ => break,
+            }
+        }
+        self.offset += self.position - start;
+    }
+
+    /// If the last character returned by `next()` was an unpaired surrogate
+    /// (decoded as U+FFFD), return its code unit.
+    pub fn unpaired_surrogate_before(&self) -> Option<u16> {
+        if self.position == 0 {
+            return None;
+        }
+        self.source.unpaired_surrogate_at(self.position - 1)
+    }
+
+    /// If the next character is an unpaired surrogate, return its code unit.
+    pub fn peek_unpaired_surrogate(&self) -> Option<u16> {
+        if self.position >= self.end {
+            return None;
+        }
+        self.source.unpaired_surrogate_at(self.position)
+    }
+
+    pub fn starts_with(&self, s: &str) -> bool {
+        let mut rest = self.clone();
+        s.chars().all(|c| rest.next() == Some(c))
+    }
+
+    /// Return the text in the range [start, end) of the source, in source
+    /// units, keeping any unpaired surrogates. The text is borrowed from the
+    /// source if possible, and otherwise decoded into `allocator`.
+    pub fn slice(&self, allocator: &'alloc Bump, start: usize, end: usize) -> &'alloc Wtf8 {
+        match self.source.try_as_str(start, end) {
+            Some(s) => Wtf8::new(s),
+            None => self.slice_to_buf(allocator, start, end).into_bump_wtf8(),
+        }
+    }
+
+    /// Copy the text in the range [start, end) of the source, in source
+    /// units, into a new Wtf8Buf.
+    pub fn slice_to_buf(
+        &self,
+        allocator: &'alloc Bump,
+        start: usize,
+        end: usize,
+    ) -> Wtf8Buf<'alloc> {
+        if let Some(s) = self.source.try_as_str(start, end) {
+            return Wtf8Buf::from_str_in(s, allocator);
+        }
+
+        let mut text = Wtf8Buf::with_capacity_in(end - start, allocator);
+        let mut index = start;
+        while index < end {
+            if let Some(unit) = self.source.unpaired_surrogate_at(index) {
+                text.push_code_unit(unit);
+                index += 1;
+                continue;
+            }
+            let (c, len) = self.source.decode_at(index);
+            text.push(c);
+            index += len;
+        }
+        text
+    }
+
+    /// Like `slice`, but for names, which can't contain unpaired surrogates.
+    /// Text that has to be decoded is interned in `atoms`, so that each
+    /// distinct name is only allocated once.
+    pub fn slice_atom(&self, atoms: &AtomTable<'alloc>, start: usize, end: usize) -> &'alloc str {
+        match self.source.try_as_str(start, end) {
+            Some(s) => s,
+            None => atoms.get(atoms.intern_with(|text| self.decode_into(text, start, end))),
+        }
+    }
+
+    fn decode_into(&self, text: &mut String<'alloc>, start: usize, end: usize) {
+        let mut index = start;
+        while index < end {
+            let (c, len) = self.source.decode_at(index);
+            text.push(c);
+            index += len;
+        }
+    }
+}
+
+impl<'alloc, S: ?Sized + SourceText> Iterator for SourceChars<'alloc, S> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.position >= self.end {
+            return None;
+        }
+
+        if let Some(byte) = self.source.ascii_at(self.position) {
+            self.position += 1;
+            self.offset += 1;
+            return Some(byte as char);
+        }
+
+        let (c, len) = self.source.decode_at(self.position);
+        self.position += len;
+        self.offset += if self.unit == S::NATIVE_UNIT {
+            len
+        } else {
+            match self.unit {
+                LocationUnit::Utf8 => c.len_utf8(),
+                LocationUnit::Utf16 => c.len_utf16(),
+            }
+        };
+        Some(c)
+    }
+}
@@ -0,0 +1,123 @@
+//! Parsing with checkpoints.
+//!
+//! Speculative parsing, e.g. to check whether the input typed so far in a
+//! REPL is complete, or to try an interpretation of a contextual keyword,
+//! can save the state of the parser and the lexer, and come back to it
+//! without lexing and parsing the source before it again.
+
+use crate::lexer::Lexer;
+use crate::parser::{Parser, ParserCheckpoint};
+use crate::source::SourceText;
+use crate::ParseOptions;
+use ast::{
+    arena,
+    types::{Module, Script},
+};
+use bumpalo::Bump;
+use generated_parser::{Result, StackValue, TerminalId, START_STATE_MODULE, START_STATE_SCRIPT};
+
+/// A parser that reads its source one token at a time, and whose state can
+/// be saved and restored with `checkpoint` and `restore`.
+pub struct SpeculativeParser<'alloc, S: ?Sized + SourceText = str> {
+    parser: Parser<'alloc>,
+    tokens: Lexer<'alloc, S>,
+}
+
+/// The saved state of a `SpeculativeParser`.
+pub struct Checkpoint<'alloc, S: ?Sized + SourceText = str> {
+    parser: ParserCheckpoint<'alloc>,
+    tokens: Lexer<'alloc, S>,
+}
+
+// Not derived, as that would require `S: Clone`.
+impl<'alloc, S: ?Sized + SourceText> Clone for Checkpoint<'alloc, S> {
+    fn clone(&self) -> Self {
+        Self {
+            parser: self.parser.clone(),
+            tokens: self.tokens.clone(),
+        }
+    }
+}
+
+impl<'alloc, S: ?Sized + SourceText> SpeculativeParser<'alloc, S> {
+    fn new(
+        allocator: &'alloc Bump,
+        source: &'alloc S,
+        options: &ParseOptions,
+        start_state: usize,
+    ) -> Self {
+        Self {
+            parser: crate::new_parser(allocator, options, start_state),
+            tokens: Lexer::with_unit(allocator, source, options.location_unit),
+        }
+    }
+
+    /// Create a parser for `source` with the Script goal.
+    pub fn new_script(allocator: &'alloc Bump, source: &'alloc S, options: &ParseOptions) -> Self {
+        Self::new(allocator, source, options, START_STATE_SCRIPT)
+    }
+
+    /// Create a parser for `source` with the Module goal.
+    pub fn new_module(allocator: &'alloc Bump, source: &'alloc S, options: &ParseOptions) -> Self {
+        Self::new(allocator, source, options, START_STATE_MODULE)
+    }
+
+    /// The offset of the end of the last token read.
+    pub fn offset(&self) -> usize {
+        self.tokens.offset()
+    }
+
+    /// Read the next token and feed it to the parser. Return false if the
+    /// end of the source has been reached.
+    ///
+    /// After an error, the parser can't be used anymore, until it's restored
+    /// to a checkpoint.
+    pub fn advance(&mut self) -> Result<'alloc, bool> {
+        let t = self.tokens.next(&self.parser)?;
+        if t.terminal_id == TerminalId::End {
+            return Ok(false);
+        }
+        self.parser.write_token(&t)?;
+        Ok(true)
+    }
+
+    /// Return true if the tokens read so far form a complete Script or
+    /// Module.
+    pub fn can_close(&self) -> bool {
+        self.parser.can_close()
+    }
+
+    /// Save the current state. The partial ASTs built so far aren't copied
+    /// until the parser pops them, so this doesn't depend on their size.
+    pub fn checkpoint(&mut self) -> Checkpoint<'alloc, S> {
+        Checkpoint {
+            parser: self.parser.checkpoint(),
+            tokens: self.tokens.clone(),
+        }
+    }
+
+    /// Go back to the state saved in `checkpoint`, which must come from this
+    /// parser. The checkpoints taken after it can't be restored anymore, and
+    /// restoring one of them panics.
+    pub fn restore(&mut self, checkpoint: Checkpoint<'alloc, S>) {
+        self.parser.restore(checkpoint.parser);
+        self.tokens = checkpoint.tokens;
+    }
+
+    fn finish(mut self) -> Result<'alloc, StackValue<'alloc>> {
+        while self.advance()? {}
+        self.parser.close(self.tokens.offset())
+    }
+
+    /// Parse the rest of the source, and return the Script. The parser must
+    /// have been created with `new_script`.
+    pub fn finish_script(self) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
+        Ok(self.finish()?.to_ast()?)
+    }
+
+    /// Parse the rest of the source, and return the Module. The parser must
+    /// have been created with `new_module`.
+    pub fn finish_module(self) -> Result<'alloc, arena::Box<'alloc, Module<'alloc>>> {
+        Ok(self.finish()?.to_ast()?)
+    }
+}
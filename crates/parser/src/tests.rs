@@ -1,29 +1,200 @@
+use std::cell::Cell;
 use std::iter;
+use std::rc::Rc;
 
 use crate::lexer::Lexer;
 use crate::parser::Parser;
-use crate::{parse_script, ParseOptions};
-use ast::{arena, source_location::SourceLocation, types::*};
+use crate::{
+    parse_expression, parse_formal_parameters, parse_function_body, parse_lazy_function,
+    parse_module, parse_script, parse_script_incrementally, reparse_script, IncrementalScript,
+    LoadedTables, LocationUnit, ParseOptions, ParseSession, SpeculativeParser, TextEdit,
+};
+use ast::{
+    arena,
+    atom_table::{Atom, AtomTable},
+    source_location::SourceLocation,
+    source_location_accessor::SourceLocationAccessor,
+    types::*,
+    visit::Pass,
+};
 use bumpalo::{self, Bump};
-use generated_parser::{self, AstBuilder, ParseError, Result, TerminalId};
+use generated_parser::{
+    self, AstBuilder, DeclarationKind, InlineToken, ParseError, Result, TerminalId, Token,
+};
 
 #[cfg(all(feature = "unstable", test))]
 mod benchmarks {
     extern crate test;
 
-    use std::fs::File;
-    use std::io::Read;
-    use test::Bencher;
+    use test::{black_box, Bencher};
 
     use crate::lexer::Lexer;
-    use crate::parse_script;
+    use crate::parser::Parser;
+    use crate::{parse_script, ParseOptions};
+    use bumpalo::Bump;
+    use generated_parser::{AstBuilder, TerminalId, START_STATE_SCRIPT, TABLES};
+
+    // Code using a wider range of the grammar than `generated_source`:
+    // classes, arrow functions, destructuring, regular expressions, labels
+    // and exceptions.
+    fn grammar_source() -> String {
+        let module = r#"{
+class Queue extends Base {
+    constructor({ limit = 10, name }, ...rest) {
+        super(name);
+        this.items = [];
+        this.limit = limit;
+        Queue.count++;
+    }
+    get size() { return this.items.length; }
+    entries() { return this.items.map((item, i) => [i, item]); }
+    drain(callback) {
+        outer: while (this.items.length > 0) {
+            const [first, ...others] = this.items;
+            try {
+                callback(first);
+            } catch ({ message }) {
+                if (/^retry/i.test(message)) continue outer;
+                throw new Error("failed on " + first + ": " + message);
+            } finally {
+                this.items = others;
+            }
+        }
+        return typeof callback === "function" ? void 0 : null;
+    }
+}
+var { a, b: [c, d = a || 1] } = { a: 1, b: [2] }, e = a > c ? a : c, f = x => x ** 2;
+switch (e) { case 1: f(e); break; default: e = e && !f(-e); }
+}
+"#;
+        module.repeat(1000)
+    }
 
     #[bench]
     fn bench_parse_grammar(b: &mut Bencher) {
-        let mut buffer = fs::read_to_string("../vue.js").expect("reading test file");
+        let buffer = grammar_source();
+        b.bytes = buffer.len() as u64;
+        b.iter(|| {
+            let allocator = &Bump::new();
+            parse_script(allocator, buffer.as_str(), &ParseOptions::new()).unwrap();
+        });
+    }
+
+    // Typical code, without any `/`, so that it can be lexed without parsing
+    // it.
+    fn generated_source() -> String {
+        let function = r#"
+function compute(values, options) {
+    // Sum the values that pass the filter.
+    var total = 0;
+    for (var index = 0; index < values.length; index++) {
+        if (options.filter(values[index]) !== false) {
+            total += values[index] * 2.5;
+        }
+    }
+    return { total: total, label: "total of " + values.length + ' items' };
+}
+"#;
+        function.repeat(1000)
+    }
+
+    #[bench]
+    fn bench_lex(b: &mut Bencher) {
+        let buffer = generated_source();
+        b.bytes = buffer.len() as u64;
+        b.iter(|| {
+            let allocator = &Bump::new();
+            let parser = Parser::new(AstBuilder::new(allocator), START_STATE_SCRIPT);
+            let mut lexer = Lexer::new(allocator, buffer.as_str());
+            while lexer.next(&parser).unwrap().terminal_id != TerminalId::End {}
+        });
+    }
+
+    #[bench]
+    fn bench_parse_generated_source(b: &mut Bencher) {
+        let buffer = generated_source();
+        b.bytes = buffer.len() as u64;
+        b.iter(|| {
+            let allocator = &Bump::new();
+            parse_script(allocator, buffer.as_str(), &ParseOptions::new()).unwrap();
+        });
+    }
+
+    // The parse tables, uncompressed, to compare lookups with TABLES.
+    struct DenseTables {
+        actions: Vec<i64>,
+        gotos: Vec<u16>,
+    }
+
+    impl DenseTables {
+        fn new() -> Self {
+            let mut actions = Vec::with_capacity(TABLES.state_count * TABLES.action_width);
+            let mut gotos = Vec::with_capacity(TABLES.state_count * TABLES.goto_width);
+            for state in 0..TABLES.state_count {
+                for t in 0..TABLES.action_width {
+                    actions.push(TABLES.action(state, t));
+                }
+                for nt in 0..TABLES.goto_width {
+                    gotos.push(TABLES.goto(state, nt) as u16);
+                }
+            }
+            DenseTables { actions, gotos }
+        }
+    }
+
+    // Pseudo-random (state, column) pairs, so that the lookups don't all hit
+    // the same cache lines.
+    fn lookups(width: usize) -> Vec<(usize, usize)> {
+        let mut seed: usize = 0x2545_f491;
+        (0..4096)
+            .map(|_| {
+                seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+                let state = (seed >> 33) % TABLES.state_count;
+                let column = (seed >> 17) % width;
+                (state, column)
+            })
+            .collect()
+    }
+
+    #[bench]
+    fn bench_action_compressed(b: &mut Bencher) {
+        let lookups = lookups(TABLES.action_width);
+        b.iter(|| {
+            for &(state, t) in &lookups {
+                black_box(TABLES.action(state, t));
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_action_dense(b: &mut Bencher) {
+        let dense = DenseTables::new();
+        let lookups = lookups(TABLES.action_width);
+        b.iter(|| {
+            for &(state, t) in &lookups {
+                black_box(dense.actions[state * TABLES.action_width + t]);
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_goto_compressed(b: &mut Bencher) {
+        let lookups = lookups(TABLES.goto_width);
+        b.iter(|| {
+            for &(state, nt) in &lookups {
+                black_box(TABLES.goto(state, nt));
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_goto_dense(b: &mut Bencher) {
+        let dense = DenseTables::new();
+        let lookups = lookups(TABLES.goto_width);
         b.iter(|| {
-            let lexer = Lexer::new(buffer.chars());
-            parse_script(lexer).unwrap();
+            for &(state, nt) in &lookups {
+                black_box(dense.gotos[state * TABLES.goto_width + nt] as usize);
+            }
         });
     }
 }
@@ -66,7 +237,19 @@ where
 {
     let buf = arena::alloc_str(allocator, &chunks_to_string(code));
     let options = ParseOptions::new();
-    parse_script(allocator, &buf, &options)
+    parse_script(allocator, buf, &options)
+}
+
+fn try_parse_module<'alloc, 'source, Source>(
+    allocator: &'alloc Bump,
+    code: Source,
+) -> Result<arena::Box<'alloc, Module<'alloc>>>
+where
+    Source: IntoChunks<'source>,
+{
+    let buf = arena::alloc_str(allocator, &chunks_to_string(code));
+    let options = ParseOptions::new();
+    parse_module(allocator, buf, &options)
 }
 
 fn assert_parses<'alloc, T: IntoChunks<'alloc>>(code: T) {
@@ -117,6 +300,18 @@ fn assert_error_eq<'alloc, T: IntoChunks<'alloc>>(code: T, expected: ParseError)
     assert_eq!(result.unwrap_err(), expected);
 }
 
+fn assert_module_parses<'alloc, T: IntoChunks<'alloc>>(code: T) {
+    let allocator = &Bump::new();
+    try_parse_module(allocator, code).unwrap();
+}
+
+fn assert_module_error_eq<'alloc, T: IntoChunks<'alloc>>(code: T, expected: ParseError) {
+    let allocator = &Bump::new();
+    let result = try_parse_module(allocator, code);
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err(), expected);
+}
+
 fn assert_incomplete<'alloc, T: IntoChunks<'alloc>>(code: T) {
     let allocator = &Bump::new();
     let result = try_parse(allocator, code);
@@ -128,8 +323,8 @@ fn assert_incomplete<'alloc, T: IntoChunks<'alloc>>(code: T) {
 // same sequence of tokens (although possibly at different offsets).
 fn assert_same_tokens<'alloc>(left: &str, right: &str) {
     let allocator = &Bump::new();
-    let mut left_lexer = Lexer::new(allocator, left.chars());
-    let mut right_lexer = Lexer::new(allocator, right.chars());
+    let mut left_lexer = Lexer::new(allocator, left);
+    let mut right_lexer = Lexer::new(allocator, right);
 
     let mut parser = Parser::new(
         AstBuilder::new(allocator),
@@ -144,12 +339,12 @@ fn assert_same_tokens<'alloc>(left: &str, right: &str) {
         assert_eq!(
             left_token.terminal_id, right_token.terminal_id,
             "at offset {} in {:?} / {} in {:?}",
-            left_token.loc.start, left, right_token.loc.start, right,
+            left_token.loc.start(), left, right_token.loc.start(), right,
         );
         assert_eq!(
             left_token.value, right_token.value,
             "at offsets {} / {}",
-            left_token.loc.start, right_token.loc.start
+            left_token.loc.start(), right_token.loc.start()
         );
 
         if left_token.terminal_id == TerminalId::End {
@@ -163,7 +358,7 @@ fn assert_same_tokens<'alloc>(left: &str, right: &str) {
 fn assert_can_close_after<'alloc, T: IntoChunks<'alloc>>(code: T) {
     let allocator = &Bump::new();
     let buf = chunks_to_string(code);
-    let mut lexer = Lexer::new(allocator, buf.chars());
+    let mut lexer = Lexer::new(allocator, buf.as_str());
     let mut parser = Parser::new(
         AstBuilder::new(allocator),
         generated_parser::START_STATE_SCRIPT,
@@ -323,6 +518,14 @@ fn test_numbers() {
      */
 
     assert_parses("1..x");
+
+    // LegacyOctalIntegerLiteral and NonOctalDecimalIntegerLiteral, in
+    // non-strict code.
+    assert_parses("017");
+    assert_parses("0019");
+    assert_parses("07.toString()");
+    assert_error_eq("07n", ParseError::IllegalCharacter('n'));
+    assert_not_implemented("08.5");
 }
 
 #[test]
@@ -367,6 +570,79 @@ fn test_identifier() {
     assert_illegal_character("_\u{1000c}();");
 }
 
+#[test]
+fn test_keywords() {
+    let allocator = &Bump::new();
+    let parser = Parser::new(
+        AstBuilder::new(allocator),
+        generated_parser::START_STATE_SCRIPT,
+    );
+    let code = "if in instanceof implements null true false of \
+                i ifs nul functions ifelse $if if_ \
+                \\u0069f ab\\u0063_de\u{b7}f";
+    let mut lexer = Lexer::new(allocator, code);
+    let mut tokens = vec![];
+    loop {
+        let token = lexer.next(&parser).unwrap();
+        if token.terminal_id == TerminalId::End {
+            break;
+        }
+        tokens.push((token.terminal_id, token.str_value().unwrap()));
+    }
+    assert_eq!(
+        tokens,
+        vec![
+            (TerminalId::If, "if"),
+            (TerminalId::In, "in"),
+            (TerminalId::Instanceof, "instanceof"),
+            (TerminalId::Implements, "implements"),
+            (TerminalId::NullLiteral, "null"),
+            (TerminalId::BooleanLiteral, "true"),
+            (TerminalId::BooleanLiteral, "false"),
+            (TerminalId::Of, "of"),
+            (TerminalId::Name, "i"),
+            (TerminalId::Name, "ifs"),
+            (TerminalId::Name, "nul"),
+            (TerminalId::Name, "functions"),
+            (TerminalId::Name, "ifelse"),
+            (TerminalId::Name, "$if"),
+            (TerminalId::Name, "if_"),
+            (TerminalId::NameWithEscape, "if"),
+            (TerminalId::NameWithEscape, "abc_de\u{b7}f"),
+        ]
+    );
+}
+
+#[test]
+fn test_inline_tokens() {
+    let allocator = &Bump::new();
+    let parser = Parser::new(
+        AstBuilder::new(allocator),
+        generated_parser::START_STATE_SCRIPT,
+    );
+    let mut lexer = Lexer::new(allocator, "a +\n(b);");
+    loop {
+        let token = lexer.next(&parser).unwrap();
+        match InlineToken::new(&token) {
+            Some(inline) => assert_eq!(inline.to_token(), token),
+            None => assert!(token.value.is_some()),
+        }
+        if token.terminal_id == TerminalId::End {
+            break;
+        }
+    }
+
+    // Tokens whose offsets don't fit in 32 bits are allocated in the arena.
+    let far_offset = u32::MAX as usize + 1;
+    if SourceLocation::offset_fits(far_offset) {
+        let far = Token::basic_token(
+            TerminalId::Semicolon,
+            SourceLocation::new(far_offset, far_offset),
+        );
+        assert_eq!(InlineToken::new(&far), None);
+    }
+}
+
 #[test]
 fn test_regexp() {
     assert_parses(r"/\w/");
@@ -448,14 +724,17 @@ fn test_awkward_chunks() {
                     operator: CompoundAssignmentOperator::Div {
                         loc: SourceLocation::new(1, 3),
                     },
-                    binding: SimpleAssignmentTarget::AssignmentTargetIdentifier(
-                        AssignmentTargetIdentifier {
-                            name: Identifier {
-                                value: "x",
+                    binding: arena::alloc(
+                        allocator,
+                        SimpleAssignmentTarget::AssignmentTargetIdentifier(
+                            AssignmentTargetIdentifier {
+                                name: Identifier {
+                                    value: "x",
+                                    loc: SourceLocation::new(0, 1),
+                                },
                                 loc: SourceLocation::new(0, 1),
                             },
-                            loc: SourceLocation::new(0, 1),
-                        },
+                        ),
                     ),
                     expression: arena::alloc(
                         allocator,
@@ -519,6 +798,78 @@ fn test_arrow_parameters() {
         "(a, {items: [...zero, one]}) => {}",
         ParseError::ArrayPatternWithNonFinalRest,
     );
+
+    assert_parses("f = (a, {b, c} = {}, ...rest) => rest;");
+    assert_parses("f = ([a, ...b], {c, ...d}) => {};");
+    assert_parses("f = (a, ...[b, {c}]) => {};");
+    assert_parses("f = (a, b) => { var a; };");
+    assert_error_eq("(a, ...b);", ParseError::ArrowParametersWithoutArrow(0));
+    assert_error_eq("x = ();", ParseError::ArrowParametersWithoutArrow(4));
+
+    assert_error_eq(
+        "(a, a) => {}",
+        ParseError::DuplicateBinding(
+            "a",
+            DeclarationKind::FormalParameter,
+            1,
+            DeclarationKind::FormalParameter,
+            4,
+        ),
+    );
+    assert_error_eq(
+        "({a = 1}, {b: [a]}) => {}",
+        ParseError::DuplicateBinding(
+            "a",
+            DeclarationKind::FormalParameter,
+            2,
+            DeclarationKind::FormalParameter,
+            15,
+        ),
+    );
+    assert_error_eq(
+        "(a, ...a) => {}",
+        ParseError::DuplicateBinding(
+            "a",
+            DeclarationKind::FormalParameter,
+            1,
+            DeclarationKind::FormalParameter,
+            7,
+        ),
+    );
+    assert_error_eq(
+        "(a, b) => { const b = 1; }",
+        ParseError::DuplicateBinding(
+            "b",
+            DeclarationKind::FormalParameter,
+            4,
+            DeclarationKind::Const,
+            18,
+        ),
+    );
+
+    assert_error_eq(
+        "(a = 1) => { 'use strict'; }",
+        ParseError::UseStrictWithNonSimpleParameters(13),
+    );
+    assert_error_eq(
+        "({a}) => { 'use strict'; }",
+        ParseError::UseStrictWithNonSimpleParameters(11),
+    );
+    assert_error_eq(
+        "function f(...a) { 'use strict'; }",
+        ParseError::UseStrictWithNonSimpleParameters(19),
+    );
+    assert_error_eq(
+        "class C { m([a]) { 'use strict'; } }",
+        ParseError::UseStrictWithNonSimpleParameters(19),
+    );
+    assert_parses("f = (a = 1) => { g(); 'use strict'; };");
+    assert_parses("f = (a = 1) => 'use strict';");
+
+    // The directive is fine with simple parameters.
+    assert_parses("function f(a, b) { 'use strict'; }");
+    assert_parses("(a) => { 'use strict'; };");
+    assert_parses("class C { m(a) { 'use strict'; } }");
 }
 
 #[test]
@@ -529,6 +880,155 @@ fn test_invalid_assignment_targets() {
     assert_error_eq("(x && y)--;", ParseError::InvalidAssignmentTarget);
 }
 
+#[test]
+fn test_strict_mode_code() {
+    assert_parses("with (x) {} eval = 1; arguments++; x = 017;");
+    assert_parses("function f() { with (x) {} eval = 08; }");
+    assert_parses("function f() { 'use strict'; } with (x) {}");
+    assert_parses("function f() { g(); 'use strict'; with (x) {} }");
+    assert_parses("x = 017; 'use strict';");
+
+    // A function with a "use strict" directive is strict mode code.
+    assert_error_eq(
+        "function f() { 'use strict'; with (x) {} }",
+        ParseError::WithInStrictMode(29),
+    );
+    assert_error_eq(
+        "function f() { 'use strict'; return 017; }",
+        ParseError::LegacyOctalInStrictMode(36),
+    );
+    assert_error_eq(
+        "function f() { 'use strict'; x = 08; }",
+        ParseError::LegacyOctalInStrictMode(33),
+    );
+    assert_error_eq(
+        "function f() { 'use strict'; eval = 1; }",
+        ParseError::InvalidAssignmentTarget,
+    );
+    assert_error_eq(
+        "function f() { 'use strict'; function g() { with (x) {} } }",
+        ParseError::WithInStrictMode(44),
+    );
+    assert_error_eq(
+        "() => { 'use strict'; with (x) {} }",
+        ParseError::WithInStrictMode(22),
+    );
+
+    // So is a script with a "use strict" directive.
+    assert_error_eq("'use strict'; with (x) {}", ParseError::WithInStrictMode(14));
+    assert_error_eq("'use strict'; x = 017;", ParseError::LegacyOctalInStrictMode(18));
+
+    // So are class bodies and modules.
+    assert_error_eq(
+        "class C { m() { with (x) {} } }",
+        ParseError::WithInStrictMode(16),
+    );
+    assert_error_eq(
+        "class C extends (017) {}",
+        ParseError::LegacyOctalInStrictMode(17),
+    );
+    assert_module_error_eq("with (x) {}", ParseError::WithInStrictMode(0));
+    assert_module_error_eq("x = 017;", ParseError::LegacyOctalInStrictMode(4));
+    assert_module_error_eq("arguments = 1;", ParseError::InvalidAssignmentTarget);
+
+    // The statements of lazy functions are dropped as they're parsed.
+    let allocator = &Bump::new();
+    assert!(matches!(
+        parse_lazily(allocator, "function f() { 'use strict'; a; with (x) {} b; }"),
+        Err(ParseError::WithInStrictMode(32))
+    ));
+    assert!(parse_lazily(allocator, "function f() { a; 'use strict'; with (x) {} b; }").is_ok());
+}
+
+#[test]
+fn test_cover_initialized_name() {
+    assert_parses("({a = 1} = obj);");
+    assert_parses("({ timeout = 5000, retries: n = 3 } = opts);");
+    assert_parses("[{a = 1}, {b: {c = 2}}] = arr;");
+    assert_parses("for ({a = 1} of list) {}");
+    assert_parses("f = ({ timeout = 5000 }) => timeout;");
+    assert_parses("f = ({a = 1} = {}) => a;");
+    assert_parses("f = (x, [{a = 1}]) => a;");
+
+    let allocator = &Bump::new();
+    let script = try_parse(allocator, "({a = 1} = obj);").unwrap();
+    match &script.statements[0] {
+        Statement::ExpressionStatement(expression) => match &**expression {
+            Expression::AssignmentExpression {
+                binding:
+                    AssignmentTarget::AssignmentTargetPattern(
+                        AssignmentTargetPattern::ObjectAssignmentTarget(target),
+                    ),
+                ..
+            } => match &target.properties[0] {
+                AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(property) => {
+                    assert_eq!(property.binding.name.value, "a");
+                    assert!(property.init.is_some());
+                }
+                other => panic!("unexpected property: {:?}", other),
+            },
+            other => panic!("unexpected expression: {:?}", other),
+        },
+        other => panic!("unexpected statement: {:?}", other),
+    }
+
+    assert_error_eq("({a = 1});", ParseError::InvalidCoverInitializedName(2));
+    assert_error_eq("x = {a = 1};", ParseError::InvalidCoverInitializedName(5));
+    assert_error_eq(
+        "({a = {b = 1}} = obj);",
+        ParseError::InvalidCoverInitializedName(7),
+    );
+}
+
+#[test]
+fn test_proto_property() {
+    assert_parses("({ __proto__: null, a: 1 });");
+    assert_parses("({ '__proto__': null });");
+    assert_parses("({ __proto__: null, __proto__() {} });");
+    assert_parses("({ __proto__: null, ['__proto__']: 1 });");
+    assert_parses("({ __proto__: null, get __proto__() { return 1; } });");
+    assert_parses("class C { __proto__ = 1; }");
+
+    let allocator = &Bump::new();
+    let script = try_parse(allocator, "({ __proto__: p, __proto__ });").unwrap();
+    match &script.statements[0] {
+        Statement::ExpressionStatement(expression) => match &**expression {
+            Expression::ObjectExpression(object) => {
+                assert!(matches!(
+                    &*object.properties[0],
+                    ObjectProperty::ProtoProperty(_)
+                ));
+                assert!(matches!(
+                    &*object.properties[1],
+                    ObjectProperty::ShorthandProperty(_)
+                ));
+            }
+            other => panic!("unexpected expression: {:?}", other),
+        },
+        other => panic!("unexpected statement: {:?}", other),
+    }
+
+    assert_error_eq(
+        "({ __proto__: a, __proto__: b });",
+        ParseError::DuplicateProto(3, 17),
+    );
+    assert_error_eq(
+        "({ __proto__: a, '__proto__': b });",
+        ParseError::DuplicateProto(3, 17),
+    );
+
+    // Destructuring patterns can have duplicate `__proto__`.
+    assert_parses("({ __proto__: a, __proto__: b } = obj);");
+    assert_parses("[{ __proto__: a, __proto__: b }] = arr;");
+    assert_parses("({ x: { __proto__: a, __proto__: b } } = obj);");
+    assert_parses("f = ({ __proto__: a, __proto__: b }) => a;");
+    assert_parses("f = ({ __proto__: a, __proto__: { __proto__: b, __proto__: c } }) => a;");
+    assert_error_eq(
+        "({ __proto__: a = { __proto__: b, __proto__: c } } = obj);",
+        ParseError::DuplicateProto(20, 34),
+    );
+}
+
 #[test]
 fn test_can_close_with_asi() {
     assert_can_close_after("2 + 2\n");
@@ -588,44 +1088,498 @@ fn test_conditional_keywords() {
 }
 
 #[test]
-fn test_async_arrows() {
-    // FIXME: async (multiple lookahead)
-    assert_not_implemented("const a = async a => 1;");
-    /*
-    assert_parses("let f = async arg => body;");
-    assert_parses("f = async (a1, a2) => {};");
-    assert_parses("f = async (a1 = b + c, ...a2) => {};");
+fn test_class_fields() {
+    assert_parses("class C { x; y = 1; static z = 2; static; static = 3; }");
+    assert_parses("class C { #x = 1; static #y; getX() { return this.#x; } }");
+    assert_parses("class C { #m() {} get #a() {} set #a(v) {} static *#g() {} }");
+    assert_parses("class C { static {} static { var x; const y = 1; } }");
+    assert_error_eq(
+        "class C { static { const x = 1; var x; } }",
+        ParseError::DuplicateBinding(
+            "x",
+            DeclarationKind::Const,
+            25,
+            DeclarationKind::Var,
+            36,
+        ),
+    );
+    assert_parses("class C { static constructor() {} prototype = 1; static [\"prototype\"] = 2; }");
+    assert_error_eq(
+        "class C { constructor = 1; }",
+        ParseError::InvalidFieldName("constructor", 10),
+    );
+    assert_error_eq(
+        "class C { \"constructor\"; }",
+        ParseError::InvalidFieldName("constructor", 10),
+    );
+    assert_error_eq(
+        "class C { static constructor = 1; }",
+        ParseError::InvalidFieldName("constructor", 17),
+    );
+    assert_error_eq(
+        "class C { static prototype = 1; }",
+        ParseError::InvalidFieldName("prototype", 17),
+    );
+    assert_error_eq(
+        "class C { static prototype() {} }",
+        ParseError::InvalidStaticMethodName("prototype", 17),
+    );
+    assert_error_eq(
+        "class C { static get prototype() {} }",
+        ParseError::InvalidStaticMethodName("prototype", 21),
+    );
+}
 
-    assert_error_eq("f = async (a, b + c) => {};", ParseError::InvalidParameter);
+#[test]
+fn test_private_names() {
+    assert_parses("class C { #x; f(o) { return o.#x; } }");
+    assert_parses("class C { #x; f() { return class { g(o) { return o.#x; } }; } }");
+    assert_parses("class C { f() { return this.#x; } #x; }");
     assert_error_eq(
-        "f = async (...a1, a2) => {};",
-        ParseError::ArrowParametersWithNonFinalRest,
+        "class C { f() { return this.#y; } }",
+        ParseError::UndeclaredPrivateName("#y", 28),
     );
-    assert_error_eq("obj.async() => {}", ParseError::ArrowHeadInvalid);
-    */
+    assert_error_eq("this.#x;", ParseError::UndeclaredPrivateName("#x", 5));
+    assert_error_eq(
+        "class C { #x; } class D { f() { this.#x; } }",
+        ParseError::UndeclaredPrivateName("#x", 37),
+    );
+    assert_error_eq(
+        "class C { #x; #x; }",
+        ParseError::DuplicatePrivateName("#x", 10, 14),
+    );
+    assert_error_eq(
+        "class C { #x; #x() {} }",
+        ParseError::DuplicatePrivateName("#x", 10, 14),
+    );
+    assert_error_eq(
+        "class C { get #x() {} static set #x(v) {} }",
+        ParseError::DuplicatePrivateName("#x", 14, 33),
+    );
+    assert_error_eq(
+        "class C { #constructor; }",
+        ParseError::InvalidPrivateName("#constructor", 10),
+    );
+    assert_error_eq(
+        "({ #m() {} });",
+        ParseError::PrivateNameInObjectLiteral("#m", 3),
+    );
+}
 
-    assert_error_eq("foo(a, b) => {}", ParseError::ArrowHeadInvalid);
+#[test]
+fn test_class_constructor() {
+    assert_parses("class C { constructor() {} static constructor() {} }");
+    assert_parses("class C { 'constructor'() {} static *constructor() {} }");
+    assert_error_eq(
+        "class C { constructor() {} constructor() {} }",
+        ParseError::DuplicateConstructor(10, 27),
+    );
+    assert_error_eq(
+        "class C { constructor() {} 'constructor'() {} }",
+        ParseError::DuplicateConstructor(10, 27),
+    );
+    assert_error_eq(
+        "class C { get constructor() {} }",
+        ParseError::InvalidConstructor(14),
+    );
+    assert_error_eq(
+        "class C { set constructor(v) {} }",
+        ParseError::InvalidConstructor(14),
+    );
+    assert_error_eq(
+        "class C { *constructor() {} }",
+        ParseError::InvalidConstructor(11),
+    );
 }
 
 #[test]
-fn test_coalesce() {
-    assert_parses("const f = options.prop ?? 0;");
-    assert_syntax_error("if (options.prop ?? 0 || options.prop > 1000) {}");
+fn test_super() {
+    assert_parses("class C extends B { constructor() { super(); } }");
+    assert_parses("class C extends B { constructor() { () => super(super.x); } }");
+    assert_parses("class C { m() { return super.m(); } static s() { super['s']; } }");
+    assert_parses("class C { get x() { return super.x; } set x(v) { super.x = v; } }");
+    assert_parses("class C { x = super.x; static y = () => super.y; static { super.z; } }");
+    assert_parses("({ m() { return super.m(); }, get x() { return super.x; } });");
+    assert_parses("class C extends B { m() { return class extends super.D {}; } }");
+
+    assert_error_eq("super();", ParseError::UnexpectedSuperCall(0));
+    assert_error_eq("super.x;", ParseError::UnexpectedSuperProperty(0));
+    assert_error_eq(
+        "function f() { super.x; }",
+        ParseError::UnexpectedSuperProperty(15),
+    );
+    assert_error_eq(
+        "class C extends B { constructor() { function f() { super(); } } }",
+        ParseError::UnexpectedSuperCall(51),
+    );
+    assert_error_eq(
+        "class C { constructor() { super(); } }",
+        ParseError::UnexpectedSuperCall(26),
+    );
+    assert_error_eq(
+        "class C extends B { m() { super(); } }",
+        ParseError::UnexpectedSuperCall(26),
+    );
+    assert_error_eq(
+        "class C extends B { static constructor() { super(); } }",
+        ParseError::UnexpectedSuperCall(43),
+    );
+    assert_error_eq(
+        "class C extends B { x = super(); }",
+        ParseError::UnexpectedSuperCall(24),
+    );
+    assert_error_eq(
+        "class C extends B { static { super(); } }",
+        ParseError::UnexpectedSuperCall(29),
+    );
+    assert_error_eq(
+        "({ constructor() { super(); } });",
+        ParseError::UnexpectedSuperCall(19),
+    );
+    assert_error_eq(
+        "class C { [super.x]() {} }",
+        ParseError::UnexpectedSuperProperty(11),
+    );
 }
 
 #[test]
-fn test_no_line_terminator_here() {
-    // Parse `code` as a Script and compute some function of the resulting AST.
-    fn parse_then<F, R>(code: &str, f: F) -> R
-    where
-        F: FnOnce(&Script) -> R,
-    {
-        let allocator = &Bump::new();
-        match try_parse(allocator, code) {
-            Err(err) => {
-                panic!("Failed to parse code {:?}: {}", code, err);
-            }
-            Ok(script) => f(&*script),
+fn test_jump_targets() {
+    assert_parses("while (1) { break; continue; }");
+    assert_parses("do { if (x) break; else continue; } while (1);");
+    assert_parses("for (;;) { switch (x) { case 1: break; default: continue; } }");
+    assert_parses("switch (x) { case 1: { break; } }");
+    assert_parses("L: { break L; }");
+    assert_parses("L: M: for (x in y) { continue L; }");
+    assert_parses("L: for (;;) { M: { continue L; } }");
+    assert_parses("L: ; L: ;");
+    assert_parses("L: { function f() { L: while (1) break L; } }");
+    assert_parses("for (;;) { () => { L: ; }; break; }");
+
+    assert_error_eq("break;", ParseError::BreakOutsideOfLoop(0));
+    assert_error_eq("continue;", ParseError::ContinueOutsideOfLoop(0));
+    assert_error_eq(
+        "switch (x) { case 1: continue; }",
+        ParseError::ContinueOutsideOfLoop(21),
+    );
+    assert_error_eq(
+        "while (1) { function f() { break; } }",
+        ParseError::BreakOutsideOfLoop(27),
+    );
+    assert_error_eq(
+        "for (;;) { () => { continue; }; }",
+        ParseError::ContinueOutsideOfLoop(19),
+    );
+    assert_error_eq("{ break L; }", ParseError::UndefinedLabel("L", 2));
+    assert_error_eq(
+        "L: while (1) { (function () { continue L; }); }",
+        ParseError::UndefinedLabel("L", 30),
+    );
+    assert_error_eq(
+        "L: { continue L; }",
+        ParseError::ContinueToNonIterationLabel("L", 5, 0),
+    );
+    assert_error_eq(
+        "L: while (1) { M: { continue M; } }",
+        ParseError::ContinueToNonIterationLabel("M", 20, 15),
+    );
+    assert_error_eq("L: { L: ; }", ParseError::DuplicateLabel("L", 0, 5));
+    assert_error_eq("L: M: { L: ; }", ParseError::DuplicateLabel("L", 0, 8));
+}
+
+#[test]
+fn test_function_context() {
+    assert_parses("function f() { return; }");
+    assert_parses("function f() { return () => { return new.target; }; }");
+    assert_parses("({ m() { return new.target; }, get x() { return arguments; } });");
+    assert_parses(
+        "class C { x = new.target; static { new.target; } y = function () { arguments; }; }",
+    );
+    assert_parses("arguments;");
+
+    assert_error_eq("return;", ParseError::ReturnOutsideOfFunction(0));
+    assert_error_eq(
+        "{ if (x) return 1; }",
+        ParseError::ReturnOutsideOfFunction(9),
+    );
+    assert_error_eq("new.target;", ParseError::NewTargetOutsideOfFunction(0));
+    assert_error_eq(
+        "() => new.target;",
+        ParseError::NewTargetOutsideOfFunction(6),
+    );
+    assert_error_eq(
+        "class C { x = arguments; }",
+        ParseError::ArgumentsInClassInitializer(14),
+    );
+    assert_error_eq(
+        "class C { x = () => arguments; }",
+        ParseError::ArgumentsInClassInitializer(20),
+    );
+    assert_error_eq(
+        "class C { static { arguments; } }",
+        ParseError::ArgumentsInClassInitializer(19),
+    );
+
+    let allocator = &Bump::new();
+    let mut options = ParseOptions::new();
+    options.allow_return_outside_function = true;
+    assert!(parse_script(allocator, "if (x) return 1;", &options).is_ok());
+    assert_eq!(
+        parse_script(allocator, "new.target;", &options).unwrap_err(),
+        ParseError::NewTargetOutsideOfFunction(0),
+    );
+}
+
+// Returns the names of the named functions in `code`, and whether each of
+// them is hoisted by the web legacy compatibility semantics, in source order.
+fn annex_b_hoisting<'alloc>(allocator: &'alloc Bump, code: &str) -> Vec<(&'alloc str, bool)> {
+    struct FunctionCollector<'alloc> {
+        functions: Vec<(&'alloc str, bool)>,
+    }
+
+    impl<'alloc> Pass<'alloc> for FunctionCollector<'alloc> {
+        fn enter_function(&mut self, ast: &mut Function<'alloc>) {
+            if let Some(name) = &ast.name {
+                self.functions
+                    .push((name.name.value, ast.is_annex_b_hoisted));
+            }
+        }
+    }
+
+    let mut script = try_parse(allocator, code).unwrap();
+    let mut collector = FunctionCollector {
+        functions: Vec::new(),
+    };
+    collector.visit_script(&mut script);
+    collector.functions
+}
+
+#[test]
+fn test_annex_b_functions() {
+    let allocator = &Bump::new();
+    assert_eq!(
+        annex_b_hoisting(allocator, "if (x) function f() {}"),
+        [("f", true)]
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "if (x) function f() {} else function g() {}"),
+        [("f", true), ("g", true)],
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "{ function f() {} } f();"),
+        [("f", true)]
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "{ function f() {} function f() {} }"),
+        [("f", true), ("f", true)],
+    );
+    assert_eq!(
+        annex_b_hoisting(
+            allocator,
+            "switch (x) { case 1: function f() {} default: function f() {} }"
+        ),
+        [("f", true), ("f", true)],
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "var f; { function f() {} }"),
+        [("f", true)]
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "function g() { { function f() {} } }"),
+        [("g", false), ("f", true)],
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "try {} catch (f) { { function f() {} } }"),
+        [("f", true)],
+    );
+
+    // Replacing the declaration with `var f` would be an Early Error.
+    assert_eq!(
+        annex_b_hoisting(allocator, "const f = 1; { function f() {} }"),
+        [("f", false)]
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "{ class f {} { function f() {} } }"),
+        [("f", false)]
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "{ function f() {} { function f() {} } }"),
+        [("f", true), ("f", false)],
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "for (const f of []) { function f() {} }"),
+        [("f", false)],
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "try {} catch ([f]) { { function f() {} } }"),
+        [("f", false)],
+    );
+
+    // Parameter names are not hoisted over.
+    assert_eq!(
+        annex_b_hoisting(allocator, "function g(f) { { function f() {} } }"),
+        [("g", false), ("f", false)],
+    );
+
+    // Generators are not hoisted, and can't be duplicated.
+    assert_eq!(
+        annex_b_hoisting(allocator, "{ function* f() {} }"),
+        [("f", false)]
+    );
+    assert_error_eq(
+        "{ function* f() {} function* f() {} }",
+        ParseError::DuplicateBinding(
+            "f",
+            DeclarationKind::LexicalAsyncOrGenerator,
+            12,
+            DeclarationKind::LexicalAsyncOrGenerator,
+            29,
+        ),
+    );
+
+    // Class bodies are strict mode code.
+    assert_error_eq(
+        "class C { m() { if (x) function f() {} } }",
+        ParseError::FunctionDeclInSingleStatement,
+    );
+    assert_error_eq(
+        "class C { m() { { function f() {} function f() {} } } }",
+        ParseError::DuplicateBinding(
+            "f",
+            DeclarationKind::LexicalFunction,
+            27,
+            DeclarationKind::LexicalFunction,
+            43,
+        ),
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "class C { m() { { function f() {} } } }"),
+        [("f", false)],
+    );
+
+    // So is code with a "use strict" directive.
+    assert_error_eq(
+        "function f() { 'use strict'; if (x) function g() {} }",
+        ParseError::FunctionDeclInSingleStatement,
+    );
+    assert_error_eq(
+        "function f() { 'use strict'; { function g() {} function g() {} } }",
+        ParseError::DuplicateBinding(
+            "g",
+            DeclarationKind::LexicalFunction,
+            40,
+            DeclarationKind::LexicalFunction,
+            56,
+        ),
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "function f() { 'use strict'; { function g() {} } }"),
+        [("f", false), ("g", false)],
+    );
+    assert_error_eq(
+        "'use strict'; if (x) function f() {}",
+        ParseError::FunctionDeclInSingleStatement,
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "'use strict'; { function f() {} }"),
+        [("f", false)],
+    );
+    assert_eq!(
+        annex_b_hoisting(allocator, "function f() { g(); 'use strict'; { function h() {} } }"),
+        [("f", false), ("h", true)],
+    );
+    assert_error_eq(
+        "if (x) l: function f() {}",
+        ParseError::LabelledFunctionDeclInSingleStatement,
+    );
+}
+
+#[test]
+fn test_async_arrows() {
+    // FIXME: async (multiple lookahead)
+    assert_not_implemented("const a = async a => 1;");
+    /*
+    assert_parses("let f = async arg => body;");
+    assert_parses("f = async (a1, a2) => {};");
+    assert_parses("f = async (a1 = b + c, ...a2) => {};");
+
+    assert_error_eq("f = async (a, b + c) => {};", ParseError::InvalidParameter);
+    assert_error_eq(
+        "f = async (...a1, a2) => {};",
+        ParseError::ArrowParametersWithNonFinalRest,
+    );
+    assert_error_eq("obj.async() => {}", ParseError::ArrowHeadInvalid);
+    */
+
+    assert_error_eq("foo(a, b) => {}", ParseError::ArrowHeadInvalid);
+}
+
+#[test]
+fn test_for_await() {
+    assert_parses("async function f() { for await (x of y) {} }");
+    assert_parses("async function f() { for await (var x of y) {} }");
+    assert_parses("async function f() { for await (const [a, b] of y) {} }");
+    assert_parses("async function* f() { for await (x of y) { await x; } }");
+    assert_parses("f = async function() { l: for await (x of y) { continue l; } };");
+    assert_parses("async function f() { for\nawait (x of y) {} }");
+
+    assert_error_eq(
+        "for await (x of y) {}",
+        ParseError::ForAwaitOutsideOfAsyncFunction(0),
+    );
+    assert_error_eq(
+        "function f() { for await (x of y) {} }",
+        ParseError::ForAwaitOutsideOfAsyncFunction(15),
+    );
+    assert_error_eq(
+        "async function f() { function g() { for await (x of y) {} } }",
+        ParseError::ForAwaitOutsideOfAsyncFunction(36),
+    );
+    assert_error_eq(
+        "async function f() { (() => { for await (x of y) {} }); }",
+        ParseError::ForAwaitOutsideOfAsyncFunction(30),
+    );
+    assert_error_eq(
+        "async function f(a = await 1) {}",
+        ParseError::AwaitInFormalParameters(21),
+    );
+
+    // FIXME: await as identifier
+    assert_not_implemented("function f() { await x; }");
+    assert_not_implemented("await;");
+}
+
+#[test]
+fn test_coalesce() {
+    assert_parses("const f = options.prop ?? 0;");
+    assert_syntax_error("if (options.prop ?? 0 || options.prop > 1000) {}");
+}
+
+#[test]
+fn test_logical_assignment() {
+    assert_parses("a &&= b;");
+    assert_parses("a ||= b;");
+    assert_parses("options.timeout ??= 5000;");
+    assert_parses("obj[key] ||= {};");
+    assert_parses("a ||= b ||= c;");
+    assert_same_tokens("a&&=b", "a &&= b");
+    assert_same_tokens("a??=b", "a ??= b");
+    assert_error_eq("f() &&= 1;", ParseError::InvalidAssignmentTarget);
+    assert_error_eq("[a] ||= b;", ParseError::InvalidAssignmentTarget);
+}
+
+#[test]
+fn test_no_line_terminator_here() {
+    // Parse `code` as a Script and compute some function of the resulting AST.
+    fn parse_then<F, R>(code: &str, f: F) -> R
+    where
+        F: FnOnce(&Script) -> R,
+    {
+        let allocator = &Bump::new();
+        match try_parse(allocator, code) {
+            Err(err) => {
+                panic!("Failed to parse code {:?}: {}", code, err);
+            }
+            Ok(script) => f(&*script),
         }
     }
 
@@ -683,3 +1637,1163 @@ fn test_no_line_terminator_here() {
     assert_syntax_error("throw\rfit;");
     assert_syntax_error("throw\r\nfit;");
 }
+
+#[test]
+fn test_import_call_and_meta() {
+    assert_parses("import('./x.js');");
+    assert_parses("f = () => import(a + b);");
+    assert_parses("var meta = 1; meta.x;");
+    assert_module_parses("import('./x.js');");
+    assert_module_parses("import.meta;");
+    assert_module_parses("function f() { return import.meta.url; }");
+    assert_module_parses("class C { static { import.meta; } }");
+
+    assert_error_eq("import.meta;", ParseError::ImportMetaOutsideOfModule(0));
+    assert_error_eq(
+        "function f() { import.meta; }",
+        ParseError::ImportMetaOutsideOfModule(15),
+    );
+    assert_error_eq(
+        "x = () => import.meta.url;",
+        ParseError::ImportMetaOutsideOfModule(10),
+    );
+    assert_syntax_error("import.target;");
+    assert_module_error_eq(
+        "function f() { new.target; } new.target;",
+        ParseError::NewTargetOutsideOfFunction(29),
+    );
+}
+
+#[test]
+fn test_top_level_await() {
+    assert_module_parses("await x;");
+    assert_module_parses("const config = await load('config.json');");
+    assert_module_parses("for await (const x of y) {}");
+    assert_module_parses("if (a) { await b; }");
+
+    let allocator = &Bump::new();
+    assert!(try_parse_module(allocator, "await x;").unwrap().is_async);
+    assert!(
+        try_parse_module(allocator, "for await (x of y) {}")
+            .unwrap()
+            .is_async
+    );
+    assert!(
+        !try_parse_module(allocator, "async function f() { await x; }")
+            .unwrap()
+            .is_async
+    );
+    assert!(
+        !try_parse_module(allocator, "f = async function() { await x; };")
+            .unwrap()
+            .is_async
+    );
+
+    assert_module_error_eq(
+        "async function f(a = await 1) {}",
+        ParseError::AwaitInFormalParameters(21),
+    );
+    assert_module_error_eq(
+        "function f() { for await (x of y) {} }",
+        ParseError::ForAwaitOutsideOfAsyncFunction(15),
+    );
+
+    // `await` is a reserved word in modules, so it's an error outside of
+    // async functions, rather than an identifier.
+    assert_module_error_eq(
+        "function f() { await x; }",
+        ParseError::AwaitOutsideOfAsyncFunction(15),
+    );
+    assert_module_error_eq(
+        "f = function () { return await x; };",
+        ParseError::AwaitOutsideOfAsyncFunction(25),
+    );
+    assert_module_error_eq("x => await x;", ParseError::AwaitOutsideOfAsyncFunction(5));
+    assert_module_parses("x.await; ({ await: 1 });");
+    let allocator = &Bump::new();
+    assert!(matches!(
+        try_parse_module(allocator, "await;"),
+        Err(ParseError::SyntaxError(_))
+    ));
+
+    // FIXME: await as identifier
+    assert_not_implemented("await x;");
+    assert_not_implemented("function f() { await x; }");
+}
+
+#[test]
+fn test_standalone_goals() {
+    let allocator = &Bump::new();
+    let options = ParseOptions::new();
+
+    assert!(parse_expression(allocator, "a + b * c", &options).is_ok());
+    assert!(parse_expression(allocator, "x = 1, y = 2", &options).is_ok());
+    assert!(parse_expression(allocator, "function () { return this; }", &options).is_ok());
+    assert!(parse_expression(allocator, "(a) => a?.b", &options).is_ok());
+    assert!(matches!(
+        parse_expression(allocator, "a; b", &options),
+        Err(ParseError::SyntaxError(_))
+    ));
+    assert!(matches!(
+        parse_expression(allocator, "", &options),
+        Err(ParseError::UnexpectedEnd)
+    ));
+    assert!(matches!(
+        parse_expression(allocator, "new.target", &options),
+        Err(ParseError::NewTargetOutsideOfFunction(0))
+    ));
+    assert!(matches!(
+        parse_expression(allocator, "({a = 1})", &options),
+        Err(ParseError::InvalidCoverInitializedName(_))
+    ));
+
+    let params = parse_formal_parameters(allocator, "a, [b, c] = d, ...e", &options).unwrap();
+    assert_eq!(params.items.len(), 2);
+    assert!(params.rest.is_some());
+    assert_eq!(params.loc, SourceLocation::new(0, 19));
+    assert!(parse_formal_parameters(allocator, "", &options)
+        .unwrap()
+        .items
+        .is_empty());
+    assert!(parse_formal_parameters(allocator, "a, a", &options).is_ok());
+    assert!(parse_formal_parameters(allocator, "a = new.target", &options).is_ok());
+    assert!(matches!(
+        parse_formal_parameters(allocator, "a, a = 1", &options),
+        Err(ParseError::DuplicateBinding(..))
+    ));
+    assert!(matches!(
+        parse_formal_parameters(allocator, "a,, b", &options),
+        Err(ParseError::SyntaxError(_))
+    ));
+
+    let body =
+        parse_function_body(allocator, "var x = 1; return x + arguments[0];", &options).unwrap();
+    assert_eq!(body.statements.len(), 2);
+    assert!(parse_function_body(allocator, "", &options)
+        .unwrap()
+        .statements
+        .is_empty());
+    assert!(parse_function_body(allocator, "if (new.target) return;", &options).is_ok());
+    assert!(parse_function_body(allocator, "'use strict'; return 1;", &options).is_ok());
+    assert!(matches!(
+        parse_function_body(allocator, "break;", &options),
+        Err(ParseError::BreakOutsideOfLoop(0))
+    ));
+    assert!(matches!(
+        parse_function_body(allocator, "const x = 1; var x;", &options),
+        Err(ParseError::DuplicateBinding(..))
+    ));
+    assert!(matches!(
+        parse_function_body(allocator, "import.meta;", &options),
+        Err(ParseError::ImportMetaOutsideOfModule(0))
+    ));
+    assert!(matches!(
+        parse_function_body(allocator, "super.x;", &options),
+        Err(ParseError::UnexpectedSuperProperty(0))
+    ));
+}
+
+fn parse_lazily<'alloc>(
+    allocator: &'alloc Bump,
+    code: &str,
+) -> Result<'alloc, arena::Box<'alloc, Script<'alloc>>> {
+    let buf = arena::alloc_str(allocator, code);
+    let mut options = ParseOptions::new();
+    options.lazy_inner_functions = true;
+    parse_script(allocator, buf, &options)
+}
+
+fn closed_over_names<'alloc>(function: &Function<'alloc>) -> Vec<&'alloc str> {
+    function
+        .lazy
+        .as_ref()
+        .unwrap()
+        .closed_over_names
+        .iter()
+        .map(|name| name.value)
+        .collect()
+}
+
+#[test]
+fn test_lazy_functions() {
+    let allocator = &Bump::new();
+    let code = arena::alloc_str(
+        allocator,
+        "var a = 1; function f(x, y = b) { var z = x + a; c = z; function g() { return d + z; } } \
+         h = function*() { return arguments; };",
+    );
+    let mut options = ParseOptions::new();
+    options.lazy_inner_functions = true;
+    let script = parse_script(allocator, code, &options).unwrap();
+
+    let f = match &script.statements[1] {
+        Statement::FunctionDeclaration(f) => f,
+        _ => panic!("expected FunctionDeclaration"),
+    };
+    assert!(f.body.statements.is_empty());
+    assert_eq!(f.params.items.len(), 2);
+    assert_eq!(closed_over_names(f), vec!["b", "a", "c", "d"]);
+    let lazy = f.lazy.as_ref().unwrap();
+    assert_eq!(lazy.loc, f.loc);
+    assert_eq!(
+        &code[lazy.loc.start()..lazy.loc.end()],
+        "function f(x, y = b) { var z = x + a; c = z; function g() { return d + z; } }"
+    );
+
+    let h = match &script.statements[2] {
+        Statement::ExpressionStatement(expr) => match &**expr {
+            Expression::AssignmentExpression { expression, .. } => match &**expression {
+                Expression::FunctionExpression(h) => h,
+                _ => panic!("expected FunctionExpression"),
+            },
+            _ => panic!("expected AssignmentExpression"),
+        },
+        _ => panic!("expected ExpressionStatement"),
+    };
+    assert!(h.is_generator);
+    assert!(closed_over_names(h).is_empty());
+
+    // The full AST can be built later from the same source.
+    let full = parse_lazy_function(allocator, code, f.lazy.as_ref().unwrap(), &options).unwrap();
+    assert!(full.lazy.is_none());
+    assert_eq!(full.loc, f.loc);
+    assert_eq!(full.name.as_ref().unwrap().name.value, "f");
+    assert_eq!(full.body.statements.len(), 3);
+    match &full.body.statements[2] {
+        Statement::FunctionDeclaration(g) => {
+            assert!(g.lazy.is_none());
+            assert_eq!(g.body.statements.len(), 1);
+        }
+        _ => panic!("expected FunctionDeclaration"),
+    }
+    let full = parse_lazy_function(allocator, code, h.lazy.as_ref().unwrap(), &options).unwrap();
+    assert!(full.is_generator);
+    assert_eq!(full.body.statements.len(), 1);
+
+    // Early Errors inside lazy functions are still reported.
+    assert!(matches!(
+        parse_lazily(allocator, "function f() { break; }"),
+        Err(ParseError::BreakOutsideOfLoop(15))
+    ));
+    assert!(matches!(
+        parse_lazily(allocator, "function f() { const x = 1; var x; }"),
+        Err(ParseError::DuplicateBinding(..))
+    ));
+    assert!(matches!(
+        parse_lazily(allocator, "function f() { super.x; }"),
+        Err(ParseError::UnexpectedSuperProperty(15))
+    ));
+    assert!(matches!(
+        parse_lazily(allocator, "function f() { a +; }"),
+        Err(ParseError::SyntaxError(_))
+    ));
+
+    assert!(matches!(
+        parse_lazily(allocator, "function f(a = 1) { 'use strict'; }"),
+        Err(ParseError::UseStrictWithNonSimpleParameters(20))
+    ));
+    assert!(parse_lazily(allocator, "function f(a = 1) { g(); 'use strict'; }").is_ok());
+
+    // Functions in class bodies are strict mode code, and refer to the
+    // PrivateIdentifiers of the class.
+    let code = arena::alloc_str(
+        allocator,
+        "class C { #x; m() { return function () { if (a) { function g() {} } \
+         return this.#x; }; } }",
+    );
+    let script = parse_script(allocator, code, &options).unwrap();
+    let class = match &script.statements[0] {
+        Statement::ClassDeclaration(class) => class,
+        _ => panic!("expected ClassDeclaration"),
+    };
+    let method = match &*class.elements[1] {
+        ClassElement::MethodDefinition {
+            method: MethodDefinition::Method(method),
+            ..
+        } => method,
+        _ => panic!("expected Method"),
+    };
+    let f = match &method.body.statements[0] {
+        Statement::ReturnStatement {
+            expression: Some(expression),
+            ..
+        } => match &**expression {
+            Expression::FunctionExpression(f) => f,
+            _ => panic!("expected FunctionExpression"),
+        },
+        _ => panic!("expected ReturnStatement"),
+    };
+    let lazy = f.lazy.as_ref().unwrap();
+    assert!(lazy.is_strict);
+    assert!(!lazy.is_module);
+    assert_eq!(closed_over_names(f), vec!["a"]);
+    let private_names: Vec<&str> = lazy.private_names.iter().map(|name| name.value).collect();
+    assert_eq!(private_names, vec!["#x"]);
+
+    // Block-level functions are hoisted only in non-strict code.
+    fn block_function<'a, 'alloc>(f: &'a Function<'alloc>) -> &'a Function<'alloc> {
+        match &f.body.statements[0] {
+            Statement::IfStatement(IfStatement { consequent, .. }) => match &**consequent {
+                Statement::BlockStatement { block, .. } => match &block.statements[0] {
+                    Statement::FunctionDeclaration(g) => g,
+                    _ => panic!("expected FunctionDeclaration"),
+                },
+                _ => panic!("expected BlockStatement"),
+            },
+            _ => panic!("expected IfStatement"),
+        }
+    }
+    let full = parse_lazy_function(allocator, code, lazy, &options).unwrap();
+    assert_eq!(full.body.statements.len(), 2);
+    assert!(!block_function(&full).is_annex_b_hoisted);
+    let code = arena::alloc_str(allocator, "function f() { if (a) { function g() {} } }");
+    let script = parse_script(allocator, code, &options).unwrap();
+    let f = match &script.statements[0] {
+        Statement::FunctionDeclaration(f) => f,
+        _ => panic!("expected FunctionDeclaration"),
+    };
+    assert!(!f.lazy.as_ref().unwrap().is_strict);
+    let full = parse_lazy_function(allocator, code, f.lazy.as_ref().unwrap(), &options).unwrap();
+    assert!(block_function(&full).is_annex_b_hoisted);
+
+    // So are functions with a "use strict" directive, and functions in code
+    // with one.
+    fn lazy_strictness(allocator: &Bump, code: &str) -> Vec<bool> {
+        struct LazyFunctionCollector {
+            is_strict: Vec<bool>,
+        }
+
+        impl<'alloc> Pass<'alloc> for LazyFunctionCollector {
+            fn enter_lazy_function(&mut self, ast: &mut LazyFunction<'alloc>) {
+                self.is_strict.push(ast.is_strict);
+            }
+        }
+
+        let mut script = parse_lazily(allocator, code).unwrap();
+        let mut collector = LazyFunctionCollector {
+            is_strict: Vec::new(),
+        };
+        collector.visit_script(&mut script);
+        collector.is_strict
+    }
+    assert_eq!(
+        lazy_strictness(allocator, "function f() { 'use strict'; } function g() {}"),
+        [true, false],
+    );
+    assert_eq!(
+        lazy_strictness(allocator, "'use strict'; function f() {} function g() {}"),
+        [true, true],
+    );
+    assert_eq!(
+        lazy_strictness(
+            allocator,
+            "x = () => { 'use strict'; return function () {}; }; function g() {}"
+        ),
+        [true, false],
+    );
+    assert_eq!(
+        lazy_strictness(allocator, "({ m() { 'use strict'; return function () {}; } });"),
+        [true],
+    );
+    assert_eq!(
+        lazy_strictness(allocator, "x = () => { g(); 'use strict'; return function () {}; };"),
+        [false],
+    );
+    let code = arena::alloc_str(
+        allocator,
+        "function f() { 'use strict'; a; b; return function () {}; }",
+    );
+    let script = parse_script(allocator, code, &options).unwrap();
+    let f = match &script.statements[0] {
+        Statement::FunctionDeclaration(f) => f,
+        _ => panic!("expected FunctionDeclaration"),
+    };
+    assert!(f.lazy.as_ref().unwrap().is_strict);
+    let full = parse_lazy_function(allocator, code, f.lazy.as_ref().unwrap(), &options).unwrap();
+    assert_eq!(full.body.statements.len(), 4);
+
+    // Functions in modules are parsed again as module code.
+    let code = arena::alloc_str(allocator, "function f() { return import.meta; }");
+    let module = parse_module(allocator, code, &options).unwrap();
+    let f = match &module.items[0] {
+        ModuleItems::Statement(statement) => match &**statement {
+            Statement::FunctionDeclaration(f) => f,
+            _ => panic!("expected FunctionDeclaration"),
+        },
+        _ => panic!("expected Statement"),
+    };
+    let lazy = f.lazy.as_ref().unwrap();
+    assert!(lazy.is_strict);
+    assert!(lazy.is_module);
+    let full = parse_lazy_function(allocator, code, lazy, &options).unwrap();
+    assert_eq!(full.body.statements.len(), 1);
+}
+
+// Returns the expression of the ExpressionStatement `statement`.
+fn statement_expression<'a, 'alloc>(statement: &'a Statement<'alloc>) -> &'a Expression<'alloc> {
+    match statement {
+        Statement::ExpressionStatement(expression) => expression,
+        _ => panic!("expected ExpressionStatement"),
+    }
+}
+
+#[test]
+fn test_utf16_and_latin1_source() {
+    let allocator = &Bump::new();
+    let mut options = ParseOptions::new();
+
+    // "\u{1F600}" takes 4 bytes in UTF-8 and 2 units in UTF-16.
+    let utf16: Vec<u16> = "'\u{1F600}'; x;".encode_utf16().collect();
+    let script = parse_script(allocator, &utf16[..], &options).unwrap();
+    match statement_expression(&script.statements[0]) {
+        Expression::LiteralStringExpression { value, loc } => {
+            assert_eq!(*value, "\u{1F600}");
+            assert_eq!(*loc, SourceLocation::new(0, 6));
+        }
+        _ => panic!("expected LiteralStringExpression"),
+    }
+    assert_eq!(
+        statement_expression(&script.statements[1]).get_loc(),
+        SourceLocation::new(8, 9)
+    );
+
+    options.location_unit = LocationUnit::Utf16;
+    let script = parse_script(allocator, &utf16[..], &options).unwrap();
+    assert_eq!(
+        statement_expression(&script.statements[1]).get_loc(),
+        SourceLocation::new(6, 7)
+    );
+    let script = parse_script(allocator, "'\u{1F600}'; x;", &options).unwrap();
+    assert_eq!(
+        statement_expression(&script.statements[1]).get_loc(),
+        SourceLocation::new(6, 7)
+    );
+
+    // Unpaired surrogates are allowed in string literals, and kept in the
+    // value, with or without escape sequences before them.
+    let with_unit = |before: &str, unit: u16, after: &str| -> Vec<u16> {
+        let mut utf16: Vec<u16> = before.encode_utf16().collect();
+        utf16.push(unit);
+        utf16.extend(after.encode_utf16());
+        utf16
+    };
+    for (utf16, expected) in &[
+        (with_unit("'a", 0xd800, "b';"), &[0x61, 0xd800, 0x62][..]),
+        (with_unit("'\\x61", 0xd800, "b';"), &[0x61, 0xd800, 0x62][..]),
+        (with_unit("'\\", 0xdc00, "';"), &[0xdc00][..]),
+        (with_unit("'", 0xfffd, "';"), &[0xfffd][..]),
+    ] {
+        let script = parse_script(allocator, &utf16[..], &options).unwrap();
+        match statement_expression(&script.statements[0]) {
+            Expression::LiteralStringExpression { value, .. } => {
+                assert_eq!(value.encode_utf16().collect::<Vec<_>>(), *expected);
+            }
+            _ => panic!("expected LiteralStringExpression"),
+        }
+    }
+    let utf16 = with_unit("`a", 0xdc00, "`;");
+    let script = parse_script(allocator, &utf16[..], &options).unwrap();
+    match statement_expression(&script.statements[0]) {
+        Expression::TemplateExpression(TemplateExpression { elements, .. }) => match &elements[0] {
+            TemplateExpressionElement::TemplateElement(element) => {
+                assert_eq!(
+                    element.raw_value.encode_utf16().collect::<Vec<_>>(),
+                    [0x61, 0xdc00]
+                );
+            }
+            _ => panic!("expected TemplateElement"),
+        },
+        _ => panic!("expected TemplateExpression"),
+    }
+
+    // Outside of literals, the code unit is reported.
+    let utf16 = [0xdc00, ';' as u16];
+    assert_eq!(
+        parse_script(allocator, &utf16[..], &options).unwrap_err(),
+        ParseError::UnpairedSurrogate(0xdc00)
+    );
+    let utf16 = with_unit("a", 0xd800, ";");
+    assert_eq!(
+        parse_script(allocator, &utf16[..], &options).unwrap_err(),
+        ParseError::UnpairedSurrogate(0xd800)
+    );
+
+    // Surrogate escape sequences are kept as code units, and paired ones are
+    // joined.
+    let script = parse_script(allocator, "'\\uD800'; '\\uD83D\\u{DE00}';", &options).unwrap();
+    match statement_expression(&script.statements[0]) {
+        Expression::LiteralStringExpression { value, .. } => {
+            assert_eq!(value.encode_utf16().collect::<Vec<_>>(), [0xd800]);
+            assert_eq!(value.as_str(), None);
+        }
+        _ => panic!("expected LiteralStringExpression"),
+    }
+    match statement_expression(&script.statements[1]) {
+        Expression::LiteralStringExpression { value, .. } => {
+            assert_eq!(*value, "\u{1F600}");
+        }
+        _ => panic!("expected LiteralStringExpression"),
+    }
+    assert_eq!(
+        parse_script(allocator, "\\uD800;", &options).unwrap_err(),
+        ParseError::InvalidEscapeSequence
+    );
+
+    // ASCII token values are borrowed from Latin-1 source.
+    let latin1: &[u8] = b"caf\xe9 = abc;";
+    let script = parse_script(allocator, latin1, &options).unwrap();
+    match statement_expression(&script.statements[0]) {
+        Expression::AssignmentExpression {
+            binding:
+                AssignmentTarget::SimpleAssignmentTarget(
+                    SimpleAssignmentTarget::AssignmentTargetIdentifier(target),
+                ),
+            expression,
+            ..
+        } => {
+            assert_eq!(target.name.value, "caf\u{e9}");
+            assert_eq!(target.loc, SourceLocation::new(0, 4));
+            match &**expression {
+                Expression::IdentifierExpression(IdentifierExpression { name, .. }) => {
+                    assert_eq!(name.value, "abc");
+                    assert_eq!(name.value.as_ptr(), latin1[7..].as_ptr());
+                }
+                _ => panic!("expected IdentifierExpression"),
+            }
+        }
+        _ => panic!("expected AssignmentExpression"),
+    }
+}
+
+#[test]
+fn test_atom_table() {
+    let allocator = &Bump::new();
+    let atoms = AtomTable::new_in(allocator);
+    assert_eq!(atoms.find("a"), None);
+    let a = atoms.intern("a");
+    assert_eq!(atoms.intern("a"), a);
+    assert_eq!(atoms.find("a"), Some(a));
+    assert_eq!(atoms.get(a), "a");
+
+    // The table grows as needed.
+    let names: Vec<String> = (0..1000).map(|i| format!("name{}", i)).collect();
+    let interned: Vec<Atom> = names.iter().map(|name| atoms.intern(name)).collect();
+    for (name, atom) in names.iter().zip(interned) {
+        assert_eq!(atoms.intern_with(|text| text.push_str(name)), atom);
+        assert_eq!(atoms.get(atom), name);
+    }
+    assert_eq!(atoms.len(), 1001);
+}
+
+#[test]
+fn test_parse_session() {
+    let mut session = ParseSession::new();
+    let options = ParseOptions::new();
+
+    // Names decoded from UTF-16 source are allocated once and shared, also
+    // by the later parses of the session.
+    let utf16: Vec<u16> = "abc + abc;".encode_utf16().collect();
+    let other_utf16: Vec<u16> = "abc;".encode_utf16().collect();
+    {
+        let script = session.parse_script(&utf16[..], &options).unwrap();
+        let name = match statement_expression(&script.statements[0]) {
+            Expression::BinaryExpression { left, right, .. } => match (&**left, &**right) {
+                (Expression::IdentifierExpression(a), Expression::IdentifierExpression(b)) => {
+                    assert_eq!(a.name.value, "abc");
+                    assert!(std::ptr::eq(a.name.value, b.name.value));
+                    a.name.value
+                }
+                _ => panic!("expected IdentifierExpressions"),
+            },
+            _ => panic!("expected BinaryExpression"),
+        };
+        let other = session.parse_script(&other_utf16[..], &options).unwrap();
+        match statement_expression(&other.statements[0]) {
+            Expression::IdentifierExpression(a) => assert!(std::ptr::eq(a.name.value, name)),
+            _ => panic!("expected IdentifierExpression"),
+        }
+        assert_eq!(session.atoms().len(), 1);
+    }
+
+    // Resetting frees the AST, but keeps the memory for the next parse.
+    assert!(session.allocated_bytes() > 0);
+    session.reset();
+    assert_eq!(session.allocated_bytes(), 0);
+    assert!(session.atoms().is_empty());
+    assert!(session.parse_module("await x;", &options).unwrap().is_async);
+}
+
+// Replaces the range [start, end) of the source of `previous` with `text`,
+// and checks that the incremental parse gives the same result as a full
+// parse of the new source.
+fn edit<'alloc>(
+    allocator: &'alloc Bump,
+    previous: IncrementalScript<'alloc>,
+    start: usize,
+    end: usize,
+    text: &str,
+) -> Result<'alloc, IncrementalScript<'alloc>> {
+    let options = ParseOptions::new();
+    let source = arena::alloc_str(
+        allocator,
+        &format!(
+            "{}{}{}",
+            &previous.source[..start],
+            text,
+            &previous.source[end..]
+        ),
+    );
+    let expected = parse_script(allocator, source, &options);
+    let result = reparse_script(
+        allocator,
+        previous,
+        &TextEdit { start, end, text },
+        &options,
+    );
+    match (&result, expected) {
+        (Ok(reparsed), Ok(expected)) => {
+            assert_eq!(reparsed.source, source);
+            assert_eq!(reparsed.script, expected);
+        }
+        (Err(err), Err(expected)) => assert_eq!(*err, expected),
+        _ => panic!("incremental and full parses disagree on {:?}", source),
+    }
+    result
+}
+
+#[test]
+fn test_incremental_reparse() {
+    let allocator = &Bump::new();
+    let options = ParseOptions::new();
+    let script = parse_script_incrementally(
+        allocator,
+        "var a = 1;\nf(a);\nfunction g() { return a; }\nb = a\n(c);\nd;",
+        &options,
+    )
+    .unwrap();
+    let last = match &script.script.statements[4] {
+        Statement::ExpressionStatement(expression) => &**expression as *const Expression,
+        _ => panic!("expected ExpressionStatement"),
+    };
+
+    // Edit inside a statement. The statements after it are reused, with
+    // their locations shifted.
+    let script = edit(allocator, script, 13, 14, "abc").unwrap();
+    assert_eq!(&script.source[11..18], "f(abc);");
+    let d = &script.script.statements[4];
+    assert_eq!(d.get_loc(), SourceLocation::new(57, 58));
+    match d {
+        Statement::ExpressionStatement(expression) => {
+            assert_eq!(&**expression as *const Expression, last)
+        }
+        _ => panic!("expected ExpressionStatement"),
+    }
+
+    // Adding a semicolon splits a statement, and removing it merges the
+    // statements again.
+    assert_eq!(&script.source[46..51], "b = a");
+    let script = edit(allocator, script, 51, 51, ";").unwrap();
+    assert_eq!(script.script.statements.len(), 6);
+    let script = edit(allocator, script, 51, 52, "").unwrap();
+    assert_eq!(script.script.statements.len(), 5);
+
+    // Comments and whitespace only move the statements after them.
+    let script = edit(allocator, script, 19, 19, "/* comment */\n\n").unwrap();
+    assert_eq!(script.script.statements[2].get_loc().start(), 34);
+
+    // Editing the end of the script.
+    let len = script.source.len();
+    let script = edit(allocator, script, len, len, " e;").unwrap();
+    assert_eq!(script.script.statements.len(), 6);
+
+    // Early Errors involving the reused statements are reported.
+    let start = script.source.find("d;").unwrap();
+    let err = edit(allocator, script, start, start + 2, "const a = 2;");
+    assert!(matches!(err, Err(ParseError::DuplicateBinding(..))));
+
+    // Invalid ranges are rejected.
+    for &(start, end) in &[(4, 3), (0, 100), (5, 6), (1, 5)] {
+        let script = parse_script_incrementally(allocator, "a;\n\"\u{e9}\";\nc;", &options).unwrap();
+        let edit = TextEdit {
+            start,
+            end,
+            text: "x",
+        };
+        assert!(matches!(
+            reparse_script(allocator, script, &edit, &options),
+            Err(ParseError::InvalidTextEdit(s, e)) if (s, e) == (start, end)
+        ));
+    }
+
+    let script = parse_script_incrementally(allocator, "a;\nb;\nc;", &options).unwrap();
+    let script = edit(allocator, script, 3, 4, "const x = 1").unwrap();
+    let err = edit(allocator, script, 0, 1, "var x");
+    assert!(matches!(err, Err(ParseError::DuplicateBinding(..))));
+    let script = parse_script_incrementally(allocator, "a;\nb;\nc;", &options).unwrap();
+    assert!(matches!(
+        edit(allocator, script, 3, 4, "b +"),
+        Err(ParseError::SyntaxError(_))
+    ));
+
+    // Lexical declarations in the edited statements change the hoisting of
+    // functions in blocks in the reused ones.
+    let script =
+        parse_script_incrementally(allocator, "{ function f() {} }\nx;\ny;", &options).unwrap();
+    edit(allocator, script, 20, 21, "const f = 1").unwrap();
+    let script =
+        parse_script_incrementally(allocator, "{ function f() {} }\nx;\ny;", &options).unwrap();
+    edit(allocator, script, 20, 21, "class f {}").unwrap();
+}
+
+#[test]
+fn test_speculative_parser() {
+    let allocator = &Bump::new();
+    let options = ParseOptions::new();
+    let code = "a; f(x, { y: [1, 2] }, function () { return z; });";
+    let expected = parse_script(allocator, code, &options).unwrap();
+
+    let mut parser = SpeculativeParser::new_script(allocator, code, &options);
+    while parser.offset() < 10 {
+        assert!(parser.advance().unwrap());
+    }
+    assert!(!parser.can_close());
+    let offset = parser.offset();
+    let checkpoint = parser.checkpoint();
+
+    // The partial ASTs on the stack, like the statements and arguments parsed
+    // so far, are left as they were by parsing after the checkpoint.
+    for _ in 0..2 {
+        while parser.offset() < 40 {
+            assert!(parser.advance().unwrap());
+        }
+        parser.restore(checkpoint.clone());
+        assert_eq!(parser.offset(), offset);
+    }
+    let inner_checkpoint = parser.checkpoint();
+    while parser.offset() < 20 {
+        assert!(parser.advance().unwrap());
+    }
+    parser.restore(inner_checkpoint.clone());
+    parser.restore(checkpoint.clone());
+    assert_eq!(parser.finish_script().unwrap(), expected);
+
+    // The reduce actions after a checkpoint can modify the nodes inside the
+    // values they pop, like the elements of an array, or an ObjectLiteral
+    // reinterpreted as a pattern.
+    let nested_code = "x = [1, [2, 3], 4]; ({ a: [b, { c }] } = d); ({ e: [f] }) => f;";
+    let expected_nested = parse_script(allocator, nested_code, &options).unwrap();
+    for offset in 0..nested_code.len() {
+        let mut parser = SpeculativeParser::new_script(allocator, nested_code, &options);
+        while parser.offset() < offset {
+            assert!(parser.advance().unwrap());
+        }
+        let checkpoint = parser.checkpoint();
+        while parser.advance().unwrap() {}
+        parser.restore(checkpoint);
+        assert_eq!(parser.finish_script().unwrap(), expected_nested);
+    }
+
+    // Restoring a checkpoint discards the ones taken after it.
+    let mut parser = SpeculativeParser::new_script(allocator, code, &options);
+    let checkpoint = parser.checkpoint();
+    assert!(parser.advance().unwrap());
+    let inner_checkpoint = parser.checkpoint();
+    parser.restore(checkpoint);
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        parser.restore(inner_checkpoint)
+    }))
+    .is_err());
+
+    let code = "a;\nb;\nc;";
+    let expected = parse_script(allocator, code, &options).unwrap();
+    let mut parser = SpeculativeParser::new_script(allocator, code, &options);
+    while parser.offset() < 5 {
+        assert!(parser.advance().unwrap());
+    }
+    let checkpoint = parser.checkpoint();
+    while parser.advance().unwrap() {}
+    assert!(parser.can_close());
+    parser.restore(checkpoint);
+    assert_eq!(parser.finish_script().unwrap(), expected);
+
+    // A failed speculation can be undone.
+    let code = "a;\nb + ;";
+    let mut parser = SpeculativeParser::new_script(allocator, code, &options);
+    assert!(parser.advance().unwrap());
+    assert!(parser.advance().unwrap());
+    let checkpoint = parser.checkpoint();
+    assert!(parser.advance().unwrap());
+    assert!(parser.advance().unwrap());
+    assert!(matches!(parser.advance(), Err(ParseError::SyntaxError(_))));
+    parser.restore(checkpoint);
+    assert_eq!(parser.offset(), 2);
+    assert!(parser.can_close());
+
+    let mut parser = SpeculativeParser::new_module(allocator, "await a;", &options);
+    let checkpoint = parser.checkpoint();
+    assert!(parser.advance().unwrap());
+    parser.restore(checkpoint);
+    assert_eq!(parser.offset(), 0);
+    assert_eq!(
+        parser.finish_module().unwrap(),
+        parse_module(allocator, "await a;", &options).unwrap()
+    );
+}
+
+#[test]
+fn test_resource_limits() {
+    let allocator = &Bump::new();
+    let mut options = ParseOptions::new();
+
+    let code = format!("x = {}1{};", "(".repeat(100000), ")".repeat(100000));
+    assert!(matches!(
+        parse_script(allocator, code.as_str(), &options),
+        Err(ParseError::TooDeeplyNested(_))
+    ));
+    let code = format!("[{}a{}] = x;", "[".repeat(10000), "]".repeat(10000));
+    assert!(matches!(
+        parse_script(allocator, code.as_str(), &options),
+        Err(ParseError::TooDeeplyNested(_))
+    ));
+
+    // The depth doesn't depend on the length of lists, but chains of
+    // left-associative operators are as deep as they are long.
+    options.max_nesting_depth = Some(20);
+    let code = "x = a + b + c + d + e + f;\n".repeat(1000);
+    assert!(parse_script(allocator, code.as_str(), &options).is_ok());
+    let code = format!("x = a{};", " + a".repeat(19));
+    assert!(parse_script(allocator, code.as_str(), &options).is_ok());
+    let code = format!("x = a{};", " + a".repeat(20));
+    assert_eq!(
+        parse_script(allocator, code.as_str(), &options).unwrap_err(),
+        ParseError::TooDeeplyNested(84)
+    );
+    let code = format!("x = a{};", ".b(c)[d]".repeat(7));
+    assert_eq!(
+        parse_script(allocator, code.as_str(), &options).unwrap_err(),
+        ParseError::TooDeeplyNested(55)
+    );
+    assert!(matches!(
+        parse_script(
+            allocator,
+            "x = [[[[[[[[[[[[[[[[[[[[1]]]]]]]]]]]]]]]]]]];",
+            &options
+        ),
+        Err(ParseError::TooDeeplyNested(_))
+    ));
+    options.max_nesting_depth = None;
+    let code = format!("x = {}1{};", "(".repeat(2000), ")".repeat(2000));
+    assert!(parse_script(allocator, code.as_str(), &options).is_ok());
+
+    options.max_tokens = Some(3);
+    assert!(parse_script(allocator, "a; b", &options).is_ok());
+    assert_eq!(
+        parse_script(allocator, "a; b;", &options).unwrap_err(),
+        ParseError::TooManyTokens(4)
+    );
+    options.max_tokens = None;
+
+    let calls = Rc::new(Cell::new(0));
+    let calls_clone = calls.clone();
+    options.is_cancelled = Some(Rc::new(move || {
+        calls_clone.set(calls_clone.get() + 1);
+        calls_clone.get() > 1
+    }));
+    assert!(parse_script(allocator, "a;", &options).is_ok());
+    assert_eq!(calls.get(), 0);
+    let code = "a;".repeat(1000);
+    assert_eq!(
+        parse_script(allocator, code.as_str(), &options).unwrap_err(),
+        ParseError::Cancelled
+    );
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn test_long_chains() {
+    let allocator = &Bump::new();
+    let options = ParseOptions::new();
+
+    // Long chains of left-associative operators don't grow the parser stack,
+    // but the passes over the AST recurse into them.
+    let chain = format!("a{}", " + a".repeat(300000));
+    let code = format!("x = {};", chain);
+    assert!(matches!(
+        parse_script(allocator, code.as_str(), &options),
+        Err(ParseError::TooDeeplyNested(_))
+    ));
+    let code = format!("{{ function f() {{}} }} x = {};", chain);
+    assert!(matches!(
+        parse_script(allocator, code.as_str(), &options),
+        Err(ParseError::TooDeeplyNested(_))
+    ));
+    let code = format!("x = a{};", "?.b.c(d)[e]".repeat(100000));
+    assert!(matches!(
+        parse_script(allocator, code.as_str(), &options),
+        Err(ParseError::TooDeeplyNested(_))
+    ));
+
+    // Chains under the limit can be visited.
+    struct Counter {
+        count: usize,
+    }
+    impl<'alloc> Pass<'alloc> for Counter {
+        fn enter_identifier(&mut self, _ast: &mut Identifier<'alloc>) {
+            self.count += 1;
+        }
+    }
+    let code = format!(
+        "{{ function f() {{}} }} x = a{} + a{};",
+        " + a".repeat(500),
+        ".b(c)".repeat(200)
+    );
+    let mut script = parse_script(allocator, code.as_str(), &options).unwrap();
+    let mut counter = Counter { count: 0 };
+    counter.visit_script(&mut script);
+    assert_eq!(counter.count, 704);
+}
+
+// Write the compiled tables in the format of the files read by LoadedTables.
+fn compiled_tables_file() -> String {
+    fn field<T: ToString>(name: &str, values: &[T]) -> String {
+        let mut line = format!("{} {}", name, values.len());
+        for value in values {
+            line.push(' ');
+            line.push_str(&value.to_string());
+        }
+        line.push('\n');
+        line
+    }
+
+    let tables = generated_parser::TABLES;
+    let mut text = String::from("jsparagus-tables 2\n");
+    text += &field("terminals", &generated_parser::TERMINAL_NAMES);
+    text += &field("nonterminals", &generated_parser::NONTERMINAL_NAMES);
+    let start_states: Vec<_> = generated_parser::START_STATES
+        .iter()
+        .flat_map(|&(name, state)| vec![name.to_string(), state.to_string()])
+        .collect();
+    text += &field("start_states", &start_states);
+    let (action_base, action_default): (Vec<_>, Vec<_>) =
+        tables.action_rows.iter().cloned().unzip();
+    let (action_check, action_values): (Vec<_>, Vec<_>) =
+        tables.action_entries.iter().cloned().unzip();
+    let (goto_base, goto_default): (Vec<_>, Vec<_>) = tables.goto_rows.iter().cloned().unzip();
+    let (goto_check, goto_values): (Vec<_>, Vec<_>) = tables.goto_entries.iter().cloned().unzip();
+    text += &field("action_base", &action_base);
+    text += &field("action_check", &action_check);
+    text += &field("action_values", &action_values);
+    text += &field("action_default", &action_default);
+    text += &field("goto_base", &goto_base);
+    text += &field("goto_check", &goto_check);
+    text += &field("goto_values", &goto_values);
+    text += &field("goto_default", &goto_default);
+    let special_cases: Vec<_> = tables
+        .special_cases
+        .iter()
+        .flat_map(|case| vec![case.same_line, case.new_line])
+        .collect();
+    text += &field("special_cases", &special_cases);
+    let error_codes: Vec<_> = tables
+        .error_codes
+        .iter()
+        .map(|code| match code {
+            Some(code) => format!("{:?}", code),
+            None => String::from("-"),
+        })
+        .collect();
+    text += &field("error_codes", &error_codes);
+    text += &format!("productions {}\n", tables.reduce_simulator.len());
+    for (&(num_pops, nt), key) in tables
+        .reduce_simulator
+        .iter()
+        .zip(generated_parser::REDUCE_KEYS.iter())
+    {
+        text += &format!("{} {} {}\n", num_pops, nt, key);
+    }
+    text
+}
+
+// Check that `tables` parse like the compiled tables.
+fn assert_parses_like_compiled_tables(tables: LoadedTables) {
+    let allocator = &Bump::new();
+    let mut options = ParseOptions::new();
+    options.tables = Some(Rc::new(tables));
+
+    for code in &[
+        "a = b + c * d;",
+        "if (x) y(); else { z = /re/g; }",
+        "a\nb\n++c",
+        "do x; while (y) z",
+        "function f(a, ...b) { return a\n+ b; }",
+        "x => x /y/ 2",
+        "x +",
+    ] {
+        assert_eq!(
+            format!("{:?}", parse_script(allocator, *code, &options)),
+            format!("{:?}", parse_script(allocator, *code, &ParseOptions::new())),
+        );
+    }
+    assert_eq!(
+        format!("{:?}", parse_module(allocator, "await a;", &options)),
+        format!(
+            "{:?}",
+            parse_module(allocator, "await a;", &ParseOptions::new())
+        ),
+    );
+    assert_eq!(
+        parse_expression(allocator, "a ? b : c", &options).unwrap(),
+        parse_expression(allocator, "a ? b : c", &ParseOptions::new()).unwrap(),
+    );
+}
+
+#[test]
+fn test_loaded_tables() {
+    let text = compiled_tables_file();
+    assert_parses_like_compiled_tables(LoadedTables::parse(&text).unwrap());
+
+    // Productions must have the same reduce action as one in the compiled
+    // grammar.
+    let modified = text.replace("=> script(Some($0))", "=> experimental_script($0)");
+    let error = LoadedTables::parse(&modified).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("experimental_script"));
+
+    let error = LoadedTables::parse("jsparagus-tables 0\n").err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let truncated = &text[..text.len() / 2];
+    assert!(LoadedTables::parse(truncated).is_err());
+}
+
+#[test]
+fn test_loaded_tables_file() {
+    // Written by the parser generator, with `make tables`.
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../js_parser/parser_generated.jsparagus_tables"
+    );
+    if !std::path::Path::new(path).exists() {
+        eprintln!("skipping test_loaded_tables_file: run `make tables` first");
+        return;
+    }
+    assert_parses_like_compiled_tables(LoadedTables::from_file(path).unwrap());
+}
+
+// Replace the values of the field `name` in a tables file.
+fn edit_field(text: &str, name: &str, edit: impl FnOnce(&mut Vec<String>)) -> String {
+    let mut edit = Some(edit);
+    let mut result = String::new();
+    for line in text.lines() {
+        let mut words = line.split(' ');
+        if words.next() == Some(name) {
+            words.next();
+            let mut values: Vec<String> = words.map(String::from).collect();
+            (edit.take().unwrap())(&mut values);
+            result += &format!("{} {} {}\n", name, values.len(), values.join(" "));
+        } else {
+            result += line;
+            result.push('\n');
+        }
+    }
+    result
+}
+
+fn assert_invalid_tables(text: &str, message: &str) {
+    let error = LoadedTables::parse(text).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains(message), "{}", error);
+}
+
+#[test]
+fn test_loaded_tables_entries() {
+    let tables = generated_parser::TABLES;
+    let text = compiled_tables_file();
+    let state_count = tables.state_count;
+    let prod_count = tables.reduce_simulator.len();
+    let special_case_count = tables.special_cases.len();
+
+    assert_invalid_tables(
+        &edit_field(&text, "action_default", |values| {
+            values[0] = state_count.to_string()
+        }),
+        &format!("invalid state {} in action_default", state_count),
+    );
+    assert_invalid_tables(
+        &edit_field(&text, "goto_values", |values| {
+            values[0] = state_count.to_string()
+        }),
+        &format!("invalid state {} in goto_values", state_count),
+    );
+    assert_invalid_tables(
+        &edit_field(&text, "start_states", |values| {
+            values[1] = state_count.to_string()
+        }),
+        &format!("invalid state {} in start_states", state_count),
+    );
+
+    // Reductions by production `n` are the action `-n - 1`.
+    let reduce = (-(prod_count as i16) - 1).to_string();
+    assert_invalid_tables(
+        &edit_field(&text, "action_values", |values| {
+            values[0] = reduce
+        }),
+        &format!("invalid production {} in action_values", prod_count),
+    );
+
+    // Special cases are the actions from SPECIAL_CASE_TAG up.
+    let special_case = (-0x8000 + special_case_count as i16).to_string();
+    assert_invalid_tables(
+        &edit_field(&text, "action_default", |values| {
+            values[0] = special_case
+        }),
+        &format!("invalid special case {} in action_default", special_case_count),
+    );
+    assert_invalid_tables(
+        &edit_field(&text, "special_cases", |values| {
+            values.pop();
+        }),
+        "two actions for each case",
+    );
+}
+
+#[test]
+fn test_loaded_tables_actions() {
+    let tables = generated_parser::TABLES;
+    let text = compiled_tables_file();
+
+    // Productions must pop as many values, and push the same nonterminal,
+    // as the compiled production with the same reduce action.
+    let (num_pops, nt) = tables.reduce_simulator[0];
+    let key = generated_parser::REDUCE_KEYS[0];
+    let production = format!("{} {} {}\n", num_pops, nt, key);
+    assert_invalid_tables(
+        &text.replace(&production, &format!("{} {} {}\n", num_pops + 1, nt, key)),
+        "differs from the compiled grammar",
+    );
+    assert_invalid_tables(
+        &text.replace(&production, &format!("{} {} {}\n", num_pops, nt + 1, key)),
+        "differs from the compiled grammar",
+    );
+
+    // States that shift an ErrorToken must have an error code.
+    let error_token = TerminalId::ErrorToken as usize;
+    let state = (0..tables.state_count)
+        .find(|&state| tables.error_codes[state].is_some())
+        .unwrap();
+    assert!(tables.action(state, error_token) >= 0);
+    assert_invalid_tables(
+        &edit_field(&text, "error_codes", |values| {
+            values[state] = String::from("-")
+        }),
+        &format!("missing error code for state {}", state),
+    );
+
+    // Only the goal states accept, at the end of the input.
+    let accept = (-0x4000).to_string();
+    assert_invalid_tables(
+        &edit_field(&text, "action_default", |values| {
+            values[0] = accept
+        }),
+        "ACCEPT in state 0, which is not a goal state",
+    );
+}
@@ -86,10 +86,13 @@ def main():
         target = 'python'
     elif out_filename.endswith('.rs'):
         target = 'rust'
+    elif out_filename.endswith('.jsparagus_tables'):
+        target = 'tables'
     elif out_filename.endswith('.jsparagus_dump'):
         target = 'dump'
     else:
-        raise ValueError("-o file extension should be .py, .rs, or .jsparagus_dump")
+        raise ValueError("-o file extension should be .py, .rs, .jsparagus_tables, "
+                         "or .jsparagus_dump")
 
     # Load input and analyze it.
     if from_source:
@@ -105,7 +108,7 @@ def main():
 
     # Generate output.
     try:
-        if target in ('python', 'rust'):
+        if target in ('python', 'rust', 'tables'):
             with open(out_filename, 'w') as f:
                 jsparagus.gen.generate_parser(f, states,
                                               target=target,
@@ -28,7 +28,7 @@ def _get_punctuators():
     punctuators = '''
         { ( ) [ ] . ... ; , < > <= >= == != === !== + - * % ** ++ --
         << >> >>> & | ^ ! ~ && || ? : = += -= *= %=
-        **= ><<= >>= >>>= &= |= ^= =>
+        **= ><<= >>= >>>= &= |= ^= &&= ||= ??= =>
     '''.split()
 
     return '|'.join(
@@ -193,6 +193,7 @@ ECMASCRIPT_CONDITIONAL_KEYWORDS = [
     'async',
     'from',
     'get',
+    'meta',
     'of',
     'set',
     'target',
@@ -35,8 +35,9 @@ def load_lexical_grammar(filename):
 ECMASCRIPT_SYNTACTIC_GOAL_NTS = [
     'Script',
     'Module',
-    # 'FormalParameters',
-    # 'FunctionBody',
+    'StandaloneExpression',
+    'StandaloneFormalParameters',
+    'StandaloneFunctionBody',
 ]
 
 # Identifiers are complicated. A "synthetic terminal" is a shorthand symbol
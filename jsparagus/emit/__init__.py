@@ -1,6 +1,6 @@
 """Emit code and parser tables in Python and Rust."""
 
-__all__ = ['write_python_parser', 'write_rust_parser']
+__all__ = ['write_python_parser', 'write_rust_parser', 'write_rust_parser_tables_file']
 
 from .python import write_python_parser
-from .rust import write_rust_parser
+from .rust import write_rust_parser, write_rust_parser_tables_file
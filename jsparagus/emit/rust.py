@@ -1,11 +1,12 @@
 """Emit code and parser tables in Rust."""
 
+import collections
 import json
 import re
 import unicodedata
 import sys
 
-from ..runtime import (ERROR, ErrorToken, SPECIAL_CASE_TAG)
+from ..runtime import (ACCEPT, ErrorToken)
 from ..ordered import OrderedSet
 
 from ..grammar import (CallMethod, Some, is_concrete_element, Nt, Optional)
@@ -41,6 +42,9 @@ TERMINAL_NAMES = {
     '^=': 'BitwiseXorAssign',
     '|=': 'BitwiseOrAssign',
     '**=': 'ExponentiateAssign',
+    '&&=': 'LogicalAndAssign',
+    '||=': 'LogicalOrAssign',
+    '??=': 'CoalesceAssign',
     '.': 'Dot',
     '**': 'Exponentiate',
     '?.': 'OptionalChain',
@@ -69,6 +73,19 @@ TERMINAL_NAMES = {
     '...': 'Ellipsis',
 }
 
+# Encoding of the actions in the Rust parse tables, in 16 bits. It's ordered
+# like the encoding in jsparagus/runtime.py: a shift to state n is n, a
+# reduction by production n is -n - 1, above ACCEPT, ERROR is ACCEPT - 1, and
+# special case n is SPECIAL_CASE_TAG + n, below ERROR. The parser uses the
+# actions as they are stored, sign-extended to i64, without decoding them.
+RUST_ACCEPT = -0x4000
+RUST_ERROR = RUST_ACCEPT - 1
+RUST_SPECIAL_CASE_TAG = -0x8000
+
+# Version of the format written by RustParserWriter.emit_tables_file.
+TABLES_FILE_VERSION = 2
+
+
 class RustParserWriter:
     def __init__(self, out, parser_states, fallible_methods):
         self.out = out
@@ -85,6 +102,7 @@ class RustParserWriter:
         self.special_case_cache = {}
 
     def emit(self):
+        self.compress_tables()
         self.header()
         self.terminal_id()
         self.actions()
@@ -113,7 +131,10 @@ class RustParserWriter:
         self.write(0, "use crate::error::Result;")
         self.write(0, "use crate::token::Token;")
         self.write(0, "")
-        self.write(0, "const ERROR: i64 = {};", hex(ERROR))
+        self.write(0, "pub(crate) const ERROR: i64 = {};", hex(RUST_ERROR))
+        self.write(0, "pub(crate) const ACCEPT: i64 = {};", hex(RUST_ACCEPT))
+        self.write(0, "pub(crate) const SPECIAL_CASE_TAG: i64 = {};",
+                   hex(RUST_SPECIAL_CASE_TAG))
         self.write(0, "")
 
     def terminal_name(self, value):
@@ -146,52 +167,108 @@ class RustParserWriter:
         self.write(0, "}")
         self.write(0, "")
 
-    def add_special_case(self, code):
-        if code not in self.special_case_cache:
+        self.write(0, "pub static TERMINAL_NAMES: [&str; {}] = [", len(self.terminals))
+        for t in self.terminals:
+            self.write(1, "{},", rust_string_literal(self.terminal_name(t)))
+        self.write(0, "];")
+        self.write(0, "")
+
+    def add_special_case(self, case):
+        if case not in self.special_case_cache:
             index = len(self.special_cases)
-            self.special_cases.append(code)
-            self.special_case_cache[code] = index
-        return self.special_case_cache[code]
+            self.special_cases.append(case)
+            self.special_case_cache[case] = index
+        return self.special_case_cache[case]
 
     def render_action(self, action):
+        """Encode an action as described above RUST_ACCEPT."""
         if isinstance(action, tuple):
             if action[0] == 'IfSameLine':
                 _, a1, a2 = action
-                if a1 is None:
-                    a1 = 'ERROR'
-                if a2 is None:
-                    a2 = 'ERROR'
-                index = self.add_special_case(
-                    "if token.is_on_new_line { %s } else { %s }"
-                    % (a2, a1))
+                # The action if the token is on the same line, and the
+                # action if it's on a new line.
+                index = self.add_special_case((
+                    self.render_action('ERROR' if a1 is None else a1),
+                    self.render_action('ERROR' if a2 is None else a2)))
             else:
                 raise ValueError("unrecognized kind of special case: {!r}".format(action))
-            return SPECIAL_CASE_TAG + index
+            assert RUST_SPECIAL_CASE_TAG + index < RUST_ERROR
+            return RUST_SPECIAL_CASE_TAG + index
         elif action == 'ERROR':
-            return action
+            return RUST_ERROR
+        elif action == ACCEPT:
+            return RUST_ACCEPT
         else:
             assert isinstance(action, int)
+            assert RUST_ACCEPT < action < 0x8000
             return action
 
+    def compress_tables(self):
+        # Each row of the action table is a state. Its most common action
+        # becomes the default for the row, and the other actions are packed
+        # into a single array, shared by all rows.
+        rows = []
+        for state in self.states:
+            rows.append([self.render_action(state.action_row.get(t, "ERROR"))
+                         for t in self.terminals])
+        self.action_tables = compress_table(rows, len(self.terminals))
+
+        # The goto table is compressed by column, as most nonterminals lead
+        # to the same state from most of the states where they can be
+        # reduced. Entries that are never looked up are 0, which doesn't
+        # count as the most common target.
+        columns = []
+        for nt in self.nonterminals:
+            columns.append([state.ctn_row.get(nt, 0) for state in self.states])
+        self.goto_tables = compress_table(columns, len(self.states), unused=0)
+
     def actions(self):
+        self.write_compressed_table("ACTION", "i16", self.action_tables)
+
+    def write_compressed_table(self, name, value_type, tables):
+        base, check, values, default = tables
         self.write(0, "#[rustfmt::skip]")
-        self.write(0, "static ACTIONS: [i64; {}] = [",
-                   len(self.states) * len(self.terminals))
-        for i, state in enumerate(self.states):
-            self.write(1, "// {}. {}", i, state.traceback() or "<empty>")
-            self.write(1, "{},",
-                       ", ".join(str(self.render_action(state.action_row.get(t, "ERROR")))
-                                 for t in self.terminals))
-            if i < len(self.states) - 1:
-                self.write(0, "")
+        self.write(0, "static {}_ROWS: [(u32, {}); {}] = [", name, value_type, len(base))
+        self.write_pairs(list(zip(base, default)))
         self.write(0, "];")
         self.write(0, "")
+        self.write(0, "#[rustfmt::skip]")
+        self.write(0, "static {}_ENTRIES: [(u16, {}); {}] = [", name, value_type, len(check))
+        self.write_pairs(list(zip(check, values)))
+        self.write(0, "];")
+        self.write(0, "")
+
+    def write_pairs(self, pairs):
+        for i in range(0, len(pairs), 8):
+            self.write(1, "{}", ' '.join("({}, {}),".format(a, b) for a, b in pairs[i:i + 8]))
 
     def emit_special_cases(self):
-        self.write(0, "static SPECIAL_CASES: [fn(&Token<'_>) -> i64; {}] = [",
+        self.write(0, "/// An action that depends on whether the token is on a new line.")
+        self.write(0, "#[derive(Clone, Copy, Debug)]")
+        self.write(0, "pub struct SpecialCase {")
+        self.write(1, "pub same_line: i64,")
+        self.write(1, "pub new_line: i64,")
+        self.write(0, "}")
+        self.write(0, "")
+        self.write(0, "impl SpecialCase {")
+        self.write(1, "pub fn action(&self, token: &Token) -> i64 {")
+        self.write(2, "if token.is_on_new_line {")
+        self.write(3, "self.new_line")
+        self.write(2, "} else {")
+        self.write(3, "self.same_line")
+        self.write(2, "}")
+        self.write(1, "}")
+        self.write(0, "}")
+        self.write(0, "")
+
+        def render(action):
+            return "ERROR" if action == RUST_ERROR else str(action)
+
+        self.write(0, "static SPECIAL_CASES: [SpecialCase; {}] = [",
                    len(self.special_cases))
-        for i, code in enumerate(self.special_cases):
-            self.write(1, "|token| {{ {} }},", code)
+        for same_line, new_line in self.special_cases:
+            self.write(1, "SpecialCase {{ same_line: {}, new_line: {} }},",
+                       render(same_line), render(new_line))
         self.write(0, "];")
         self.write(0, "")
 
@@ -284,7 +361,8 @@ class RustParserWriter:
             assert not boxed
             rty = '()'
         elif ty == types.TokenType:
-            rty = "Token<'alloc>"
+            # Tokens are popped off the stack by value, never boxed.
+            return "Token<'alloc>"
         elif ty.name == 'Option' and len(ty.args) == 1:
             # We auto-translate `Box<Option<T>>` to `Option<Box<T>>` since
             # that's basically the same thing but more efficient.
@@ -341,17 +419,15 @@ class RustParserWriter:
         self.write(0, "}")
         self.write(0, "")
 
-    def goto(self):
-        self.write(0, "#[rustfmt::skip]")
-        self.write(0, "static GOTO: [u16; {}] = [",
-                   len(self.states) * len(self.nonterminals))
-        for state in self.states:
-            row = state.ctn_row
-            self.write(1, "{}", ' '.join("{},".format(row.get(nt, 0))
-                                         for nt in self.nonterminals))
+        self.write(0, "pub static NONTERMINAL_NAMES: [&str; {}] = [", len(self.nonterminals))
+        for nt in self.nonterminals:
+            self.write(1, "{},", rust_string_literal(self.nonterminal_to_camel(nt)))
         self.write(0, "];")
         self.write(0, "")
 
+    def goto(self):
+        self.write_compressed_table("GOTO", "u16", self.goto_tables)
+
     def element_type(self, e):
         # Mostly duplicated from types.py. :(
         g = self.grammar
@@ -369,6 +445,15 @@ class RustParserWriter:
             assert False, "unexpected element type: {!r}".format(e)
 
     def reduce(self):
+        # Each production is reduced by a function of its own. In a single
+        # function matching on `prod`, the locals of all the arms get separate
+        # stack slots, and the stack frame gets so large that probing it costs
+        # more than the reduce action itself.
+        prods = [(i, prod) for i, prod in enumerate(self.prods)
+                 if prod.nt in self.nonterminals]
+        for i, prod in prods:
+            self.reduce_production(i, prod)
+
         # Note use of std::vec::Vec below: we have imported `arena::Vec` in this module,
         # since every other data structure mentioned in this file lives in the arena.
         self.write(0, "pub fn reduce<'alloc>(")
@@ -377,149 +462,219 @@ class RustParserWriter:
         self.write(1, "stack: &mut std::vec::Vec<StackValue<'alloc>>,")
         self.write(0, ") -> Result<'alloc, NonterminalId> {")
         self.write(1, "match prod {")
-        for i, prod in enumerate(self.prods):
-            # If prod.nt is not in nonterminals, that means it's a goal
-            # nonterminal, only accepted, never reduced.
-            if prod.nt in self.nonterminals:
-                self.write(2, "{} => {{", i)
-                self.write(3, "// {}",
-                           self.grammar.production_to_str(prod.nt, prod.rhs, prod.reducer))
-
-                # At run time, the top of the stack will be one value per
-                # concrete symbol in the RHS of the production we're reducing.
-                # We are about to emit code to pop these values from the stack,
-                # one at a time. They come off the stack in reverse order.
-                elements = [e for e in prod.rhs if is_concrete_element(e)]
-
-                # We can emit three different kinds of code here:
-                #
-                # 1.  Full compilation. Pop each value from the stack; if it's
-                #     used, downcast it to its actual type and store it in a
-                #     local variable (otherwise just drop it). Then, evaulate
-                #     the reduce-expression. Push the result back onto the
-                #     stack.
-                #
-                # 2.  `is_discarding_reduction`: A reduce expression that is
-                #     just an integer is retaining one stack value and dropping
-                #     the rest. We skip the downcast in this case.
-                #
-                # 3.  `is_trivial_reduction`: A production has only one
-                #     concrete symbol in it, and the reducer is just `0`.
-                #     We don't have to do anything at all here.
-                is_trivial_reduction = len(elements) == 1 and prod.reducer == 0
-                is_discarding_reduction = isinstance(prod.reducer, int)
-
-                # While compiling, figure out which elements are used.
-                variable_used = [False] * len(elements)
-
-                def compile_reduce_expr(expr):
-                    """Compile a reduce expression to Rust"""
-                    if isinstance(expr, CallMethod):
-                        method_type = self.grammar.methods[expr.method]
-                        method_name = self.method_name_to_rust(expr.method)
-                        assert len(method_type.argument_types) == len(expr.args)
-
-                        # Given arguments can contain any mutable call,
-                        # store them in local variable first.
-                        arg_defs = ''
-                        args = ''
-                        i = 0
-                        for ty, arg in zip(method_type.argument_types,
-                                           expr.args):
-                            if ty != types.UnitType:
-                                arg_defs += 'let a{} = {};'.format(i, compile_reduce_expr(arg))
-                                args += 'a{},'.format(i)
-                                i += 1
-                        call = "{{ {} handler.{}({}) }}".format(
-                            arg_defs, method_name, args)
-
-                        # Extremely bad hack. In Rust, since type inference is
-                        # currently so poor, we don't have enough information
-                        # to know if this method can fail or not, and Rust
-                        # requires us to know that.
-                        if method_name in self.fallible_methods:
-                            call += "?"
-                        return call
-                    elif isinstance(expr, Some):
-                        return "Some({})".format(compile_reduce_expr(expr.inner))
-                    elif expr is None:
-                        return "None"
-                    else:
-                        # can't be 'accept' because we filter out InitNt productions
-                        assert isinstance(expr, int)
-                        variable_used[expr] = True
-                        return "x{}".format(expr)
-
-                compiled_expr = compile_reduce_expr(prod.reducer)
-
-                if not is_trivial_reduction:
-                    for index, e in reversed(list(enumerate(elements))):
-                        if variable_used[index]:
-                            ty = self.element_type(e)
-                            rust_ty = self.type_to_rust(ty, "", boxed=True)
-                            if is_discarding_reduction:
-                                self.write(3, "let x{} = stack.pop().unwrap();", index)
-                            else:
-                                self.write(3, "let x{}: {} = stack.pop().unwrap().to_ast()?;", index, rust_ty)
-                        else:
-                            self.write(3, "stack.pop();", index)
+        for i, prod in prods:
+            self.write(2, "{} => reduce_{}(handler, stack),", i, i)
+        self.write(2, '_ => panic!("no such production: {}", prod),')
+        self.write(1, "}")
+        self.write(0, "}")
+        self.write(0, "")
 
+    def reduce_production(self, prod_index, prod):
+        # At run time, the top of the stack will be one value per
+        # concrete symbol in the RHS of the production we're reducing.
+        # We are about to emit code to pop these values from the stack,
+        # one at a time. They come off the stack in reverse order.
+        elements = [e for e in prod.rhs if is_concrete_element(e)]
+
+        # We can emit three different kinds of code here:
+        #
+        # 1.  Full compilation. Pop each value from the stack; if it's
+        #     used, downcast it to its actual type and store it in a
+        #     local variable (otherwise just drop it). Then, evaulate
+        #     the reduce-expression. Push the result back onto the
+        #     stack.
+        #
+        # 2.  `is_discarding_reduction`: A reduce expression that is
+        #     just an integer is retaining one stack value and dropping
+        #     the rest. We skip the downcast in this case.
+        #
+        # 3.  `is_trivial_reduction`: A production has only one
+        #     concrete symbol in it, and the reducer is just `0`.
+        #     We don't have to do anything at all here.
+        is_trivial_reduction = len(elements) == 1 and prod.reducer == 0
+        is_discarding_reduction = isinstance(prod.reducer, int)
+
+        # While compiling, figure out which elements are used.
+        variable_used = [False] * len(elements)
+
+        def compile_reduce_expr(expr):
+            """Compile a reduce expression to Rust"""
+            if isinstance(expr, CallMethod):
+                method_type = self.grammar.methods[expr.method]
+                method_name = self.method_name_to_rust(expr.method)
+                assert len(method_type.argument_types) == len(expr.args)
+
+                # Given arguments can contain any mutable call,
+                # store them in local variable first.
+                arg_defs = ''
+                args = ''
+                i = 0
+                for ty, arg in zip(method_type.argument_types,
+                                   expr.args):
+                    if ty != types.UnitType:
+                        arg_defs += 'let a{} = {};'.format(i, compile_reduce_expr(arg))
+                        args += 'a{},'.format(i)
+                        i += 1
+                call = "{{ {} handler.{}({}) }}".format(
+                    arg_defs, method_name, args)
+
+                # Extremely bad hack. In Rust, since type inference is
+                # currently so poor, we don't have enough information
+                # to know if this method can fail or not, and Rust
+                # requires us to know that.
+                if method_name in self.fallible_methods:
+                    call += "?"
+                return call
+            elif isinstance(expr, Some):
+                return "Some({})".format(compile_reduce_expr(expr.inner))
+            elif expr is None:
+                return "None"
+            else:
+                # can't be 'accept' because we filter out InitNt productions
+                assert isinstance(expr, int)
+                variable_used[expr] = True
+                return "x{}".format(expr)
+
+        compiled_expr = compile_reduce_expr(prod.reducer)
+
+        # Trivial reductions leave the stack as it is, and only reductions that
+        # call a method use the handler.
+        handler = "handler" if "handler." in compiled_expr else "_handler"
+        stack = "_stack" if is_trivial_reduction else "stack"
+        self.write(0, "#[inline(never)]")
+        self.write(0, "fn reduce_{}<'alloc>(", prod_index)
+        self.write(1, "{}: &mut AstBuilder<'alloc>,", handler)
+        self.write(1, "{}: &mut std::vec::Vec<StackValue<'alloc>>,", stack)
+        self.write(0, ") -> Result<'alloc, NonterminalId> {")
+        self.write(1, "// {}",
+                   self.grammar.production_to_str(prod.nt, prod.rhs, prod.reducer))
+
+        if not is_trivial_reduction:
+            for index, e in reversed(list(enumerate(elements))):
+                if variable_used[index]:
+                    ty = self.element_type(e)
+                    rust_ty = self.type_to_rust(ty, "", boxed=True)
                     if is_discarding_reduction:
-                        self.write(3, "stack.push({});", compiled_expr)
+                        self.write(1, "let x{} = stack.pop().unwrap();", index)
                     else:
-                        self.write(3, "stack.push(TryIntoStack::try_into_stack({})?);", compiled_expr)
+                        self.write(1, "let x{}: {} = stack.pop().unwrap().to_ast()?;", index, rust_ty)
+                else:
+                    self.write(1, "stack.pop();", index)
 
-                self.write(3, "Ok(NonterminalId::{})",
-                           self.nonterminal_to_camel(prod.nt))
-                self.write(2, "}")
-        self.write(2, '_ => panic!("no such production: {}", prod),')
-        self.write(1, "}")
+            if is_discarding_reduction:
+                self.write(1, "stack.push({});", compiled_expr)
+            else:
+                self.write(1, "stack.push(TryIntoStack::try_into_stack({})?);", compiled_expr)
+
+        self.write(1, "Ok(NonterminalId::{})",
+                   self.nonterminal_to_camel(prod.nt))
         self.write(0, "}")
         self.write(0, "")
 
     def reduce_simulator(self):
         prods = [prod for prod in self.prods if prod.nt in self.nonterminals]
-        self.write(0, "static REDUCE_SIMULATOR: [(usize, NonterminalId); {}] = [", len(prods))
+        self.write(0, "static REDUCE_SIMULATOR: [(usize, usize); {}] = [", len(prods))
         for prod in prods:
             elements = [e for e in prod.rhs if is_concrete_element(e)]
-            self.write(1, "({}, NonterminalId::{}),", len(elements), self.nonterminal_to_camel(prod.nt))
+            self.write(1, "({}, NonterminalId::{} as usize),", len(elements), self.nonterminal_to_camel(prod.nt))
         self.write(0, "];")
         self.write(0, "")
 
+        # Parse tables loaded at run time are mapped to the `reduce` function
+        # by these keys.
+        self.write(0, "pub static REDUCE_KEYS: [&str; {}] = [", len(prods))
+        for prod in prods:
+            self.write(1, "{},", rust_string_literal(self.reduce_key(prod)))
+        self.write(0, "];")
+        self.write(0, "")
+
+    def reduce_key(self, prod):
+        return self.grammar.production_to_str(prod.nt, prod.rhs, prod.reducer)
+
     def entry(self):
         self.write(0, "#[derive(Clone, Copy)]")
         self.write(0, "pub struct ParserTables<'a> {")
         self.write(1, "pub state_count: usize,")
-        self.write(1, "pub action_table: &'a [i64],")
         self.write(1, "pub action_width: usize,")
-        self.write(1, "pub special_cases: &'a [fn (&Token) -> i64],")
+        self.write(1, "pub special_cases: &'a [SpecialCase],")
         self.write(1, "pub error_codes: &'a [Option<ErrorCode>],")
-        self.write(1, "pub reduce_simulator: &'a [(usize, NonterminalId)],")
-        self.write(1, "pub goto_table: &'a [u16],")
+        self.write(1, "// The number of elements and the goto column of each production.")
+        self.write(1, "pub reduce_simulator: &'a [(usize, usize)],")
+        self.write(1, "// The production of the `reduce` function with the same reduce")
+        self.write(1, "// action as each production, if it isn't the same production, as in")
+        self.write(1, "// parse tables loaded at run time.")
+        self.write(1, "pub reduce_actions: Option<&'a [usize]>,")
         self.write(1, "pub goto_width: usize,")
+        self.write(0, "")
+        self.write(1, "// The action table, indexed by state and terminal, and the goto")
+        self.write(1, "// table, indexed by nonterminal and state, are compressed by row")
+        self.write(1, "// displacement. `rows` has the base and the default of each row.")
+        self.write(1, "// The entry for (row, column) is the value at index `base + column`")
+        self.write(1, "// in `entries`, if the check at that index is `row`. Otherwise, it's")
+        self.write(1, "// the default. Keeping each pair together, a lookup reads two cache")
+        self.write(1, "// lines.")
+        self.write(1, "pub action_rows: &'a [(u32, i16)],")
+        self.write(1, "pub action_entries: &'a [(u16, i16)],")
+        self.write(1, "pub goto_rows: &'a [(u32, u16)],")
+        self.write(1, "pub goto_entries: &'a [(u16, u16)],")
         self.write(0, "}")
         self.write(0, "")
 
         self.write(0, "impl<'a> ParserTables<'a> {")
         self.write(1, "pub fn check(&self) {")
-        self.write(2, "assert_eq!(")
-        self.write(3, "self.action_table.len(),")
-        self.write(3, "(self.state_count * self.action_width) as usize")
-        self.write(2, ");")
-        self.write(2, "assert_eq!(self.goto_table.len(), (self.state_count * self.goto_width) as usize);")
+        self.write(2, "assert_eq!(self.action_rows.len(), self.state_count);")
+        self.write(2, "assert_eq!(self.goto_rows.len(), self.goto_width);")
+        self.write(1, "}")
+        self.write(0, "")
+        self.write(1, "/// The action for terminal `t` in `state`, encoded as described in")
+        self.write(1, "/// jsparagus/emit/rust.py.")
+        self.write(1, "#[inline]")
+        self.write(1, "pub fn action(&self, state: usize, t: usize) -> i64 {")
+        self.write(2, "let (base, default) = self.action_rows[state];")
+        self.write(2, "let (check, value) = self.action_entries[base as usize + t];")
+        self.write(2, "if check as usize == state {")
+        self.write(3, "value as i64")
+        self.write(2, "} else {")
+        self.write(3, "default as i64")
+        self.write(2, "}")
+        self.write(1, "}")
+        self.write(0, "")
+        self.write(1, "/// The production to pass to `reduce` to reduce the production")
+        self.write(1, "/// `prod` of these tables.")
+        self.write(1, "#[inline]")
+        self.write(1, "pub fn reduce_action(&self, prod: usize) -> usize {")
+        self.write(2, "match self.reduce_actions {")
+        self.write(3, "Some(reduce_actions) => reduce_actions[prod],")
+        self.write(3, "None => prod,")
+        self.write(2, "}")
+        self.write(1, "}")
+        self.write(0, "")
+        self.write(1, "/// The state reached from `state` after reducing the nonterminal `nt`.")
+        self.write(1, "#[inline]")
+        self.write(1, "pub fn goto(&self, state: usize, nt: usize) -> usize {")
+        self.write(2, "let (base, default) = self.goto_rows[nt];")
+        self.write(2, "let (check, value) = self.goto_entries[base as usize + state];")
+        self.write(2, "if check as usize == nt {")
+        self.write(3, "value as usize")
+        self.write(2, "} else {")
+        self.write(3, "default as usize")
+        self.write(2, "}")
         self.write(1, "}")
         self.write(0, "}")
         self.write(0, "")
 
+        self.write(0, "// {}", self.size_report())
         self.write(0, "pub static TABLES: ParserTables<'static> = ParserTables {")
         self.write(1, "state_count: {},", len(self.states))
-        self.write(1, "action_table: &ACTIONS,")
         self.write(1, "action_width: {},", len(self.terminals))
         self.write(1, "special_cases: &SPECIAL_CASES,")
         self.write(1, "error_codes: &STATE_TO_ERROR_CODE,")
         self.write(1, "reduce_simulator: &REDUCE_SIMULATOR,")
-        self.write(1, "goto_table: &GOTO,")
+        self.write(1, "reduce_actions: None,")
         self.write(1, "goto_width: {},".format(len(self.nonterminals)))
+        for name in ["action", "goto"]:
+            for part in ["rows", "entries"]:
+                self.write(1, "{}_{}: &{}_{},", name, part, name.upper(), part.upper())
         self.write(0, "};")
         self.write(0, "")
 
@@ -529,6 +684,137 @@ class RustParserWriter:
                        self.to_snek_case(init_nt.name).upper(), index)
             self.write(0, "")
 
+        self.write(0, "pub static START_STATES: [(&str, usize); {}] = [",
+                   len(self.init_state_map))
+        for init_nt, index in self.init_state_map.items():
+            self.write(1, "({}, {}),", rust_string_literal(init_nt.name), index)
+        self.write(0, "];")
+        self.write(0, "")
+
+    def emit_tables_file(self):
+        """Write the parse tables in the text format read by
+        crates/generated_parser/src/loaded_tables.rs.
+
+        Each line is a field name followed by its values, except for the
+        productions, which take a line each.
+        """
+        self.compress_tables()
+
+        def field(name, values):
+            self.write(0, "{} {}", name, " ".join(str(v) for v in [len(values)] + list(values)))
+
+        self.write(0, "jsparagus-tables {}", TABLES_FILE_VERSION)
+        field("terminals", [self.terminal_name(t) for t in self.terminals])
+        field("nonterminals", [self.nonterminal_to_camel(nt) for nt in self.nonterminals])
+        field("start_states", [item
+                               for init_nt, index in self.init_state_map.items()
+                               for item in (init_nt.name, index)])
+        for name, tables in [("action", self.action_tables), ("goto", self.goto_tables)]:
+            for part, values in zip(["base", "check", "values", "default"], tables):
+                field("{}_{}".format(name, part), values)
+        field("special_cases", [action for case in self.special_cases for action in case])
+        field("error_codes", ["-" if state.error_code is None
+                              else self.to_camel_case(state.error_code)
+                              for state in self.states])
+
+        prods = [prod for prod in self.prods if prod.nt in self.nonterminals]
+        self.write(0, "productions {}", len(prods))
+        for prod in prods:
+            elements = [e for e in prod.rhs if is_concrete_element(e)]
+            self.write(0, "{} {} {}", len(elements),
+                       self.nonterminals.index(prod.nt), self.reduce_key(prod))
+
+    def size_report(self):
+        """Return a description of the size of the tables, compared to dense
+        tables of i64 actions and u16 gotos."""
+        dense = (len(self.states) * len(self.terminals) * 8
+                 + len(self.states) * len(self.nonterminals) * 2)
+        compressed = (compressed_table_size(self.action_tables)
+                      + compressed_table_size(self.goto_tables))
+        return "parser tables: {} bytes, instead of {} bytes for dense tables".format(
+            compressed, dense)
+
+
+def rust_string_literal(s):
+    return '"' + s.replace('\\', '\\\\').replace('"', '\\"') + '"'
+
+
+# Sentinel in the `check` array of a compressed table, for unused entries.
+UNUSED_CHECK = 0xffff
+
+
+def compress_table(rows, width, unused=None):
+    """Compress a table, given as a list of rows of length `width`, by row
+    displacement.
+
+    The most common value of each row becomes its default, and the other
+    values are packed into a single array, where the rows overlap as long as
+    they don't use the same entry. `unused` is a value that is never looked
+    up, and can be replaced by the default.
+
+    Return (base, check, values, default) as described in ParserTables.
+    """
+    assert len(rows) < UNUSED_CHECK
+
+    defaults = []
+    entries = []
+    for row in rows:
+        counts = collections.Counter(v for v in row if v != unused)
+        default = counts.most_common(1)[0][0] if counts else (unused or 0)
+        defaults.append(default)
+        entries.append([(column, v) for column, v in enumerate(row)
+                        if v != default and v != unused])
+
+    base = [0] * len(rows)
+    check = []
+    values = []
+    first_free = 0
+    # Placing the rows with the most entries first leaves fewer holes.
+    for i in sorted(range(len(rows)), key=lambda i: -len(entries[i])):
+        row_entries = entries[i]
+        if not row_entries:
+            continue
+        offset = max(first_free - row_entries[0][0], 0)
+        while not all(offset + column >= len(check)
+                      or check[offset + column] == UNUSED_CHECK
+                      for column, _ in row_entries):
+            offset += 1
+        base[i] = offset
+        for column, v in row_entries:
+            index = offset + column
+            if index >= len(check):
+                check.extend([UNUSED_CHECK] * (index + 1 - len(check)))
+                values.extend([0] * (index + 1 - len(values)))
+            check[index] = i
+            values[index] = v
+        while first_free < len(check) and check[first_free] != UNUSED_CHECK:
+            first_free += 1
+
+    # Every `base[row] + column` must be in bounds.
+    padding = max(base) + width - len(check)
+    if padding > 0:
+        check.extend([UNUSED_CHECK] * padding)
+        values.extend([0] * padding)
+
+    # Check that the compressed table gives back all the entries.
+    for i, row in enumerate(rows):
+        for column, v in enumerate(row):
+            if v == unused:
+                continue
+            index = base[i] + column
+            found = values[index] if check[index] == i else defaults[i]
+            assert found == v, (i, column, found, v)
+
+    return base, check, values, defaults
+
+
+def compressed_table_size(tables):
+    """The size in bytes of a compressed table of 16-bit values, as written by
+    RustParserWriter.write_compressed_table: 8 bytes for each (u32, u16) row,
+    with padding, and 4 bytes for each (u16, u16) entry."""
+    base, check, values, default = tables
+    return len(base) * 8 + len(check) * 4
+
 
 def write_rust_parser(out, parser_states, handler_info):
     if not handler_info:
@@ -539,4 +825,10 @@ def write_rust_parser(out, parser_states, handler_info):
             handler_info_json = json.load(json_file)
         fallible_methods = handler_info_json["fallible-methods"]
 
-    RustParserWriter(out, parser_states, fallible_methods).emit()
+    writer = RustParserWriter(out, parser_states, fallible_methods)
+    writer.emit()
+    print(writer.size_report(), file=sys.stderr)
+
+
+def write_rust_parser_tables_file(out, parser_states):
+    RustParserWriter(out, parser_states, []).emit_tables_file()
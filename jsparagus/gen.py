@@ -1596,7 +1596,7 @@ def generate_parser_states(grammar, *, verbose=False, progress=False):
 
 
 def generate_parser(out, source, *, verbose=False, progress=False, target='python', handler_info=None):
-    assert target in ('python', 'rust')
+    assert target in ('python', 'rust', 'tables')
 
     if isinstance(source, Grammar):
         parser_states = generate_parser_states(
@@ -1608,6 +1608,8 @@ def generate_parser(out, source, *, verbose=False, progress=False, target='pytho
 
     if target == 'rust':
         emit.write_rust_parser(out, parser_states, handler_info)
+    elif target == 'tables':
+        emit.write_rust_parser_tables_file(out, parser_states)
     else:
         emit.write_python_parser(out, parser_states)
 